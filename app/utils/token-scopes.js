@@ -5,6 +5,7 @@ const DESCRIPTIONS = {
   'publish-new': 'Publish new crates',
   'publish-update': 'Publish new versions of existing crates',
   yank: 'Yank and unyank crate versions',
+  read: 'Download crates and read the index of private registries',
 };
 
 export function scopeDescription(scope) {
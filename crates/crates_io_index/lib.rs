@@ -7,6 +7,7 @@ mod credentials;
 mod data;
 mod repo;
 mod ser;
+pub mod signing;
 #[cfg(feature = "testing")]
 pub mod testing;
 
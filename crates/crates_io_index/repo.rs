@@ -60,9 +60,12 @@ impl RepositoryConfig {
 }
 
 pub struct Repository {
-    checkout_path: TempDir,
+    checkout_path: PathBuf,
     repository: git2::Repository,
     credentials: Credentials,
+    /// The temporary directory of the checkout, which is deleted together
+    /// with the `Repository`. This is `None` for persistent checkouts.
+    _temp_dir: Option<TempDir>,
 }
 
 impl Repository {
@@ -80,27 +83,76 @@ impl Repository {
     ///
     #[instrument(skip_all)]
     pub fn open(repository_config: &RepositoryConfig) -> anyhow::Result<Self> {
-        let checkout_path = tempfile::Builder::new()
+        let temp_dir = tempfile::Builder::new()
             .prefix("git")
             .tempdir()
             .context("Failed to create temporary directory")?;
 
-        let Some(checkout_path_str) = checkout_path.path().to_str() else {
-            return Err(anyhow!("Failed to convert Path to &str"));
-        };
+        let checkout_path = temp_dir.path().to_path_buf();
+        clone(repository_config, &checkout_path)?;
+
+        Self::from_checkout(repository_config, checkout_path, Some(temp_dir))
+    }
+
+    /// Opens an existing checkout of the crate index in `checkout_path` and
+    /// resets it to the `HEAD` of the remote git server, or clones the crate
+    /// index into `checkout_path` if there is no checkout yet.
+    ///
+    /// Unlike [Self::open], this only has to fetch the new commits of the
+    /// crate index. The checkout must not be used by multiple processes at
+    /// the same time.
+    ///
+    /// # Errors
+    ///
+    /// - If the existing checkout belongs to a different remote.
+    /// - If cloning or fetching the crate index fails.
+    /// - If reading the global git config fails.
+    ///
+    #[instrument(skip_all, fields(checkout_path = %checkout_path.display()))]
+    pub fn open_persistent(
+        repository_config: &RepositoryConfig,
+        checkout_path: &Path,
+    ) -> anyhow::Result<Self> {
+        let credentials = &repository_config.credentials;
+
+        if checkout_path.join(".git").exists() {
+            let repository = git2::Repository::open(checkout_path)
+                .context("Failed to open existing index repository")?;
+            let remote = repository.find_remote("origin")?;
+            let index_location = repository_config.index_location.as_str();
+            if remote.url() != Some(index_location) {
+                return Err(anyhow!(
+                    "Existing checkout at {} does not belong to {index_location}",
+                    checkout_path.display()
+                ));
+            }
+
+            info!("Fetching index into existing checkout");
+            let mut command = Command::new("git");
+            let command = command.current_dir(checkout_path);
+            run_via_cli(command.args(["fetch", "origin", "HEAD"]), credentials)
+                .context("Failed to fetch index repository")?;
 
-        run_via_cli(
-            Command::new("git").args([
-                "clone",
-                "--single-branch",
-                repository_config.index_location.as_str(),
-                checkout_path_str,
-            ]),
-            &repository_config.credentials,
-        )
-        .context("Failed to clone index repository")?;
-
-        let repository = git2::Repository::open(checkout_path.path())
+            let mut command = Command::new("git");
+            let command = command.current_dir(checkout_path);
+            run_via_cli(command.args(["reset", "--hard", "FETCH_HEAD"]), credentials)
+                .context("Failed to reset index repository")?;
+        } else {
+            std::fs::create_dir_all(checkout_path)
+                .context("Failed to create checkout directory")?;
+
+            clone(repository_config, checkout_path)?;
+        }
+
+        Self::from_checkout(repository_config, checkout_path.to_path_buf(), None)
+    }
+
+    fn from_checkout(
+        repository_config: &RepositoryConfig,
+        checkout_path: PathBuf,
+        temp_dir: Option<TempDir>,
+    ) -> anyhow::Result<Self> {
+        let repository = git2::Repository::open(&checkout_path)
             .context("Failed to open cloned index repository")?;
 
         // All commits to the index registry made through crates.io will be made by bors, the Rust
@@ -120,6 +172,7 @@ impl Repository {
             checkout_path,
             repository,
             credentials: repository_config.credentials.clone(),
+            _temp_dir: temp_dir,
         })
     }
 
@@ -129,9 +182,12 @@ impl Repository {
     /// This is similar to [Self::relative_index_file], but returns the absolute
     /// path.
     pub fn index_file(&self, name: &str) -> PathBuf {
-        self.checkout_path
-            .path()
-            .join(Self::relative_index_file(name))
+        self.checkout_path.join(Self::relative_index_file(name))
+    }
+
+    /// Returns the absolute path to the local checkout of the crate index.
+    pub fn checkout_path(&self) -> &Path {
+        &self.checkout_path
     }
 
     /// Returns the relative path to the crate index file.
@@ -186,7 +242,7 @@ impl Repository {
         // git add $file
         let mut index = self.repository.index()?;
 
-        if self.checkout_path.path().join(modified_file).exists() {
+        if self.checkout_path.join(modified_file).exists() {
             index.add_path(modified_file)?;
         } else {
             index.remove_path(modified_file)?;
@@ -208,6 +264,9 @@ impl Repository {
 
     /// Gets a list of files that have been modified since a given `starting_commit`
     /// (use `starting_commit = None` for a list of all files).
+    ///
+    /// Files that have been deleted since the `starting_commit` are included
+    /// as well, so callers need to check whether the files still exist.
     #[instrument(skip_all)]
     pub fn get_files_modified_since(
         &self,
@@ -243,9 +302,7 @@ impl Repository {
             .context("failed to run diff")?;
         let files = diff
             .deltas()
-            .map(|delta| delta.new_file())
-            .filter(|file| file.exists())
-            .map(|file| file.path().unwrap().to_path_buf())
+            .map(|delta| delta.new_file().path().unwrap().to_path_buf())
             .collect();
 
         Ok(files)
@@ -267,7 +324,7 @@ impl Repository {
     pub fn commit_and_push(&self, message: &str, modified_file: &Path) -> anyhow::Result<()> {
         info!("Committing and pushing \"{message}\"");
 
-        let relative_path = modified_file.strip_prefix(self.checkout_path.path())?;
+        let relative_path = modified_file.strip_prefix(&self.checkout_path)?;
         self.perform_commit_and_push(message, relative_path)
             .map(|_| info!("Commit and push finished for \"{message}\""))
             .map_err(|err| {
@@ -321,13 +378,31 @@ impl Repository {
     /// This function also temporarily sets the `GIT_SSH_COMMAND` environment
     /// variable to ensure that `git push` commands are able to succeed.
     pub fn run_command(&self, command: &mut Command) -> anyhow::Result<()> {
-        let checkout_path = self.checkout_path.path();
+        let checkout_path = &self.checkout_path;
         command.current_dir(checkout_path);
 
         run_via_cli(command, &self.credentials)
     }
 }
 
+/// Clones the crate index from a remote git server into `checkout_path`.
+fn clone(repository_config: &RepositoryConfig, checkout_path: &Path) -> anyhow::Result<()> {
+    let Some(checkout_path_str) = checkout_path.to_str() else {
+        return Err(anyhow!("Failed to convert Path to &str"));
+    };
+
+    run_via_cli(
+        Command::new("git").args([
+            "clone",
+            "--single-branch",
+            repository_config.index_location.as_str(),
+            checkout_path_str,
+        ]),
+        &repository_config.credentials,
+    )
+    .context("Failed to clone index repository")
+}
+
 /// Runs the specified `git` command through the `git` CLI.
 ///
 /// This function also temporarily sets the `GIT_SSH_COMMAND` environment
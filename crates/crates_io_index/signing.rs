@@ -0,0 +1,490 @@
+//! Signed index metadata, loosely following the design of
+//! [TUF](https://theupdateframework.io/).
+//!
+//! The metadata files are stored next to the sparse index files under the
+//! [`METADATA_PREFIX`] directory, which can't clash with any crate name:
+//!
+//! - `_meta/root.json` lists the keys that are trusted to sign the other
+//!   metadata files. It is signed by an offline root key, which allows the
+//!   online signing key of the registry to be rotated by publishing a new
+//!   root file.
+//! - `_meta/crates/{path}.json` contains the hash of a single index file and
+//!   is updated whenever the index file changes.
+//! - `_meta/snapshot.json` contains the hashes of all index files at a given
+//!   point in time, and expires so that a mirror can't keep serving an
+//!   outdated index forever.
+//!
+//! Mirrors pin the public root key and use the `verify_*` functions of this
+//! module to check the files they download.
+
+use crate::Repository;
+use anyhow::{anyhow, bail, ensure, Context};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::signature::{Ed25519KeyPair, KeyPair, UnparsedPublicKey, ED25519};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+
+pub const METADATA_PREFIX: &str = "_meta";
+pub const ROOT_PATH: &str = "_meta/root.json";
+pub const SNAPSHOT_PATH: &str = "_meta/snapshot.json";
+
+/// Returns the path of the signed metadata file for the index file of the
+/// given crate, relative to the root of the sparse index.
+pub fn index_file_metadata_path(name: &str) -> String {
+    let path = Repository::relative_index_file_for_url(name);
+    format!("{METADATA_PREFIX}/crates/{path}.json")
+}
+
+/// An Ed25519 key that is used to sign metadata files.
+pub struct SigningKey {
+    key_pair: Ed25519KeyPair,
+}
+
+impl SigningKey {
+    /// Generates a new random key, and returns it together with its base64
+    /// encoded seed.
+    pub fn generate() -> anyhow::Result<(Self, String)> {
+        let mut seed = [0; 32];
+        SystemRandom::new()
+            .fill(&mut seed)
+            .map_err(|_| anyhow!("Failed to generate a random seed"))?;
+
+        let encoded = STANDARD.encode(seed);
+        Ok((Self::from_seed(&seed)?, encoded))
+    }
+
+    /// Creates a key from its base64 encoded 32 byte seed.
+    pub fn from_base64(seed: &str) -> anyhow::Result<Self> {
+        let seed = STANDARD
+            .decode(seed.trim())
+            .context("Failed to decode signing key")?;
+        Self::from_seed(&seed)
+    }
+
+    fn from_seed(seed: &[u8]) -> anyhow::Result<Self> {
+        let key_pair = Ed25519KeyPair::from_seed_unchecked(seed)
+            .map_err(|error| anyhow!("Invalid signing key: {error}"))?;
+        Ok(Self { key_pair })
+    }
+
+    pub fn verifying_key(&self) -> VerifyingKey {
+        VerifyingKey(self.key_pair.public_key().as_ref().to_vec())
+    }
+
+    pub fn sign<T: Serialize>(&self, signed: T) -> anyhow::Result<Signed<T>> {
+        let message = canonical_json(&serde_json::to_value(&signed)?)?;
+        let signature = Signature {
+            keyid: self.verifying_key().key_id(),
+            sig: STANDARD.encode(self.key_pair.sign(&message)),
+        };
+
+        Ok(Signed {
+            signed,
+            signatures: vec![signature],
+        })
+    }
+}
+
+impl fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SigningKey")
+            .field("key_id", &self.verifying_key().key_id())
+            .finish_non_exhaustive()
+    }
+}
+
+/// The public part of a [`SigningKey`], serialized as base64.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct VerifyingKey(Vec<u8>);
+
+impl VerifyingKey {
+    pub fn from_base64(key: &str) -> anyhow::Result<Self> {
+        let key = STANDARD
+            .decode(key.trim())
+            .context("Failed to decode public key")?;
+        ensure!(key.len() == 32, "Invalid public key length: {}", key.len());
+        Ok(Self(key))
+    }
+
+    pub fn to_base64(&self) -> String {
+        STANDARD.encode(&self.0)
+    }
+
+    /// The hex encoded SHA-256 hash of the public key.
+    pub fn key_id(&self) -> String {
+        sha256(&self.0)
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+        let key = UnparsedPublicKey::new(&ED25519, &self.0);
+        key.verify(message, signature).is_ok()
+    }
+}
+
+impl fmt::Debug for VerifyingKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("VerifyingKey")
+            .field(&self.to_base64())
+            .finish()
+    }
+}
+
+impl TryFrom<String> for VerifyingKey {
+    type Error = anyhow::Error;
+
+    fn try_from(key: String) -> anyhow::Result<Self> {
+        Self::from_base64(&key)
+    }
+}
+
+impl From<VerifyingKey> for String {
+    fn from(key: VerifyingKey) -> Self {
+        key.to_base64()
+    }
+}
+
+/// A metadata file, together with the signatures of its `signed` part.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Signed<T> {
+    pub signed: T,
+    pub signatures: Vec<Signature>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Signature {
+    pub keyid: String,
+    pub sig: String,
+}
+
+/// The list of keys that are trusted to sign snapshots and index files.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Root {
+    /// Incremented whenever the trusted keys change, to prevent rollbacks to
+    /// older root files.
+    pub version: u32,
+    pub expires: DateTime<Utc>,
+    /// The trusted keys, indexed by their [`VerifyingKey::key_id()`].
+    pub keys: BTreeMap<String, VerifyingKey>,
+}
+
+impl Root {
+    pub fn new(version: u32, expires: DateTime<Utc>, keys: Vec<VerifyingKey>) -> Self {
+        let keys = keys.into_iter().map(|key| (key.key_id(), key)).collect();
+        Self {
+            version,
+            expires,
+            keys,
+        }
+    }
+}
+
+/// The hash of a single index file.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IndexFileMetadata {
+    pub name: String,
+    #[serde(flatten)]
+    pub hash: IndexFileHash,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl IndexFileMetadata {
+    pub fn new(name: &str, content: &[u8], timestamp: DateTime<Utc>) -> Self {
+        let name = name.to_string();
+        let hash = IndexFileHash::new(content);
+        Self {
+            name,
+            hash,
+            timestamp,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexFileHash {
+    pub sha256: String,
+    pub length: u64,
+}
+
+impl IndexFileHash {
+    pub fn new(content: &[u8]) -> Self {
+        let sha256 = sha256(content);
+        let length = content.len() as u64;
+        Self { sha256, length }
+    }
+
+    pub fn verify(&self, content: &[u8]) -> anyhow::Result<()> {
+        ensure!(
+            *self == Self::new(content),
+            "Index file does not match the signed hash"
+        );
+        Ok(())
+    }
+}
+
+/// The hashes of all index files at a given point in time.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub timestamp: DateTime<Utc>,
+    pub expires: DateTime<Utc>,
+    /// The index file hashes, indexed by crate name.
+    pub files: BTreeMap<String, IndexFileHash>,
+}
+
+/// Verifies a root file against the pinned root key of the registry.
+pub fn verify_root(
+    bytes: &[u8],
+    root_key: &VerifyingKey,
+    now: DateTime<Utc>,
+) -> anyhow::Result<Root> {
+    let keys = BTreeMap::from([(root_key.key_id(), root_key.clone())]);
+    let root: Root = verify_signed(bytes, &keys)?;
+    ensure!(
+        root.expires > now,
+        "Root metadata expired at {}",
+        root.expires
+    );
+    Ok(root)
+}
+
+/// Verifies a snapshot file against the keys of a verified root file.
+pub fn verify_snapshot(bytes: &[u8], root: &Root, now: DateTime<Utc>) -> anyhow::Result<Snapshot> {
+    let snapshot: Snapshot = verify_signed(bytes, &root.keys)?;
+    ensure!(
+        snapshot.expires > now,
+        "Snapshot metadata expired at {}",
+        snapshot.expires
+    );
+    Ok(snapshot)
+}
+
+/// Verifies the signature of a metadata file of a single index file against
+/// the given keys, without comparing it to the index file or a snapshot.
+pub fn verify_index_file_metadata(
+    bytes: &[u8],
+    keys: &BTreeMap<String, VerifyingKey>,
+) -> anyhow::Result<IndexFileMetadata> {
+    verify_signed(bytes, keys)
+}
+
+/// Verifies an index file against its signed metadata file, the keys of a
+/// verified root file and a verified snapshot.
+///
+/// The index file has to match the hash in the snapshot, unless it has been
+/// updated after the snapshot was taken. This prevents a mirror from serving
+/// an index file that was superseded before the snapshot, even though it
+/// still has a valid signature.
+pub fn verify_index_file(
+    name: &str,
+    content: &[u8],
+    metadata: &[u8],
+    root: &Root,
+    snapshot: &Snapshot,
+) -> anyhow::Result<IndexFileMetadata> {
+    let metadata = verify_index_file_metadata(metadata, &root.keys)?;
+    ensure!(
+        metadata.name == name,
+        "Metadata belongs to crate `{}` instead of `{name}`",
+        metadata.name
+    );
+    metadata.hash.verify(content)?;
+
+    if snapshot.files.get(name) != Some(&metadata.hash) {
+        ensure!(
+            metadata.timestamp > snapshot.timestamp,
+            "Index file of `{name}` does not match the snapshot from {}",
+            snapshot.timestamp
+        );
+    }
+
+    Ok(metadata)
+}
+
+/// Parses a [`Signed`] metadata file and returns its content, if it has a
+/// valid signature by at least one of the given keys.
+fn verify_signed<T: DeserializeOwned>(
+    bytes: &[u8],
+    keys: &BTreeMap<String, VerifyingKey>,
+) -> anyhow::Result<T> {
+    let Signed { signed, signatures } = serde_json::from_slice::<Signed<Value>>(bytes)
+        .context("Failed to parse signed metadata")?;
+
+    let message = canonical_json(&signed)?;
+    let is_valid = signatures.iter().any(|signature| {
+        let key = keys.get(&signature.keyid);
+        let sig = STANDARD.decode(&signature.sig);
+        matches!((key, sig), (Some(key), Ok(sig)) if key.verify(&message, &sig))
+    });
+
+    if !is_valid {
+        bail!("Metadata has no valid signature by a trusted key");
+    }
+
+    serde_json::from_value(signed).context("Failed to parse metadata")
+}
+
+/// Serializes a JSON value with sorted object keys and without whitespace,
+/// so that the signed bytes don't depend on how the file was formatted.
+fn canonical_json(value: &Value) -> anyhow::Result<Vec<u8>> {
+    fn sort_keys(value: &Value) -> Value {
+        match value {
+            Value::Object(map) => {
+                let sorted = map
+                    .iter()
+                    .map(|(key, value)| (key.clone(), sort_keys(value)))
+                    .collect::<BTreeMap<_, _>>();
+                Value::Object(sorted.into_iter().collect())
+            }
+            Value::Array(values) => Value::Array(values.iter().map(sort_keys).collect()),
+            value => value.clone(),
+        }
+    }
+
+    Ok(serde_json::to_vec(&sort_keys(value))?)
+}
+
+fn sha256(bytes: &[u8]) -> String {
+    let digest = ring::digest::digest(&ring::digest::SHA256, bytes);
+    hex::encode(digest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use claims::*;
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-05-01T12:00:00Z")
+            .unwrap()
+            .into()
+    }
+
+    fn keys() -> (SigningKey, SigningKey, Root) {
+        let (root_key, _) = SigningKey::generate().unwrap();
+        let (online_key, _) = SigningKey::generate().unwrap();
+
+        let expires = now() + Duration::days(365);
+        let root = Root::new(1, expires, vec![online_key.verifying_key()]);
+        (root_key, online_key, root)
+    }
+
+    #[test]
+    fn key_encoding() {
+        let (key, seed) = SigningKey::generate().unwrap();
+        let decoded = assert_ok!(SigningKey::from_base64(&seed));
+        assert_eq!(decoded.verifying_key(), key.verifying_key());
+
+        let public_key = key.verifying_key().to_base64();
+        assert_ok_eq!(VerifyingKey::from_base64(&public_key), key.verifying_key());
+
+        assert_err!(SigningKey::from_base64("foo"));
+        assert_err!(VerifyingKey::from_base64("Zm9v"));
+    }
+
+    #[test]
+    fn metadata_paths() {
+        assert_eq!(index_file_metadata_path("a"), "_meta/crates/1/a.json");
+        assert_eq!(
+            index_file_metadata_path("Serde"),
+            "_meta/crates/se/rd/serde.json"
+        );
+    }
+
+    #[test]
+    fn root_verification() {
+        let (root_key, online_key, root) = keys();
+
+        let bytes = serde_json::to_vec(&root_key.sign(root).unwrap()).unwrap();
+        let root = assert_ok!(verify_root(&bytes, &root_key.verifying_key(), now()));
+        assert_eq!(root.version, 1);
+
+        // Only the offline root key is trusted to sign root files
+        assert_err!(verify_root(&bytes, &online_key.verifying_key(), now()));
+
+        let later = now() + Duration::days(400);
+        assert_err!(verify_root(&bytes, &root_key.verifying_key(), later));
+    }
+
+    #[test]
+    fn snapshot_verification() {
+        let (root_key, online_key, root) = keys();
+
+        let snapshot = Snapshot {
+            timestamp: now(),
+            expires: now() + Duration::days(1),
+            files: BTreeMap::from([("foo".to_string(), IndexFileHash::new(b"foo"))]),
+        };
+        let signed = online_key.sign(snapshot).unwrap();
+
+        // Pretty-printing doesn't invalidate the signature
+        let bytes = serde_json::to_vec_pretty(&signed).unwrap();
+        let snapshot = assert_ok!(verify_snapshot(&bytes, &root, now()));
+        assert_ok!(snapshot.files["foo"].verify(b"foo"));
+        assert_err!(snapshot.files["foo"].verify(b"bar"));
+
+        let later = now() + Duration::days(2);
+        assert_err!(verify_snapshot(&bytes, &root, later));
+
+        // Tampering with the content invalidates the signature
+        let mut signed = signed;
+        signed.signed.expires = later + Duration::days(1);
+        let bytes = serde_json::to_vec(&signed).unwrap();
+        assert_err!(verify_snapshot(&bytes, &root, later));
+
+        // Snapshots signed by the root key are not trusted
+        let snapshot = Snapshot {
+            timestamp: now(),
+            expires: now() + Duration::days(1),
+            files: BTreeMap::new(),
+        };
+        let bytes = serde_json::to_vec(&root_key.sign(snapshot).unwrap()).unwrap();
+        assert_err!(verify_snapshot(&bytes, &root, now()));
+    }
+
+    #[test]
+    fn index_file_verification() {
+        let (_, online_key, root) = keys();
+
+        let content = b"{\"name\":\"foo\",\"vers\":\"1.0.0\"}\n";
+        let metadata = IndexFileMetadata::new("foo", content, now());
+        let bytes = serde_json::to_vec(&online_key.sign(metadata).unwrap()).unwrap();
+
+        let snapshot = |timestamp, files| Snapshot {
+            timestamp,
+            expires: timestamp + Duration::days(1),
+            files,
+        };
+        let files = BTreeMap::from([("foo".to_string(), IndexFileHash::new(content))]);
+        let current = snapshot(now(), files);
+
+        let metadata = assert_ok!(verify_index_file("foo", content, &bytes, &root, &current));
+        assert_eq!(metadata.hash.length, content.len() as u64);
+
+        assert_err!(verify_index_file(
+            "foo",
+            b"tampered",
+            &bytes,
+            &root,
+            &current
+        ));
+        assert_err!(verify_index_file("bar", content, &bytes, &root, &current));
+
+        // Index files that were updated after the snapshot are accepted...
+        let older = snapshot(now() - Duration::hours(1), BTreeMap::new());
+        assert_ok!(verify_index_file("foo", content, &bytes, &root, &older));
+
+        // ...but files that were superseded before the snapshot are not.
+        let files = BTreeMap::from([("foo".to_string(), IndexFileHash::new(b"newer"))]);
+        let newer = snapshot(now() + Duration::hours(1), files);
+        assert_err!(verify_index_file("foo", content, &bytes, &root, &newer));
+        let newer = snapshot(now() + Duration::hours(1), BTreeMap::new());
+        assert_err!(verify_index_file("foo", content, &bytes, &root, &newer));
+    }
+}
@@ -165,6 +165,31 @@ impl UpstreamIndex {
 
         Ok(())
     }
+
+    pub fn delete_file(&self, path: &str) -> anyhow::Result<()> {
+        let repo = self.repository.lock().unwrap();
+
+        let head = repo.head()?;
+        let head_oid = head
+            .target()
+            .ok_or_else(|| anyhow!("Missing target for HEAD"))?;
+
+        let parent = repo.find_commit(head_oid)?;
+        let tree = repo.find_tree(parent.tree_id())?;
+
+        let message = format!("Delete `{path}`");
+
+        let tree_oid = TreeUpdateBuilder::new()
+            .remove(path)
+            .create_updated(&repo, &tree)?;
+
+        let new_tree = repo.find_tree(tree_oid)?;
+
+        let sig = repo.signature()?;
+        repo.commit(Some("HEAD"), &sig, &sig, &message, &new_tree, &[&parent])?;
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,7 @@
+alter table version_owner_actions
+    drop column trusted_publisher_id;
+
+alter table api_tokens
+    drop column trusted_publisher_id;
+
+drop table trusted_publishers;
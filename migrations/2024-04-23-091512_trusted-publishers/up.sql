@@ -0,0 +1,47 @@
+create table trusted_publishers
+(
+    id                serial
+        constraint trusted_publishers_pk
+            primary key,
+    crate_id          integer   not null
+        constraint trusted_publishers_crates_id_fk
+            references crates
+            on delete cascade,
+    repository_owner  varchar   not null,
+    repository_name   varchar   not null,
+    workflow_filename varchar   not null,
+    environment       varchar,
+    created_by        integer   not null
+        constraint trusted_publishers_users_id_fk
+            references users,
+    created_at        timestamp not null default now()
+);
+
+create index trusted_publishers_crate_id_index
+    on trusted_publishers (crate_id);
+
+comment on table trusted_publishers is 'CI workflows that are allowed to exchange OIDC identity tokens for short-lived publish tokens of a crate.';
+comment on column trusted_publishers.id is 'Unique identifier of the trusted publisher configuration.';
+comment on column trusted_publishers.crate_id is 'Reference to the crate in the `crates` table.';
+comment on column trusted_publishers.repository_owner is 'Owner of the repository that the CI workflow runs in (e.g. `rust-lang`).';
+comment on column trusted_publishers.repository_name is 'Name of the repository that the CI workflow runs in (e.g. `crates.io`).';
+comment on column trusted_publishers.workflow_filename is 'Filename of the CI workflow that is allowed to publish (e.g. `release.yml`).';
+comment on column trusted_publishers.environment is 'Optional deployment environment that the CI workflow has to run in.';
+comment on column trusted_publishers.created_by is 'Reference to the user in the `users` table that created the configuration. Tokens minted for this configuration are owned by this user.';
+comment on column trusted_publishers.created_at is 'Date and time when the configuration was created.';
+
+alter table api_tokens
+    add column trusted_publisher_id integer
+        constraint api_tokens_trusted_publishers_id_fk
+            references trusted_publishers
+            on delete set null;
+
+comment on column api_tokens.trusted_publisher_id is 'Reference to the trusted publisher configuration in the `trusted_publishers` table, if the token was minted via an OIDC token exchange.';
+
+alter table version_owner_actions
+    add column trusted_publisher_id integer
+        constraint version_owner_actions_trusted_publishers_id_fk
+            references trusted_publishers
+            on delete set null;
+
+comment on column version_owner_actions.trusted_publisher_id is 'Reference to the trusted publisher configuration in the `trusted_publishers` table, if the action was performed with a token minted via an OIDC token exchange.';
@@ -0,0 +1,45 @@
+create table crate_audit_actions
+(
+    id             serial
+        constraint crate_audit_actions_pk
+            primary key,
+    crate_id       integer   not null,
+    crate_name     varchar   not null,
+    version        varchar,
+    action         integer   not null,
+    user_id        integer
+        constraint crate_audit_actions_users_id_fk
+            references users,
+    api_token_id   integer
+        constraint crate_audit_actions_api_tokens_id_fk
+            references api_tokens
+            on delete set null,
+    target_user_id integer
+        constraint crate_audit_actions_target_users_id_fk
+            references users,
+    target_team_id integer
+        constraint crate_audit_actions_teams_id_fk
+            references teams
+            on delete set null,
+    details        varchar,
+    time           timestamp not null default now()
+);
+
+create index crate_audit_actions_crate_id_index
+    on crate_audit_actions (crate_id);
+
+create index crate_audit_actions_crate_name_index
+    on crate_audit_actions (crate_name);
+
+comment on table crate_audit_actions is 'Audit log of ownership, publishing and administrative actions performed on crates.';
+comment on column crate_audit_actions.id is 'Unique identifier of the audit log entry.';
+comment on column crate_audit_actions.crate_id is 'Identifier of the crate in the `crates` table that the action was performed on. This is intentionally not a foreign key, so that the log survives the deletion of the crate.';
+comment on column crate_audit_actions.crate_name is 'Name of the crate that the action was performed on, which allows administrators to look up the log of deleted crates.';
+comment on column crate_audit_actions.version is 'Version number that the action was performed on, if the action is specific to a version.';
+comment on column crate_audit_actions.action is 'Type of the action that was performed (see `CrateAction` in `src/models/action.rs`).';
+comment on column crate_audit_actions.user_id is 'Reference to the user in the `users` table that performed the action, or `NULL` if it was performed by an administrator via the command line.';
+comment on column crate_audit_actions.api_token_id is 'Reference to the API token in the `api_tokens` table that was used to perform the action, if any.';
+comment on column crate_audit_actions.target_user_id is 'Reference to the user in the `users` table that the action was targeted at (e.g. the invited or removed owner), if any.';
+comment on column crate_audit_actions.target_team_id is 'Reference to the team in the `teams` table that the action was targeted at (e.g. the added or removed team owner), if any.';
+comment on column crate_audit_actions.details is 'Optional free-form details about the action.';
+comment on column crate_audit_actions.time is 'Date and time when the action was performed.';
@@ -0,0 +1,15 @@
+create or replace function ensure_crate_name_not_reserved() returns trigger as $$
+begin
+    if canon_crate_name(new.name) in (
+        select canon_crate_name(name) from reserved_crate_names
+    ) then
+        raise exception 'cannot upload crate with reserved name';
+    end if;
+    return new;
+end;
+$$ language plpgsql;
+
+delete from reserved_crate_names where expires_at is not null;
+
+alter table reserved_crate_names
+    drop column expires_at;
@@ -0,0 +1,16 @@
+alter table reserved_crate_names
+    add column expires_at timestamp;
+
+comment on column reserved_crate_names.expires_at is 'Date and time when the reservation expires, or `NULL` if the name is reserved permanently. Temporary reservations are used as a cooldown after a crate was deleted by its owners.';
+
+create or replace function ensure_crate_name_not_reserved() returns trigger as $$
+begin
+    if canon_crate_name(new.name) in (
+        select canon_crate_name(name) from reserved_crate_names
+        where expires_at is null or expires_at > now()
+    ) then
+        raise exception 'cannot upload crate with reserved name';
+    end if;
+    return new;
+end;
+$$ language plpgsql;
@@ -0,0 +1,7 @@
+alter table version_owner_actions
+    drop column message,
+    drop column advisory;
+
+alter table versions
+    drop column yank_message,
+    drop column yank_advisory;
@@ -0,0 +1,13 @@
+alter table versions
+    add column yank_message varchar,
+    add column yank_advisory varchar;
+
+comment on column versions.yank_message is 'Optional message explaining why the version was yanked. Cleared when the version is unyanked.';
+comment on column versions.yank_advisory is 'Optional ID of a security advisory (e.g. `RUSTSEC-2024-0001` or `GHSA-xxxx-xxxx-xxxx`) that the version was yanked for. Cleared when the version is unyanked.';
+
+alter table version_owner_actions
+    add column message varchar,
+    add column advisory varchar;
+
+comment on column version_owner_actions.message is 'Optional message that was given when the action was performed (e.g. the reason for a yank).';
+comment on column version_owner_actions.advisory is 'Optional ID of a security advisory that was given when the action was performed.';
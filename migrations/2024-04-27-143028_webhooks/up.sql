@@ -0,0 +1,67 @@
+create table webhooks
+(
+    id         serial
+        constraint webhooks_pk
+            primary key,
+    crate_id   integer
+        constraint webhooks_crates_id_fk
+            references crates
+            on delete cascade,
+    user_id    integer   not null
+        constraint webhooks_users_id_fk
+            references users
+            on delete cascade,
+    url        varchar   not null,
+    secret     varchar   not null,
+    events     text[]    not null,
+    created_at timestamp not null default now()
+);
+
+create index webhooks_crate_id_index
+    on webhooks (crate_id);
+
+create index webhooks_user_id_index
+    on webhooks (user_id);
+
+comment on table webhooks is 'URLs that are notified about events of a crate, or of all crates owned by a user.';
+comment on column webhooks.id is 'Unique identifier of the webhook.';
+comment on column webhooks.crate_id is 'Reference to the crate in the `crates` table. If this is `NULL`, the webhook is notified about events of all crates owned by `user_id`.';
+comment on column webhooks.user_id is 'Reference to the user in the `users` table that registered the webhook.';
+comment on column webhooks.url is 'URL that the event payloads are sent to via `POST` requests.';
+comment on column webhooks.secret is 'Shared secret that is used to sign the event payloads with HMAC-SHA256.';
+comment on column webhooks.events is 'Names of the events that the webhook is notified about (e.g. `publish` or `yank`).';
+comment on column webhooks.created_at is 'Date and time when the webhook was registered.';
+
+create table webhook_deliveries
+(
+    id              serial
+        constraint webhook_deliveries_pk
+            primary key,
+    webhook_id      integer   not null
+        constraint webhook_deliveries_webhooks_id_fk
+            references webhooks
+            on delete cascade,
+    event           varchar   not null,
+    payload         jsonb     not null,
+    attempts        integer   not null default 0,
+    response_status integer,
+    error           varchar,
+    created_at      timestamp not null default now(),
+    last_attempt_at timestamp,
+    delivered_at    timestamp
+);
+
+create index webhook_deliveries_webhook_id_index
+    on webhook_deliveries (webhook_id);
+
+comment on table webhook_deliveries is 'Delivery history of the events that were sent to webhooks.';
+comment on column webhook_deliveries.id is 'Unique identifier of the delivery. This is sent to the receiver in the `X-Crates-Io-Delivery` header.';
+comment on column webhook_deliveries.webhook_id is 'Reference to the webhook in the `webhooks` table.';
+comment on column webhook_deliveries.event is 'Name of the event (e.g. `publish` or `yank`).';
+comment on column webhook_deliveries.payload is 'JSON payload that is sent to the webhook URL.';
+comment on column webhook_deliveries.attempts is 'Number of delivery attempts so far.';
+comment on column webhook_deliveries.response_status is 'HTTP status code of the response to the last delivery attempt, if any response was received.';
+comment on column webhook_deliveries.error is 'Error message of the last delivery attempt, if it failed.';
+comment on column webhook_deliveries.created_at is 'Date and time when the event happened.';
+comment on column webhook_deliveries.last_attempt_at is 'Date and time of the last delivery attempt.';
+comment on column webhook_deliveries.delivered_at is 'Date and time when the event was successfully delivered.';
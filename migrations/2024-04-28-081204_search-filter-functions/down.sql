@@ -0,0 +1 @@
+drop function rust_version_parts(text);
@@ -0,0 +1,9 @@
+-- Parses a `rust-version` like `1.70` into a `[major, minor, patch]` array,
+-- which compares like the corresponding semver versions. Returns `NULL` for
+-- invalid values.
+create function rust_version_parts(text) returns bigint[] immutable parallel safe as $$
+    select (string_to_array($1, '.')::bigint[] || array[0, 0]::bigint[])[1:3]
+    where $1 ~ '^[0-9]{1,18}(\.[0-9]{1,18}){0,2}$'
+$$ language sql;
+
+comment on function rust_version_parts(text) is 'Parses a `rust-version` like `1.70` into a `[major, minor, patch]` array, or returns `NULL` for invalid values.';
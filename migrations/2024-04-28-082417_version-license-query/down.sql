@@ -0,0 +1,4 @@
+drop function license_matches(text, text[]);
+
+alter table versions
+    drop column license_query;
@@ -0,0 +1,16 @@
+-- The license expressions are parsed with the `spdx` crate on publish, like
+-- they are validated. Existing versions are backfilled with the
+-- `crates-admin update-license-queries` command.
+alter table versions
+    add column license_query text;
+
+comment on column versions.license_query is 'The SPDX license expression of the version as a `tsquery` over its license identifiers, as normalised by the `spdx` crate on publish. `NULL` if the license expression could not be parsed.';
+
+-- Checks whether a crate with the given `versions.license_query` can be used
+-- under the given licenses, by evaluating the query against a `tsvector` of
+-- the licenses.
+create function license_matches(query text, licenses text[]) returns boolean immutable strict parallel safe as $$
+    select $1::tsquery @@ array_to_tsvector($2)
+$$ language sql;
+
+comment on function license_matches(text, text[]) is 'Checks whether a crate with the given `versions.license_query` can be used under the given SPDX license identifiers.';
@@ -0,0 +1 @@
+drop table mirror_progress;
@@ -0,0 +1,13 @@
+create table mirror_progress
+(
+    upstream   varchar   not null
+        constraint mirror_progress_pk
+            primary key,
+    commit     varchar   not null,
+    updated_at timestamp not null default now()
+);
+
+comment on table mirror_progress is 'Progress of mirroring upstream registries, used to resume the mirror job incrementally.';
+comment on column mirror_progress.upstream is 'URL of the upstream git index.';
+comment on column mirror_progress.commit is 'The last upstream index commit that was mirrored completely.';
+comment on column mirror_progress.updated_at is 'Date and time when the progress was last updated.';
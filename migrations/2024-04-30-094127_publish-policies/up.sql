@@ -0,0 +1,30 @@
+create table publish_policies
+(
+    crate_id                   integer   not null
+        constraint publish_policies_pk
+            primary key
+        constraint publish_policies_crates_id_fk
+            references crates
+            on delete cascade,
+    allowed_users              integer[] not null default '{}',
+    allowed_teams              integer[] not null default '{}',
+    prerelease_users           integer[] not null default '{}',
+    prerelease_teams           integer[] not null default '{}',
+    require_token_expiry       boolean   not null default false,
+    require_trusted_publishing boolean   not null default false,
+    updated_by                 integer   not null
+        constraint publish_policies_users_id_fk
+            references users,
+    updated_at                 timestamp not null default now()
+);
+
+comment on table publish_policies is 'Restrictions that the owners of a crate have configured for publishing new versions of it.';
+comment on column publish_policies.crate_id is 'Reference to the crate in the `crates` table.';
+comment on column publish_policies.allowed_users is 'IDs of the users in the `users` table that may publish new versions. If both this and `allowed_teams` are empty, all owners may publish.';
+comment on column publish_policies.allowed_teams is 'IDs of the teams in the `teams` table whose members may publish new versions.';
+comment on column publish_policies.prerelease_users is 'IDs of the users in the `users` table that may publish prerelease versions. If both this and `prerelease_teams` are empty, prerelease versions are not restricted any further.';
+comment on column publish_policies.prerelease_teams is 'IDs of the teams in the `teams` table whose members may publish prerelease versions.';
+comment on column publish_policies.require_token_expiry is 'Whether new versions may only be published with API tokens that have an expiry date.';
+comment on column publish_policies.require_trusted_publishing is 'Whether new versions may only be published with API tokens that were minted via trusted publishing.';
+comment on column publish_policies.updated_by is 'Reference to the user in the `users` table that last changed the policy.';
+comment on column publish_policies.updated_at is 'Date and time when the policy was last changed.';
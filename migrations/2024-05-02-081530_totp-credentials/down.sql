@@ -0,0 +1 @@
+drop table totp_credentials;
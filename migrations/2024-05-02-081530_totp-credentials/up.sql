@@ -0,0 +1,22 @@
+create table totp_credentials
+(
+    user_id        integer   not null
+        constraint totp_credentials_pk
+            primary key
+        constraint totp_credentials_users_id_fk
+            references users
+            on delete cascade,
+    secret         bytea     not null,
+    created_at     timestamp not null default now(),
+    enabled_at     timestamp,
+    last_used_step bigint,
+    recovery_codes bytea[]   not null default '{}'
+);
+
+comment on table totp_credentials is 'TOTP secrets and recovery codes of users that enrolled in two-factor authentication.';
+comment on column totp_credentials.user_id is 'Reference to the user in the `users` table.';
+comment on column totp_credentials.secret is 'Shared secret for generating the time-based one-time passwords.';
+comment on column totp_credentials.created_at is 'Date and time when the enrollment was started.';
+comment on column totp_credentials.enabled_at is 'Date and time when the enrollment was confirmed with a valid code, or NULL if the enrollment is still pending.';
+comment on column totp_credentials.last_used_step is 'Time step of the last accepted code, which prevents codes from being used more than once.';
+comment on column totp_credentials.recovery_codes is 'SHA256 hashes of the unused recovery codes.';
@@ -0,0 +1,27 @@
+create table api_token_usages
+(
+    id           bigserial
+        constraint api_token_usages_pk
+            primary key,
+    api_token_id integer   not null
+        constraint api_token_usages_api_tokens_id_fk
+            references api_tokens
+            on delete cascade,
+    used_at      timestamp not null default now(),
+    endpoint     varchar   not null,
+    crate_name   varchar,
+    ip           inet,
+    rejection_reason varchar
+);
+
+comment on table api_token_usages is 'The most recent requests that were made with an API token, including the rejected ones, so that users can spot leaked tokens. Older entries are removed whenever a new one is added.';
+comment on column api_token_usages.id is 'Unique identifier of the entry.';
+comment on column api_token_usages.api_token_id is 'Reference to the API token in the `api_tokens` table that the request was made with.';
+comment on column api_token_usages.used_at is 'Date and time of the request.';
+comment on column api_token_usages.endpoint is 'HTTP method and route of the request, e.g. `PUT /api/v1/crates/new`.';
+comment on column api_token_usages.crate_name is 'Name of the crate that the request was made for, if any.';
+comment on column api_token_usages.ip is 'IP address of the client that sent the request.';
+comment on column api_token_usages.rejection_reason is 'Reason why the token was rejected for the request, e.g. because of a scope mismatch, or `NULL` if the request was authenticated.';
+
+create index api_token_usages_api_token_id_index
+    on api_token_usages (api_token_id, id desc);
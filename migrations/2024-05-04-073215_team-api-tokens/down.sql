@@ -0,0 +1,2 @@
+alter table version_owner_actions drop column team_id;
+alter table api_tokens drop column team_id;
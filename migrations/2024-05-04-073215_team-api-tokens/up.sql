@@ -0,0 +1,15 @@
+alter table api_tokens
+    add column team_id integer
+        constraint api_tokens_teams_id_fk
+            references teams
+            on delete cascade;
+
+comment on column api_tokens.team_id is 'Reference to the team in the `teams` table that owns the token, or NULL for tokens of the user in `user_id`. Team tokens can only be used for crates that the team owns, and `user_id` is the team member that created them.';
+
+alter table version_owner_actions
+    add column team_id integer
+        constraint version_owner_actions_teams_id_fk
+            references teams
+            on delete set null;
+
+comment on column version_owner_actions.team_id is 'Reference to the team in the `teams` table, if the action was performed with an API token owned by the team.';
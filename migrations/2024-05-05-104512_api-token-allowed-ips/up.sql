@@ -0,0 +1,4 @@
+alter table api_tokens
+    add column allowed_ips cidr[];
+
+comment on column api_tokens.allowed_ips is 'NULL or an array of CIDR blocks that requests authenticated with the token have to come from.';
@@ -0,0 +1,31 @@
+create table typosquat_findings
+(
+    id               serial    not null
+        constraint typosquat_findings_pk
+            primary key,
+    flagged_crate_id integer
+        constraint typosquat_findings_crates_id_fk
+            references crates
+            on delete set null,
+    crate_name       varchar   not null,
+    similar_crate    varchar   not null,
+    reason           varchar   not null,
+    status           integer   not null default 0,
+    created_at       timestamp not null default now(),
+    reviewed_at      timestamp,
+    constraint typosquat_findings_crate_similar_crate_reason_uindex
+        unique (flagged_crate_id, similar_crate, reason)
+);
+
+create index typosquat_findings_status_index
+    on typosquat_findings (status);
+
+comment on table typosquat_findings is 'Potential typosquatting of popular crates that was found when new crates were published, so that they can be reviewed by the crates.io team.';
+comment on column typosquat_findings.id is 'Unique identifier of the finding.';
+comment on column typosquat_findings.flagged_crate_id is 'Reference to the flagged crate in the `crates` table. Set to NULL when the crate is deleted, so that the finding stays on record.';
+comment on column typosquat_findings.crate_name is 'Name of the flagged crate, which is kept after the crate has been deleted.';
+comment on column typosquat_findings.similar_crate is 'Name of the popular crate that the flagged crate may be typosquatting.';
+comment on column typosquat_findings.reason is 'Description of the check that flagged the crate.';
+comment on column typosquat_findings.status is 'Review status of the finding: 0 = open, 1 = dismissed, 2 = confirmed.';
+comment on column typosquat_findings.created_at is 'Date and time when the crate was flagged.';
+comment on column typosquat_findings.reviewed_at is 'Date and time when the finding was dismissed or confirmed. NULL while the finding is open.';
@@ -0,0 +1,4 @@
+alter table api_tokens
+    add column rotated_at timestamp;
+
+comment on column api_tokens.rotated_at is 'Date and time when the token was replaced by a new token. Its `expired_at` has been shortened to a grace period at the same time, so it can not be rotated again.';
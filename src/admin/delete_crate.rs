@@ -1,3 +1,4 @@
+use crate::models::{CrateAction, NewCrateAuditAction};
 use crate::schema::{crate_owners, teams, users};
 use crate::storage::Storage;
 use crate::worker::jobs;
@@ -17,11 +18,11 @@ use std::collections::HashMap;
 pub struct Opts {
     /// Names of the crates
     #[arg(value_name = "NAME", required = true)]
-    crate_names: Vec<String>,
+    pub(crate) crate_names: Vec<String>,
 
     /// Don't ask for confirmation: yes, we are sure. Best for scripting.
     #[arg(short, long)]
-    yes: bool,
+    pub(crate) yes: bool,
 }
 
 pub fn run(opts: Opts) -> anyhow::Result<()> {
@@ -83,6 +84,11 @@ pub fn run(opts: Opts) -> anyhow::Result<()> {
             info!(%name, "Deleting crate from the database");
             if let Err(error) = diesel::delete(crates::table.find(id)).execute(conn) {
                 warn!(%name, %id, ?error, "Failed to delete crate from the database");
+            } else {
+                let action = NewCrateAuditAction::new(*id, name, CrateAction::DeleteCrate);
+                if let Err(error) = action.insert(conn) {
+                    warn!(%name, ?error, "Failed to record crate deletion in the audit log");
+                }
             }
         } else {
             info!(%name, "Skipping missing crate");
@@ -1,4 +1,4 @@
-use crate::models::update_default_version;
+use crate::models::{update_default_version, CrateAction, NewCrateAuditAction};
 use crate::schema::crates;
 use crate::storage::Storage;
 use crate::worker::jobs;
@@ -72,6 +72,13 @@ pub fn run(opts: Opts) -> anyhow::Result<()> {
             }
         }
 
+        info!(%crate_name, "Recording deleted versions in the audit log");
+        for version in &opts.versions {
+            NewCrateAuditAction::new(crate_id, crate_name, CrateAction::DeleteVersion)
+                .version(version)
+                .insert(conn)?;
+        }
+
         info!(%crate_name, %crate_id, "Updating default version in the database");
         if let Err(error) = update_default_version(crate_id, conn) {
             warn!(%crate_name, %crate_id, ?error, "Failed to update default version");
@@ -21,9 +21,20 @@ pub enum Command {
         database_url: SecretString,
         #[arg(default_value = "db-dump.tar.gz")]
         target_name: String,
+        /// Prefix of the Parquet files of the public tables.
+        #[arg(long, default_value = "db-dump-parquet")]
+        parquet_prefix: String,
+    },
+    IncrementalDumpDb {
+        #[arg(env = "READ_ONLY_REPLICA_URL")]
+        database_url: SecretString,
+        #[arg(default_value = "db-dump-incremental")]
+        target_prefix: String,
     },
     DailyDbMaintenance,
+    PruneTokenUsages,
     SquashIndex,
+    SignIndexSnapshot,
     NormalizeIndex {
         #[arg(long = "dry-run")]
         dry_run: bool,
@@ -66,8 +77,18 @@ pub fn run(command: Command) -> Result<()> {
         Command::DumpDb {
             database_url,
             target_name,
+            parquet_prefix,
+        } => {
+            jobs::DumpDb::new(database_url.expose_secret(), target_name)
+                .with_parquet_prefix(parquet_prefix)
+                .enqueue(conn)?;
+        }
+        Command::IncrementalDumpDb {
+            database_url,
+            target_prefix,
         } => {
-            jobs::DumpDb::new(database_url.expose_secret(), target_name).enqueue(conn)?;
+            jobs::IncrementalDumpDb::new(database_url.expose_secret(), target_prefix)
+                .enqueue(conn)?;
         }
         Command::SyncAdmins { force } => {
             if !force {
@@ -94,12 +115,18 @@ pub fn run(command: Command) -> Result<()> {
         Command::DailyDbMaintenance => {
             jobs::DailyDbMaintenance.enqueue(conn)?;
         }
+        Command::PruneTokenUsages => {
+            jobs::PruneTokenUsages.enqueue(conn)?;
+        }
         Command::ProcessCdnLogQueue(job) => {
             job.enqueue(conn)?;
         }
         Command::SquashIndex => {
             jobs::SquashIndex.enqueue(conn)?;
         }
+        Command::SignIndexSnapshot => {
+            jobs::SignIndexSnapshot.enqueue(conn)?;
+        }
         Command::NormalizeIndex { dry_run } => {
             jobs::NormalizeIndex::new(dry_run).enqueue(conn)?;
         }
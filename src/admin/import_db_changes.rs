@@ -0,0 +1,49 @@
+use crate::worker::jobs::dump_db;
+use anyhow::{anyhow, Context};
+use secrecy::{ExposeSecret, SecretString};
+use std::fs::{self, File};
+use std::path::PathBuf;
+
+#[derive(clap::Parser, Debug)]
+#[command(
+    name = "import-db-changes",
+    about = "Apply an incremental database dump to a database that was restored from a full dump."
+)]
+pub struct Opts {
+    /// Path to the tarball of the incremental dump, or to the directory it
+    /// was extracted to.
+    path: PathBuf,
+
+    #[arg(long, env = "DATABASE_URL", hide_env_values = true)]
+    database_url: SecretString,
+}
+
+pub fn run(opts: Opts) -> anyhow::Result<()> {
+    let database_url = opts.database_url.expose_secret();
+
+    if opts.path.is_dir() {
+        return dump_db::import_incremental(&opts.path, database_url);
+    }
+
+    let tempdir = tempfile::Builder::new()
+        .prefix("import-db-changes")
+        .tempdir()
+        .context("Failed to create temporary directory")?;
+
+    println!("Extracting {}", opts.path.display());
+    let tarball = File::open(&opts.path)
+        .with_context(|| format!("Failed to open {}", opts.path.display()))?;
+    let decoder = flate2::read::GzDecoder::new(tarball);
+    tar::Archive::new(decoder)
+        .unpack(tempdir.path())
+        .context("Failed to extract tarball")?;
+
+    // The tarball contains a single directory named after the dump timestamp
+    let directory = fs::read_dir(tempdir.path())?
+        .next()
+        .ok_or_else(|| anyhow!("The tarball is empty"))??
+        .path();
+
+    println!("Applying changes from {}", directory.display());
+    dump_db::import_incremental(&directory, database_url)
+}
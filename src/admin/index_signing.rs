@@ -0,0 +1,91 @@
+use crate::storage::Storage;
+use anyhow::Context;
+use chrono::{Duration, Utc};
+use crates_io_index::signing::{Root, Signed, SigningKey, VerifyingKey, ROOT_PATH};
+use secrecy::{ExposeSecret, SecretString};
+use std::path::PathBuf;
+
+#[derive(clap::Parser, Debug)]
+#[command(
+    name = "index-signing",
+    about = "Manage the keys that sign the metadata files of the sparse index",
+    rename_all = "kebab-case"
+)]
+pub enum Command {
+    /// Generate a new signing key and print its seed and public key.
+    GenerateKey,
+    /// Sign a root metadata file with the offline root key and print it.
+    ///
+    /// This is meant to be run on the machine that holds the root key.
+    SignRoot {
+        /// The base64 encoded seed of the offline root key
+        #[arg(long, env = "INDEX_ROOT_KEY", hide_env_values = true)]
+        root_key: SecretString,
+        /// Version of the root file, which must be larger than the previous one
+        #[arg(long)]
+        version: u32,
+        /// Number of days until the root file expires
+        #[arg(long, default_value_t = 365)]
+        expires_in_days: i64,
+        /// The base64 encoded public keys that are trusted to sign the other
+        /// metadata files
+        #[arg(long = "key", required = true)]
+        keys: Vec<String>,
+    },
+    /// Upload a root metadata file, that was created by `sign-root`.
+    UploadRoot {
+        /// Path to the signed root file
+        path: PathBuf,
+    },
+}
+
+pub fn run(command: Command) -> anyhow::Result<()> {
+    match command {
+        Command::GenerateKey => {
+            let (key, seed) = SigningKey::generate()?;
+            println!("seed: {seed}");
+            println!("public key: {}", key.verifying_key().to_base64());
+        }
+        Command::SignRoot {
+            root_key,
+            version,
+            expires_in_days,
+            keys,
+        } => {
+            let root_key = SigningKey::from_base64(root_key.expose_secret())?;
+
+            let keys = keys
+                .iter()
+                .map(|key| VerifyingKey::from_base64(key))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+
+            let expires = Utc::now() + Duration::days(expires_in_days);
+            let root = Root::new(version, expires, keys);
+            let signed = root_key.sign(root)?;
+            println!("{}", serde_json::to_string_pretty(&signed)?);
+        }
+        Command::UploadRoot { path } => {
+            let content = std::fs::read(&path)
+                .with_context(|| format!("Failed to read `{}`", path.display()))?;
+
+            let root: Signed<Root> =
+                serde_json::from_slice(&content).context("Failed to parse root file")?;
+            println!(
+                "Uploading version {} of the root file with {} keys",
+                root.signed.version,
+                root.signed.keys.len()
+            );
+
+            let storage = Storage::from_environment();
+
+            let rt = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .context("Failed to initialize tokio runtime")?;
+
+            rt.block_on(storage.upload_index_metadata(ROOT_PATH, content.into()))?;
+        }
+    }
+
+    Ok(())
+}
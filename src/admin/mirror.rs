@@ -0,0 +1,100 @@
+use crate::db;
+use crate::storage::Storage;
+use crate::worker::jobs::{self, load_mirror_progress, mirror_upstream, save_mirror_progress};
+use anyhow::Context;
+use crates_io_env_vars::var;
+use crates_io_index::signing::SigningKey;
+use crates_io_worker::BackgroundJob;
+use std::path::PathBuf;
+
+#[derive(clap::Parser, Debug)]
+#[command(
+    name = "mirror",
+    about = "Mirror the crate files and index files of an upstream registry."
+)]
+pub struct Opts {
+    /// URL of the upstream git index, e.g. `https://github.com/rust-lang/crates.io-index`.
+    index_url: String,
+
+    /// Mirror all index files, instead of only the ones that were modified
+    /// since the last mirrored commit.
+    #[arg(long)]
+    full: bool,
+
+    /// Directory in which the checkout of the upstream index is kept between
+    /// runs. The upstream index is cloned from scratch if this is not set.
+    #[arg(long, env = "MIRROR_CHECKOUT_DIR")]
+    checkout_dir: Option<PathBuf>,
+
+    /// Read crate files from `file://` download URLs of the upstream index,
+    /// e.g. to mirror from an offline copy of the upstream registry.
+    #[arg(long, env = "MIRROR_ALLOW_FILE_URLS")]
+    allow_file_urls: bool,
+
+    /// Run the mirror as a background job instead of in this process.
+    #[arg(long, conflicts_with = "full")]
+    enqueue: bool,
+}
+
+pub fn run(opts: Opts) -> anyhow::Result<()> {
+    let conn = &mut db::oneoff_connection().context("Failed to establish database connection")?;
+
+    if opts.enqueue {
+        jobs::MirrorUpstream::new(&opts.index_url).enqueue(conn)?;
+        println!("Enqueued {} background job", jobs::MirrorUpstream::JOB_NAME);
+        return Ok(());
+    }
+
+    let since = match opts.full {
+        true => None,
+        false => load_mirror_progress(&opts.index_url, conn)
+            .context("Failed to load the mirror progress")?,
+    };
+
+    match &since {
+        Some(commit) => println!("Mirroring changes since commit {commit}"),
+        None => println!("Mirroring all index files"),
+    }
+
+    let signing_key = var("INDEX_SIGNING_KEY")?
+        .map(|seed| SigningKey::from_base64(&seed))
+        .transpose()?;
+
+    let storage = Storage::from_environment();
+
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("Failed to initialize tokio runtime")?;
+
+    let future = mirror_upstream(
+        &storage,
+        signing_key.as_ref(),
+        opts.checkout_dir.as_deref(),
+        opts.allow_file_urls,
+        &opts.index_url,
+        since,
+    );
+    let report = rt.block_on(future)?;
+
+    for name in &report.failed {
+        println!("failed: {name}");
+    }
+
+    println!(
+        "Mirrored commit {}: {} index files, {} crate files, {} failed",
+        report.commit,
+        report.index_files,
+        report.crate_files,
+        report.failed.len()
+    );
+
+    if !report.failed.is_empty() {
+        anyhow::bail!("Mirroring failed, the progress was not saved");
+    }
+
+    save_mirror_progress(&opts.index_url, &report.commit, conn)
+        .context("Failed to save the mirror progress")?;
+
+    Ok(())
+}
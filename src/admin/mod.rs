@@ -3,13 +3,20 @@ pub mod delete_version;
 pub mod dialoguer;
 pub mod enqueue_job;
 pub mod git_import;
+pub mod import_db_changes;
+pub mod index_signing;
 pub mod migrate;
+pub mod mirror;
 pub mod on_call;
 pub mod populate;
 pub mod render_readmes;
+pub mod reset_two_factor;
 pub mod test_pagerduty;
 pub mod transfer_crates;
+pub mod typosquat;
 pub mod update_default_versions;
+pub mod update_license_queries;
 pub mod upload_index;
+pub mod verify_storage;
 pub mod verify_token;
 pub mod yank_version;
@@ -0,0 +1,51 @@
+use crate::admin::dialoguer;
+use crate::db;
+use crate::models::{TotpCredential, User};
+use anyhow::Context;
+
+#[derive(clap::Parser, Debug)]
+#[command(
+    name = "reset-two-factor",
+    about = "Disable two-factor authentication for a user.",
+    long_about = "Disable two-factor authentication for a user. Used by staff to restore \
+        access for users that lost both their authenticator app and their recovery codes, \
+        after verifying their identity through other means."
+)]
+pub struct Opts {
+    /// GitHub login of the user
+    login: String,
+
+    /// Don't ask for confirmation: yes, we are sure. Best for scripting.
+    #[arg(short, long)]
+    yes: bool,
+}
+
+pub fn run(opts: Opts) -> anyhow::Result<()> {
+    let conn = &mut db::oneoff_connection()?;
+
+    let user = User::find_by_login(conn, &opts.login)
+        .with_context(|| format!("Failed to find user `{}`", opts.login))?;
+
+    if TotpCredential::find(conn, user.id)?.is_none() {
+        println!(
+            "User {} has not enrolled in two-factor authentication",
+            user.gh_login
+        );
+        return Ok(());
+    }
+
+    if !opts.yes {
+        let prompt = format!(
+            "Are you sure you want to disable two-factor authentication for {} ({})?",
+            user.gh_login, user.id
+        );
+        if !dialoguer::confirm(&prompt) {
+            return Ok(());
+        }
+    }
+
+    TotpCredential::delete(conn, user.id)?;
+    info!(user = %user.gh_login, "Two-factor authentication has been disabled");
+
+    Ok(())
+}
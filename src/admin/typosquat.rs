@@ -0,0 +1,95 @@
+use crate::admin::{delete_crate, dialoguer};
+use crate::db;
+use crate::models::{TyposquatFinding, TyposquatStatus};
+use anyhow::Context;
+
+#[derive(clap::Parser, Debug)]
+#[command(
+    name = "typosquat",
+    about = "Review the crates that were flagged as potential typosquatting",
+    rename_all = "kebab-case"
+)]
+pub enum Command {
+    /// List the findings that have not been reviewed yet.
+    List {
+        /// Also list the findings that have already been reviewed
+        #[arg(long)]
+        all: bool,
+    },
+    /// Dismiss the open findings of a crate as false positives.
+    Dismiss {
+        /// Name of the flagged crate
+        name: String,
+    },
+    /// Confirm the open findings of a crate, and optionally delete the crate.
+    Confirm {
+        /// Name of the flagged crate
+        name: String,
+        /// Also delete the crate, like the `delete-crate` command does.
+        /// The findings of the crate are kept.
+        #[arg(long)]
+        delete: bool,
+        /// Don't ask for confirmation: yes, we are sure. Best for scripting.
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+pub fn run(command: Command) -> anyhow::Result<()> {
+    let conn = &mut db::oneoff_connection().context("Failed to establish database connection")?;
+
+    match command {
+        Command::List { all } => {
+            let status = (!all).then_some(TyposquatStatus::Open);
+            let findings = TyposquatFinding::list(conn, status)?;
+            if findings.is_empty() {
+                println!("No findings to review");
+            }
+
+            for finding in findings {
+                let deleted = match finding.flagged_crate_id {
+                    Some(_) => "",
+                    None => " (deleted)",
+                };
+
+                println!(
+                    "{} {}{deleted} {:?}: {} ({})",
+                    finding.created_at.format("%Y-%m-%d %H:%M"),
+                    finding.crate_name,
+                    finding.status,
+                    finding.reason,
+                    finding.similar_crate,
+                );
+            }
+        }
+        Command::Dismiss { name } => {
+            let count = TyposquatFinding::review(conn, &name, TyposquatStatus::Dismissed)?;
+            println!("Dismissed {count} findings of {name}");
+        }
+        Command::Confirm { name, delete, yes } => {
+            if !yes {
+                let prompt = if delete {
+                    format!("Are you sure you want to delete {name} and confirm its findings?")
+                } else {
+                    format!("Are you sure you want to confirm the findings of {name}?")
+                };
+                if !dialoguer::confirm(&prompt) {
+                    return Ok(());
+                }
+            }
+
+            let count = TyposquatFinding::review(conn, &name, TyposquatStatus::Confirmed)?;
+            println!("Confirmed {count} findings of {name}");
+
+            if delete {
+                let opts = delete_crate::Opts {
+                    crate_names: vec![name],
+                    yes: true,
+                };
+                delete_crate::run(opts)?;
+            }
+        }
+    }
+
+    Ok(())
+}
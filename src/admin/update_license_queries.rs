@@ -0,0 +1,43 @@
+use crate::licenses::{license_query, parse_license_expr};
+use crate::{db, schema::versions};
+use anyhow::Context;
+use diesel::prelude::*;
+use indicatif::{ProgressBar, ProgressIterator, ProgressStyle};
+
+#[derive(clap::Parser, Debug)]
+#[clap(
+    name = "update-license-queries",
+    about = "Iterates over every version ever uploaded and updates its `license_query` column."
+)]
+pub struct Opts;
+
+pub fn run(_opts: Opts) -> anyhow::Result<()> {
+    let mut conn = db::oneoff_connection().context("Failed to connect to the database")?;
+
+    let versions: Vec<(i32, String)> = versions::table
+        .select((versions::id, versions::license.assume_not_null()))
+        .filter(versions::license.is_not_null())
+        .load(&mut conn)
+        .context("Failed to load versions")?;
+
+    let pb = ProgressBar::new(versions.len() as u64);
+    pb.set_style(ProgressStyle::with_template(
+        "{bar:60} ({pos}/{len}, ETA {eta})",
+    )?);
+
+    for (version_id, license) in versions.into_iter().progress_with(pb.clone()) {
+        let query = parse_license_expr(&license)
+            .ok()
+            .map(|expression| license_query(&expression));
+
+        let result = diesel::update(versions::table.find(version_id))
+            .set(versions::license_query.eq(query))
+            .execute(&mut conn);
+
+        if let Err(error) = result {
+            pb.suspend(|| warn!(%version_id, %error, "Failed to update the license query"));
+        }
+    }
+
+    Ok(())
+}
@@ -10,7 +10,8 @@ use indicatif::{ProgressBar, ProgressIterator, ProgressStyle};
     about = "Upload index from git to S3 (http-based index)"
 )]
 pub struct Opts {
-    /// Incremental commit. Any changed files made after this commit will be uploaded.
+    /// Incremental commit. Any changed files made after this commit will be uploaded,
+    /// and any files deleted after this commit will be deleted.
     incremental_commit: Option<String>,
 }
 
@@ -50,9 +51,15 @@ pub fn run(opts: Opts) -> anyhow::Result<()> {
             anyhow!("Failed to convert file name to utf8: {file_name}",)
         })?;
 
+        if Repository::relative_index_file(crate_name) != *file {
+            pb.suspend(|| println!("skipping file `{crate_name}`"));
+            continue;
+        }
+
         let path = repo.index_file(crate_name);
         if !path.exists() {
-            pb.suspend(|| println!("skipping file `{crate_name}`"));
+            pb.suspend(|| println!("deleting file `{crate_name}`"));
+            rt.block_on(storage.sync_index(crate_name, None))?;
             continue;
         }
 
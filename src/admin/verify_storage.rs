@@ -0,0 +1,98 @@
+use crate::cloudfront::CloudFront;
+use crate::db;
+use crate::fastly::Fastly;
+use crate::storage::Storage;
+use crate::worker::jobs::{self, load_crate_files, verify_crate_files, Cdns};
+use anyhow::Context;
+use crates_io_worker::BackgroundJob;
+
+#[derive(clap::Parser, Debug)]
+#[command(
+    name = "verify-storage",
+    about = "Verify the stored crate files against the checksums in the database."
+)]
+pub struct Opts {
+    /// Name of the crate to verify. Verifies the whole store if omitted.
+    crate_name: Option<String>,
+
+    /// Move crate files with a mismatching checksum to the `quarantine/` prefix.
+    #[arg(long)]
+    quarantine: bool,
+
+    /// Run the verification as a background job instead of in this process.
+    #[arg(long)]
+    enqueue: bool,
+}
+
+pub fn run(opts: Opts) -> anyhow::Result<()> {
+    let conn = &mut db::oneoff_connection().context("Failed to establish database connection")?;
+
+    if opts.enqueue {
+        jobs::VerifyCrateFiles::new(opts.crate_name, opts.quarantine).enqueue(conn)?;
+        println!(
+            "Enqueued {} background job",
+            jobs::VerifyCrateFiles::JOB_NAME
+        );
+        return Ok(());
+    }
+
+    let crate_files = load_crate_files(opts.crate_name.as_deref(), conn)
+        .context("Failed to load crate checksums from the database")?;
+
+    if let Some(crate_name) = &opts.crate_name {
+        if crate_files.is_empty() {
+            anyhow::bail!("No versions found for crate `{crate_name}`");
+        }
+    }
+
+    println!("Verifying {} crate files", crate_files.len());
+
+    let storage = Storage::from_environment();
+    let cloudfront = CloudFront::from_environment();
+    let fastly = Fastly::from_environment(reqwest::Client::new());
+    let cdns = Cdns {
+        cloudfront: cloudfront.as_ref(),
+        fastly: fastly.as_ref(),
+    };
+
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("Failed to initialize tokio runtime")?;
+
+    let report = rt.block_on(verify_crate_files(
+        &storage,
+        &cdns,
+        crate_files,
+        opts.quarantine,
+    ));
+
+    for crate_file in &report.missing {
+        println!("missing: {crate_file}");
+    }
+    for crate_file in &report.mismatched {
+        println!("checksum mismatch: {crate_file}");
+    }
+    for crate_file in &report.failed {
+        println!("failed: {crate_file}");
+    }
+
+    println!(
+        "Checked {} crate files: {} missing, {} mismatched{}, {} failed",
+        report.checked,
+        report.missing.len(),
+        report.mismatched.len(),
+        if opts.quarantine && !report.mismatched.is_empty() {
+            " (quarantined)"
+        } else {
+            ""
+        },
+        report.failed.len()
+    );
+
+    if !report.missing.is_empty() || !report.mismatched.is_empty() || !report.failed.is_empty() {
+        anyhow::bail!("Storage verification failed");
+    }
+
+    Ok(())
+}
@@ -4,19 +4,28 @@ use crate::config;
 use crate::db::{connection_url, ConnectionConfig};
 use std::ops::Deref;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use crate::email::Emails;
 use crate::metrics::{InstanceMetrics, ServiceMetrics};
 use crate::rate_limiter::RateLimiter;
 use crate::storage::Storage;
+use crate::typosquat;
+use crate::util::jwks::JwksCache;
 use axum::extract::{FromRef, FromRequestParts, State};
 use crates_io_github::GitHubClient;
 use deadpool_diesel::postgres::{Manager as DeadpoolManager, Pool as DeadpoolPool};
 use deadpool_diesel::Runtime;
+use diesel::PgConnection;
 use oauth2::basic::BasicClient;
+use parking_lot::Mutex;
 
 type DeadpoolResult = Result<deadpool_diesel::postgres::Connection, deadpool_diesel::PoolError>;
 
+/// Time after which the typosquatting cache is rebuilt, so that crates
+/// which became popular in the meantime are picked up.
+const TYPOSQUAT_CACHE_LIFETIME: Duration = Duration::from_secs(24 * 60 * 60); // 1 day
+
 /// The `App` struct holds the main components of the application like
 /// the database connection pool and configurations
 pub struct App {
@@ -49,6 +58,15 @@ pub struct App {
 
     /// Rate limit select actions.
     pub rate_limiter: RateLimiter,
+
+    /// A lazily initialised cache of the most popular crates, which is used
+    /// to warn about potential typosquatting when new crates are published,
+    /// and the time when it was built.
+    typosquat_cache: Mutex<Option<(Instant, Arc<typosquat::Cache>)>>,
+
+    /// Cache of the public keys of the OIDC issuers used for trusted
+    /// publishing
+    pub jwks_cache: JwksCache,
 }
 
 impl App {
@@ -128,10 +146,39 @@ impl App {
             service_metrics: ServiceMetrics::new().expect("could not initialize service metrics"),
             instance_metrics,
             rate_limiter: RateLimiter::new(config.rate_limiter.clone()),
+            typosquat_cache: Mutex::new(None),
+            jwks_cache: JwksCache::default(),
             config: Arc::new(config),
         }
     }
 
+    /// Returns the typosquatting cache, building it if required.
+    ///
+    /// The popular crates are queried again once the cache is older than a
+    /// day. Unlike in the background worker, the
+    /// cache does not send any notifications. Errors are not cached, so the
+    /// initialisation is retried on the next publish.
+    ///
+    /// The lock is not held while the cache is built, so concurrent publishes
+    /// might build it more than once, but don't have to wait for each other.
+    pub fn typosquat_cache(
+        &self,
+        conn: &mut PgConnection,
+    ) -> Result<Arc<typosquat::Cache>, typosquat::CacheError> {
+        if let Some((built_at, cache)) = &*self.typosquat_cache.lock() {
+            if built_at.elapsed() < TYPOSQUAT_CACHE_LIFETIME {
+                return Ok(cache.clone());
+            }
+        }
+
+        let config = typosquat::Config::from_env()
+            .map_err(|e| typosquat::CacheError::Config(Arc::new(e)))?;
+        let cache = Arc::new(typosquat::Cache::new(vec![], &config, conn)?);
+        *self.typosquat_cache.lock() = Some((Instant::now(), cache.clone()));
+
+        Ok(cache)
+    }
+
     /// A unique key to generate signed cookies
     pub fn session_key(&self) -> &cookie::Key {
         &self.config.session_key
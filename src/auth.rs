@@ -1,21 +1,43 @@
+use crate::app::{App, AppState};
 use crate::controllers;
 use crate::controllers::util::RequestPartsExt;
+use crate::middleware::app::RequestApp;
 use crate::middleware::log_request::RequestLogExt;
+use crate::middleware::real_ip::RealIp;
 use crate::middleware::session::RequestSession;
+use crate::middleware::token_usage::{TokenUsage, TokenUsageLog};
 use crate::models::token::{CrateScope, EndpointScope};
-use crate::models::{ApiToken, User};
+use crate::models::{ApiToken, Crate, Owner, Rights, TotpCredential, User};
 use crate::util::errors::{
-    account_locked, forbidden, internal, AppResult, InsecurelyGeneratedTokenRevoked,
+    account_locked, custom, forbidden, internal, AppResult, BoxedAppError,
+    InsecurelyGeneratedTokenRevoked,
 };
+use axum::extract::MatchedPath;
 use chrono::Utc;
-use diesel::PgConnection;
-use http::header;
+use diesel::prelude::*;
+use http::request::Parts;
+use http::{header, StatusCode};
+use ipnetwork::IpNetwork;
+
+/// Session key of the unix timestamp when the user last confirmed their
+/// identity with a two-factor authentication code.
+pub const TWO_FACTOR_SESSION_KEY: &str = "two_factor_verified_at";
+
+/// Error message for requests with a team token that are not about a crate
+/// that the team owns.
+const TEAM_TOKEN_ERROR_MESSAGE: &str =
+    "this token is owned by a team and can only be used for crates that the team owns";
+
+/// Number of seconds after a two-factor verification during which actions
+/// that require two-factor authentication can be performed.
+const TWO_FACTOR_SESSION_LIFETIME: i64 = 15 * 60;
 
 #[derive(Debug, Clone)]
 pub struct AuthCheck {
     allow_token: bool,
     endpoint_scope: Option<EndpointScope>,
     crate_name: Option<String>,
+    require_two_factor: bool,
 }
 
 impl AuthCheck {
@@ -27,6 +49,7 @@ impl AuthCheck {
             allow_token: true,
             endpoint_scope: None,
             crate_name: None,
+            require_two_factor: false,
         }
     }
 
@@ -36,6 +59,7 @@ impl AuthCheck {
             allow_token: false,
             endpoint_scope: None,
             crate_name: None,
+            require_two_factor: false,
         }
     }
 
@@ -44,6 +68,7 @@ impl AuthCheck {
             allow_token: self.allow_token,
             endpoint_scope: Some(endpoint_scope),
             crate_name: self.crate_name.clone(),
+            require_two_factor: self.require_two_factor,
         }
     }
 
@@ -52,6 +77,20 @@ impl AuthCheck {
             allow_token: self.allow_token,
             endpoint_scope: self.endpoint_scope,
             crate_name: Some(crate_name.to_string()),
+            require_two_factor: self.require_two_factor,
+        }
+    }
+
+    /// Requires users that enabled two-factor authentication to have
+    /// confirmed their identity with a code recently, if the request is
+    /// authenticated via cookie and the `two_factor_step_up` option is
+    /// enabled.
+    pub fn with_two_factor(&self) -> Self {
+        Self {
+            allow_token: self.allow_token,
+            endpoint_scope: self.endpoint_scope,
+            crate_name: self.crate_name.clone(),
+            require_two_factor: true,
         }
     }
 
@@ -64,36 +103,128 @@ impl AuthCheck {
         let auth = authenticate(request, conn)?;
 
         if let Some(token) = auth.api_token() {
-            if !self.allow_token {
-                let error_message =
-                    "API Token authentication was explicitly disallowed for this API";
-                request.request_log().add("cause", error_message);
-
-                return Err(forbidden(
-                    "this action can only be performed on the crates.io website",
-                ));
+            let result = self.check_token(request, conn, token);
+            let rejection_reason = result.as_ref().err().map(|(cause, _)| *cause);
+            note_token_usage(
+                request,
+                token.id,
+                self.crate_name.as_deref(),
+                rejection_reason,
+            );
+
+            if let Err((cause, error)) = result {
+                request.request_log().add("cause", cause);
+                return Err(error);
             }
+        }
 
-            if !self.endpoint_scope_matches(token.endpoint_scopes.as_ref()) {
-                let error_message = "Endpoint scope mismatch";
+        let step_up = self.require_two_factor && request.app().config.two_factor_step_up;
+        if step_up && auth.api_token().is_none() {
+            let user_id = auth.user_id();
+            if TotpCredential::is_enabled(conn, user_id)? && !is_two_factor_verified(request) {
+                let error_message = "Two-factor verification missing or expired";
                 request.request_log().add("cause", error_message);
 
                 return Err(forbidden(
-                    "this token does not have the required permissions to perform this action",
+                    "this action requires two-factor authentication. Please confirm your identity with a code from your authenticator app and try again.",
                 ));
             }
+        }
 
-            if !self.crate_scope_matches(token.crate_scopes.as_ref()) {
-                let error_message = "Crate scope mismatch";
-                request.request_log().add("cause", error_message);
+        Ok(auth)
+    }
 
-                return Err(forbidden(
+    /// Checks whether the token may be used for the request, and returns the
+    /// internal cause and the error response otherwise.
+    fn check_token<T: RequestPartsExt>(
+        &self,
+        request: &T,
+        conn: &mut PgConnection,
+        token: &ApiToken,
+    ) -> Result<(), (&'static str, BoxedAppError)> {
+        if !self.allow_token {
+            return Err((
+                "API Token authentication was explicitly disallowed for this API",
+                forbidden("this action can only be performed on the crates.io website"),
+            ));
+        }
+
+        if !self.endpoint_scope_matches(token.endpoint_scopes.as_ref()) {
+            return Err((
+                "Endpoint scope mismatch",
+                forbidden(
                     "this token does not have the required permissions to perform this action",
-                ));
-            }
+                ),
+            ));
         }
 
-        Ok(auth)
+        if !self.crate_scope_matches(token.crate_scopes.as_ref()) {
+            return Err((
+                "Crate scope mismatch",
+                forbidden(
+                    "this token does not have the required permissions to perform this action",
+                ),
+            ));
+        }
+
+        if !is_ip_allowed(request, token.allowed_ips.as_ref()) {
+            return Err((
+                "Client IP outside of the allowed CIDR blocks",
+                forbidden("this token can not be used from your IP address"),
+            ));
+        }
+
+        if let Some(team_id) = token.team_id {
+            self.check_team_token(conn, team_id)?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks whether a token that is owned by the given team may be used for
+    /// the request, which is only the case for crates that the team owns.
+    fn check_team_token(
+        &self,
+        conn: &mut PgConnection,
+        team_id: i32,
+    ) -> Result<(), (&'static str, BoxedAppError)> {
+        // A new crate is only owned by the user that published it, so the
+        // team would lose access to it right away.
+        if self.endpoint_scope == Some(EndpointScope::PublishNew) {
+            return Err((
+                "Team token used to publish a new crate",
+                forbidden(
+                    "this token is owned by a team and can not be used to publish new crates. Please publish the first version with a personal token and add the team as an owner afterwards.",
+                ),
+            ));
+        }
+
+        let Some(crate_name) = &self.crate_name else {
+            return Err((
+                "Team token used for an endpoint without a crate",
+                forbidden(TEAM_TOKEN_ERROR_MESSAGE),
+            ));
+        };
+
+        let owners = Crate::by_name(crate_name)
+            .first::<Crate>(conn)
+            .optional()
+            .and_then(|krate| krate.map(|krate| krate.owners(conn)).transpose())
+            .map_err(|err| ("Failed to load the crate owners", err.into()))?
+            .unwrap_or_default();
+
+        let is_owner = owners
+            .iter()
+            .any(|owner| matches!(owner, Owner::Team(team) if team.id == team_id));
+
+        if !is_owner {
+            return Err((
+                "Team token used for a crate that the team does not own",
+                forbidden(TEAM_TOKEN_ERROR_MESSAGE),
+            ));
+        }
+
+        Ok(())
     }
 
     fn endpoint_scope_matches(&self, token_scopes: Option<&Vec<EndpointScope>>) -> bool {
@@ -104,6 +235,16 @@ impl AuthCheck {
             // The token is NOT a legacy token, and the endpoint only allows legacy tokens.
             (Some(_), None) => false,
 
+            // The token is NOT a legacy token, and the endpoint only reads from the registry. Tokens
+            // that are able to publish crates can also read them, since cargo needs to read the
+            // index of the registry to publish.
+            (Some(token_scopes), Some(EndpointScope::Read)) => token_scopes.iter().any(|scope| {
+                matches!(
+                    scope,
+                    EndpointScope::Read | EndpointScope::PublishNew | EndpointScope::PublishUpdate
+                )
+            }),
+
             // The token is NOT a legacy token, and the endpoint allows a certain endpoint scope or a legacy token.
             (Some(token_scopes), Some(endpoint_scope)) => token_scopes.contains(endpoint_scope),
         }
@@ -128,6 +269,101 @@ impl AuthCheck {
     }
 }
 
+/// Checks whether the client IP of the request is in one of the CIDR blocks
+/// that the token is restricted to.
+fn is_ip_allowed<T: RequestPartsExt>(request: &T, allowed_ips: Option<&Vec<IpNetwork>>) -> bool {
+    let Some(allowed_ips) = allowed_ips else {
+        return true;
+    };
+
+    let Some(real_ip) = request.extensions().get::<RealIp>() else {
+        return false;
+    };
+
+    allowed_ips
+        .iter()
+        .any(|network| network.contains(**real_ip))
+}
+
+/// Notes the request for the usage log of the API token, which is written
+/// to the database by the [`token_usage`](crate::middleware::token_usage)
+/// middleware after the request has been handled.
+fn note_token_usage<T: RequestPartsExt>(
+    request: &T,
+    api_token_id: i32,
+    crate_name: Option<&str>,
+    rejection_reason: Option<&'static str>,
+) {
+    let Some(log) = request.extensions().get::<TokenUsageLog>() else {
+        return;
+    };
+
+    let path = match request.extensions().get::<MatchedPath>() {
+        Some(matched_path) => matched_path.as_str(),
+        None => request.uri().path(),
+    };
+
+    log.set(TokenUsage {
+        api_token_id,
+        endpoint: format!("{} {path}", request.method()),
+        crate_name: crate_name.map(ToString::to_string),
+        ip: request
+            .extensions()
+            .get::<RealIp>()
+            .map(|real_ip| IpNetwork::from(**real_ip)),
+        rejection_reason,
+    });
+}
+
+/// Checks whether the session contains a recent two-factor verification.
+fn is_two_factor_verified<T: RequestPartsExt>(request: &T) -> bool {
+    let verified_at = request
+        .session()
+        .get(TWO_FACTOR_SESSION_KEY)
+        .and_then(|s| s.parse::<i64>().ok());
+
+    verified_at.is_some_and(|verified_at| {
+        Utc::now().timestamp() - verified_at < TWO_FACTOR_SESSION_LIFETIME
+    })
+}
+
+/// Checks that the request is allowed to read from the registry.
+///
+/// This is a no-op unless the `auth_required` option is enabled, in which
+/// case the request needs a cookie session or an API token with the `read`,
+/// `publish-new` or `publish-update` endpoint scope. Requests without any credentials are rejected with a
+/// `401 Unauthorized` response, which tells cargo to retry with its token.
+pub async fn check_read_access(
+    state: &AppState,
+    req: &Parts,
+    crate_name: Option<&str>,
+) -> AppResult<()> {
+    if !state.config.auth_required {
+        return Ok(());
+    }
+
+    let has_cookie = req.session().get("user_id").is_some();
+    if !has_cookie && !req.headers.contains_key(header::AUTHORIZATION) {
+        let cause = "no cookie session or auth header found";
+        req.request_log().add("cause", cause);
+
+        return Err(custom(
+            StatusCode::UNAUTHORIZED,
+            "this registry requires authentication",
+        ));
+    }
+
+    let mut auth_check = AuthCheck::default().with_endpoint_scope(EndpointScope::Read);
+    if let Some(crate_name) = crate_name {
+        auth_check = auth_check.for_crate(crate_name);
+    }
+
+    let req = req.clone();
+    let conn = state.db_read_prefer_primary().await?;
+    conn.interact(move |conn| auth_check.check(&req, conn).map(|_| ()))
+        .await?
+}
+
 #[derive(Debug)]
 pub enum Authentication {
     Cookie(CookieAuthentication),
@@ -154,6 +390,40 @@ impl Authentication {
         self.api_token().map(|token| token.id)
     }
 
+    /// Returns the ID of the trusted publisher configuration if the request
+    /// was authenticated with a token minted via trusted publishing.
+    pub fn trusted_publisher_id(&self) -> Option<i32> {
+        self.api_token()
+            .and_then(|token| token.trusted_publisher_id)
+    }
+
+    /// Returns the ID of the team that owns the API token, if the request
+    /// was authenticated with a token of a team.
+    pub fn team_id(&self) -> Option<i32> {
+        self.api_token().and_then(|token| token.team_id)
+    }
+
+    /// Returns the access rights to a crate with the given owners.
+    ///
+    /// Tokens that are owned by a team act on behalf of the team instead of
+    /// the user that created them, so they only have publish rights for
+    /// crates that the team owns.
+    pub async fn rights(&self, app: &App, owners: &[Owner]) -> AppResult<Rights> {
+        let Some(team_id) = self.team_id() else {
+            return self.user().rights(app, owners).await;
+        };
+
+        let is_owner = owners
+            .iter()
+            .any(|owner| matches!(owner, Owner::Team(team) if team.id == team_id));
+
+        Ok(if is_owner {
+            Rights::Publish
+        } else {
+            Rights::None
+        })
+    }
+
     pub fn api_token(&self) -> Option<&ApiToken> {
         match self {
             Authentication::Token(token) => Some(&token.token),
@@ -225,10 +495,18 @@ fn authenticate_via_token<T: RequestPartsExt>(
         internal("user_id from token not found in database")
     })?;
 
+    // Tokens of a team stop working if the member that created them has been
+    // locked out. The other members of the team can rotate them instead.
     ensure_not_locked(&user)?;
 
     req.request_log().add("uid", token.user_id);
     req.request_log().add("tokenid", token.id);
+    if let Some(trusted_publisher_id) = token.trusted_publisher_id {
+        req.request_log().add("trustpub", trusted_publisher_id);
+    }
+    if let Some(team_id) = token.team_id {
+        req.request_log().add("team", team_id);
+    }
 
     Ok(Some(TokenAuthentication { user, token }))
 }
@@ -351,6 +629,24 @@ mod tests {
         assert!(!auth_check.crate_scope_matches(Some(&vec![cs("actix-*")])));
     }
 
+    #[test]
+    fn read_endpoint() {
+        let auth_check = AuthCheck::default()
+            .with_endpoint_scope(EndpointScope::Read)
+            .for_crate("tokio-console");
+
+        assert!(auth_check.endpoint_scope_matches(None));
+        assert!(auth_check.endpoint_scope_matches(Some(&vec![EndpointScope::PublishNew])));
+        assert!(auth_check.endpoint_scope_matches(Some(&vec![EndpointScope::PublishUpdate])));
+        assert!(!auth_check.endpoint_scope_matches(Some(&vec![EndpointScope::Yank])));
+        assert!(!auth_check.endpoint_scope_matches(Some(&vec![EndpointScope::ChangeOwners])));
+        assert!(auth_check.endpoint_scope_matches(Some(&vec![EndpointScope::Read])));
+
+        assert!(auth_check.crate_scope_matches(None));
+        assert!(auth_check.crate_scope_matches(Some(&vec![cs("tokio-*")])));
+        assert!(!auth_check.crate_scope_matches(Some(&vec![cs("anyhow")])));
+    }
+
     #[test]
     fn owner_change_endpoint() {
         let auth_check = AuthCheck::default()
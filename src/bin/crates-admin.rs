@@ -2,9 +2,10 @@
 extern crate tracing;
 
 use crates_io::admin::{
-    delete_crate, delete_version, enqueue_job, git_import, migrate, populate, render_readmes,
-    test_pagerduty, transfer_crates, update_default_versions, upload_index, verify_token,
-    yank_version,
+    delete_crate, delete_version, enqueue_job, git_import, import_db_changes, index_signing,
+    migrate, mirror, populate, render_readmes, reset_two_factor, test_pagerduty, transfer_crates,
+    typosquat, update_default_versions, update_license_queries, upload_index, verify_storage,
+    verify_token, yank_version,
 };
 
 #[derive(clap::Parser, Debug)]
@@ -24,6 +25,15 @@ enum Command {
     #[clap(subcommand)]
     EnqueueJob(enqueue_job::Command),
     UpdateDefaultVersions(update_default_versions::Opts),
+    UpdateLicenseQueries(update_license_queries::Opts),
+    #[clap(subcommand)]
+    IndexSigning(index_signing::Command),
+    VerifyStorage(verify_storage::Opts),
+    Mirror(mirror::Opts),
+    ImportDbChanges(import_db_changes::Opts),
+    ResetTwoFactor(reset_two_factor::Opts),
+    #[clap(subcommand)]
+    Typosquat(typosquat::Command),
 }
 
 fn main() -> anyhow::Result<()> {
@@ -52,6 +62,13 @@ fn main() -> anyhow::Result<()> {
         Command::GitImport(opts) => git_import::run(opts),
         Command::EnqueueJob(command) => enqueue_job::run(command),
         Command::UpdateDefaultVersions(opts) => update_default_versions::run(opts),
+        Command::UpdateLicenseQueries(opts) => update_license_queries::run(opts),
+        Command::IndexSigning(command) => index_signing::run(command),
+        Command::VerifyStorage(opts) => verify_storage::run(opts),
+        Command::Mirror(opts) => mirror::run(opts),
+        Command::ImportDbChanges(opts) => import_db_changes::run(opts),
+        Command::ResetTwoFactor(opts) => reset_two_factor::run(opts),
+        Command::Typosquat(command) => typosquat::run(command),
     }
 }
 
@@ -4,6 +4,7 @@ mod cdn_log_storage;
 mod database_pools;
 mod sentry;
 mod server;
+mod trusted_publishing;
 
 pub use self::base::Base;
 pub use self::cdn_log_queue::CdnLogQueueConfig;
@@ -11,3 +12,4 @@ pub use self::cdn_log_storage::CdnLogStorageConfig;
 pub use self::database_pools::{DatabasePools, DbPoolConfig};
 pub use self::sentry::SentryConfig;
 pub use self::server::Server;
+pub use self::trusted_publishing::TrustedPublishingConfig;
@@ -8,13 +8,15 @@ use crate::Env;
 use super::base::Base;
 use super::database_pools::DatabasePools;
 use crate::config::cdn_log_storage::CdnLogStorageConfig;
-use crate::config::CdnLogQueueConfig;
+use crate::config::{CdnLogQueueConfig, TrustedPublishingConfig};
 use crate::middleware::cargo_compat::StatusCodeConfig;
 use crate::storage::StorageConfig;
 use crates_io_env_vars::{list, list_parsed, required_var, var, var_parsed};
+use crates_io_index::signing::SigningKey;
 use http::HeaderValue;
 use std::collections::{HashMap, HashSet};
 use std::net::IpAddr;
+use std::path::PathBuf;
 use std::str::FromStr;
 use std::time::Duration;
 
@@ -46,6 +48,12 @@ pub struct Server {
     pub max_features: usize,
     pub rate_limiter: HashMap<LimitedAction, RateLimiterConfig>,
     pub new_version_rate_limit: Option<u32>,
+
+    /// Number of typosquatting checks that have to flag the name of a new
+    /// crate for the same popular crate to reject the publish. If not set,
+    /// potential typosquatting only results in warnings.
+    pub typosquat_block_threshold: Option<usize>,
+
     pub blocked_traffic: Vec<(String, Vec<String>)>,
     pub blocked_ips: HashSet<IpAddr>,
     pub max_allowed_page_offset: u32,
@@ -74,7 +82,45 @@ pub struct Server {
     /// non-API requests?
     pub serve_html: bool,
 
+    /// Should the server serve the sparse index under `/index/`? This is
+    /// meant for self-hosted deployments without an index CDN.
+    pub serve_sparse_index: bool,
+
+    /// Should reading from the registry (the API, the sparse index and crate
+    /// downloads) require authentication? This is meant for private
+    /// registries and advertised to cargo via `auth-required` in the
+    /// `config.json` file of the sparse index.
+    pub auth_required: bool,
+
+    /// The online key used to sign the metadata files of the sparse index.
+    /// No metadata files are generated if this is not set.
+    pub index_signing_key: Option<SigningKey>,
+
+    /// The directory in which the checkouts of upstream indexes are kept
+    /// between runs of the mirror job. If this is not set, the upstream
+    /// index is cloned from scratch on every run.
+    pub mirror_checkout_dir: Option<PathBuf>,
+
+    /// Should the mirror job read crate files from `file://` URLs in the
+    /// `dl` field of the upstream index configuration? This is only meant
+    /// for mirroring from an offline copy and for tests, since it allows
+    /// the upstream to read arbitrary files of our file system.
+    pub mirror_allow_file_urls: bool,
+
+    /// Should webhooks be delivered to loopback, private and link-local
+    /// addresses? This is only meant for local development and tests, since
+    /// it allows webhook owners to send requests into our own network.
+    pub allow_private_webhook_urls: bool,
+
+    /// Should users that enabled two-factor authentication have to confirm
+    /// their identity with a code before sensitive actions? This stays off
+    /// until the frontend is able to ask for the code.
+    pub two_factor_step_up: bool,
+
     pub content_security_policy: Option<HeaderValue>,
+
+    /// Configuration of the OIDC issuer used for trusted publishing.
+    pub trusted_publishing: TrustedPublishingConfig,
 }
 
 impl Server {
@@ -109,6 +155,18 @@ impl Server {
     ///   endpoint even with a healthy database pool.
     /// - `BLOCKED_ROUTES`: A comma separated list of HTTP route patterns that are manually blocked
     ///   by an operator (e.g. `/crates/:crate_id/:version/download`).
+    /// - `SERVE_SPARSE_INDEX`: Whether to serve the sparse index from the application itself
+    ///   under `/index/`. Defaults to `false`.
+    /// - `AUTH_REQUIRED`: Whether all read access to the registry requires authentication, for
+    ///   private registries. Defaults to `false`.
+    /// - `INDEX_SIGNING_KEY`: The base64 encoded seed of the Ed25519 key that is used to sign the
+    ///   metadata files of the sparse index. If not set, no metadata files are generated.
+    /// - `MIRROR_CHECKOUT_DIR`: The directory in which the mirror job keeps its checkouts of
+    ///   upstream indexes. If not set, the upstream index is cloned from scratch on every run.
+    /// - `MIRROR_ALLOW_FILE_URLS`: Whether the mirror job may read crate files from `file://`
+    ///   download URLs of the upstream index. Defaults to `false`.
+    /// - `TWO_FACTOR_STEP_UP`: Whether users with two-factor authentication have to confirm their
+    ///   identity with a code before sensitive actions. Defaults to `false`.
     ///
     /// # Panics
     ///
@@ -170,6 +228,9 @@ impl Server {
             cdn_domain = storage.cdn_prefix.as_ref().map(|cdn_prefix| format!("https://{cdn_prefix}")).unwrap_or_default()
         );
 
+        let domain_name = dotenvy::var("DOMAIN_NAME").unwrap_or_else(|_| "crates.io".into());
+        let trusted_publishing = TrustedPublishingConfig::from_environment(&domain_name)?;
+
         Ok(Server {
             db: DatabasePools::full_from_environment(&base)?,
             storage,
@@ -188,13 +249,14 @@ impl Server {
             max_features: DEFAULT_MAX_FEATURES,
             rate_limiter,
             new_version_rate_limit: var_parsed("MAX_NEW_VERSIONS_DAILY")?,
+            typosquat_block_threshold: var_parsed("TYPOSQUAT_BLOCK_THRESHOLD")?,
             blocked_traffic: blocked_traffic(),
             blocked_ips,
             max_allowed_page_offset: var_parsed("WEB_MAX_ALLOWED_PAGE_OFFSET")?.unwrap_or(200),
             page_offset_ua_blocklist,
             page_offset_cidr_blocklist,
             excluded_crate_names,
-            domain_name: dotenvy::var("DOMAIN_NAME").unwrap_or_else(|_| "crates.io".into()),
+            domain_name,
             allowed_origins,
             downloads_persist_interval: var_parsed("DOWNLOADS_PERSIST_INTERVAL_MS")?
                 .map(Duration::from_millis)
@@ -214,7 +276,17 @@ impl Server {
                 .unwrap_or(StatusCodeConfig::AdjustAll),
             serve_dist: true,
             serve_html: true,
+            serve_sparse_index: var_parsed("SERVE_SPARSE_INDEX")?.unwrap_or(false),
+            auth_required: var_parsed("AUTH_REQUIRED")?.unwrap_or(false),
+            index_signing_key: var("INDEX_SIGNING_KEY")?
+                .map(|seed| SigningKey::from_base64(&seed))
+                .transpose()?,
+            mirror_checkout_dir: var_parsed("MIRROR_CHECKOUT_DIR")?,
+            mirror_allow_file_urls: var_parsed("MIRROR_ALLOW_FILE_URLS")?.unwrap_or(false),
+            allow_private_webhook_urls: var_parsed("WEBHOOKS_ALLOW_PRIVATE_URLS")?.unwrap_or(false),
+            two_factor_step_up: var_parsed("TWO_FACTOR_STEP_UP")?.unwrap_or(false),
             content_security_policy: Some(content_security_policy.parse()?),
+            trusted_publishing,
         })
     }
 }
@@ -0,0 +1,52 @@
+use crates_io_env_vars::{var, var_parsed};
+use std::time::Duration;
+
+const DEFAULT_ISSUER: &str = "https://token.actions.githubusercontent.com";
+
+/// Short-lived tokens minted for trusted publishers expire after 30 minutes
+/// by default, which should be enough for a regular `cargo publish` run.
+const DEFAULT_TOKEN_LIFETIME: Duration = Duration::from_secs(30 * 60);
+
+/// Configuration of the OIDC issuer that is trusted to authenticate CI
+/// workflows for trusted publishing.
+#[derive(Debug, Clone)]
+pub struct TrustedPublishingConfig {
+    /// The expected `iss` claim of the identity tokens.
+    pub issuer: String,
+    /// The URL of the JSON Web Key Set that is used to verify the identity
+    /// token signatures.
+    pub jwks_url: String,
+    /// The expected `aud` claim of the identity tokens.
+    pub audience: String,
+    /// How long the minted API tokens are valid for.
+    pub token_lifetime: Duration,
+}
+
+impl TrustedPublishingConfig {
+    /// Pulls values from the following environment variables:
+    ///
+    /// - `TRUSTED_PUBLISHING_ISSUER`: The OIDC issuer URL. Defaults to the
+    ///   GitHub Actions issuer.
+    /// - `TRUSTED_PUBLISHING_JWKS_URL`: The JWKS URL of the issuer. Defaults
+    ///   to `{issuer}/.well-known/jwks`.
+    /// - `TRUSTED_PUBLISHING_AUDIENCE`: The expected token audience. Defaults
+    ///   to the domain name of the server.
+    /// - `TRUSTED_PUBLISHING_TOKEN_LIFETIME_SECONDS`: The lifetime of the
+    ///   minted API tokens. Defaults to 30 minutes.
+    pub fn from_environment(domain_name: &str) -> anyhow::Result<Self> {
+        let issuer = var("TRUSTED_PUBLISHING_ISSUER")?.unwrap_or_else(|| DEFAULT_ISSUER.into());
+        let jwks_url = var("TRUSTED_PUBLISHING_JWKS_URL")?
+            .unwrap_or_else(|| format!("{}/.well-known/jwks", issuer.trim_end_matches('/')));
+        let audience = var("TRUSTED_PUBLISHING_AUDIENCE")?.unwrap_or_else(|| domain_name.into());
+        let token_lifetime = var_parsed("TRUSTED_PUBLISHING_TOKEN_LIFETIME_SECONDS")?
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_TOKEN_LIFETIME);
+
+        Ok(Self {
+            issuer,
+            jwks_url,
+            audience,
+            token_lifetime,
+        })
+    }
+}
@@ -65,14 +65,18 @@ pub mod util;
 
 pub mod category;
 pub mod crate_owner_invitation;
+pub mod feed;
 pub mod git;
 pub mod github;
 pub mod keyword;
 pub mod krate;
 pub mod metrics;
 pub mod site_metadata;
+pub mod sparse_index;
 pub mod summary;
 pub mod team;
 pub mod token;
+pub mod trusted_publishing;
 pub mod user;
 pub mod version;
+pub mod webhook;
@@ -271,14 +271,15 @@ pub async fn handle_invite(state: AppState, req: BytesRequest) -> AppResult<Json
     conn.interact(move |conn| {
         let auth = AuthCheck::default().check(&req, conn)?;
         let user_id = auth.user_id();
+        let api_token_id = auth.api_token_id();
 
         let config = &state.config;
 
         let invitation = CrateOwnerInvitation::find_by_id(user_id, crate_invite.crate_id, conn)?;
         if crate_invite.accepted {
-            invitation.accept(conn, config)?;
+            invitation.accept(conn, config, api_token_id)?;
         } else {
-            invitation.decline(conn)?;
+            invitation.decline(conn, api_token_id)?;
         }
 
         Ok(Json(json!({ "crate_owner_invitation": crate_invite })))
@@ -297,7 +298,7 @@ pub async fn handle_invite_with_token(
 
         let invitation = CrateOwnerInvitation::find_by_token(&token, conn)?;
         let crate_id = invitation.crate_id;
-        invitation.accept(conn, config)?;
+        invitation.accept(conn, config, None)?;
 
         Ok(Json(json!({
             "crate_owner_invitation": {
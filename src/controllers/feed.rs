@@ -0,0 +1,256 @@
+//! Atom feeds of newly published versions, so that tools can watch crates
+//! for updates without having to poll the JSON API of every single crate.
+
+use crate::controllers::frontend_prelude::*;
+use crate::controllers::helpers::{etag, if_none_match};
+use crate::models::{Crate, OwnerKind, Team, User};
+use crate::schema::{crate_owners, crates, teams, users, versions};
+use crate::sql::lower;
+use crate::util::errors::crate_not_found;
+use crate::views::atom::{self, Author, Entry, Feed};
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+/// Maximum number of entries in a feed
+const FEED_SIZE: i64 = 25;
+
+/// The versions that should be included in a feed.
+enum FeedFilter {
+    Crate(i32),
+    Owner(OwnerKind, i32),
+    All,
+}
+
+#[derive(Debug, Queryable)]
+struct FeedVersion {
+    num: String,
+    /// Changes when the version is yanked or unyanked.
+    updated_at: NaiveDateTime,
+    yanked: bool,
+    crate_name: String,
+    description: Option<String>,
+    published_by: Option<String>,
+}
+
+fn load_versions(conn: &mut PgConnection, filter: FeedFilter) -> QueryResult<Vec<FeedVersion>> {
+    // The publisher is taken from `versions.published_by` instead of the
+    // `versions_published_by` table, since the email addresses in the latter
+    // must not be exposed publicly.
+    let mut query = versions::table
+        .inner_join(crates::table)
+        .left_join(users::table)
+        .select((
+            versions::num,
+            versions::updated_at,
+            versions::yanked,
+            crates::name,
+            crates::description,
+            users::gh_login.nullable(),
+        ))
+        .order(versions::id.desc())
+        .limit(FEED_SIZE)
+        .into_boxed();
+
+    match filter {
+        FeedFilter::Crate(crate_id) => {
+            query = query.filter(versions::crate_id.eq(crate_id));
+        }
+        FeedFilter::Owner(owner_kind, owner_id) => {
+            let crate_ids = crate_owners::table
+                .filter(crate_owners::deleted.eq(false))
+                .filter(crate_owners::owner_kind.eq(owner_kind))
+                .filter(crate_owners::owner_id.eq(owner_id))
+                .select(crate_owners::crate_id);
+
+            query = query.filter(versions::crate_id.eq_any(crate_ids));
+        }
+        FeedFilter::All => {}
+    }
+
+    query.load(conn)
+}
+
+fn build_feed(
+    app: &AppState,
+    path: &str,
+    page: &str,
+    title: String,
+    versions: Vec<FeedVersion>,
+    fallback_updated: NaiveDateTime,
+) -> Feed {
+    let base_url = format!("https://{}", app.config.domain_name);
+
+    // Yanking a version changes its entry, so the feed is updated by it
+    // as well, and not only by new versions.
+    let updated = versions
+        .iter()
+        .map(|version| version.updated_at)
+        .max()
+        .unwrap_or(fallback_updated);
+
+    let entries = versions
+        .into_iter()
+        .map(|version| {
+            let url = format!("{base_url}/crates/{}/{}", version.crate_name, version.num);
+            let mut title = format!("{} {}", version.crate_name, version.num);
+            if version.yanked {
+                title.push_str(" (yanked)");
+            }
+
+            Entry {
+                id: url.clone(),
+                title,
+                updated: version.updated_at,
+                link: url,
+                author: version.published_by.map(|login| Author {
+                    uri: format!("{base_url}/users/{login}"),
+                    name: login,
+                }),
+                summary: version.description,
+            }
+        })
+        .collect();
+
+    Feed {
+        id: format!("{base_url}{path}"),
+        title,
+        updated,
+        self_link: format!("{base_url}{path}"),
+        alternate_link: format!("{base_url}{page}"),
+        entries,
+    }
+}
+
+/// Renders the feed, or returns a `304 Not Modified` response if the client
+/// already has the current version of it, according to the `If-None-Match`
+/// or `If-Modified-Since` request headers.
+fn feed_response(req: &Parts, feed: Feed) -> Response {
+    let body = feed.render();
+
+    let etag = etag(body.as_bytes());
+
+    let updated = DateTime::<Utc>::from_naive_utc_and_offset(feed.updated, Utc);
+    let last_modified = updated.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+
+    let headers = [(header::ETAG, etag), (header::LAST_MODIFIED, last_modified)];
+
+    if is_not_modified(req, &headers[0].1, updated) {
+        return (StatusCode::NOT_MODIFIED, headers).into_response();
+    }
+
+    let content_type = [(header::CONTENT_TYPE, atom::CONTENT_TYPE)];
+    (content_type, headers, body).into_response()
+}
+
+fn is_not_modified(req: &Parts, etag: &str, updated: DateTime<Utc>) -> bool {
+    // `If-None-Match` takes precedence over `If-Modified-Since`, see
+    // https://www.rfc-editor.org/rfc/rfc9110#section-13.1.3
+    if let Some(matches) = if_none_match(req, etag) {
+        return matches;
+    }
+
+    req.headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| DateTime::parse_from_rfc2822(value).ok())
+        .is_some_and(|since| updated.timestamp() <= since.timestamp())
+}
+
+/// Handles the `GET /feeds/crates/:crate_id` route.
+pub async fn krate(
+    app: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+) -> AppResult<Response> {
+    let conn = app.db_read().await?;
+    conn.interact(move |conn| {
+        let krate: Crate = Crate::by_name(&crate_name)
+            .first(conn)
+            .optional()?
+            .ok_or_else(|| crate_not_found(&crate_name))?;
+
+        let versions = load_versions(conn, FeedFilter::Crate(krate.id))?;
+
+        let feed = build_feed(
+            &app,
+            &format!("/api/v1/feeds/crates/{}", krate.name),
+            &format!("/crates/{}", krate.name),
+            format!("New versions of {}", krate.name),
+            versions,
+            krate.created_at,
+        );
+
+        Ok(feed_response(&req, feed))
+    })
+    .await?
+}
+
+/// Handles the `GET /feeds/users/:user_id` route.
+pub async fn user(app: AppState, Path(user_name): Path<String>, req: Parts) -> AppResult<Response> {
+    let conn = app.db_read().await?;
+    conn.interact(move |conn| {
+        let user: User = users::table
+            .filter(lower(users::gh_login).eq(lower(&user_name)))
+            .order(users::id.desc())
+            .first(conn)?;
+
+        let versions = load_versions(conn, FeedFilter::Owner(OwnerKind::User, user.id))?;
+
+        let feed = build_feed(
+            &app,
+            &format!("/api/v1/feeds/users/{}", user.gh_login),
+            &format!("/users/{}", user.gh_login),
+            format!("New versions of crates owned by {}", user.gh_login),
+            versions,
+            NaiveDateTime::default(),
+        );
+
+        Ok(feed_response(&req, feed))
+    })
+    .await?
+}
+
+/// Handles the `GET /feeds/teams/:team_id` route.
+pub async fn team(app: AppState, Path(team_name): Path<String>, req: Parts) -> AppResult<Response> {
+    let conn = app.db_read().await?;
+    conn.interact(move |conn| {
+        let team: Team = teams::table
+            .filter(teams::login.eq(&team_name))
+            .first(conn)?;
+
+        let versions = load_versions(conn, FeedFilter::Owner(OwnerKind::Team, team.id))?;
+
+        let feed = build_feed(
+            &app,
+            &format!("/api/v1/feeds/teams/{}", team.login),
+            &format!("/teams/{}", team.login),
+            format!("New versions of crates owned by {}", team.login),
+            versions,
+            NaiveDateTime::default(),
+        );
+
+        Ok(feed_response(&req, feed))
+    })
+    .await?
+}
+
+/// Handles the `GET /feeds/updates` route.
+///
+/// This is the feed equivalent of the "just updated" section of the summary.
+pub async fn updates(app: AppState, req: Parts) -> AppResult<Response> {
+    let conn = app.db_read().await?;
+    conn.interact(move |conn| {
+        let versions = load_versions(conn, FeedFilter::All)?;
+
+        let feed = build_feed(
+            &app,
+            "/api/v1/feeds/updates",
+            "/",
+            "Recently published crate versions".to_string(),
+            versions,
+            NaiveDateTime::default(),
+        );
+
+        Ok(feed_response(&req, feed))
+    })
+    .await?
+}
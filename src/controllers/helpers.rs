@@ -1,12 +1,39 @@
 use crate::controllers::cargo_prelude::{AppResult, Response};
 use axum::response::IntoResponse;
 use axum::Json;
+use http::header;
+use http::request::Parts;
+use sha2::{Digest, Sha256};
 
+mod krate;
 pub(crate) mod pagination;
 
+pub(crate) use self::krate::{ensure_owner, find_crate};
 pub(crate) use self::pagination::Paginate;
 
 pub fn ok_true() -> AppResult<Response> {
     let json = json!({ "ok": true });
     Ok(Json(json).into_response())
 }
+
+/// Returns a strong `ETag` header value derived from the response body.
+pub fn etag(body: &[u8]) -> String {
+    let hash = hex::encode(Sha256::digest(body));
+    format!("\"{}\"", &hash[..32])
+}
+
+/// Checks whether the `If-None-Match` request header matches the given
+/// `ETag`, or `None` if the request has no such header.
+pub fn if_none_match(req: &Parts, etag: &str) -> Option<bool> {
+    let if_none_match = req.headers.get(header::IF_NONE_MATCH)?;
+    let Ok(if_none_match) = if_none_match.to_str() else {
+        return Some(false);
+    };
+
+    let matches = if_none_match
+        .split(',')
+        .map(|tag| tag.trim())
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag);
+
+    Some(matches)
+}
@@ -0,0 +1,38 @@
+use crate::app::AppState;
+use crate::models::{Crate, Owner, Rights, User};
+use crate::util::errors::{crate_not_found, forbidden, AppResult};
+use diesel::prelude::*;
+use tokio::runtime::Handle;
+
+/// Loads the crate with the given name, or returns a "not found" error.
+pub fn find_crate(conn: &mut PgConnection, crate_name: &str) -> AppResult<Crate> {
+    Crate::by_name(crate_name)
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| crate_not_found(crate_name))
+}
+
+/// Ensures that the user is a full owner of the crate, which is required to
+/// manage the crate settings described by `what` (e.g. "webhooks"), and
+/// returns the current owners of the crate.
+///
+/// This has to be called from a blocking context, since checking the team
+/// memberships of the user might require requests to GitHub.
+pub fn ensure_owner(
+    app: &AppState,
+    conn: &mut PgConnection,
+    user: &User,
+    krate: &Crate,
+    what: &str,
+) -> AppResult<Vec<Owner>> {
+    let owners = krate.owners(conn)?;
+    match Handle::current().block_on(user.rights(app, &owners))? {
+        Rights::Full => Ok(owners),
+        Rights::Publish => Err(forbidden(format!(
+            "team members don't have permission to manage {what}"
+        ))),
+        Rights::None => Err(forbidden(format!(
+            "only owners have permission to manage {what}"
+        ))),
+    }
+}
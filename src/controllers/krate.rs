@@ -1,7 +1,10 @@
+pub mod audit_log;
+pub mod delete;
 pub mod downloads;
 pub mod follow;
 pub mod metadata;
 pub mod owners;
 pub mod publish;
+pub mod publish_policy;
 pub mod search;
 pub mod versions;
@@ -0,0 +1,126 @@
+//! Endpoint for reading the audit log of a crate
+
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::controllers::helpers::pagination::{Paginated, PaginationOptions};
+use crate::controllers::helpers::Paginate;
+use crate::models::{Crate, CrateAuditAction, Rights, Team, User};
+use crate::schema::{api_tokens, crate_audit_actions, teams, users};
+use crate::util::errors::{crate_not_found, custom};
+use crate::views::{EncodableAuditApiToken, EncodableCrateAuditAction};
+use std::collections::{HashMap, HashSet};
+use tokio::runtime::Handle;
+
+/// Handles the `GET /crates/:crate_id/audit_log` route.
+///
+/// The audit log is only visible to the owners of the crate and to
+/// administrators. Since the log is retained after a crate has been deleted,
+/// administrators can also read the log of crates that no longer exist, which
+/// includes the logs of all deleted crates that had the same name.
+pub async fn audit_log(
+    app: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    let conn = app.db_read_prefer_primary().await?;
+    conn.interact(move |conn| {
+        let auth = AuthCheck::default()
+            .for_crate(&crate_name)
+            .check(&req, conn)?;
+
+        let user = auth.user();
+
+        let krate: Option<Crate> = Crate::by_name(&crate_name).first(conn).optional()?;
+        let query = crate_audit_actions::table.into_boxed();
+        let query = match krate {
+            Some(krate) => {
+                let owners = krate.owners(conn)?;
+                let rights = Handle::current().block_on(auth.rights(&app, &owners))?;
+                if rights < Rights::Publish && !user.is_admin {
+                    return Err(custom(
+                        StatusCode::FORBIDDEN,
+                        "only owners have permission to view the audit log",
+                    ));
+                }
+
+                // A deleted crate with the same name had a different ID, so
+                // its log is not visible to the owners of the new crate.
+                query.filter(crate_audit_actions::crate_id.eq(krate.id))
+            }
+            None if user.is_admin => query.filter(crate_audit_actions::crate_name.eq(crate_name)),
+            None => return Err(crate_not_found(&crate_name)),
+        };
+
+        let pagination = PaginationOptions::builder().gather(&req)?;
+        let data: Paginated<CrateAuditAction> = query
+            .order(crate_audit_actions::id.desc())
+            .pages_pagination(pagination)
+            .load(conn)?;
+
+        let total = data.total();
+        let next_page = data.next_page_params().map(|p| req.query_with_params(p));
+        let prev_page = data.prev_page_params().map(|p| req.query_with_params(p));
+
+        let user_ids = data
+            .iter()
+            .flat_map(|action| [action.user_id, action.target_user_id])
+            .flatten()
+            .collect::<HashSet<_>>();
+
+        let token_ids = data
+            .iter()
+            .filter_map(|action| action.api_token_id)
+            .collect::<HashSet<_>>();
+
+        let team_ids = data
+            .iter()
+            .filter_map(|action| action.target_team_id)
+            .collect::<HashSet<_>>();
+
+        let users: HashMap<i32, User> = users::table
+            .filter(users::id.eq_any(user_ids))
+            .load::<User>(conn)?
+            .into_iter()
+            .map(|user| (user.id, user))
+            .collect();
+
+        let api_tokens: HashMap<i32, EncodableAuditApiToken> = api_tokens::table
+            .filter(api_tokens::id.eq_any(token_ids))
+            .select((api_tokens::id, api_tokens::name))
+            .load::<(i32, String)>(conn)?
+            .into_iter()
+            .map(|(id, name)| (id, EncodableAuditApiToken { id, name }))
+            .collect();
+
+        let teams: HashMap<i32, Team> = teams::table
+            .filter(teams::id.eq_any(team_ids))
+            .load::<Team>(conn)?
+            .into_iter()
+            .map(|team| (team.id, team))
+            .collect();
+
+        let actions = data
+            .into_iter()
+            .map(|action| {
+                let user = action.user_id.and_then(|id| users.get(&id).cloned());
+                let api_token = action
+                    .api_token_id
+                    .and_then(|id| api_tokens.get(&id).cloned());
+                let target_user = action.target_user_id.and_then(|id| users.get(&id).cloned());
+                let target_team = action.target_team_id.and_then(|id| teams.get(&id).cloned());
+
+                EncodableCrateAuditAction::from(action, user, api_token, target_user, target_team)
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({
+            "actions": actions,
+            "meta": {
+                "total": total,
+                "next_page": next_page,
+                "prev_page": prev_page,
+            },
+        })))
+    })
+    .await?
+}
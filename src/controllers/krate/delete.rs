@@ -0,0 +1,148 @@
+//! Endpoint for deleting a crate by its owners
+
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::models::{Crate, CrateAction, NewCrateAuditAction, Rights};
+use crate::schema::{crate_downloads, crates, dependencies, reserved_crate_names};
+use crate::sql::canon_crate_name;
+use crate::util::errors::{crate_not_found, custom};
+use crate::worker::jobs;
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::dsl::{exists, select};
+use tokio::runtime::Handle;
+
+/// Crates that were created less than this many hours ago can be deleted by
+/// their owners regardless of their download count.
+const GRACE_PERIOD_HOURS: i64 = 72;
+
+/// Crates that are older than the grace period can only be deleted if they
+/// have fewer downloads than this.
+const DOWNLOADS_THRESHOLD: i64 = 500;
+
+/// After a crate was deleted its name is reserved for this many hours, to
+/// prevent someone else from immediately taking over the name.
+const NAME_COOLDOWN_HOURS: i64 = 24;
+
+/// Handles the `DELETE /crates/:crate_id` route.
+///
+/// Owners can delete a crate if no other crate depends on it, and if it was
+/// either published recently or has barely been downloaded yet. Everything
+/// else still has to go through the crates.io team.
+pub async fn delete(
+    app: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+) -> AppResult<Response> {
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let auth = AuthCheck::only_cookie()
+            .with_two_factor()
+            .check(&req, conn)?;
+        let user = auth.user();
+
+        let crate_name = conn.transaction(|conn| {
+            let krate: Crate = Crate::by_name(&crate_name)
+                .first(conn)
+                .optional()?
+                .ok_or_else(|| crate_not_found(&crate_name))?;
+
+            let owners = krate.owners(conn)?;
+            if Handle::current().block_on(user.rights(&app, &owners))? < Rights::Full {
+                return Err(custom(
+                    StatusCode::FORBIDDEN,
+                    "only owners have permission to delete crates",
+                ));
+            }
+
+            let has_reverse_dependencies: bool = select(exists(
+                dependencies::table.filter(dependencies::crate_id.eq(krate.id)),
+            ))
+            .get_result(conn)?;
+
+            if has_reverse_dependencies {
+                return Err(custom(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    "only crates without reverse dependencies can be deleted",
+                ));
+            }
+
+            let now = Utc::now().naive_utc();
+            if !is_recent(&krate, now) {
+                let downloads: i64 = crate_downloads::table
+                    .find(krate.id)
+                    .select(crate_downloads::downloads)
+                    .first(conn)
+                    .optional()?
+                    .unwrap_or_default();
+
+                if downloads >= DOWNLOADS_THRESHOLD {
+                    return Err(custom(
+                        StatusCode::UNPROCESSABLE_ENTITY,
+                        format!(
+                            "only crates with less than {DOWNLOADS_THRESHOLD} downloads can be \
+                            deleted after the grace period of {GRACE_PERIOD_HOURS} hours"
+                        ),
+                    ));
+                }
+            }
+
+            info!(crate.name = %krate.name, user.id = user.id, "Deleting crate");
+            diesel::delete(crates::table.find(krate.id)).execute(conn)?;
+
+            reserve_name(
+                &krate.name,
+                now + Duration::hours(NAME_COOLDOWN_HOURS),
+                conn,
+            )?;
+
+            NewCrateAuditAction::new(krate.id, &krate.name, CrateAction::DeleteCrate)
+                .actor(user.id, auth.api_token_id())
+                .insert(conn)?;
+
+            jobs::enqueue_sync_to_index(&krate.name, conn)?;
+
+            Ok::<_, BoxedAppError>(krate.name)
+        })?;
+
+        // The crate is already gone from the database and its removal from the
+        // index has been enqueued at this point, so failing to clean up the
+        // storage should not fail the request.
+        let storage = &app.storage;
+        if let Err(error) = Handle::current().block_on(storage.delete_all_crate_files(&crate_name))
+        {
+            warn!(%crate_name, ?error, "Failed to delete crate files");
+        }
+
+        if let Err(error) = Handle::current().block_on(storage.delete_all_readmes(&crate_name)) {
+            warn!(%crate_name, ?error, "Failed to delete readme files");
+        }
+
+        ok_true()
+    })
+    .await?
+}
+
+fn is_recent(krate: &Crate, now: NaiveDateTime) -> bool {
+    krate.created_at > now - Duration::hours(GRACE_PERIOD_HOURS)
+}
+
+/// Temporarily reserves the name of a deleted crate until `expires_at`.
+fn reserve_name(name: &str, expires_at: NaiveDateTime, conn: &mut PgConnection) -> QueryResult<()> {
+    // A previous cooldown for the same name might still be around, since
+    // expired reservations are not cleaned up automatically.
+    diesel::delete(
+        reserved_crate_names::table
+            .filter(canon_crate_name(reserved_crate_names::name).eq(canon_crate_name(name)))
+            .filter(reserved_crate_names::expires_at.is_not_null()),
+    )
+    .execute(conn)?;
+
+    diesel::insert_into(reserved_crate_names::table)
+        .values((
+            reserved_crate_names::name.eq(name),
+            reserved_crate_names::expires_at.eq(expires_at),
+        ))
+        .execute(conn)?;
+
+    Ok(())
+}
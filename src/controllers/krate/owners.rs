@@ -3,9 +3,13 @@
 use crate::auth::AuthCheck;
 use crate::controllers::prelude::*;
 use crate::models::token::EndpointScope;
-use crate::models::{Crate, Owner, Rights, Team, User};
+use crate::models::{
+    Crate, CrateAction, NewCrateAuditAction, Owner, Rights, Team, User, WebhookEvent,
+    WebhookPayload,
+};
 use crate::util::errors::{bad_request, crate_not_found, custom};
 use crate::views::EncodableOwner;
+use crate::worker::jobs;
 use tokio::runtime::Handle;
 
 /// Handles the `GET /crates/:crate_id/owners` route.
@@ -106,9 +110,11 @@ async fn modify_owners(
         let auth = AuthCheck::default()
             .with_endpoint_scope(EndpointScope::ChangeOwners)
             .for_crate(&crate_name)
+            .with_two_factor()
             .check(&parts, conn)?;
 
         let user = auth.user();
+        let api_token_id = auth.api_token_id();
 
         conn.transaction(|conn| {
             let krate: Crate = Crate::by_name(&crate_name)
@@ -118,7 +124,7 @@ async fn modify_owners(
 
             let owners = krate.owners(conn)?;
 
-            match Handle::current().block_on(user.rights(&app, &owners))? {
+            match Handle::current().block_on(auth.rights(&app, &owners))? {
                 Rights::Full => {}
                 // Yes!
                 Rights::Publish => {
@@ -143,13 +149,23 @@ async fn modify_owners(
                     if owners.iter().any(login_test) {
                         return Err(bad_request(format_args!("`{login}` is already an owner")));
                     }
-                    let msg = krate.owner_add(&app, conn, user, login)?;
+                    let msg = krate.owner_add(&app, conn, user, api_token_id, login)?;
                     msgs.push(msg);
                 }
                 msgs.join(",")
             } else {
                 for login in &logins {
-                    krate.owner_remove(conn, login)?;
+                    let owner = krate.owner_remove(conn, login)?;
+
+                    NewCrateAuditAction::new(krate.id, &krate.name, CrateAction::RemoveOwner)
+                        .actor(user.id, api_token_id)
+                        .target_owner(&owner)
+                        .insert(conn)?;
+
+                    let payload = WebhookPayload::new(WebhookEvent::OwnerRemove, &krate.name)
+                        .user(user)
+                        .owner(&owner);
+                    jobs::enqueue_webhooks(krate.id, &payload, conn)?;
                 }
                 if User::owning(&krate, conn)?.is_empty() {
                     return Err(bad_request(
@@ -11,14 +11,15 @@ use diesel::dsl::{exists, select};
 use hex::ToHex;
 use hyper::body::Buf;
 use sha2::{Digest, Sha256};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use tokio::runtime::Handle;
 use url::Url;
 
 use crate::controllers::cargo_prelude::*;
 use crate::models::{
-    insert_version_owner_action, Category, Crate, DependencyKind, Keyword, NewCrate, NewVersion,
-    Rights, VersionAction,
+    Category, Crate, CrateAction, DependencyKind, Keyword, NewCrate, NewCrateAuditAction,
+    NewVersion, NewVersionOwnerAction, PublishPolicy, Rights, VersionAction, WebhookEvent,
+    WebhookPayload,
 };
 
 use crate::licenses::parse_license_expr;
@@ -27,6 +28,7 @@ use crate::models::token::EndpointScope;
 use crate::rate_limiter::LimitedAction;
 use crate::schema::*;
 use crate::sql::canon_crate_name;
+use crate::typosquat;
 use crate::util::errors::{bad_request, custom, internal, AppResult};
 use crate::util::Maximums;
 use crate::views::{
@@ -92,6 +94,7 @@ pub async fn publish(app: AppState, req: BytesRequest) -> AppResult<Json<GoodCra
             .check(&req, conn)?;
 
         let api_token_id = auth.api_token_id();
+        let trusted_publisher_id = auth.trusted_publisher_id();
         let user = auth.user();
 
         let verified_email_address = user.verified_email(conn)?;
@@ -278,6 +281,13 @@ pub async fn publish(app: AppState, req: BytesRequest) -> AppResult<Json<GoodCra
             validate_dependency(dep)?;
         }
 
+        // Warn about popular crates with similar names, before any data is
+        // written in case the configured threshold rejects the name.
+        let typosquat_warnings = match existing_crate {
+            Some(_) => vec![],
+            None => check_typosquatting(&app, conn, &metadata.name, user.id)?,
+        };
+
         // Create a transaction on the database, if there are no errors,
         // commit the transactions to record a new or updated crate.
         conn.transaction(|conn| {
@@ -309,10 +319,15 @@ pub async fn publish(app: AppState, req: BytesRequest) -> AppResult<Json<GoodCra
             };
 
             let owners = krate.owners(conn)?;
-            if Handle::current().block_on(user.rights(&app, &owners))? < Rights::Publish {
+            if Handle::current().block_on(auth.rights(&app, &owners))? < Rights::Publish {
                 return Err(custom(StatusCode::FORBIDDEN, MISSING_RIGHTS_ERROR_MESSAGE));
             }
 
+            if let Some(policy) = PublishPolicy::for_crate(conn, krate.id)? {
+                let token = auth.api_token();
+                Handle::current().block_on(policy.check(&app, &owners, user, token, &version))?;
+            }
+
             if krate.name != *name {
                 return Err(bad_request(format_args!(
                     "crate was previously named `{}`",
@@ -349,16 +364,23 @@ pub async fn publish(app: AppState, req: BytesRequest) -> AppResult<Json<GoodCra
             )?
             .save(conn, &verified_email_address)?;
 
-            insert_version_owner_action(
-                conn,
-                version.id,
-                user.id,
-                api_token_id,
-                VersionAction::Publish,
-            )?;
+            NewVersionOwnerAction::new(version.id, user.id, VersionAction::Publish)
+                .api_token(api_token_id, trusted_publisher_id, auth.team_id())
+                .insert(conn)?;
+
+            NewCrateAuditAction::new(krate.id, &krate.name, CrateAction::Publish)
+                .version(&version.num)
+                .actor(user.id, api_token_id)
+                .insert(conn)?;
+
+            let payload = WebhookPayload::new(WebhookEvent::Publish, &krate.name)
+                .version(&version.num)
+                .user(user);
+            jobs::enqueue_webhooks(krate.id, &payload, conn)?;
 
             // Link this new version to all dependencies
             add_dependencies(conn, &deps, version.id)?;
+            let dependency_warnings = check_dependency_requirements(conn, &deps)?;
 
             // Insert the default version if it doesn't already exist. Compared
             // to only using a background job, this prevents us from getting
@@ -419,18 +441,19 @@ pub async fn publish(app: AppState, req: BytesRequest) -> AppResult<Json<GoodCra
                 UpdateDefaultVersion::new(krate.id).enqueue(conn)?;
             }
 
-            // Experiment: check new crates for potential typosquatting.
+            // Record potential typosquatting of new crates for review by the
+            // crates.io team, in addition to the warnings above.
             if existing_crate.is_none() {
                 CheckTyposquat::new(&krate.name).enqueue(conn)?;
             }
 
-            // The `other` field on `PublishWarnings` was introduced to handle a temporary warning
-            // that is no longer needed. As such, crates.io currently does not return any `other`
-            // warnings at this time, but if we need to, the field is available.
+            // The `other` field on `PublishWarnings` is currently only used
+            // for dev-dependencies that can't be resolved, and for names that
+            // are similar to popular crates.
             let warnings = PublishWarnings {
                 invalid_categories: ignored_invalid_categories,
                 invalid_badges: vec![],
-                other: vec![],
+                other: [dependency_warnings, typosquat_warnings].concat(),
             };
 
             Ok(Json(GoodCrate {
@@ -442,6 +465,69 @@ pub async fn publish(app: AppState, req: BytesRequest) -> AppResult<Json<GoodCra
     .await?
 }
 
+/// Checks the name of a new crate against the most popular crates, and
+/// returns a warning for each popular crate with a similar name.
+///
+/// The publish is rejected instead if the number of checks that flag the same
+/// popular crate reaches the configured `typosquat_block_threshold`. Errors
+/// of the checks themselves are only logged, since they are not on the
+/// critical path for publishing.
+fn check_typosquatting(
+    app: &AppState,
+    conn: &mut PgConnection,
+    name: &str,
+    user_id: i32,
+) -> AppResult<Vec<String>> {
+    let cache = match app.typosquat_cache(conn) {
+        Ok(cache) => cache,
+        Err(error) => {
+            warn!(%error, "Failed to initialise the typosquatting cache");
+            return Ok(vec![]);
+        }
+    };
+
+    let krate = Box::new(typosquat::Crate::owned_by(user_id));
+    let squats = match cache.get_harness().check_package(name, krate) {
+        Ok(squats) => squats,
+        Err(error) => {
+            warn!(%error, name, "Failed to check new crate for potential typosquatting");
+            return Ok(vec![]);
+        }
+    };
+
+    let mut reasons_by_crate: BTreeMap<&str, BTreeSet<String>> = BTreeMap::new();
+    for squat in &squats {
+        reasons_by_crate
+            .entry(squat.package())
+            .or_default()
+            .insert(squat.to_string());
+    }
+
+    if let Some(threshold) = app.config.typosquat_block_threshold {
+        let blocking = reasons_by_crate
+            .iter()
+            .find(|(_, reasons)| reasons.len() >= threshold);
+
+        if let Some((similar_crate, _)) = blocking {
+            return Err(bad_request(format!(
+                "the crate name `{name}` is too similar to the popular crate `{similar_crate}`. \
+                Please choose a different name, or send us an email to help@crates.io \
+                if you believe this is a mistake."
+            )));
+        }
+    }
+
+    let warnings = reasons_by_crate
+        .into_iter()
+        .map(|(similar_crate, reasons)| {
+            let reasons = reasons.into_iter().collect::<Vec<_>>().join(", ");
+            format!("the crate name `{name}` is similar to the popular crate `{similar_crate}` ({reasons})")
+        })
+        .collect();
+
+    Ok(warnings)
+}
+
 /// Counts the number of versions for `crate_id` that were published within
 /// the last 24 hours.
 fn count_versions_published_today(crate_id: i32, conn: &mut PgConnection) -> QueryResult<i64> {
@@ -495,9 +581,17 @@ fn split_body(mut bytes: Bytes) -> AppResult<(Bytes, Bytes)> {
 }
 
 fn is_reserved_name(name: &str, conn: &mut PgConnection) -> QueryResult<bool> {
-    select(exists(reserved_crate_names::table.filter(
-        canon_crate_name(reserved_crate_names::name).eq(canon_crate_name(name)),
-    )))
+    use diesel::dsl::now;
+
+    select(exists(
+        reserved_crate_names::table
+            .filter(canon_crate_name(reserved_crate_names::name).eq(canon_crate_name(name)))
+            .filter(
+                reserved_crate_names::expires_at
+                    .is_null()
+                    .or(reserved_crate_names::expires_at.gt(now)),
+            ),
+    ))
     .get_result(conn)
 }
 
@@ -699,6 +793,63 @@ pub fn add_dependencies(
     Ok(())
 }
 
+/// Checks that the version requirement of each dependency matches at least
+/// one non-yanked version of the dependency.
+///
+/// Normal and build dependencies that can't be resolved are rejected, since
+/// they would break the build of every dependent. Dev-dependencies are only
+/// needed to build the crate's own tests, so they produce a warning instead.
+#[instrument(skip_all)]
+pub fn check_dependency_requirements(
+    conn: &mut PgConnection,
+    deps: &[EncodableCrateDependency],
+) -> AppResult<Vec<String>> {
+    let mut available = HashMap::<String, Vec<semver::Version>>::new();
+    versions::table
+        .inner_join(crates::table)
+        .filter(crates::name.eq_any(deps.iter().map(|d| &d.name)))
+        .filter(versions::yanked.eq(false))
+        .select((crates::name, versions::num))
+        .load_iter::<(String, String), DefaultLoadingMode>(conn)?
+        .try_for_each(|result| {
+            let (name, num) = result?;
+            if let Ok(num) = semver::Version::parse(&num) {
+                available.entry(name).or_default().push(num);
+            }
+            QueryResult::Ok(())
+        })?;
+
+    let mut warnings = vec![];
+    for dep in deps {
+        // `validate_dependency()` already ensured that the requirement is valid
+        let Ok(req) = semver::VersionReq::parse(&dep.version_req) else {
+            continue;
+        };
+
+        let versions = available
+            .get(&dep.name)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        if versions.iter().any(|version| req.matches(version)) {
+            continue;
+        }
+
+        if dep.kind == Some(DependencyKind::Dev) {
+            warnings.push(format!(
+                "no non-yanked version of `{}` matches the dev-dependency requirement `{}`",
+                dep.name, dep.version_req
+            ));
+        } else {
+            return Err(bad_request(format_args!(
+                "no non-yanked version of `{}` matches the dependency requirement `{}`",
+                dep.name, dep.version_req
+            )));
+        }
+    }
+
+    Ok(warnings)
+}
+
 impl From<TarballError> for BoxedAppError {
     fn from(error: TarballError) -> Self {
         match error {
@@ -0,0 +1,166 @@
+//! Endpoints for managing the publish policy of a crate.
+
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::controllers::helpers::{ensure_owner, find_crate};
+use crate::models::{
+    Crate, CrateAction, NewCrateAuditAction, NewPublishPolicy, Owner, PublishPolicy,
+};
+use crate::schema::{teams, users};
+use crate::views::EncodablePublishPolicy;
+use tokio::runtime::Handle;
+
+/// Loads the logins of the given users and teams, sorted alphabetically.
+fn logins(conn: &mut PgConnection, user_ids: &[i32], team_ids: &[i32]) -> QueryResult<Vec<String>> {
+    let mut logins: Vec<String> = users::table
+        .filter(users::id.eq_any(user_ids))
+        .select(users::gh_login)
+        .load(conn)?;
+
+    let team_logins: Vec<String> = teams::table
+        .filter(teams::id.eq_any(team_ids))
+        .select(teams::login)
+        .load(conn)?;
+
+    logins.extend(team_logins);
+    logins.sort();
+    Ok(logins)
+}
+
+fn encode_policy(
+    conn: &mut PgConnection,
+    krate: &Crate,
+    policy: Option<PublishPolicy>,
+) -> QueryResult<EncodablePublishPolicy> {
+    let Some(policy) = policy else {
+        return Ok(EncodablePublishPolicy::unrestricted(&krate.name));
+    };
+
+    let allowed_publishers = logins(conn, &policy.allowed_users, &policy.allowed_teams)?;
+    let prerelease_publishers = logins(conn, &policy.prerelease_users, &policy.prerelease_teams)?;
+
+    Ok(EncodablePublishPolicy::from(
+        policy,
+        &krate.name,
+        allowed_publishers,
+        prerelease_publishers,
+    ))
+}
+
+/// Handles the `GET /crates/:crate_id/publish_policy` route.
+pub async fn show(
+    app: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    let conn = app.db_read_prefer_primary().await?;
+    conn.interact(move |conn| {
+        let auth = AuthCheck::only_cookie().check(&req, conn)?;
+
+        let krate = find_crate(conn, &crate_name)?;
+        ensure_owner(&app, conn, auth.user(), &krate, "the publish policy")?;
+
+        let policy = PublishPolicy::for_crate(conn, krate.id)?;
+        let publish_policy = encode_policy(conn, &krate, policy)?;
+
+        Ok(Json(json!({ "publish_policy": publish_policy })))
+    })
+    .await?
+}
+
+#[derive(Deserialize)]
+pub struct PublishPolicyRequest {
+    publish_policy: PublishPolicyBody,
+}
+
+#[derive(Deserialize)]
+pub struct PublishPolicyBody {
+    #[serde(default)]
+    allowed_publishers: Vec<String>,
+    #[serde(default)]
+    prerelease_publishers: Vec<String>,
+    #[serde(default)]
+    require_token_expiry: bool,
+    #[serde(default)]
+    require_trusted_publishing: bool,
+}
+
+/// Splits a list of owner logins into the IDs of the users and the IDs of
+/// the teams. Only current owners of the crate may be listed.
+fn resolve_owners(owners: &[Owner], logins: &[String]) -> AppResult<(Vec<i32>, Vec<i32>)> {
+    let mut user_ids = Vec::new();
+    let mut team_ids = Vec::new();
+    for login in logins {
+        let owner = owners
+            .iter()
+            .find(|owner| owner.login().eq_ignore_ascii_case(login))
+            .ok_or_else(|| bad_request(format_args!("`{login}` is not an owner of this crate")))?;
+
+        let ids = match owner {
+            Owner::User(_) => &mut user_ids,
+            Owner::Team(_) => &mut team_ids,
+        };
+        if !ids.contains(&owner.id()) {
+            ids.push(owner.id());
+        }
+    }
+
+    Ok((user_ids, team_ids))
+}
+
+/// Handles the `PUT /crates/:crate_id/publish_policy` route.
+///
+/// The policy can only be changed with cookie authentication, so that API
+/// tokens restricted by the policy can't be used to loosen it. Owners that
+/// the current policy excludes from publishing can't change it either.
+pub async fn update(
+    app: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+    Json(body): Json<PublishPolicyRequest>,
+) -> AppResult<Json<Value>> {
+    let body = body.publish_policy;
+
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let auth = AuthCheck::only_cookie()
+            .with_two_factor()
+            .check(&req, conn)?;
+        let user = auth.user();
+
+        let krate = find_crate(conn, &crate_name)?;
+        let owners = ensure_owner(&app, conn, user, &krate, "the publish policy")?;
+
+        if let Some(policy) = PublishPolicy::for_crate(conn, krate.id)? {
+            Handle::current().block_on(policy.check_update(&app, &owners, user))?;
+        }
+
+        let (allowed_users, allowed_teams) = resolve_owners(&owners, &body.allowed_publishers)?;
+        let (prerelease_users, prerelease_teams) =
+            resolve_owners(&owners, &body.prerelease_publishers)?;
+
+        let policy = conn.transaction(|conn| {
+            let policy = NewPublishPolicy {
+                crate_id: krate.id,
+                allowed_users,
+                allowed_teams,
+                prerelease_users,
+                prerelease_teams,
+                require_token_expiry: body.require_token_expiry,
+                require_trusted_publishing: body.require_trusted_publishing,
+                updated_by: user.id,
+            }
+            .upsert(conn)?;
+
+            NewCrateAuditAction::new(krate.id, &krate.name, CrateAction::UpdatePublishPolicy)
+                .actor(user.id, None)
+                .insert(conn)?;
+
+            QueryResult::Ok(policy)
+        })?;
+
+        let publish_policy = encode_policy(conn, &krate, Some(policy))?;
+        Ok(Json(json!({ "publish_policy": publish_policy })))
+    })
+    .await?
+}
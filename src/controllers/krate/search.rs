@@ -15,7 +15,11 @@ use crate::views::EncodableCrate;
 
 use crate::controllers::helpers::pagination::{Page, Paginated, PaginationOptions};
 use crate::models::krate::ALL_COLUMNS;
-use crate::sql::{array_agg, canon_crate_name, lower};
+use crate::sql::{array_agg, canon_crate_name, license_matches, lower, rust_version_parts};
+
+use self::query::{RustVersionBound, SearchQuery};
+
+mod query;
 
 /// Handles the `GET /crates` route.
 /// Returns a list of crates. Called in a variety of scenarios in the
@@ -56,8 +60,36 @@ pub async fn search(app: AppState, req: Parts) -> AppResult<Json<Value>> {
         // an Internal Server Error ourselves.
         let q_string = option_param("q").map(|q| q.replace('\u{0}', ""));
 
+        // Split the qualifiers like `keyword:async` from the free text part
+        // of the query, which is used for the full text search.
+        let mut search_query = match &q_string {
+            Some(q_string) => SearchQuery::parse(q_string)?,
+            None => SearchQuery::default(),
+        };
+
+        // The filters of the `license:`, `rust-version:` and `updated:`
+        // qualifiers are also available as separate parameters.
+        if let Some(rust_version) = option_param("rust_version") {
+            search_query.add_rust_version(rust_version)?;
+        }
+        if let Some(licenses) = option_param("license") {
+            for license in licenses.split(',') {
+                search_query.add_license(license.trim())?;
+            }
+        }
+        if let Some(updated_after) = option_param("updated_after") {
+            search_query.set_updated_after(updated_after)?;
+        }
+        if let Some(updated_before) = option_param("updated_before") {
+            search_query.set_updated_before(updated_before)?;
+        }
+
+        let q_string = q_string.as_ref().map(|_| search_query.text.as_str());
+        let include_yanked = include_yanked && !search_query.exclude_yanked;
+
         let filter_params = FilterParams {
-            q_string: q_string.as_deref(),
+            q_string,
+            search_query: Some(&search_query),
             include_yanked,
             category: option_param("category"),
             all_keywords: option_param("all_keywords"),
@@ -85,7 +117,7 @@ pub async fn search(app: AppState, req: Parts) -> AppResult<Json<Value>> {
             .left_join(recent_crate_downloads::table)
             .select(selection);
 
-        if let Some(q_string) = &q_string {
+        if let Some(q_string) = q_string {
             if !q_string.is_empty() {
                 let sort = sort.unwrap_or("relevance");
 
@@ -251,6 +283,7 @@ pub async fn search(app: AppState, req: Parts) -> AppResult<Json<Value>> {
 #[derive(Default)]
 struct FilterParams<'a> {
     q_string: Option<&'a str>,
+    search_query: Option<&'a SearchQuery>,
     include_yanked: bool,
     category: Option<&'a str>,
     all_keywords: Option<&'a str>,
@@ -308,7 +341,12 @@ impl<'a> FilterParams<'a> {
             }
         }
 
-        if let Some(cat) = self.category {
+        let qualified_categories = self.search_query.iter().flat_map(|q| &q.categories);
+        let categories = self
+            .category
+            .into_iter()
+            .chain(qualified_categories.map(String::as_str));
+        for cat in categories {
             query = query.filter(
                 crates::id.eq_any(
                     crates_categories::table
@@ -390,6 +428,93 @@ impl<'a> FilterParams<'a> {
             query = query.filter(crates::name.eq_any(self.ids(req).unwrap()));
         }
 
+        if let Some(search_query) = self.search_query {
+            for keyword in &search_query.keywords {
+                query = query.filter(
+                    crates::id.eq_any(
+                        crates_keywords::table
+                            .select(crates_keywords::crate_id)
+                            .inner_join(keywords::table)
+                            .filter(lower(keywords::keyword).eq(keyword)),
+                    ),
+                );
+            }
+
+            for owner in &search_query.owners {
+                let owner = owner.to_lowercase();
+                let org_teams = format!("github:{}:%", escape_like(&owner));
+
+                let user_ids = users::table
+                    .select(users::id)
+                    .filter(lower(users::gh_login).eq(owner.clone()));
+                let team_ids = teams::table.select(teams::id).filter(
+                    lower(teams::login)
+                        .eq(owner)
+                        .or(lower(teams::login).like(org_teams)),
+                );
+
+                query = query.filter(
+                    crates::id.eq_any(
+                        crate_owners::table
+                            .select(crate_owners::crate_id)
+                            .filter(crate_owners::deleted.eq(false))
+                            .filter(
+                                crate_owners::owner_kind
+                                    .eq(OwnerKind::User)
+                                    .and(crate_owners::owner_id.eq_any(user_ids))
+                                    .or(crate_owners::owner_kind
+                                        .eq(OwnerKind::Team)
+                                        .and(crate_owners::owner_id.eq_any(team_ids))),
+                            ),
+                    ),
+                );
+            }
+
+            if !search_query.licenses.is_empty() {
+                query = query.filter(
+                    crates::id.eq_any(
+                        default_versions::table
+                            .inner_join(versions::table)
+                            .select(default_versions::crate_id)
+                            .filter(license_matches(
+                                versions::license_query,
+                                search_query.license_names(),
+                            )),
+                    ),
+                );
+            }
+
+            if let Some(updated_after) = search_query.updated_after {
+                query = query.filter(crates::updated_at.ge(updated_after));
+            }
+
+            if let Some(updated_before) = search_query.updated_before {
+                query = query.filter(crates::updated_at.lt(updated_before));
+            }
+
+            if search_query.rust_version.is_some() {
+                let rust_version = rust_version_parts(versions::rust_version);
+                let mut default_versions = default_versions::table
+                    .inner_join(versions::table)
+                    .select(default_versions::crate_id)
+                    .filter(rust_version.is_not_null())
+                    .into_boxed();
+
+                for bound in search_query.rust_version_bounds() {
+                    default_versions = match bound {
+                        RustVersionBound::AtLeast(parts) => {
+                            default_versions.filter(rust_version.ge(parts))
+                        }
+                        RustVersionBound::Below(parts) => {
+                            default_versions.filter(rust_version.lt(parts))
+                        }
+                    };
+                }
+
+                query = query.filter(crates::id.eq_any(default_versions));
+            }
+        }
+
         if !self.include_yanked {
             query = query.filter(exists(
                 versions::table
@@ -631,6 +756,14 @@ mod seek {
     }
 }
 
+/// Escapes the characters that have a special meaning in `LIKE` patterns.
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
 type BoxedCondition<'a> = Box<
     dyn BoxableExpression<
             LeftJoinQuerySource<
@@ -0,0 +1,416 @@
+//! Parser for the structured search syntax that is supported in the `q`
+//! parameter of the `GET /crates` route.
+//!
+//! A query consists of free text and qualifiers, e.g.
+//! `http keyword:async category:network-programming license:MIT
+//! owner:rust-lang rust-version:<=1.70 updated:>180d -yanked`. The free text
+//! is used for the full text search, while the qualifiers are turned into
+//! filters.
+
+use crate::licenses::parse_license_expr;
+use crate::util::errors::{bad_request, AppResult};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Utc};
+use semver::{Comparator, Op, Version, VersionReq};
+use spdx::LicenseId;
+
+const QUALIFIERS: &[&str] = &[
+    "keyword",
+    "category",
+    "owner",
+    "license",
+    "rust-version",
+    "updated",
+];
+
+#[derive(Debug, Default, PartialEq)]
+pub struct SearchQuery {
+    /// The free text part of the query, without any qualifiers
+    pub text: String,
+    /// Keywords that a crate must have, in lowercase
+    pub keywords: Vec<String>,
+    /// Category slugs that a crate must be in (or in a subcategory of)
+    pub categories: Vec<String>,
+    /// Logins of users or teams, or GitHub organizations, that must own a crate
+    pub owners: Vec<String>,
+    /// SPDX licenses, under which the default version of a crate must be
+    /// usable. If multiple licenses are given, any combination of them is
+    /// accepted.
+    pub licenses: Vec<LicenseId>,
+    /// Requirement for the `rust-version` of the default version of a crate
+    pub rust_version: Option<VersionReq>,
+    /// Only include crates that were updated at or after this time
+    pub updated_after: Option<NaiveDateTime>,
+    /// Only include crates that were updated before this time
+    pub updated_before: Option<NaiveDateTime>,
+    /// Only include crates that have at least one non-yanked version
+    pub exclude_yanked: bool,
+}
+
+impl SearchQuery {
+    pub fn parse(query: &str) -> AppResult<Self> {
+        let mut search_query = SearchQuery::default();
+        let mut text = Vec::new();
+
+        for term in query.split_whitespace() {
+            if term == "-yanked" {
+                search_query.exclude_yanked = true;
+                continue;
+            }
+
+            let Some((name, value)) = split_qualifier(term) else {
+                text.push(term);
+                continue;
+            };
+
+            if let Some(name) = name.strip_prefix('-') {
+                return Err(bad_request(format!(
+                    "the search qualifier `{name}` can not be negated"
+                )));
+            }
+
+            if value.is_empty() {
+                return Err(bad_request(format!(
+                    "missing value for the search qualifier `{name}`"
+                )));
+            }
+
+            match name {
+                "keyword" => search_query.keywords.push(value.to_lowercase()),
+                "category" => search_query.categories.push(value.to_string()),
+                "owner" => search_query.owners.push(value.to_string()),
+                "license" => search_query.add_license(value)?,
+                "rust-version" => search_query.add_rust_version(value)?,
+                "updated" => {
+                    if let Some(value) = value.strip_prefix('>') {
+                        search_query.set_updated_after(value)?;
+                    } else if let Some(value) = value.strip_prefix('<') {
+                        search_query.set_updated_before(value)?;
+                    } else {
+                        return Err(bad_request(format!(
+                            "invalid `updated` qualifier `{value}`, expected `>` or `<` followed by a date like `2024-01-31` or a number of days like `180d`"
+                        )));
+                    }
+                }
+                _ => {
+                    return Err(bad_request(format!(
+                        "unknown search qualifier `{name}`, supported qualifiers are: {}",
+                        QUALIFIERS.join(", ")
+                    )))
+                }
+            }
+        }
+
+        search_query.text = text.join(" ");
+        Ok(search_query)
+    }
+
+    /// Adds an SPDX license identifier to the accepted licenses.
+    pub fn add_license(&mut self, value: &str) -> AppResult<()> {
+        // The identifier is parsed like the license expressions of crates,
+        // since that normalizes some identifiers (e.g. `GPL-3.0-only`).
+        let license = parse_license_expr(value).ok().and_then(|expression| {
+            let mut requirements = expression.requirements();
+            let requirement = requirements.next()?;
+            match requirements.next() {
+                Some(_) => None,
+                None => requirement.req.license.id(),
+            }
+        });
+
+        let license = license
+            .ok_or_else(|| bad_request(format!("unknown SPDX license identifier `{value}`")))?;
+
+        self.licenses.push(license);
+        Ok(())
+    }
+
+    /// Adds a requirement like `<=1.70` for the `rust-version` of crates.
+    /// Multiple requirements must all be satisfied.
+    pub fn add_rust_version(&mut self, value: &str) -> AppResult<()> {
+        let requirement = parse_rust_version_req(value)
+            .ok_or_else(|| bad_request(format!("invalid `rust-version` requirement `{value}`")))?;
+
+        self.rust_version
+            .get_or_insert(VersionReq::STAR)
+            .comparators
+            .extend(requirement.comparators);
+        Ok(())
+    }
+
+    /// Restricts the results to crates that were updated at or after the
+    /// given date (e.g. `2024-01-31`) or number of days ago (e.g. `180d`).
+    pub fn set_updated_after(&mut self, value: &str) -> AppResult<()> {
+        self.updated_after = Some(parse_updated(value)?);
+        Ok(())
+    }
+
+    /// Restricts the results to crates that were last updated before the
+    /// given date (e.g. `2024-01-31`) or number of days ago (e.g. `180d`).
+    pub fn set_updated_before(&mut self, value: &str) -> AppResult<()> {
+        self.updated_before = Some(parse_updated(value)?);
+        Ok(())
+    }
+
+    /// Returns the names of the licenses of the `license:` qualifiers, in the
+    /// form that the `license_matches()` SQL function expects.
+    pub fn license_names(&self) -> Vec<&'static str> {
+        self.licenses.iter().map(|license| license.name).collect()
+    }
+
+    /// Returns the bounds for the `[major, minor, patch]` parts of the
+    /// `rust-version` of crates, which together are equivalent to the
+    /// requirement of the `rust-version:` qualifiers.
+    pub fn rust_version_bounds(&self) -> Vec<RustVersionBound> {
+        let Some(requirement) = &self.rust_version else {
+            return vec![];
+        };
+
+        requirement
+            .comparators
+            .iter()
+            .flat_map(RustVersionBound::from_comparator)
+            .collect()
+    }
+}
+
+/// A bound on the `[major, minor, patch]` parts of the `rust-version` of
+/// crates, which compare like the corresponding versions. Since the parts are
+/// integers, all comparisons can be expressed with these two bounds.
+#[derive(Debug, PartialEq)]
+pub enum RustVersionBound {
+    AtLeast(Vec<i64>),
+    Below(Vec<i64>),
+}
+
+impl RustVersionBound {
+    /// Converts a comparator into bounds, following the semver rules for
+    /// partial versions, e.g. `>1.70` is equivalent to `>=1.71.0`.
+    fn from_comparator(comparator: &Comparator) -> Vec<Self> {
+        let part = |part: u64| i64::try_from(part).unwrap_or(i64::MAX);
+        let major = part(comparator.major);
+        let minor = comparator.minor.map(part);
+        let patch = comparator.patch.map(part);
+
+        // The version that is bumped in the last given part, e.g. `1.71.0`
+        // for `1.70`, which is the exclusive upper bound of all versions
+        // starting with `1.70`.
+        let next = match (minor, patch) {
+            (Some(minor), Some(patch)) => vec![major, minor, patch.saturating_add(1)],
+            (Some(minor), None) => vec![major, minor.saturating_add(1), 0],
+            (None, _) => vec![major.saturating_add(1), 0, 0],
+        };
+        let version = vec![major, minor.unwrap_or(0), patch.unwrap_or(0)];
+
+        match comparator.op {
+            Op::Exact | Op::Wildcard => vec![Self::AtLeast(version), Self::Below(next)],
+            Op::Greater => vec![Self::AtLeast(next)],
+            Op::GreaterEq => vec![Self::AtLeast(version)],
+            Op::Less => vec![Self::Below(version)],
+            Op::LessEq => vec![Self::Below(next)],
+            // `parse_rust_version_req()` only produces the operators above,
+            // so anything else conservatively matches no crates.
+            _ => vec![Self::Below(vec![0, 0, 0])],
+        }
+    }
+}
+
+/// Splits a term like `keyword:async` into the qualifier name and value.
+///
+/// Terms that only look like qualifiers by accident (e.g. `std::io`) are
+/// treated as free text.
+fn split_qualifier(term: &str) -> Option<(&str, &str)> {
+    let (name, value) = term.split_once(':')?;
+    if value.starts_with(':') {
+        return None;
+    }
+
+    let unprefixed = name.strip_prefix('-').unwrap_or(name);
+    let mut chars = unprefixed.chars();
+    let is_name = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    is_name.then_some((name, value))
+}
+
+/// Parses a `rust-version` requirement like `<=1.70`. A version without an
+/// operator matches all crates that can be built with exactly that Rust
+/// version, e.g. `1.70` is equivalent to `<=1.70.0`.
+fn parse_rust_version_req(value: &str) -> Option<VersionReq> {
+    let version = value.trim_start_matches(['<', '>', '=']);
+    let operator = &value[..value.len() - version.len()];
+    if !matches!(operator, "" | "<" | "<=" | ">" | ">=" | "=") {
+        return None;
+    }
+
+    let full_version = parse_rust_version(version)?;
+
+    let requirement = match operator {
+        "" => format!("<={full_version}"),
+        operator => format!("{operator}{version}"),
+    };
+    VersionReq::parse(&requirement).ok()
+}
+
+/// Parses either a date like `2024-01-31` (midnight UTC) or a number of days
+/// like `180d`, which is relative to the current time.
+fn parse_updated(value: &str) -> AppResult<NaiveDateTime> {
+    let days = value
+        .strip_suffix('d')
+        .and_then(|days| days.parse::<u32>().ok());
+
+    let time = match days {
+        Some(days) => Utc::now()
+            .naive_utc()
+            .checked_sub_signed(Duration::days(days.into())),
+        None => NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .ok()
+            .and_then(|date| date.and_hms_opt(0, 0, 0)),
+    };
+
+    // Times outside of this range can't be represented by the database.
+    let time = time.filter(|time| (1..=9999).contains(&time.year()));
+
+    time.ok_or_else(|| {
+        bad_request(format!(
+            "invalid update time `{value}`, expected a date like `2024-01-31` or a number of days like `180d`"
+        ))
+    })
+}
+
+/// Parses a `rust-version` value like `1.70` into a full semver version.
+fn parse_rust_version(value: &str) -> Option<Version> {
+    let mut parts = value.split('.').map(|part| part.parse::<u64>().ok());
+    let major = parts.next()??;
+    let minor = parts.next().unwrap_or(Some(0))?;
+    let patch = parts.next().unwrap_or(Some(0))?;
+    if parts.next().is_some() {
+        return None;
+    }
+
+    Some(Version::new(major, minor, patch))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use insta::assert_snapshot;
+
+    fn error(query: &str) -> String {
+        SearchQuery::parse(query).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn parses_text() {
+        let query = SearchQuery::parse("  serde   json ").unwrap();
+        assert_eq!(query.text, "serde json");
+        assert_eq!(
+            query,
+            SearchQuery {
+                text: "serde json".into(),
+                ..Default::default()
+            }
+        );
+
+        let query = SearchQuery::parse("std::io").unwrap();
+        assert_eq!(query.text, "std::io");
+    }
+
+    #[test]
+    fn parses_qualifiers() {
+        let query = SearchQuery::parse(
+            "http keyword:Async category:network-programming license:MIT owner:rust-lang rust-version:<=1.70 -yanked",
+        )
+        .unwrap();
+
+        assert_eq!(query.text, "http");
+        assert_eq!(query.keywords, vec!["async"]);
+        assert_eq!(query.categories, vec!["network-programming"]);
+        assert_eq!(query.owners, vec!["rust-lang"]);
+        assert_eq!(query.licenses, vec![spdx::license_id("MIT").unwrap()]);
+        assert_eq!(
+            query.rust_version,
+            Some(VersionReq::parse("<=1.70").unwrap())
+        );
+        assert!(query.exclude_yanked);
+    }
+
+    #[test]
+    fn combines_rust_version_qualifiers() {
+        use RustVersionBound::*;
+
+        let query = SearchQuery::parse("rust-version:>=1.60 rust-version:<1.70").unwrap();
+        assert_eq!(
+            query.rust_version_bounds(),
+            vec![AtLeast(vec![1, 60, 0]), Below(vec![1, 70, 0])]
+        );
+    }
+
+    #[test]
+    fn rust_version_without_operator() {
+        let query = SearchQuery::parse("rust-version:1.70").unwrap();
+        // `1.70` is equivalent to `<=1.70.0`
+        assert_eq!(
+            query.rust_version_bounds(),
+            vec![RustVersionBound::Below(vec![1, 70, 1])]
+        );
+    }
+
+    #[test]
+    fn rust_version_bounds_of_partial_versions() {
+        use RustVersionBound::*;
+
+        let bounds = |query| SearchQuery::parse(query).unwrap().rust_version_bounds();
+        assert_eq!(bounds("rust-version:>1.70"), vec![AtLeast(vec![1, 71, 0])]);
+        assert_eq!(
+            bounds("rust-version:>1.70.1"),
+            vec![AtLeast(vec![1, 70, 2])]
+        );
+        assert_eq!(bounds("rust-version:<=1.70"), vec![Below(vec![1, 71, 0])]);
+        assert_eq!(bounds("rust-version:<=1"), vec![Below(vec![2, 0, 0])]);
+        assert_eq!(
+            bounds("rust-version:=1.70"),
+            vec![AtLeast(vec![1, 70, 0]), Below(vec![1, 71, 0])]
+        );
+        assert_eq!(bounds("rust"), vec![]);
+    }
+
+    #[test]
+    fn parses_updated_qualifiers() {
+        let query = SearchQuery::parse("updated:>2024-01-31 updated:<2024-03-01").unwrap();
+        let date = |month, day| {
+            NaiveDate::from_ymd_opt(2024, month, day)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+        };
+        assert_eq!(query.updated_after, date(1, 31));
+        assert_eq!(query.updated_before, date(3, 1));
+
+        let query = SearchQuery::parse("updated:>180d").unwrap();
+        let expected = Utc::now().naive_utc() - Duration::days(180);
+        let difference = query.updated_after.unwrap() - expected;
+        assert!(difference.num_seconds().abs() < 60);
+        assert_eq!(query.updated_before, None);
+    }
+
+    #[test]
+    fn license_names() {
+        let query = SearchQuery::parse("license:MIT license:GPL-3.0-only").unwrap();
+        assert_eq!(query.license_names(), vec!["MIT", "GPL-3.0"]);
+    }
+
+    #[test]
+    fn errors() {
+        assert_snapshot!(error("foo:bar"), @"unknown search qualifier `foo`, supported qualifiers are: keyword, category, owner, license, rust-version, updated");
+        assert_snapshot!(error("keyword:"), @"missing value for the search qualifier `keyword`");
+        assert_snapshot!(error("-keyword:async"), @"the search qualifier `keyword` can not be negated");
+        assert_snapshot!(error("license:mit"), @"unknown SPDX license identifier `mit`");
+        assert_snapshot!(error("license:MIT/Apache-2.0"), @"unknown SPDX license identifier `MIT/Apache-2.0`");
+        assert_snapshot!(error("rust-version:~1.70"), @"invalid `rust-version` requirement `~1.70`");
+        assert_snapshot!(error("rust-version:<=1.70.0.1"), @"invalid `rust-version` requirement `<=1.70.0.1`");
+        assert_snapshot!(error("updated:2024-01-31"), @"invalid `updated` qualifier `2024-01-31`, expected `>` or `<` followed by a date like `2024-01-31` or a number of days like `180d`");
+        assert_snapshot!(error("updated:>2024-13-01"), @"invalid update time `2024-13-01`, expected a date like `2024-01-31` or a number of days like `180d`");
+        assert_snapshot!(error("updated:<-5d"), @"invalid update time `-5d`, expected a date like `2024-01-31` or a number of days like `180d`");
+        assert_snapshot!(error("updated:>1000000d"), @"invalid update time `1000000d`, expected a date like `2024-01-31` or a number of days like `180d`");
+        assert_snapshot!(error("updated:>4294967295d"), @"invalid update time `4294967295d`, expected a date like `2024-01-31` or a number of days like `180d`");
+    }
+}
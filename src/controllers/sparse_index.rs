@@ -0,0 +1,106 @@
+//! Serves the sparse index from the application itself, for self-hosted
+//! deployments that don't have an index CDN in front of them.
+//!
+//! See <https://doc.rust-lang.org/cargo/reference/registry-index.html#sparse-protocol>
+
+use axum::body::Bytes;
+use crates_io_index::signing::{
+    index_file_metadata_path, METADATA_PREFIX, ROOT_PATH, SNAPSHOT_PATH,
+};
+use crates_io_index::Repository;
+
+use crate::auth::check_read_access;
+use crate::controllers::frontend_prelude::*;
+use crate::controllers::helpers::{etag, if_none_match};
+use crate::util::errors::{internal, not_found};
+
+const CONTENT_TYPE_JSON: &str = "application/json";
+const CONTENT_TYPE_INDEX: &str = "text/plain";
+
+/// Handles the `GET /index/config.json` route.
+pub async fn config(app: AppState, req: Parts) -> Response {
+    let base_url = format!("https://{}", app.config.domain_name);
+    let mut config = json!({
+        "dl": format!("{base_url}/api/v1/crates"),
+        "api": base_url,
+    });
+
+    // cargo fetches this file without credentials, and only sends its token
+    // for the other index files if the registry asks for it here.
+    if app.config.auth_required {
+        config["auth-required"] = json!(true);
+    }
+
+    index_response(&req, CONTENT_TYPE_JSON, config.to_string().into())
+}
+
+/// Handles the `GET /index/*path` route.
+pub async fn index_file(
+    app: AppState,
+    Path(path): Path<String>,
+    req: Parts,
+) -> AppResult<Response> {
+    if path.starts_with(METADATA_PREFIX) {
+        return metadata_file(app, path, req).await;
+    }
+
+    // Only the canonical path of an index file is accepted, e.g. `se/rd/serde`
+    let name = path.rsplit('/').next().unwrap_or_default();
+    if name.is_empty() || Repository::relative_index_file_for_url(name) != path {
+        return Err(not_found());
+    }
+
+    check_read_access(&app, &req, Some(name)).await?;
+
+    let content = app
+        .storage
+        .read_index_file(name)
+        .await
+        .map_err(|error| internal(format!("failed to read index file: {error}")))?
+        .ok_or_else(not_found)?;
+
+    Ok(index_response(&req, CONTENT_TYPE_INDEX, content))
+}
+
+/// Serves the signed metadata files of the index, see the
+/// [`crates_io_index::signing`] module.
+async fn metadata_file(app: AppState, path: String, req: Parts) -> AppResult<Response> {
+    let crate_name = match path.as_str() {
+        ROOT_PATH | SNAPSHOT_PATH => None,
+        _ => {
+            let file_name = path.rsplit('/').next().unwrap_or_default();
+            let name = file_name.strip_suffix(".json").unwrap_or_default();
+            if name.is_empty() || index_file_metadata_path(name) != path {
+                return Err(not_found());
+            }
+            Some(name)
+        }
+    };
+
+    check_read_access(&app, &req, crate_name).await?;
+
+    let content = app
+        .storage
+        .read_index_metadata(&path)
+        .await
+        .map_err(|error| internal(format!("failed to read index metadata file: {error}")))?
+        .ok_or_else(not_found)?;
+
+    Ok(index_response(&req, CONTENT_TYPE_JSON, content))
+}
+
+/// Returns the file content, or a `304 Not Modified` response if the
+/// `If-None-Match` request header shows that cargo already has it.
+fn index_response(req: &Parts, content_type: &'static str, content: Bytes) -> Response {
+    let etag = etag(&content);
+
+    if if_none_match(req, &etag) == Some(true) {
+        return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response();
+    }
+
+    let headers = [
+        (header::CONTENT_TYPE, content_type.to_string()),
+        (header::ETAG, etag),
+    ];
+    (headers, content).into_response()
+}
@@ -1,22 +1,33 @@
 use super::frontend_prelude::*;
 
-use crate::models::ApiToken;
-use crate::schema::api_tokens;
+use crate::models::{ApiToken, NewApiToken, Team, User};
+use crate::schema::{api_tokens, teams};
+use crate::util::errors::{custom, forbidden, not_found};
 use crate::util::rfc3339;
-use crate::views::EncodableApiTokenWithToken;
+use crate::views::{EncodableApiTokenUsage, EncodableApiTokenWithToken};
 
+use crate::app::App;
 use crate::auth::AuthCheck;
-use crate::models::token::{CrateScope, EndpointScope};
+use crate::models::token::{ApiTokenUsage, CrateScope, EndpointScope};
 use axum::extract::Query;
 use axum::response::IntoResponse;
-use chrono::NaiveDateTime;
+use chrono::{Duration, NaiveDateTime, Utc};
 use diesel::data_types::PgInterval;
 use diesel::dsl::{now, IntervalDsl};
+use ipnetwork::IpNetwork;
 use serde_json as json;
+use tokio::runtime::Handle;
+
+/// Number of hours that a rotated token stays valid after the new token has
+/// been created.
+const ROTATION_OVERLAP_HOURS: i64 = 1;
 
 #[derive(Deserialize)]
 pub struct GetParams {
     expired_days: Option<i32>,
+    /// Login of a team, e.g. `github:org:team`, to list the tokens of the
+    /// team instead of the tokens of the user
+    team: Option<String>,
 }
 
 impl GetParams {
@@ -40,9 +51,29 @@ pub async fn list(
         let auth = AuthCheck::only_cookie().check(&req, conn)?;
         let user = auth.user();
 
-        let tokens: Vec<ApiToken> = ApiToken::belonging_to(user)
+        let query = match &params.team {
+            Some(login) => {
+                let team = Team::find_by_login(conn, login)
+                    .optional()?
+                    .ok_or_else(|| bad_request(format!("could not find team `{login}`")))?;
+
+                if !Handle::current().block_on(team.contains_user(&app, user))? {
+                    return Err(forbidden("only members of a team can list its API tokens"));
+                }
+
+                api_tokens::table
+                    .filter(api_tokens::team_id.eq(team.id))
+                    .into_boxed()
+            }
+            None => ApiToken::belonging_to(user)
+                .filter(api_tokens::team_id.is_null())
+                .into_boxed(),
+        };
+
+        let tokens: Vec<ApiToken> = query
             .select(ApiToken::as_select())
             .filter(api_tokens::revoked.eq(false))
+            .filter(api_tokens::trusted_publisher_id.is_null())
             .filter(
                 api_tokens::expired_at.is_null().or(api_tokens::expired_at
                     .assume_not_null()
@@ -68,6 +99,11 @@ pub async fn new(app: AppState, req: BytesRequest) -> AppResult<Json<Value>> {
             endpoint_scopes: Option<Vec<String>>,
             #[serde(default, with = "rfc3339::option")]
             expired_at: Option<NaiveDateTime>,
+            /// Login of the team that should own the token, e.g.
+            /// `github:org:team`
+            team: Option<String>,
+            /// CIDR blocks or IP addresses that the token can be used from
+            allowed_ips: Option<Vec<String>>,
         }
 
         /// The incoming serialization format for the `ApiToken` model.
@@ -84,7 +120,7 @@ pub async fn new(app: AppState, req: BytesRequest) -> AppResult<Json<Value>> {
             return Err(bad_request("name must have a value"));
         }
 
-        let auth = AuthCheck::default().check(&req, conn)?;
+        let auth = AuthCheck::default().with_two_factor().check(&req, conn)?;
         if auth.api_token_id().is_some() {
             return Err(bad_request(
                 "cannot use an API token to create a new API token",
@@ -125,14 +161,63 @@ pub async fn new(app: AppState, req: BytesRequest) -> AppResult<Json<Value>> {
             .transpose()
             .map_err(|_err| bad_request("invalid endpoint scope"))?;
 
-        let api_token = ApiToken::insert_with_scopes(
-            conn,
-            user.id,
+        let allowed_ips = new
+            .api_token
+            .allowed_ips
+            .unwrap_or_default()
+            .iter()
+            .map(|block| {
+                parse_allowed_ip(block)
+                    .ok_or_else(|| bad_request(format!("invalid CIDR block: {block}")))
+            })
+            .collect::<AppResult<Vec<_>>>()?;
+
+        let team = match new.api_token.team {
+            Some(login) => {
+                let team = Team::find_by_login(conn, &login)
+                    .optional()?
+                    .ok_or_else(|| bad_request(format!("could not find team `{login}`")))?;
+
+                if !Handle::current().block_on(team.contains_user(&app, user))? {
+                    return Err(forbidden(
+                        "only members of a team can create API tokens for it",
+                    ));
+                }
+
+                Some(team)
+            }
+            None => None,
+        };
+
+        // Tokens without IP restrictions can be used from everywhere
+        let allowed_ips = (!allowed_ips.is_empty()).then_some(allowed_ips);
+
+        if team.is_some() {
+            // Team tokens must not fall back to the legacy scope, which
+            // would allow them to act on behalf of the user
+            let Some(endpoint_scopes) = &endpoint_scopes else {
+                return Err(bad_request("API tokens of a team require endpoint scopes"));
+            };
+
+            // New crates are only owned by the user that published them
+            if endpoint_scopes.contains(&EndpointScope::PublishNew) {
+                return Err(bad_request(
+                    "API tokens of a team can not have the `publish-new` scope",
+                ));
+            }
+        }
+
+        let api_token = NewApiToken {
+            user_id: user.id,
             name,
             crate_scopes,
             endpoint_scopes,
-            new.api_token.expired_at,
-        )?;
+            expired_at: new.api_token.expired_at,
+            team_id: team.map(|team| team.id),
+            allowed_ips,
+            ..Default::default()
+        }
+        .insert(conn)?;
         let api_token = EncodableApiTokenWithToken::from(api_token);
 
         Ok(Json(json!({ "api_token": api_token })))
@@ -140,21 +225,139 @@ pub async fn new(app: AppState, req: BytesRequest) -> AppResult<Json<Value>> {
     .await?
 }
 
+/// Parses a CIDR block or a single IP address, and clears the host bits,
+/// since the `cidr` column type does not accept them.
+fn parse_allowed_ip(block: &str) -> Option<IpNetwork> {
+    let network = block.parse::<IpNetwork>().ok()?;
+    IpNetwork::new(network.network(), network.prefix()).ok()
+}
+
+/// Handles the `PUT /me/tokens/:id/rotate` route.
+///
+/// Creates a new token with the same name, scopes and expiry date. The old
+/// token stays valid for a short while, so that it can be replaced
+/// everywhere it is used. It can still be revoked explicitly if it has
+/// leaked.
+pub async fn rotate(app: AppState, Path(id): Path<i32>, req: Parts) -> AppResult<Json<Value>> {
+    let conn = &mut *app.db_write().await?;
+    conn.interact(move |conn| {
+        let auth = AuthCheck::default().with_two_factor().check(&req, conn)?;
+        if auth.api_token_id().is_some() {
+            return Err(bad_request(
+                "cannot use an API token to rotate an API token",
+            ));
+        }
+
+        let user = auth.user();
+
+        conn.transaction(|conn| {
+            let token: ApiToken = api_tokens::table
+                .find(id)
+                .select(ApiToken::as_select())
+                .filter(api_tokens::revoked.eq(false))
+                .filter(api_tokens::trusted_publisher_id.is_null())
+                .filter(
+                    api_tokens::expired_at
+                        .is_null()
+                        .or(api_tokens::expired_at.assume_not_null().gt(now)),
+                )
+                .for_update()
+                .first(conn)
+                .optional()?
+                .ok_or_else(not_found)?;
+
+            if !can_manage(&app, conn, user, &token)? {
+                return Err(not_found());
+            }
+
+            if token.rotated_at.is_some() {
+                let detail = "this token has already been rotated";
+                return Err(custom(StatusCode::CONFLICT, detail));
+            }
+
+            let valid_until = Utc::now().naive_utc() + Duration::hours(ROTATION_OVERLAP_HOURS);
+            let api_token = token.rotate(conn, user.id, valid_until)?;
+            let api_token = EncodableApiTokenWithToken::from(api_token);
+
+            Ok(Json(json!({ "api_token": api_token })))
+        })
+    })
+    .await?
+}
+
+/// Handles the `GET /me/tokens/:id/usage` route.
+pub async fn usage(app: AppState, Path(id): Path<i32>, req: Parts) -> AppResult<Json<Value>> {
+    let conn = &mut *app.db_read_prefer_primary().await?;
+    conn.interact(move |conn| {
+        let auth = AuthCheck::only_cookie().check(&req, conn)?;
+        let user = auth.user();
+
+        let token: ApiToken = api_tokens::table
+            .find(id)
+            .select(ApiToken::as_select())
+            .first(conn)
+            .optional()?
+            .ok_or_else(not_found)?;
+
+        if !can_manage(&app, conn, user, &token)? {
+            return Err(not_found());
+        }
+
+        let usage = ApiTokenUsage::recent(conn, token.id)?
+            .into_iter()
+            .map(EncodableApiTokenUsage::from)
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({ "usage": usage })))
+    })
+    .await?
+}
+
 /// Handles the `DELETE /me/tokens/:id` route.
 pub async fn revoke(app: AppState, Path(id): Path<i32>, req: Parts) -> AppResult<Json<Value>> {
     let conn = &mut *app.db_write().await?;
     conn.interact(move |conn| {
         let auth = AuthCheck::default().check(&req, conn)?;
         let user = auth.user();
-        diesel::update(ApiToken::belonging_to(user).find(id))
-            .set(api_tokens::revoked.eq(true))
-            .execute(conn)?;
+
+        let token: Option<ApiToken> = api_tokens::table
+            .find(id)
+            .select(ApiToken::as_select())
+            .first(conn)
+            .optional()?;
+
+        if let Some(token) = token {
+            if can_manage(&app, conn, user, &token)? {
+                diesel::update(api_tokens::table.find(token.id))
+                    .set(api_tokens::revoked.eq(true))
+                    .execute(conn)?;
+            }
+        }
 
         Ok(Json(json!({})))
     })
     .await?
 }
 
+/// Checks whether the user can list, rotate and revoke the token.
+///
+/// Tokens that are owned by a team can be managed by all current members of
+/// the team, independently of the member that created them. All other tokens
+/// can only be managed by their user.
+fn can_manage(
+    app: &App,
+    conn: &mut PgConnection,
+    user: &User,
+    token: &ApiToken,
+) -> AppResult<bool> {
+    let Some(team_id) = token.team_id else {
+        return Ok(token.user_id == user.id);
+    };
+
+    let team: Team = teams::table.find(team_id).first(conn)?;
+    Handle::current().block_on(team.contains_user(app, user))
+}
+
 /// Handles the `DELETE /tokens/current` route.
 pub async fn revoke_current(app: AppState, req: Parts) -> AppResult<Response> {
     let conn = &mut *app.db_write().await?;
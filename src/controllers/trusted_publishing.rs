@@ -0,0 +1,336 @@
+//! Endpoints for managing trusted publishers of a crate and for exchanging
+//! OIDC identity tokens of CI workflows for short-lived publish tokens.
+
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::controllers::helpers::{ensure_owner, find_crate};
+use crate::models::token::{CrateScope, EndpointScope};
+use crate::models::{NewApiToken, NewTrustedPublisher, Rights, TrustedPublisher, User};
+use crate::schema::{api_tokens, trusted_publishers};
+use crate::util::errors::{custom, forbidden};
+use crate::views::{EncodableApiTokenWithToken, EncodableTrustedPublisher};
+use chrono::Utc;
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use tokio::runtime::Handle;
+
+/// The signature algorithms used by the OIDC issuers of common CI providers
+const SUPPORTED_ALGORITHMS: [Algorithm; 2] = [Algorithm::RS256, Algorithm::ES256];
+
+/// Allowed clock skew in seconds when validating the `exp`, `nbf` and `iat`
+/// claims
+const CLOCK_SKEW_LEEWAY: u64 = 60;
+
+/// The subset of the GitHub Actions OIDC identity token claims that we care
+/// about.
+///
+/// See <https://docs.github.com/en/actions/deployment/security-hardening-your-deployments/about-security-hardening-with-openid-connect#understanding-the-oidc-token>.
+#[derive(Debug, Deserialize)]
+struct IdentityClaims {
+    iat: Option<i64>,
+    /// The repository in `owner/name` format
+    repository: String,
+    repository_owner: String,
+    /// The workflow file in `owner/name/.github/workflows/file.yml@ref` format
+    workflow_ref: String,
+    environment: Option<String>,
+}
+
+impl IdentityClaims {
+    fn repository_name(&self) -> Option<&str> {
+        let (owner, name) = self.repository.split_once('/')?;
+        owner
+            .eq_ignore_ascii_case(&self.repository_owner)
+            .then_some(name)
+    }
+
+    fn workflow_filename(&self) -> Option<&str> {
+        let (path, _ref) = self.workflow_ref.split_once('@')?;
+        let path = path.strip_prefix(&self.repository)?;
+        path.strip_prefix("/.github/workflows/")
+    }
+}
+
+/// Verifies the signature and the claims of an OIDC identity token.
+async fn verify_identity_token(state: &AppState, token: &str) -> AppResult<IdentityClaims> {
+    let config = &state.config.trusted_publishing;
+
+    let header =
+        jsonwebtoken::decode_header(token).map_err(|e| bad_request(format!("invalid JWT: {e}")))?;
+    if !SUPPORTED_ALGORITHMS.contains(&header.alg) {
+        return Err(bad_request("invalid JWT: unsupported signature algorithm"));
+    }
+    let kid = header
+        .kid
+        .ok_or_else(|| bad_request("invalid JWT: missing key id"))?;
+
+    let keys = state
+        .jwks_cache
+        .get(&config.jwks_url, &kid)
+        .await
+        .map_err(|e| {
+            warn!("Failed to fetch JWKS from {}: {e}", config.jwks_url);
+            custom(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "failed to fetch the public keys of the OIDC issuer",
+            )
+        })?;
+
+    let key = keys
+        .find(&kid)
+        .ok_or_else(|| bad_request(format!("invalid JWT: unknown key id `{kid}`")))?;
+    let key = DecodingKey::from_jwk(key).map_err(|e| bad_request(format!("invalid JWT: {e}")))?;
+
+    let mut validation = Validation::new(header.alg);
+    validation.leeway = CLOCK_SKEW_LEEWAY;
+    validation.validate_nbf = true;
+    validation.set_issuer(&[&config.issuer]);
+    validation.set_audience(&[&config.audience]);
+    validation.set_required_spec_claims(&["exp", "iss", "aud"]);
+
+    let claims = jsonwebtoken::decode::<IdentityClaims>(token, &key, &validation)
+        .map_err(|e| match e.kind() {
+            ErrorKind::InvalidIssuer => bad_request("invalid JWT: unexpected issuer"),
+            ErrorKind::InvalidAudience => bad_request("invalid JWT: unexpected audience"),
+            ErrorKind::ExpiredSignature => bad_request("invalid JWT: token has expired"),
+            ErrorKind::ImmatureSignature => bad_request("invalid JWT: token is not valid yet"),
+            _ => bad_request(format!("invalid JWT: {e}")),
+        })?
+        .claims;
+
+    // The `iat` claim is not validated by `jsonwebtoken`
+    let now = Utc::now().timestamp();
+    if claims
+        .iat
+        .is_some_and(|iat| iat - CLOCK_SKEW_LEEWAY as i64 > now)
+    {
+        return Err(bad_request("invalid JWT: token is not valid yet"));
+    }
+
+    Ok(claims)
+}
+
+/// Handles the `GET /crates/:crate_id/trusted_publishers` route.
+pub async fn list(
+    app: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    let conn = app.db_read_prefer_primary().await?;
+    conn.interact(move |conn| {
+        let auth = AuthCheck::only_cookie().check(&req, conn)?;
+
+        let krate = find_crate(conn, &crate_name)?;
+        ensure_owner(&app, conn, auth.user(), &krate, "trusted publishers")?;
+
+        let trusted_publishers = TrustedPublisher::for_crate(conn, krate.id)?
+            .into_iter()
+            .map(|publisher| EncodableTrustedPublisher::from(publisher, &krate.name))
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({ "trusted_publishers": trusted_publishers })))
+    })
+    .await?
+}
+
+#[derive(Deserialize)]
+pub struct NewTrustedPublisherRequest {
+    trusted_publisher: NewTrustedPublisherBody,
+}
+
+#[derive(Deserialize)]
+pub struct NewTrustedPublisherBody {
+    repository_owner: String,
+    repository_name: String,
+    workflow_filename: String,
+    environment: Option<String>,
+}
+
+impl NewTrustedPublisherBody {
+    fn validate(&self) -> AppResult<()> {
+        fn is_valid_segment(value: &str) -> bool {
+            !value.is_empty() && !value.contains('/') && !value.chars().any(char::is_whitespace)
+        }
+
+        if !is_valid_segment(&self.repository_owner) {
+            return Err(bad_request("invalid repository owner"));
+        }
+
+        if !is_valid_segment(&self.repository_name) {
+            return Err(bad_request("invalid repository name"));
+        }
+
+        let workflow_filename = &self.workflow_filename;
+        if !is_valid_segment(workflow_filename)
+            || !(workflow_filename.ends_with(".yml") || workflow_filename.ends_with(".yaml"))
+        {
+            return Err(bad_request(
+                "workflow filename must be a `.yml` or `.yaml` file name without a path",
+            ));
+        }
+
+        if self.environment.as_ref().is_some_and(|env| env.is_empty()) {
+            return Err(bad_request("environment must not be empty"));
+        }
+
+        Ok(())
+    }
+}
+
+/// Handles the `PUT /crates/:crate_id/trusted_publishers` route.
+pub async fn create(
+    app: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+    Json(body): Json<NewTrustedPublisherRequest>,
+) -> AppResult<Json<Value>> {
+    let body = body.trusted_publisher;
+    body.validate()?;
+
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let auth = AuthCheck::only_cookie()
+            .with_two_factor()
+            .check(&req, conn)?;
+        let user = auth.user();
+
+        let krate = find_crate(conn, &crate_name)?;
+        ensure_owner(&app, conn, user, &krate, "trusted publishers")?;
+
+        let trusted_publisher = NewTrustedPublisher {
+            crate_id: krate.id,
+            repository_owner: &body.repository_owner,
+            repository_name: &body.repository_name,
+            workflow_filename: &body.workflow_filename,
+            environment: body.environment.as_deref(),
+            created_by: user.id,
+        }
+        .insert(conn)?;
+
+        let trusted_publisher = EncodableTrustedPublisher::from(trusted_publisher, &krate.name);
+        Ok(Json(json!({ "trusted_publisher": trusted_publisher })))
+    })
+    .await?
+}
+
+/// Handles the `DELETE /crates/:crate_id/trusted_publishers/:id` route.
+pub async fn delete(
+    app: AppState,
+    Path((crate_name, id)): Path<(String, i32)>,
+    req: Parts,
+) -> AppResult<Response> {
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let auth = AuthCheck::only_cookie()
+            .with_two_factor()
+            .check(&req, conn)?;
+
+        let krate = find_crate(conn, &crate_name)?;
+        ensure_owner(&app, conn, auth.user(), &krate, "trusted publishers")?;
+
+        conn.transaction(|conn| {
+            // Revoke all tokens that were minted for this configuration
+            diesel::update(api_tokens::table)
+                .filter(api_tokens::trusted_publisher_id.eq(id))
+                .set(api_tokens::revoked.eq(true))
+                .execute(conn)?;
+
+            let deleted = diesel::delete(trusted_publishers::table)
+                .filter(trusted_publishers::id.eq(id))
+                .filter(trusted_publishers::crate_id.eq(krate.id))
+                .execute(conn)?;
+
+            if deleted == 0 {
+                return Err(custom(
+                    StatusCode::NOT_FOUND,
+                    "trusted publisher configuration not found",
+                ));
+            }
+
+            ok_true()
+        })
+    })
+    .await?
+}
+
+#[derive(Deserialize)]
+pub struct ExchangeRequest {
+    /// The OIDC identity token of the CI workflow
+    jwt: String,
+    /// The name of the crate that the CI workflow wants to publish
+    #[serde(rename = "crate")]
+    krate: String,
+}
+
+/// Handles the `PUT /trusted_publishing/tokens` route.
+///
+/// Verifies the OIDC identity token of a CI workflow and, if it matches one
+/// of the trusted publishers of the requested crate, returns a short-lived
+/// API token that can only be used to publish new versions of that crate.
+pub async fn exchange(app: AppState, Json(body): Json<ExchangeRequest>) -> AppResult<Json<Value>> {
+    let claims = verify_identity_token(&app, &body.jwt).await?;
+
+    let repository_name = claims
+        .repository_name()
+        .ok_or_else(|| bad_request("invalid JWT: malformed `repository` claim"))?
+        .to_string();
+    let workflow_filename = claims
+        .workflow_filename()
+        .ok_or_else(|| bad_request("invalid JWT: malformed `workflow_ref` claim"))?
+        .to_string();
+
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let crate_name = body.krate;
+        let krate = find_crate(conn, &crate_name)?;
+
+        let trusted_publisher = TrustedPublisher::for_crate(conn, krate.id)?
+            .into_iter()
+            .find(|publisher| {
+                publisher.matches(
+                    &claims.repository_owner,
+                    &repository_name,
+                    &workflow_filename,
+                    claims.environment.as_deref(),
+                )
+            })
+            .ok_or_else(|| {
+                forbidden(format!(
+                    "no trusted publisher configuration of `{crate_name}` matches the identity token"
+                ))
+            })?;
+
+        // The minted token is owned by the user that created the
+        // configuration, so they have to still be an owner of the crate.
+        let user = User::find(conn, trusted_publisher.created_by)?;
+        let owners = krate.owners(conn)?;
+        if Handle::current().block_on(user.rights(&app, &owners))? < Rights::Publish {
+            return Err(forbidden(
+                "the trusted publisher configuration was created by a user that is no longer an owner of the crate",
+            ));
+        }
+
+        let name = format!(
+            "Trusted publishing: {}/{}/{}",
+            claims.repository_owner, repository_name, workflow_filename
+        );
+        let crate_scope = CrateScope::try_from(krate.name.as_str()).map_err(bad_request)?;
+        let expired_at = (Utc::now() + app.config.trusted_publishing.token_lifetime).naive_utc();
+
+        // The token is only allowed to publish new versions of the crate
+        let api_token = NewApiToken {
+            user_id: user.id,
+            name: &name,
+            crate_scopes: Some(vec![crate_scope]),
+            endpoint_scopes: Some(vec![EndpointScope::PublishUpdate]),
+            expired_at: Some(expired_at),
+            trusted_publisher_id: Some(trusted_publisher.id),
+            ..Default::default()
+        }
+        .insert(conn)?;
+
+        let api_token = EncodableApiTokenWithToken::from(api_token);
+        Ok(Json(json!({ "api_token": api_token })))
+    })
+    .await?
+}
@@ -108,7 +108,7 @@ pub async fn update_user(
         use self::emails::user_id;
         use diesel::insert_into;
 
-        let auth = AuthCheck::default().check(&req, conn)?;
+        let auth = AuthCheck::default().with_two_factor().check(&req, conn)?;
         let user = auth.user();
 
         // need to check if current user matches user to be updated
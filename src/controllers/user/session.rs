@@ -5,6 +5,7 @@ use oauth2::reqwest::http_client;
 use oauth2::{AuthorizationCode, CsrfToken, Scope, TokenResponse};
 use tokio::runtime::Handle;
 
+use crate::auth::TWO_FACTOR_SESSION_KEY;
 use crate::email::Emails;
 use crate::middleware::log_request::RequestLogExt;
 use crate::middleware::session::SessionExtension;
@@ -113,6 +114,7 @@ pub async fn authorize(
 
         // Log in by setting a cookie and the middleware authentication
         session.insert("user_id".to_string(), user.id.to_string());
+        session.remove(TWO_FACTOR_SESSION_KEY);
 
         Ok(())
     })
@@ -154,6 +156,7 @@ fn save_user_to_database(
 /// Handles the `DELETE /api/private/session` route.
 pub async fn logout(session: SessionExtension) -> Json<bool> {
     session.remove("user_id");
+    session.remove(TWO_FACTOR_SESSION_KEY);
     Json(true)
 }
 
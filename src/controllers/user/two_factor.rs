@@ -0,0 +1,163 @@
+//! Endpoints for enrolling in two-factor authentication and for confirming
+//! the identity of the user before sensitive actions.
+//!
+//! Users that enabled two-factor authentication have to submit a code from
+//! their authenticator app (or one of their recovery codes) to
+//! `PUT /api/private/session/two_factor` before performing actions that use
+//! `AuthCheck::with_two_factor()`. The verification is stored in the session
+//! cookie and expires after a few minutes. This is only enforced if the
+//! `TWO_FACTOR_STEP_UP` option is enabled.
+
+use crate::auth::{AuthCheck, TWO_FACTOR_SESSION_KEY};
+use crate::controllers::frontend_prelude::*;
+use crate::middleware::session::SessionExtension;
+use crate::models::{NewTotpCredential, TotpCredential};
+use crate::rate_limiter::LimitedAction;
+use crate::util::errors::forbidden;
+use crate::util::totp;
+use chrono::Utc;
+
+#[derive(Deserialize)]
+pub struct CodeRequest {
+    code: String,
+}
+
+/// Handles the `GET /me/two_factor` route.
+pub async fn show(app: AppState, req: Parts) -> AppResult<Json<Value>> {
+    let conn = app.db_read_prefer_primary().await?;
+    conn.interact(move |conn| {
+        let auth = AuthCheck::only_cookie().check(&req, conn)?;
+
+        let credential = TotpCredential::find(conn, auth.user_id())?
+            .filter(|credential| credential.enabled_at.is_some());
+
+        let recovery_codes_remaining = credential
+            .as_ref()
+            .map(|credential| credential.recovery_codes.len())
+            .unwrap_or_default();
+
+        Ok(Json(json!({
+            "two_factor": {
+                "enabled": credential.is_some(),
+                "recovery_codes_remaining": recovery_codes_remaining,
+            }
+        })))
+    })
+    .await?
+}
+
+/// Handles the `PUT /me/two_factor` route.
+///
+/// Starts the enrollment by generating a new secret, which has to be added
+/// to an authenticator app and confirmed via `PUT /me/two_factor/confirm`.
+pub async fn begin_enrollment(app: AppState, req: Parts) -> AppResult<Json<Value>> {
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let auth = AuthCheck::only_cookie().check(&req, conn)?;
+        let user = auth.user();
+
+        if TotpCredential::is_enabled(conn, user.id)? {
+            return Err(bad_request("two-factor authentication is already enabled"));
+        }
+
+        let secret = totp::generate_secret();
+        NewTotpCredential {
+            user_id: user.id,
+            secret: &secret,
+        }
+        .insert(conn)?;
+
+        let issuer = &app.config.domain_name;
+        Ok(Json(json!({
+            "secret": totp::encode_secret(&secret),
+            "provisioning_uri": totp::provisioning_uri(&secret, issuer, &user.gh_login),
+        })))
+    })
+    .await?
+}
+
+/// Handles the `PUT /me/two_factor/confirm` route.
+///
+/// Enables two-factor authentication if the code matches the secret of the
+/// pending enrollment, and returns the recovery codes. The recovery codes
+/// can't be retrieved again later.
+pub async fn confirm_enrollment(
+    app: AppState,
+    session: SessionExtension,
+    req: Parts,
+    Json(body): Json<CodeRequest>,
+) -> AppResult<Json<Value>> {
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let auth = AuthCheck::only_cookie().check(&req, conn)?;
+        let user = auth.user();
+
+        let credential = TotpCredential::find(conn, user.id)?
+            .filter(|credential| credential.enabled_at.is_none())
+            .ok_or_else(|| bad_request("no pending two-factor enrollment found"))?;
+
+        app.rate_limiter
+            .check_rate_limit(user.id, LimitedAction::TwoFactorVerification, conn)?;
+
+        if !credential.verify(conn, &body.code)? {
+            return Err(bad_request("invalid two-factor authentication code"));
+        }
+
+        let recovery_codes = credential.enable(conn)?;
+
+        let now = Utc::now().timestamp().to_string();
+        session.insert(TWO_FACTOR_SESSION_KEY.to_string(), now);
+
+        Ok(Json(json!({ "recovery_codes": recovery_codes })))
+    })
+    .await?
+}
+
+/// Handles the `DELETE /me/two_factor` route.
+pub async fn disable(app: AppState, req: Parts) -> AppResult<Response> {
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let auth = AuthCheck::only_cookie()
+            .with_two_factor()
+            .check(&req, conn)?;
+
+        TotpCredential::delete(conn, auth.user_id())?;
+
+        ok_true()
+    })
+    .await?
+}
+
+/// Handles the `PUT /api/private/session/two_factor` route.
+///
+/// Confirms the identity of the user with a code from their authenticator
+/// app or one of their recovery codes.
+pub async fn verify(
+    app: AppState,
+    session: SessionExtension,
+    req: Parts,
+    Json(body): Json<CodeRequest>,
+) -> AppResult<Response> {
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let auth = AuthCheck::only_cookie().check(&req, conn)?;
+        let user = auth.user();
+
+        let credential = TotpCredential::find(conn, user.id)?
+            .filter(|credential| credential.enabled_at.is_some())
+            .ok_or_else(|| bad_request("two-factor authentication is not enabled"))?;
+
+        app.rate_limiter
+            .check_rate_limit(user.id, LimitedAction::TwoFactorVerification, conn)?;
+
+        if !credential.verify(conn, &body.code)? {
+            return Err(forbidden("invalid two-factor authentication code"));
+        }
+
+        let now = Utc::now().timestamp().to_string();
+        session.insert(TWO_FACTOR_SESSION_KEY.to_string(), now);
+
+        ok_true()
+    })
+    .await?
+}
@@ -1,3 +1,4 @@
+pub mod dependency_tree;
 pub mod downloads;
 pub mod metadata;
 pub mod yank;
@@ -0,0 +1,583 @@
+//! Endpoint for resolving the transitive dependency graph of a crate version
+//!
+//! Features are activated following the same rules as cargo, including the
+//! `dep:` and `dep?/feature` syntax of the index `features2` field. Versions
+//! are picked greedily though: each requirement resolves to the highest
+//! non-yanked version matching it, unless a semver-compatible version of the
+//! same crate satisfying the requirement was already picked elsewhere in the
+//! graph. There is no backtracking, so the result may differ from a
+//! `Cargo.lock` file in rare cases.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use cfg_expr::targets::{get_builtin_target_by_triple, TargetInfo};
+use cfg_expr::{Expression, Predicate};
+use indexmap::IndexMap;
+
+use crate::controllers::frontend_prelude::*;
+
+use crate::models::{Dependency, DependencyKind};
+use crate::schema::{crates, dependencies, versions};
+use crate::util::errors::version_not_found;
+use crate::views::{EncodableResolvedCrate, EncodableResolvedDependency};
+
+use super::version_and_crate;
+
+/// The maximum number of crate versions in a resolved dependency tree.
+const MAX_TREE_SIZE: usize = 500;
+
+type Features = BTreeMap<String, Vec<String>>;
+
+/// Handles the `GET /crates/:crate_id/:version/dependency_tree` route.
+///
+/// The following query parameters are supported:
+///
+/// - `features`: comma-separated list of features to enable on the root crate
+/// - `default_features`: set to `false` to disable the default features of
+///   the root crate
+/// - `target`: only include platform-specific dependencies that apply to the
+///   given target triple (all of them are included by default)
+/// - `include_dev`: set to `yes` to include the dev-dependencies of the root
+///   crate
+///
+/// The root crate is always the first entry of the returned `crates` list.
+pub async fn dependency_tree(
+    state: AppState,
+    Path((crate_name, version)): Path<(String, String)>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    if semver::Version::parse(&version).is_err() {
+        return Err(version_not_found(&crate_name, &version));
+    }
+
+    let params = req.query();
+
+    let mut features = params
+        .get("features")
+        .map(|features| {
+            features
+                .split(',')
+                .map(str::trim)
+                .filter(|feature| !feature.is_empty())
+                .map(ToString::to_string)
+                .collect::<BTreeSet<_>>()
+        })
+        .unwrap_or_default();
+
+    if params.get("default_features").map(String::as_str) != Some("false") {
+        features.insert("default".to_string());
+    }
+
+    let target = match params.get("target") {
+        Some(triple) => Some(
+            get_builtin_target_by_triple(triple)
+                .ok_or_else(|| bad_request(format_args!("unknown target `{triple}`")))?,
+        ),
+        None => None,
+    };
+
+    let include_dev = params.get("include_dev").map(String::as_str) == Some("yes");
+
+    let conn = state.db_read().await?;
+    conn.interact(move |conn| {
+        let (version, krate) = version_and_crate(conn, &crate_name, &version)?;
+
+        let root = Node {
+            crate_id: krate.id,
+            name: krate.name,
+            num: semver::Version::parse(&version.num)
+                .map_err(|_| version_not_found(&crate_name, &version.num))?,
+            features: Some(parse_features(version.features)),
+            requested: features,
+            enabled: BTreeSet::new(),
+            dependencies: Vec::new(),
+        };
+
+        let resolver = Resolver {
+            conn,
+            target,
+            include_dev,
+            candidates: HashMap::new(),
+            nodes: IndexMap::new(),
+        };
+
+        let crates = resolver.resolve(version.id, root)?;
+        Ok(Json(json!({ "crates": crates })))
+    })
+    .await?
+}
+
+struct Resolver<'a> {
+    conn: &'a mut PgConnection,
+    target: Option<&'static TargetInfo>,
+    include_dev: bool,
+    /// Non-yanked versions of each crate, sorted from highest to lowest.
+    candidates: HashMap<i32, Vec<(i32, semver::Version)>>,
+    /// Selected crate versions keyed by version ID, in discovery order.
+    nodes: IndexMap<i32, Node>,
+}
+
+struct Node {
+    crate_id: i32,
+    name: String,
+    num: semver::Version,
+    /// Feature definitions of this crate version, which are loaded in bulk
+    /// before the version is processed.
+    features: Option<Features>,
+    /// Features requested by the dependents of this crate version.
+    requested: BTreeSet<String>,
+    /// Features enabled after following the feature definitions.
+    enabled: BTreeSet<String>,
+    dependencies: Vec<EncodableResolvedDependency>,
+}
+
+impl Resolver<'_> {
+    fn resolve(mut self, root_id: i32, root: Node) -> AppResult<Vec<EncodableResolvedCrate>> {
+        self.nodes.insert(root_id, root);
+
+        // The tree is resolved level by level, so that the dependencies,
+        // candidate versions and features of all crate versions in a level
+        // can be loaded with one query each.
+        let mut level = vec![root_id];
+        while !level.is_empty() {
+            let mut seen = HashSet::new();
+            level.retain(|version_id| seen.insert(*version_id));
+
+            self.load_features(&level)?;
+            let mut dependencies = self.load_dependencies(&level, root_id)?;
+            let crate_ids = dependencies.values().flatten().map(|(dep, _)| dep.crate_id);
+            self.load_candidates(crate_ids.collect())?;
+
+            let mut next_level = Vec::new();
+            for version_id in level {
+                let is_root = version_id == root_id;
+                let deps = dependencies.remove(&version_id).unwrap_or_default();
+                next_level.extend(self.process(version_id, is_root, deps)?);
+            }
+            level = next_level;
+        }
+
+        let crates = self
+            .nodes
+            .into_values()
+            .map(|node| EncodableResolvedCrate {
+                name: node.name,
+                version: node.num.to_string(),
+                features: node.enabled.into_iter().collect(),
+                dependencies: node.dependencies,
+            })
+            .collect();
+
+        Ok(crates)
+    }
+
+    /// Loads the feature definitions of the given crate versions, unless
+    /// they are already known.
+    fn load_features(&mut self, version_ids: &[i32]) -> QueryResult<()> {
+        let missing = version_ids
+            .iter()
+            .copied()
+            .filter(|id| self.nodes[id].features.is_none())
+            .collect::<Vec<_>>();
+
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        let features: Vec<(i32, Value)> = versions::table
+            .filter(versions::id.eq_any(missing))
+            .select((versions::id, versions::features))
+            .load(self.conn)?;
+
+        for (version_id, features) in features {
+            if let Some(node) = self.nodes.get_mut(&version_id) {
+                node.features = Some(parse_features(features));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Loads the dependencies of the given crate versions that apply to the
+    /// requested tree, keyed by version ID.
+    fn load_dependencies(
+        &mut self,
+        version_ids: &[i32],
+        root_id: i32,
+    ) -> QueryResult<HashMap<i32, Vec<(Dependency, String)>>> {
+        let deps: Vec<(Dependency, String)> = dependencies::table
+            .inner_join(crates::table)
+            .filter(dependencies::version_id.eq_any(version_ids))
+            .select((dependencies::all_columns, crates::name))
+            .order((dependencies::optional, crates::name))
+            .load(self.conn)?;
+
+        let is_included = |dep: &Dependency| {
+            let is_root = dep.version_id == root_id;
+            (dep.kind != DependencyKind::Dev || (is_root && self.include_dev))
+                && self.matches_target(dep.target.as_deref())
+        };
+
+        let mut dependencies = HashMap::<_, Vec<_>>::new();
+        for (dep, crate_name) in deps.into_iter().filter(|(dep, _)| is_included(dep)) {
+            dependencies
+                .entry(dep.version_id)
+                .or_default()
+                .push((dep, crate_name));
+        }
+
+        Ok(dependencies)
+    }
+
+    /// Loads the non-yanked versions of the given crates, unless they are
+    /// already known.
+    fn load_candidates(&mut self, crate_ids: HashSet<i32>) -> QueryResult<()> {
+        let missing = crate_ids
+            .into_iter()
+            .filter(|crate_id| !self.candidates.contains_key(crate_id))
+            .collect::<Vec<_>>();
+
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        let versions: Vec<(i32, i32, String)> = versions::table
+            .filter(versions::crate_id.eq_any(&missing))
+            .filter(versions::yanked.eq(false))
+            .select((versions::crate_id, versions::id, versions::num))
+            .load(self.conn)?;
+
+        let mut candidates = missing
+            .into_iter()
+            .map(|crate_id| (crate_id, Vec::new()))
+            .collect::<HashMap<_, _>>();
+
+        for (crate_id, id, num) in versions {
+            if let (Some(versions), Ok(num)) =
+                (candidates.get_mut(&crate_id), semver::Version::parse(&num))
+            {
+                versions.push((id, num));
+            }
+        }
+
+        for (crate_id, mut versions) in candidates {
+            versions.sort_by(|(_, a), (_, b)| b.cmp(a));
+            self.candidates.insert(crate_id, versions);
+        }
+
+        Ok(())
+    }
+
+    /// Activates the requested features of a crate version and selects
+    /// versions for all of its enabled dependencies.
+    ///
+    /// Returns the IDs of the crate versions that have to be (re)processed
+    /// because they were added to the tree or got new features requested.
+    fn process(
+        &mut self,
+        version_id: i32,
+        is_root: bool,
+        deps: Vec<(Dependency, String)>,
+    ) -> AppResult<Vec<i32>> {
+        let node = &self.nodes[&version_id];
+        let optional_deps = deps
+            .iter()
+            .filter(|(dep, _)| dep.optional)
+            .map(|(dep, crate_name)| dep.explicit_name.as_deref().unwrap_or(crate_name))
+            .collect::<BTreeSet<_>>();
+
+        let no_features = Features::new();
+        let features = node.features.as_ref().unwrap_or(&no_features);
+        let activation = Activation::new(features, &optional_deps, &node.requested);
+        if is_root {
+            if let Some(feature) = activation.unknown_features.first() {
+                return Err(bad_request(format_args!(
+                    "crate `{}` does not have a feature `{feature}`",
+                    node.name
+                )));
+            }
+        }
+
+        let mut updated = Vec::new();
+        let mut edges = Vec::with_capacity(deps.len());
+        for (dep, crate_name) in deps {
+            let name = dep.explicit_name.unwrap_or_else(|| crate_name.clone());
+            if dep.optional && !activation.dependencies.contains(&name) {
+                continue;
+            }
+
+            let mut requested = dep.features.into_iter().collect::<BTreeSet<_>>();
+            if dep.default_features {
+                requested.insert("default".to_string());
+            }
+            if let Some(features) = activation.dependency_features.get(&name) {
+                requested.extend(features.iter().cloned());
+            }
+
+            let selected = self.select(dep.crate_id, &dep.req);
+            if let Some((selected_id, num)) = &selected {
+                if let Some(child) = self.nodes.get_mut(selected_id) {
+                    let len = child.requested.len();
+                    child.requested.extend(requested);
+                    if child.requested.len() > len {
+                        updated.push(*selected_id);
+                    }
+                } else {
+                    if self.nodes.len() >= MAX_TREE_SIZE {
+                        return Err(bad_request(format_args!(
+                            "the dependency tree has more than {MAX_TREE_SIZE} crate versions"
+                        )));
+                    }
+
+                    let child = Node {
+                        crate_id: dep.crate_id,
+                        name: crate_name.clone(),
+                        num: num.clone(),
+                        features: None,
+                        requested,
+                        enabled: BTreeSet::new(),
+                        dependencies: Vec::new(),
+                    };
+
+                    self.nodes.insert(*selected_id, child);
+                    updated.push(*selected_id);
+                }
+            }
+
+            edges.push(EncodableResolvedDependency {
+                name,
+                crate_id: crate_name,
+                req: dep.req,
+                optional: dep.optional,
+                target: dep.target,
+                kind: dep.kind,
+                version: selected.map(|(_, num)| num.to_string()),
+            });
+        }
+
+        let node = &mut self.nodes[&version_id];
+        node.enabled = activation.features;
+        node.dependencies = edges;
+
+        Ok(updated)
+    }
+
+    /// Selects the crate version that a dependency requirement resolves to.
+    ///
+    /// The candidate versions of the crate must have been loaded already.
+    fn select(&self, crate_id: i32, req: &str) -> Option<(i32, semver::Version)> {
+        let req = semver::VersionReq::parse(req).ok()?;
+
+        let (id, best) = self
+            .candidates
+            .get(&crate_id)?
+            .iter()
+            .find(|(_, num)| req.matches(num))?;
+
+        // Like cargo, reuse a version that was already picked for another
+        // requirement, if it is semver-compatible with the best match.
+        let existing = self
+            .nodes
+            .iter()
+            .filter(|(_, node)| node.crate_id == crate_id && req.matches(&node.num))
+            .filter(|(_, node)| compatibility(&node.num) == compatibility(best))
+            .max_by(|(_, a), (_, b)| a.num.cmp(&b.num))
+            .map(|(id, node)| (*id, node.num.clone()));
+
+        existing.or_else(|| Some((*id, best.clone())))
+    }
+
+    /// Checks whether a platform-specific dependency applies to the requested
+    /// target. Without a target, all dependencies apply.
+    fn matches_target(&self, dependency_target: Option<&str>) -> bool {
+        let (Some(target), Some(dependency_target)) = (self.target, dependency_target) else {
+            return true;
+        };
+
+        if !dependency_target.starts_with("cfg(") {
+            return dependency_target == target.triple.as_str();
+        }
+
+        Expression::parse(dependency_target)
+            .map(|expression| {
+                expression.eval(|predicate| match predicate {
+                    Predicate::Target(predicate) => predicate.matches(target),
+                    _ => false,
+                })
+            })
+            .unwrap_or(false)
+    }
+}
+
+/// The features and optional dependencies that are enabled on a crate version.
+#[derive(Debug, Default)]
+struct Activation {
+    features: BTreeSet<String>,
+    /// Names (as used in `Cargo.toml`) of the enabled optional dependencies.
+    dependencies: BTreeSet<String>,
+    /// Features to enable on dependencies, keyed by dependency name.
+    dependency_features: BTreeMap<String, BTreeSet<String>>,
+    unknown_features: Vec<String>,
+}
+
+impl Activation {
+    fn new(
+        features: &Features,
+        optional_deps: &BTreeSet<&str>,
+        requested: &BTreeSet<String>,
+    ) -> Self {
+        // Optional dependencies have an implicit feature of the same name,
+        // unless they are referenced via `dep:` somewhere.
+        let explicit_deps = features
+            .values()
+            .flatten()
+            .filter_map(|value| value.strip_prefix("dep:"))
+            .collect::<BTreeSet<_>>();
+
+        let has_implicit_feature =
+            |name: &str| optional_deps.contains(name) && !explicit_deps.contains(name);
+
+        let mut activation = Self::default();
+        let mut visited = BTreeSet::new();
+        let mut stack = requested.iter().map(String::as_str).collect::<Vec<_>>();
+        while let Some(value) = stack.pop() {
+            if let Some(dep) = value.strip_prefix("dep:") {
+                activation.dependencies.insert(dep.to_string());
+            } else if let Some((dep, feature)) = value.split_once('/') {
+                // `dep?/feature` only enables the feature if the dependency
+                // is enabled by something else.
+                let dep = match dep.strip_suffix('?') {
+                    Some(dep) => dep,
+                    None => {
+                        activation.dependencies.insert(dep.to_string());
+                        if has_implicit_feature(dep) {
+                            activation.features.insert(dep.to_string());
+                        }
+                        dep
+                    }
+                };
+
+                activation
+                    .dependency_features
+                    .entry(dep.to_string())
+                    .or_default()
+                    .insert(feature.to_string());
+            } else if visited.insert(value) {
+                if let Some(values) = features.get(value) {
+                    activation.features.insert(value.to_string());
+                    stack.extend(values.iter().map(String::as_str));
+                } else if has_implicit_feature(value) {
+                    activation.features.insert(value.to_string());
+                    activation.dependencies.insert(value.to_string());
+                } else if value != "default" {
+                    activation.unknown_features.push(value.to_string());
+                }
+            }
+        }
+
+        activation
+    }
+}
+
+fn parse_features(features: Value) -> Features {
+    serde_json::from_value(features).unwrap_or_default()
+}
+
+/// Versions with the same compatibility key are semver-compatible.
+fn compatibility(version: &semver::Version) -> (u64, u64, u64) {
+    match (version.major, version.minor) {
+        (0, 0) => (0, 0, version.patch),
+        (0, minor) => (0, minor, 0),
+        (major, _) => (major, 0, 0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn features(entries: &[(&str, &[&str])]) -> Features {
+        entries
+            .iter()
+            .map(|(name, values)| {
+                let values = values.iter().map(ToString::to_string).collect();
+                (name.to_string(), values)
+            })
+            .collect()
+    }
+
+    fn activate(features: &Features, optional_deps: &[&str], requested: &[&str]) -> Activation {
+        let optional_deps = optional_deps.iter().copied().collect();
+        let requested = requested.iter().map(ToString::to_string).collect();
+        Activation::new(features, &optional_deps, &requested)
+    }
+
+    fn set(values: &[&str]) -> BTreeSet<String> {
+        values.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn activation_follows_feature_definitions() {
+        let features = features(&[("default", &["std"]), ("std", &["alloc"]), ("alloc", &[])]);
+
+        let activation = activate(&features, &[], &["default"]);
+        assert_eq!(activation.features, set(&["alloc", "default", "std"]));
+        assert!(activation.dependencies.is_empty());
+
+        let activation = activate(&features, &[], &["alloc"]);
+        assert_eq!(activation.features, set(&["alloc"]));
+    }
+
+    #[test]
+    fn activation_of_optional_dependencies() {
+        let features = features(&[("default", &["serde"]), ("json", &["serde_json/std"])]);
+
+        let activation = activate(&features, &["serde", "serde_json"], &["default", "json"]);
+        assert_eq!(
+            activation.features,
+            set(&["default", "json", "serde", "serde_json"])
+        );
+        assert_eq!(activation.dependencies, set(&["serde", "serde_json"]));
+        assert_eq!(activation.dependency_features["serde_json"], set(&["std"]));
+    }
+
+    #[test]
+    fn activation_with_dep_syntax() {
+        let features = features(&[
+            ("serde", &["dep:serde", "rgb?/serde"]),
+            ("rgb", &["dep:rgb"]),
+        ]);
+
+        let activation = activate(&features, &["serde", "rgb"], &["serde"]);
+        assert_eq!(activation.features, set(&["serde"]));
+        assert_eq!(activation.dependencies, set(&["serde"]));
+        assert_eq!(activation.dependency_features["rgb"], set(&["serde"]));
+
+        let activation = activate(&features, &["serde", "rgb"], &["serde", "rgb"]);
+        assert_eq!(activation.dependencies, set(&["rgb", "serde"]));
+    }
+
+    #[test]
+    fn activation_reports_unknown_features() {
+        let features = features(&[("std", &[])]);
+
+        let activation = activate(&features, &["serde"], &["default", "std", "unknown"]);
+        assert_eq!(activation.unknown_features, vec!["unknown"]);
+
+        let features = self::features(&[("serde", &["dep:serde"])]);
+        let activation = activate(&features, &["serde", "rgb"], &["rgb"]);
+        assert!(activation.unknown_features.is_empty());
+        assert_eq!(activation.dependencies, set(&["rgb"]));
+    }
+
+    #[test]
+    fn semver_compatibility() {
+        let compatibility = |version| compatibility(&semver::Version::parse(version).unwrap());
+
+        assert_eq!(compatibility("1.2.3"), compatibility("1.9.0"));
+        assert_ne!(compatibility("1.2.3"), compatibility("2.0.0"));
+        assert_eq!(compatibility("0.2.3"), compatibility("0.2.9"));
+        assert_ne!(compatibility("0.2.3"), compatibility("0.3.0"));
+        assert_ne!(compatibility("0.0.3"), compatibility("0.0.4"));
+    }
+}
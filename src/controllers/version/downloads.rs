@@ -3,6 +3,7 @@
 //! Crate level functionality is located in `krate::downloads`.
 
 use super::version_and_crate;
+use crate::auth::check_read_access;
 use crate::controllers::prelude::*;
 use crate::models::VersionDownload;
 use crate::schema::*;
@@ -17,6 +18,8 @@ pub async fn download(
     Path((crate_name, version)): Path<(String, String)>,
     req: Parts,
 ) -> AppResult<Response> {
+    check_read_access(&app, &req, Some(&crate_name)).await?;
+
     let wants_json = req.wants_json();
     let redirect_url = app.storage.crate_location(&crate_name, &version);
     if wants_json {
@@ -5,10 +5,12 @@ use crate::auth::AuthCheck;
 use crate::controllers::cargo_prelude::*;
 use crate::models::token::EndpointScope;
 use crate::models::Rights;
-use crate::models::{insert_version_owner_action, VersionAction};
+use crate::models::{
+    NewCrateAuditAction, NewVersionOwnerAction, VersionAction, WebhookEvent, WebhookPayload,
+};
 use crate::rate_limiter::LimitedAction;
 use crate::schema::versions;
-use crate::util::errors::{custom, version_not_found};
+use crate::util::errors::{bad_request, custom, version_not_found};
 use crate::worker::jobs;
 use crate::worker::jobs::UpdateDefaultVersion;
 use crates_io_worker::BackgroundJob;
@@ -23,12 +25,27 @@ use tokio::runtime::Handle;
 /// Crate deletion is not implemented to avoid breaking builds,
 /// and the goal of yanking a crate is to prevent crates
 /// beginning to depend on the yanked crate version.
+///
+/// The request body is optional. If present, it may contain a `message`
+/// explaining why the version was yanked and the ID of a security `advisory`
+/// (`RUSTSEC-*` or `GHSA-*`) that affects the version. Yanking an already
+/// yanked version replaces the parts of the previous reason that are present
+/// in the request, and keeps the others.
 pub async fn yank(
     app: AppState,
     Path((crate_name, version)): Path<(String, String)>,
-    req: Parts,
+    req: BytesRequest,
 ) -> AppResult<Response> {
-    modify_yank(crate_name, version, app, req, true).await
+    let (req, body) = req.0.into_parts();
+
+    let reason = if body.is_empty() {
+        YankReason::default()
+    } else {
+        serde_json::from_slice(&body).map_err(|_| bad_request("invalid json request"))?
+    };
+    reason.validate()?;
+
+    modify_yank(crate_name, version, app, req, true, reason).await
 }
 
 /// Handles the `PUT /crates/:crate_id/:version/unyank` route.
@@ -37,7 +54,93 @@ pub async fn unyank(
     Path((crate_name, version)): Path<(String, String)>,
     req: Parts,
 ) -> AppResult<Response> {
-    modify_yank(crate_name, version, app, req, false).await
+    modify_yank(crate_name, version, app, req, false, YankReason::default()).await
+}
+
+/// The maximum length of a yank message, in characters.
+const MAX_MESSAGE_LENGTH: usize = 1000;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct YankReason {
+    message: Option<String>,
+    advisory: Option<String>,
+}
+
+impl YankReason {
+    fn is_present(&self) -> bool {
+        self.message.is_some() || self.advisory.is_some()
+    }
+
+    /// Fills in the parts that are missing in this reason from the reason
+    /// that is already stored for the version.
+    fn or(self, message: Option<String>, advisory: Option<String>) -> Self {
+        Self {
+            message: self.message.or(message),
+            advisory: self.advisory.or(advisory),
+        }
+    }
+
+    /// Formats the reason for the `details` of the crate audit log.
+    fn details(&self) -> Option<String> {
+        match (&self.message, &self.advisory) {
+            (Some(message), Some(advisory)) => Some(format!("{message} ({advisory})")),
+            (Some(message), None) => Some(message.clone()),
+            (None, Some(advisory)) => Some(advisory.clone()),
+            (None, None) => None,
+        }
+    }
+
+    fn validate(&self) -> AppResult<()> {
+        if let Some(message) = &self.message {
+            if message.trim().is_empty() {
+                return Err(bad_request("yank message must not be empty"));
+            }
+            if message.chars().count() > MAX_MESSAGE_LENGTH {
+                return Err(bad_request(format_args!(
+                    "yank message must not be longer than {MAX_MESSAGE_LENGTH} characters"
+                )));
+            }
+        }
+
+        if let Some(advisory) = &self.advisory {
+            if !is_valid_advisory_id(advisory) {
+                return Err(bad_request(format_args!(
+                    "invalid advisory ID `{advisory}`, expected a `RUSTSEC-YYYY-NNNN` or \
+                    `GHSA-xxxx-xxxx-xxxx` identifier"
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks whether `id` looks like a RustSec (`RUSTSEC-2024-0001`) or GitHub
+/// (`GHSA-xxxx-xxxx-xxxx`) security advisory ID.
+fn is_valid_advisory_id(id: &str) -> bool {
+    if let Some(rest) = id.strip_prefix("RUSTSEC-") {
+        return matches!(
+            rest.split_once('-'),
+            Some((year, number))
+                if year.len() == 4
+                    && year.bytes().all(|b| b.is_ascii_digit())
+                    && number.len() >= 4
+                    && number.bytes().all(|b| b.is_ascii_digit())
+        );
+    }
+
+    if let Some(rest) = id.strip_prefix("GHSA-") {
+        // GitHub uses a restricted base32 alphabet for the three segments.
+        const ALPHABET: &[u8] = b"23456789cfghjmpqrvwx";
+
+        let segments = rest.split('-').collect::<Vec<_>>();
+        return segments.len() == 3
+            && segments.iter().all(|segment| {
+                segment.len() == 4 && segment.bytes().all(|b| ALPHABET.contains(&b))
+            });
+    }
+
+    false
 }
 
 /// Changes `yanked` flag on a crate version record
@@ -47,6 +150,7 @@ async fn modify_yank(
     state: AppState,
     req: Parts,
     yanked: bool,
+    reason: YankReason,
 ) -> AppResult<Response> {
     // FIXME: Should reject bad requests before authentication, but can't due to
     // lifetime issues with `req`.
@@ -68,10 +172,11 @@ async fn modify_yank(
 
         let (version, krate) = version_and_crate(conn, &crate_name, &version)?;
         let api_token_id = auth.api_token_id();
+        let trusted_publisher_id = auth.trusted_publisher_id();
         let user = auth.user();
         let owners = krate.owners(conn)?;
 
-        if Handle::current().block_on(user.rights(&state, &owners))? < Rights::Publish {
+        if Handle::current().block_on(auth.rights(&state, &owners))? < Rights::Publish {
             if user.is_admin {
                 let action = if yanked { "yanking" } else { "unyanking" };
                 warn!(
@@ -86,13 +191,29 @@ async fn modify_yank(
             }
         }
 
-        if version.yanked == yanked {
+        // Yanking an already yanked version only updates its reason, so that
+        // e.g. an advisory can be added once it has been published.
+        let reason = if version.yanked && yanked {
+            reason.or(version.yank_message.clone(), version.yank_advisory.clone())
+        } else {
+            reason
+        };
+        let reason_changed =
+            version.yank_message != reason.message || version.yank_advisory != reason.advisory;
+        let update_reason = version.yanked && yanked && reason.is_present() && reason_changed;
+
+        if version.yanked == yanked && !update_reason {
             // The crate is already in the state requested, nothing to do
             return ok_true();
         }
 
+        // Unyanking clears the reason, but it is kept in the audit actions.
         diesel::update(&version)
-            .set(versions::yanked.eq(yanked))
+            .set((
+                versions::yanked.eq(yanked),
+                versions::yank_message.eq(&reason.message),
+                versions::yank_advisory.eq(&reason.advisory),
+            ))
             .execute(conn)?;
 
         let action = if yanked {
@@ -101,7 +222,35 @@ async fn modify_yank(
             VersionAction::Unyank
         };
 
-        insert_version_owner_action(conn, version.id, user.id, api_token_id, action)?;
+        NewVersionOwnerAction::new(version.id, user.id, action)
+            .api_token(api_token_id, trusted_publisher_id, auth.team_id())
+            .reason(reason.message.as_deref(), reason.advisory.as_deref())
+            .insert(conn)?;
+
+        let details = reason.details();
+        let mut audit_action = NewCrateAuditAction::new(krate.id, &krate.name, action.into())
+            .version(&version.num)
+            .actor(user.id, api_token_id);
+        if let Some(details) = &details {
+            audit_action = audit_action.details(details);
+        }
+        audit_action.insert(conn)?;
+
+        if update_reason {
+            // The yank reason is not part of the index, and the webhooks have
+            // already been notified about the yank itself.
+            return ok_true();
+        }
+
+        let event = if yanked {
+            WebhookEvent::Yank
+        } else {
+            WebhookEvent::Unyank
+        };
+        let payload = WebhookPayload::new(event, &krate.name)
+            .version(&version.num)
+            .user(user);
+        jobs::enqueue_webhooks(krate.id, &payload, conn)?;
 
         jobs::enqueue_sync_to_index(&krate.name, conn)?;
 
@@ -111,3 +260,25 @@ async fn modify_yank(
     })
     .await?
 }
+
+#[cfg(test)]
+mod tests {
+    use super::is_valid_advisory_id;
+
+    #[test]
+    fn advisory_ids() {
+        assert!(is_valid_advisory_id("RUSTSEC-2024-0001"));
+        assert!(is_valid_advisory_id("RUSTSEC-2019-10000"));
+        assert!(is_valid_advisory_id("GHSA-jfh8-c2jp-5v3q"));
+
+        assert!(!is_valid_advisory_id(""));
+        assert!(!is_valid_advisory_id("CVE-2024-1234"));
+        assert!(!is_valid_advisory_id("RUSTSEC-24-0001"));
+        assert!(!is_valid_advisory_id("RUSTSEC-2024-001"));
+        assert!(!is_valid_advisory_id("RUSTSEC-2024-000a"));
+        assert!(!is_valid_advisory_id("rustsec-2024-0001"));
+        assert!(!is_valid_advisory_id("GHSA-jfh8-c2jp"));
+        assert!(!is_valid_advisory_id("GHSA-JFH8-C2JP-5V3Q"));
+        assert!(!is_valid_advisory_id("GHSA-abcd-c2jp-5v3q"));
+    }
+}
@@ -0,0 +1,298 @@
+//! Endpoints for managing webhooks that are notified about crate events,
+//! and for inspecting their delivery history.
+
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::controllers::helpers::pagination::{Paginated, PaginationOptions};
+use crate::controllers::helpers::{ensure_owner, find_crate, Paginate};
+use crate::models::{Crate, NewWebhook, User, Webhook, WebhookDelivery, WebhookEvent};
+use crate::schema::{webhook_deliveries, webhooks};
+use crate::util::errors::custom;
+use crate::util::ip::is_public_address;
+use crate::util::token::generate_secure_alphanumeric_string;
+use crate::views::{EncodableWebhook, EncodableWebhookDelivery, EncodableWebhookWithSecret};
+use std::net::IpAddr;
+use url::{Host, Url};
+
+/// Maximum number of webhooks per crate, or per user for user-level webhooks
+const MAX_WEBHOOKS: usize = 10;
+
+/// Maximum length of a webhook URL
+const MAX_URL_LENGTH: usize = 1000;
+
+/// Length of the generated webhook secrets
+const SECRET_LENGTH: usize = 32;
+
+/// Loads a webhook and ensures that the authenticated user is allowed to
+/// manage it.
+fn find_webhook(
+    app: &AppState,
+    conn: &mut PgConnection,
+    user: &User,
+    id: i32,
+) -> AppResult<Webhook> {
+    let not_found = || custom(StatusCode::NOT_FOUND, "webhook not found");
+
+    let webhook = webhooks::table
+        .find(id)
+        .select(Webhook::as_select())
+        .first(conn)
+        .optional()?
+        .ok_or_else(not_found)?;
+
+    match webhook.crate_id {
+        Some(crate_id) => {
+            let krate: Crate = Crate::all().find(crate_id).first(conn)?;
+            ensure_owner(app, conn, user, &krate, "webhooks")?;
+            Ok(webhook)
+        }
+        None if webhook.user_id == user.id => Ok(webhook),
+        None => Err(not_found()),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct NewWebhookRequest {
+    webhook: NewWebhookBody,
+}
+
+#[derive(Deserialize)]
+pub struct NewWebhookBody {
+    url: String,
+    /// The events to subscribe to. Defaults to all events.
+    #[serde(default = "all_events")]
+    events: Vec<WebhookEvent>,
+}
+
+fn all_events() -> Vec<WebhookEvent> {
+    WebhookEvent::ALL.to_vec()
+}
+
+impl NewWebhookBody {
+    /// Validates the webhook. URLs with hostnames are only checked against
+    /// non-public addresses when delivering events, since they can resolve
+    /// to different addresses over time.
+    fn validate(&self, allow_private: bool) -> AppResult<()> {
+        if self.url.len() > MAX_URL_LENGTH {
+            return Err(bad_request(format_args!(
+                "webhook URL must not be longer than {MAX_URL_LENGTH} characters"
+            )));
+        }
+
+        let url = Url::parse(&self.url)
+            .map_err(|error| bad_request(format_args!("invalid webhook URL: {error}")))?;
+
+        if !matches!(url.scheme(), "http" | "https") || !url.has_host() {
+            return Err(bad_request("webhook URL must be an `http` or `https` URL"));
+        }
+
+        let ip: Option<IpAddr> = match url.host() {
+            Some(Host::Ipv4(ip)) => Some(ip.into()),
+            Some(Host::Ipv6(ip)) => Some(ip.into()),
+            _ => None,
+        };
+        if !allow_private && ip.is_some_and(|ip| !is_public_address(ip)) {
+            return Err(bad_request(
+                "webhook URL must not point to a non-public address",
+            ));
+        }
+
+        if self.events.is_empty() {
+            return Err(bad_request("webhook must subscribe to at least one event"));
+        }
+
+        Ok(())
+    }
+
+    fn insert(
+        &self,
+        conn: &mut PgConnection,
+        krate: Option<&Crate>,
+        user_id: i32,
+    ) -> AppResult<EncodableWebhookWithSecret> {
+        let mut events = self
+            .events
+            .iter()
+            .map(WebhookEvent::as_str)
+            .collect::<Vec<_>>();
+        events.sort_unstable();
+        events.dedup();
+
+        let secret = generate_secure_alphanumeric_string(SECRET_LENGTH);
+
+        let webhook = NewWebhook {
+            crate_id: krate.map(|krate| krate.id),
+            user_id,
+            url: &self.url,
+            secret: &secret,
+            events,
+        }
+        .insert(conn)?;
+
+        Ok(EncodableWebhookWithSecret {
+            webhook: EncodableWebhook::from(webhook, krate.map(|krate| krate.name.as_str())),
+            secret,
+        })
+    }
+}
+
+fn too_many_webhooks() -> BoxedAppError {
+    bad_request(format_args!(
+        "the maximum number of {MAX_WEBHOOKS} webhooks has been reached"
+    ))
+}
+
+/// Handles the `GET /crates/:crate_id/webhooks` route.
+pub async fn list_for_crate(
+    app: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    let conn = app.db_read_prefer_primary().await?;
+    conn.interact(move |conn| {
+        let auth = AuthCheck::only_cookie().check(&req, conn)?;
+
+        let krate = find_crate(conn, &crate_name)?;
+        ensure_owner(&app, conn, auth.user(), &krate, "webhooks")?;
+
+        let webhooks = Webhook::for_crate(conn, krate.id)?
+            .into_iter()
+            .map(|webhook| EncodableWebhook::from(webhook, Some(&krate.name)))
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({ "webhooks": webhooks })))
+    })
+    .await?
+}
+
+/// Handles the `PUT /crates/:crate_id/webhooks` route.
+///
+/// The response contains the generated secret, which is used to sign the
+/// payloads sent to the webhook. It can not be retrieved again later.
+pub async fn create_for_crate(
+    app: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+    Json(body): Json<NewWebhookRequest>,
+) -> AppResult<Json<Value>> {
+    let body = body.webhook;
+    body.validate(app.config.allow_private_webhook_urls)?;
+
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let auth = AuthCheck::only_cookie()
+            .with_two_factor()
+            .check(&req, conn)?;
+        let user = auth.user();
+
+        let krate = find_crate(conn, &crate_name)?;
+        ensure_owner(&app, conn, user, &krate, "webhooks")?;
+
+        if Webhook::for_crate(conn, krate.id)?.len() >= MAX_WEBHOOKS {
+            return Err(too_many_webhooks());
+        }
+
+        let webhook = body.insert(conn, Some(&krate), user.id)?;
+
+        Ok(Json(json!({ "webhook": webhook })))
+    })
+    .await?
+}
+
+/// Handles the `GET /me/webhooks` route.
+pub async fn list_for_user(app: AppState, req: Parts) -> AppResult<Json<Value>> {
+    let conn = app.db_read_prefer_primary().await?;
+    conn.interact(move |conn| {
+        let auth = AuthCheck::only_cookie().check(&req, conn)?;
+
+        let webhooks = Webhook::for_user(conn, auth.user_id())?
+            .into_iter()
+            .map(|webhook| EncodableWebhook::from(webhook, None))
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({ "webhooks": webhooks })))
+    })
+    .await?
+}
+
+/// Handles the `PUT /me/webhooks` route.
+///
+/// User-level webhooks are notified about the events of all crates that the
+/// user owns, and about invitations to become an owner of other crates.
+pub async fn create_for_user(
+    app: AppState,
+    req: Parts,
+    Json(body): Json<NewWebhookRequest>,
+) -> AppResult<Json<Value>> {
+    let body = body.webhook;
+    body.validate(app.config.allow_private_webhook_urls)?;
+
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let auth = AuthCheck::only_cookie()
+            .with_two_factor()
+            .check(&req, conn)?;
+        let user_id = auth.user_id();
+
+        if Webhook::for_user(conn, user_id)?.len() >= MAX_WEBHOOKS {
+            return Err(too_many_webhooks());
+        }
+
+        let webhook = body.insert(conn, None, user_id)?;
+
+        Ok(Json(json!({ "webhook": webhook })))
+    })
+    .await?
+}
+
+/// Handles the `DELETE /webhooks/:id` route.
+pub async fn delete(app: AppState, Path(id): Path<i32>, req: Parts) -> AppResult<Response> {
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let auth = AuthCheck::only_cookie()
+            .with_two_factor()
+            .check(&req, conn)?;
+
+        let webhook = find_webhook(&app, conn, auth.user(), id)?;
+        diesel::delete(&webhook).execute(conn)?;
+
+        ok_true()
+    })
+    .await?
+}
+
+/// Handles the `GET /webhooks/:id/deliveries` route.
+pub async fn deliveries(app: AppState, Path(id): Path<i32>, req: Parts) -> AppResult<Json<Value>> {
+    let conn = app.db_read_prefer_primary().await?;
+    conn.interact(move |conn| {
+        let auth = AuthCheck::only_cookie().check(&req, conn)?;
+
+        let webhook = find_webhook(&app, conn, auth.user(), id)?;
+
+        let pagination = PaginationOptions::builder().gather(&req)?;
+        let data: Paginated<WebhookDelivery> = webhook_deliveries::table
+            .filter(webhook_deliveries::webhook_id.eq(webhook.id))
+            .order(webhook_deliveries::id.desc())
+            .pages_pagination(pagination)
+            .load(conn)?;
+
+        let total = data.total();
+        let next_page = data.next_page_params().map(|p| req.query_with_params(p));
+        let prev_page = data.prev_page_params().map(|p| req.query_with_params(p));
+
+        let deliveries = data
+            .into_iter()
+            .map(EncodableWebhookDelivery::from)
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({
+            "deliveries": deliveries,
+            "meta": {
+                "total": total,
+                "next_page": next_page,
+                "prev_page": prev_page,
+            },
+        })))
+    })
+    .await?
+}
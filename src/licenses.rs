@@ -1,4 +1,5 @@
-use spdx::{Expression, ParseError};
+use spdx::expression::{ExprNode, Operator};
+use spdx::{Expression, LicenseItem, ParseError};
 
 const PARSE_MODE: spdx::ParseMode = spdx::ParseMode {
     allow_lower_case_operators: false,
@@ -11,9 +12,45 @@ pub fn parse_license_expr(s: &str) -> Result<Expression, ParseError> {
     Expression::parse_mode(s, PARSE_MODE)
 }
 
+/// Converts a license expression into a `tsquery` over its license
+/// identifiers, which is stored in `versions.license_query` and evaluated by
+/// the `license_matches()` SQL function.
+///
+/// License exceptions and `or-later` suffixes are dropped, so e.g.
+/// `GPL-3.0+ WITH Classpath-exception-2.0` is matched by `GPL-3.0`.
+pub fn license_query(expression: &Expression) -> String {
+    let mut stack = Vec::new();
+    for node in expression.iter() {
+        match node {
+            ExprNode::Req(req) => {
+                let lexeme = match &req.req.license {
+                    LicenseItem::Spdx { id, .. } => id.name.to_string(),
+                    other => other.to_string(),
+                };
+                stack.push(format!(
+                    "'{}'",
+                    lexeme.replace('\\', "\\\\").replace('\'', "''")
+                ));
+            }
+            ExprNode::Op(op) => {
+                let operator = match op {
+                    Operator::And => '&',
+                    Operator::Or => '|',
+                };
+                // The nodes are in postfix order, and `Expression` guarantees
+                // that every operator has two operands.
+                let rhs = stack.pop().unwrap_or_default();
+                let lhs = stack.pop().unwrap_or_default();
+                stack.push(format!("({lhs} {operator} {rhs})"));
+            }
+        }
+    }
+    stack.pop().unwrap_or_default()
+}
+
 #[cfg(test)]
 mod tests {
-    use super::parse_license_expr;
+    use super::{license_query, parse_license_expr};
 
     #[test]
     fn licenses() {
@@ -26,4 +63,24 @@ mod tests {
 
         assert_err!(parse_license_expr("apache 2.0"));
     }
+
+    #[test]
+    fn license_queries() {
+        fn query(s: &str) -> String {
+            license_query(&parse_license_expr(s).unwrap())
+        }
+
+        assert_eq!(query("MIT"), "'MIT'");
+        assert_eq!(query("MIT OR Apache-2.0"), "('MIT' | 'Apache-2.0')");
+        assert_eq!(query("MIT/Apache-2.0"), "('MIT' | 'Apache-2.0')");
+        assert_eq!(
+            query("MIT AND (Apache-2.0 OR Zlib)"),
+            "('MIT' & ('Apache-2.0' | 'Zlib'))"
+        );
+        assert_eq!(query("Apache-2.0 WITH LLVM-exception"), "'Apache-2.0'");
+        assert_eq!(query("GPL-3.0+"), "'GPL-3.0'");
+        assert_eq!(query("GPL-3.0-only"), "'GPL-3.0'");
+        assert_eq!(query("GPL-3.0-or-later"), "'GPL-3.0'");
+        assert_eq!(query("LicenseRef-Proprietary"), "'LicenseRef-Proprietary'");
+    }
 }
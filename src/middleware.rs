@@ -1,4 +1,5 @@
 pub mod app;
+mod auth_required;
 mod block_traffic;
 pub mod cargo_compat;
 mod common_headers;
@@ -10,6 +11,7 @@ pub mod real_ip;
 mod require_user_agent;
 pub mod session;
 mod static_or_continue;
+pub mod token_usage;
 mod update_metrics;
 
 use ::sentry::integrations::tower as sentry_tower;
@@ -78,7 +80,15 @@ pub fn apply_axum_middleware(state: AppState, router: Router<()>) -> Router {
         .layer(conditional_layer(config.serve_html, || {
             from_fn_with_state(state.clone(), ember_html::serve_html)
         }))
-        .layer(AddExtensionLayer::new(state.clone()));
+        .layer(AddExtensionLayer::new(state.clone()))
+        .layer(from_fn_with_state(
+            state.clone(),
+            token_usage::record_token_usage,
+        ))
+        // Needs the `AppState` extension for the token authentication
+        .layer(conditional_layer(config.auth_required, || {
+            from_fn_with_state(state.clone(), auth_required::middleware)
+        }));
 
     router
         .layer(middlewares_2)
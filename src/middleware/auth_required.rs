@@ -0,0 +1,56 @@
+//! Middleware that requires authentication for all read access to the API,
+//! when the registry is running in private mode (`AUTH_REQUIRED`).
+//!
+//! Only `GET` and `HEAD` requests are checked here, since all other endpoints
+//! already perform their own authentication. The session routes stay public so
+//! that users are able to log in, the metrics routes are protected by their own
+//! token, and the download endpoint performs its own crate-specific check.
+//!
+//! For routes with a `:crate_id` segment, the crate scopes of the API token
+//! are checked against that crate. All other routes return data about many
+//! crates at once (e.g. the search or the summary), so they can't be read
+//! with tokens that are restricted to some crates, or with tokens that are
+//! owned by a team.
+
+use crate::app::AppState;
+use crate::auth::check_read_access;
+use axum::extract::{MatchedPath, RawPathParams, Request};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use http::Method;
+
+const PUBLIC_PATH_PREFIXES: &[&str] = &["/api/private/session", "/api/private/metrics"];
+const DOWNLOAD_PATH: &str = "/api/v1/crates/:crate_id/:version/download";
+
+pub async fn middleware(
+    matched_path: Option<MatchedPath>,
+    path_params: Option<RawPathParams>,
+    state: AppState,
+    req: Request,
+    next: Next,
+) -> Response {
+    let path = req.uri().path();
+    let is_read = req.method() == Method::GET || req.method() == Method::HEAD;
+    let is_public = PUBLIC_PATH_PREFIXES
+        .iter()
+        .any(|prefix| path.starts_with(prefix));
+    let is_download = matched_path.is_some_and(|path| path.as_str() == DOWNLOAD_PATH);
+
+    if !is_read || !path.starts_with("/api/") || is_public || is_download {
+        return next.run(req).await;
+    }
+
+    let crate_name = path_params.as_ref().and_then(|params| {
+        params
+            .iter()
+            .find(|(key, _)| *key == "crate_id")
+            .map(|(_, value)| value)
+    });
+
+    let (parts, body) = req.into_parts();
+    if let Err(error) = check_read_access(&state, &parts, crate_name).await {
+        return error.into_response();
+    }
+
+    next.run(Request::from_parts(parts, body)).await
+}
@@ -0,0 +1,81 @@
+//! Adds the requests that were made with an API token to the usage log of
+//! the token.
+//!
+//! The usage is noted by [`crate::auth::AuthCheck::check`], which might run
+//! on a read-only replica connection, and is written to the primary database
+//! in a separate task once the request has been handled, so that the response
+//! is not delayed by it. Nothing is recorded in read-only mode.
+//!
+//! The usage logs are trimmed to their maximum length by the
+//! [`PruneTokenUsages`](crate::worker::jobs::PruneTokenUsages) background job.
+
+use crate::app::AppState;
+use crate::models::token::NewApiTokenUsage;
+use anyhow::anyhow;
+use axum::extract::Request;
+use axum::middleware::Next;
+use axum::response::Response;
+use ipnetwork::IpNetwork;
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+/// A request that was made with an API token.
+#[derive(Debug, Clone)]
+pub struct TokenUsage {
+    pub api_token_id: i32,
+    pub endpoint: String,
+    pub crate_name: Option<String>,
+    pub ip: Option<IpNetwork>,
+    /// `None` if the token was accepted for the request.
+    pub rejection_reason: Option<&'static str>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TokenUsageLog(Arc<Mutex<Option<TokenUsage>>>);
+
+impl TokenUsageLog {
+    /// Notes the usage of a token, replacing the usage that was noted
+    /// earlier during the same request, if any.
+    pub fn set(&self, usage: TokenUsage) {
+        *self.0.lock() = Some(usage);
+    }
+}
+
+pub async fn record_token_usage(state: AppState, mut req: Request, next: Next) -> Response {
+    let log = TokenUsageLog::default();
+    req.extensions_mut().insert(log.clone());
+
+    let response = next.run(req).await;
+
+    let usage = log.0.lock().take();
+    if let Some(usage) = usage {
+        if !state.config.db.are_all_read_only() {
+            tokio::spawn(async move {
+                if let Err(error) = save(&state, usage).await {
+                    warn!("Failed to record API token usage: {error}");
+                }
+            });
+        }
+    }
+
+    response
+}
+
+async fn save(state: &AppState, usage: TokenUsage) -> anyhow::Result<()> {
+    let conn = state.db_write().await?;
+    conn.interact(move |conn| {
+        let usage = NewApiTokenUsage {
+            api_token_id: usage.api_token_id,
+            endpoint: &usage.endpoint,
+            crate_name: usage.crate_name.as_deref(),
+            ip: usage.ip,
+            rejection_reason: usage.rejection_reason,
+        };
+
+        usage.insert(conn)
+    })
+    .await
+    .map_err(|err| anyhow!(err.to_string()))??;
+
+    Ok(())
+}
@@ -1,4 +1,7 @@
-pub use self::action::{insert_version_owner_action, VersionAction, VersionOwnerAction};
+pub use self::action::{
+    CrateAction, CrateAuditAction, NewCrateAuditAction, NewVersionOwnerAction, VersionAction,
+    VersionOwnerAction,
+};
 pub use self::category::{Category, CrateCategory, NewCategory};
 pub use self::crate_owner_invitation::{CrateOwnerInvitation, NewCrateOwnerInvitationOutcome};
 pub use self::default_versions::update_default_version;
@@ -9,11 +12,18 @@ pub use self::follow::Follow;
 pub use self::keyword::{CrateKeyword, Keyword};
 pub use self::krate::{Crate, CrateVersions, NewCrate, RecentCrateDownloads};
 pub use self::owner::{CrateOwner, Owner, OwnerKind};
+pub use self::publish_policy::{NewPublishPolicy, PublishPolicy};
 pub use self::rights::Rights;
 pub use self::team::{NewTeam, Team};
-pub use self::token::{ApiToken, CreatedApiToken};
+pub use self::token::{ApiToken, CreatedApiToken, NewApiToken};
+pub use self::totp::{NewTotpCredential, TotpCredential};
+pub use self::trusted_publisher::{NewTrustedPublisher, TrustedPublisher};
+pub use self::typosquat_finding::{NewTyposquatFinding, TyposquatFinding, TyposquatStatus};
 pub use self::user::{NewUser, User};
 pub use self::version::{NewVersion, TopVersions, Version};
+pub use self::webhook::{
+    NewWebhook, NewWebhookDelivery, Webhook, WebhookDelivery, WebhookEvent, WebhookPayload,
+};
 
 pub mod helpers;
 
@@ -28,8 +38,13 @@ mod follow;
 mod keyword;
 pub mod krate;
 mod owner;
+mod publish_policy;
 mod rights;
 mod team;
 pub mod token;
+mod totp;
+mod trusted_publisher;
+mod typosquat_finding;
 pub mod user;
 pub mod version;
+mod webhook;
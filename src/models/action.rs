@@ -1,4 +1,4 @@
-use crate::models::{ApiToken, User, Version};
+use crate::models::{ApiToken, Owner, User, Version};
 use crate::schema::*;
 use crate::sql::pg_enum;
 use chrono::NaiveDateTime;
@@ -30,7 +30,7 @@ impl From<VersionAction> for String {
     }
 }
 
-#[derive(Debug, Clone, Copy, Queryable, Identifiable, Associations)]
+#[derive(Debug, Clone, Queryable, Identifiable, Associations)]
 #[diesel(
     table_name = version_owner_actions,
     check_for_backend(diesel::pg::Pg),
@@ -45,6 +45,10 @@ pub struct VersionOwnerAction {
     pub api_token_id: Option<i32>,
     pub action: VersionAction,
     pub time: NaiveDateTime,
+    pub trusted_publisher_id: Option<i32>,
+    pub message: Option<String>,
+    pub advisory: Option<String>,
+    pub team_id: Option<i32>,
 }
 
 impl VersionOwnerAction {
@@ -77,21 +81,203 @@ impl VersionOwnerAction {
     }
 }
 
-pub fn insert_version_owner_action(
-    conn: &mut PgConnection,
-    version_id_: i32,
-    user_id_: i32,
-    api_token_id_: Option<i32>,
-    action_: VersionAction,
-) -> QueryResult<VersionOwnerAction> {
-    use version_owner_actions::dsl::{action, api_token_id, user_id, version_id};
-
-    diesel::insert_into(version_owner_actions::table)
-        .values((
-            version_id.eq(version_id_),
-            user_id.eq(user_id_),
-            api_token_id.eq(api_token_id_),
-            action.eq(action_),
-        ))
-        .get_result(conn)
+#[derive(Debug, Insertable)]
+#[diesel(table_name = version_owner_actions, check_for_backend(diesel::pg::Pg))]
+pub struct NewVersionOwnerAction<'a> {
+    version_id: i32,
+    user_id: i32,
+    api_token_id: Option<i32>,
+    trusted_publisher_id: Option<i32>,
+    team_id: Option<i32>,
+    action: VersionAction,
+    message: Option<&'a str>,
+    advisory: Option<&'a str>,
+}
+
+impl<'a> NewVersionOwnerAction<'a> {
+    pub fn new(version_id: i32, user_id: i32, action: VersionAction) -> Self {
+        Self {
+            version_id,
+            user_id,
+            api_token_id: None,
+            trusted_publisher_id: None,
+            team_id: None,
+            action,
+            message: None,
+            advisory: None,
+        }
+    }
+
+    /// Sets the API token that was used for the action, and the trusted
+    /// publisher or team that the token belongs to, if any.
+    pub fn api_token(
+        mut self,
+        api_token_id: Option<i32>,
+        trusted_publisher_id: Option<i32>,
+        team_id: Option<i32>,
+    ) -> Self {
+        self.api_token_id = api_token_id;
+        self.trusted_publisher_id = trusted_publisher_id;
+        self.team_id = team_id;
+        self
+    }
+
+    /// Sets the yank message and the ID of the security advisory.
+    pub fn reason(mut self, message: Option<&'a str>, advisory: Option<&'a str>) -> Self {
+        self.message = message;
+        self.advisory = advisory;
+        self
+    }
+
+    pub fn insert(&self, conn: &mut PgConnection) -> QueryResult<VersionOwnerAction> {
+        diesel::insert_into(version_owner_actions::table)
+            .values(self)
+            .get_result(conn)
+    }
+}
+
+pg_enum! {
+    pub enum CrateAction {
+        Publish = 0,
+        Yank = 1,
+        Unyank = 2,
+        InviteOwner = 3,
+        AcceptInvite = 4,
+        DeclineInvite = 5,
+        AddOwner = 6,
+        RemoveOwner = 7,
+        DeleteCrate = 8,
+        DeleteVersion = 9,
+        UpdatePublishPolicy = 10,
+    }
+}
+
+impl From<CrateAction> for &'static str {
+    fn from(action: CrateAction) -> Self {
+        match action {
+            CrateAction::Publish => "publish",
+            CrateAction::Yank => "yank",
+            CrateAction::Unyank => "unyank",
+            CrateAction::InviteOwner => "invite_owner",
+            CrateAction::AcceptInvite => "accept_invite",
+            CrateAction::DeclineInvite => "decline_invite",
+            CrateAction::AddOwner => "add_owner",
+            CrateAction::RemoveOwner => "remove_owner",
+            CrateAction::DeleteCrate => "delete_crate",
+            CrateAction::DeleteVersion => "delete_version",
+            CrateAction::UpdatePublishPolicy => "update_publish_policy",
+        }
+    }
+}
+
+impl From<CrateAction> for String {
+    fn from(action: CrateAction) -> Self {
+        let string: &'static str = action.into();
+
+        string.into()
+    }
+}
+
+impl From<VersionAction> for CrateAction {
+    fn from(action: VersionAction) -> Self {
+        match action {
+            VersionAction::Publish => CrateAction::Publish,
+            VersionAction::Yank => CrateAction::Yank,
+            VersionAction::Unyank => CrateAction::Unyank,
+        }
+    }
+}
+
+/// The model representing a row in the `crate_audit_actions` database table.
+///
+/// In contrast to [`VersionOwnerAction`], these entries don't reference the
+/// crate with a foreign key, so that the audit log of a crate is retained even
+/// after the crate itself has been deleted. The `crate_id` distinguishes the
+/// log of a deleted crate from the log of a new crate with the same name.
+#[derive(Debug, Clone, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = crate_audit_actions, check_for_backend(diesel::pg::Pg))]
+pub struct CrateAuditAction {
+    pub id: i32,
+    pub crate_id: i32,
+    pub crate_name: String,
+    pub version: Option<String>,
+    pub action: CrateAction,
+    pub user_id: Option<i32>,
+    pub api_token_id: Option<i32>,
+    pub target_user_id: Option<i32>,
+    pub target_team_id: Option<i32>,
+    pub details: Option<String>,
+    pub time: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate_audit_actions, check_for_backend(diesel::pg::Pg))]
+pub struct NewCrateAuditAction<'a> {
+    crate_id: i32,
+    crate_name: &'a str,
+    version: Option<&'a str>,
+    action: CrateAction,
+    user_id: Option<i32>,
+    api_token_id: Option<i32>,
+    target_user_id: Option<i32>,
+    target_team_id: Option<i32>,
+    details: Option<&'a str>,
+}
+
+impl<'a> NewCrateAuditAction<'a> {
+    pub fn new(crate_id: i32, crate_name: &'a str, action: CrateAction) -> Self {
+        Self {
+            crate_id,
+            crate_name,
+            version: None,
+            action,
+            user_id: None,
+            api_token_id: None,
+            target_user_id: None,
+            target_team_id: None,
+            details: None,
+        }
+    }
+
+    pub fn version(mut self, version: &'a str) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    /// Sets the user that performed the action, and the API token that was
+    /// used for it, if any.
+    pub fn actor(mut self, user_id: i32, api_token_id: Option<i32>) -> Self {
+        self.user_id = Some(user_id);
+        self.api_token_id = api_token_id;
+        self
+    }
+
+    pub fn target_user(mut self, user_id: i32) -> Self {
+        self.target_user_id = Some(user_id);
+        self
+    }
+
+    pub fn target_team(mut self, team_id: i32) -> Self {
+        self.target_team_id = Some(team_id);
+        self
+    }
+
+    pub fn target_owner(self, owner: &Owner) -> Self {
+        match owner {
+            Owner::User(user) => self.target_user(user.id),
+            Owner::Team(team) => self.target_team(team.id),
+        }
+    }
+
+    pub fn details(mut self, details: &'a str) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    pub fn insert(&self, conn: &mut PgConnection) -> QueryResult<CrateAuditAction> {
+        diesel::insert_into(crate_audit_actions::table)
+            .values(self)
+            .returning(CrateAuditAction::as_returning())
+            .get_result(conn)
+    }
 }
@@ -4,9 +4,13 @@ use http::StatusCode;
 use secrecy::SecretString;
 
 use crate::config;
-use crate::models::{CrateOwner, OwnerKind};
+use crate::models::{
+    CrateAction, CrateOwner, NewCrateAuditAction, Owner, OwnerKind, User, WebhookEvent,
+    WebhookPayload,
+};
 use crate::schema::{crate_owner_invitations, crate_owners, crates};
 use crate::util::errors::{custom, AppResult};
+use crate::worker::jobs;
 
 #[derive(Debug)]
 pub enum NewCrateOwnerInvitationOutcome {
@@ -96,7 +100,17 @@ impl CrateOwnerInvitation {
             .first::<Self>(conn)
     }
 
-    pub fn accept(self, conn: &mut PgConnection, config: &config::Server) -> AppResult<()> {
+    /// Accepts the invitation and adds the invited user as an owner of the
+    /// crate.
+    ///
+    /// `api_token_id` is recorded in the crate audit log, if the invitation
+    /// was accepted using an API token.
+    pub fn accept(
+        self,
+        conn: &mut PgConnection,
+        config: &config::Server,
+        api_token_id: Option<i32>,
+    ) -> AppResult<()> {
         if self.is_expired(config) {
             let crate_name: String = crates::table
                 .find(self.crate_id)
@@ -127,17 +141,45 @@ impl CrateOwnerInvitation {
 
             diesel::delete(&self).execute(conn)?;
 
+            let crate_name: String = crates::table
+                .find(self.crate_id)
+                .select(crates::name)
+                .first(conn)?;
+
+            NewCrateAuditAction::new(self.crate_id, &crate_name, CrateAction::AcceptInvite)
+                .actor(self.invited_user_id, api_token_id)
+                .insert(conn)?;
+
+            let user = User::find(conn, self.invited_user_id)?;
+            let owner = Owner::User(user.clone());
+            let payload = WebhookPayload::new(WebhookEvent::OwnerAdd, &crate_name)
+                .user(&user)
+                .owner(&owner);
+            jobs::enqueue_webhooks(self.crate_id, &payload, conn)?;
+
             Ok(())
         })
     }
 
-    pub fn decline(self, conn: &mut PgConnection) -> QueryResult<()> {
+    pub fn decline(self, conn: &mut PgConnection, api_token_id: Option<i32>) -> QueryResult<()> {
         // The check to prevent declining expired invitations is *explicitly* missing. We do not
         // care if an expired invitation is declined, as that just removes the invitation from the
         // database.
 
-        diesel::delete(&self).execute(conn)?;
-        Ok(())
+        conn.transaction(|conn| {
+            diesel::delete(&self).execute(conn)?;
+
+            let crate_name: String = crates::table
+                .find(self.crate_id)
+                .select(crates::name)
+                .first(conn)?;
+
+            NewCrateAuditAction::new(self.crate_id, &crate_name, CrateAction::DeclineInvite)
+                .actor(self.invited_user_id, api_token_id)
+                .insert(conn)?;
+
+            Ok(())
+        })
     }
 
     pub fn is_expired(&self, config: &config::Server) -> bool {
@@ -12,10 +12,12 @@ use crate::controllers::helpers::pagination::*;
 use crate::email::Email;
 use crate::models::version::TopVersions;
 use crate::models::{
-    CrateOwner, CrateOwnerInvitation, Dependency, NewCrateOwnerInvitationOutcome, Owner, OwnerKind,
-    ReverseDependency, User, Version,
+    CrateAction, CrateOwner, CrateOwnerInvitation, Dependency, NewCrateAuditAction,
+    NewCrateOwnerInvitationOutcome, Owner, OwnerKind, ReverseDependency, User, Version,
+    WebhookEvent, WebhookPayload,
 };
 use crate::util::errors::{version_not_found, AppResult};
+use crate::worker::jobs;
 
 use crate::models::helpers::with_count::*;
 use crate::schema::*;
@@ -355,18 +357,29 @@ impl Crate {
         app: &App,
         conn: &mut PgConnection,
         req_user: &User,
+        api_token_id: Option<i32>,
         login: &str,
     ) -> AppResult<String> {
         use diesel::insert_into;
 
         let owner = Owner::find_or_create_by_login(app, conn, req_user, login)?;
 
-        match owner {
+        match &owner {
             // Users are invited and must accept before being added
             Owner::User(user) => {
                 let config = &app.config;
                 match CrateOwnerInvitation::create(user.id, req_user.id, self.id, conn, config)? {
                     NewCrateOwnerInvitationOutcome::InviteCreated { plaintext_token } => {
+                        NewCrateAuditAction::new(self.id, &self.name, CrateAction::InviteOwner)
+                            .actor(req_user.id, api_token_id)
+                            .target_user(user.id)
+                            .insert(conn)?;
+
+                        let payload = WebhookPayload::new(WebhookEvent::OwnerInvite, &self.name)
+                            .user(req_user)
+                            .owner(&owner);
+                        jobs::enqueue_webhooks(self.id, &payload, conn)?;
+
                         if let Ok(Some(recipient)) = user.verified_email(conn) {
                             // Swallow any error. Whether or not the email is sent, the invitation
                             // entry will be created in the database and the user will see the
@@ -393,7 +406,7 @@ impl Crate {
                 }
             }
             // Teams are added as owners immediately
-            owner @ Owner::Team(_) => {
+            Owner::Team(_) => {
                 insert_into(crate_owners::table)
                     .values(&CrateOwner {
                         crate_id: self.id,
@@ -407,6 +420,16 @@ impl Crate {
                     .set(crate_owners::deleted.eq(false))
                     .execute(conn)?;
 
+                NewCrateAuditAction::new(self.id, &self.name, CrateAction::AddOwner)
+                    .actor(req_user.id, api_token_id)
+                    .target_owner(&owner)
+                    .insert(conn)?;
+
+                let payload = WebhookPayload::new(WebhookEvent::OwnerAdd, &self.name)
+                    .user(req_user)
+                    .owner(&owner);
+                jobs::enqueue_webhooks(self.id, &payload, conn)?;
+
                 Ok(format!(
                     "team {} has been added as an owner of crate {}",
                     owner.login(),
@@ -416,14 +439,15 @@ impl Crate {
         }
     }
 
-    pub fn owner_remove(&self, conn: &mut PgConnection, login: &str) -> AppResult<()> {
+    /// Removes the owner with the given login from the crate and returns it.
+    pub fn owner_remove(&self, conn: &mut PgConnection, login: &str) -> AppResult<Owner> {
         let owner = Owner::find_by_login(conn, login)?;
 
         let target = crate_owners::table.find((self.id(), owner.id(), owner.kind()));
         diesel::update(target)
             .set(crate_owners::deleted.eq(true))
             .execute(conn)?;
-        Ok(())
+        Ok(owner)
     }
 
     /// Returns (dependency, dependent crate name, dependent crate downloads)
@@ -0,0 +1,184 @@
+use chrono::NaiveDateTime;
+use diesel::dsl::now;
+use diesel::prelude::*;
+
+use crate::app::App;
+use crate::models::{ApiToken, Crate, Owner, User};
+use crate::schema::publish_policies;
+use crate::util::errors::{forbidden, AppResult};
+
+/// The model representing a row in the `publish_policies` database table.
+///
+/// A publish policy restricts who may publish new versions of a crate and
+/// which kind of API tokens they have to use for it. Crates without a
+/// policy can be published by all owners with any suitably scoped token.
+#[derive(Clone, Debug, Identifiable, Queryable, Selectable, Associations)]
+#[diesel(
+    table_name = publish_policies,
+    check_for_backend(diesel::pg::Pg),
+    primary_key(crate_id),
+    belongs_to(Crate),
+    belongs_to(User, foreign_key = updated_by),
+)]
+pub struct PublishPolicy {
+    pub crate_id: i32,
+    pub allowed_users: Vec<i32>,
+    pub allowed_teams: Vec<i32>,
+    pub prerelease_users: Vec<i32>,
+    pub prerelease_teams: Vec<i32>,
+    pub require_token_expiry: bool,
+    pub require_trusted_publishing: bool,
+    pub updated_by: i32,
+    pub updated_at: NaiveDateTime,
+}
+
+impl PublishPolicy {
+    /// Returns the publish policy of a crate, if the owners have configured
+    /// one.
+    pub fn for_crate(conn: &mut PgConnection, crate_id: i32) -> QueryResult<Option<Self>> {
+        publish_policies::table
+            .find(crate_id)
+            .select(Self::as_select())
+            .first(conn)
+            .optional()
+    }
+
+    /// Checks whether `user` may publish `version` of the crate with the
+    /// given API token (or `None` for cookie authentication).
+    ///
+    /// Teams in the allow lists only grant access while they are still
+    /// owners of the crate, so `owners` has to contain the current owners.
+    pub async fn check(
+        &self,
+        app: &App,
+        owners: &[Owner],
+        user: &User,
+        token: Option<&ApiToken>,
+        version: &semver::Version,
+    ) -> AppResult<()> {
+        if self.require_trusted_publishing && token.and_then(|t| t.trusted_publisher_id).is_none() {
+            return Err(forbidden(
+                "the publish policy of this crate only allows publishing via trusted publishing",
+            ));
+        }
+
+        if self.require_token_expiry && token.and_then(|t| t.expired_at).is_none() {
+            return Err(forbidden(
+                "the publish policy of this crate only allows publishing with API tokens that have an expiry date",
+            ));
+        }
+
+        let team_id = token.and_then(|t| t.team_id);
+
+        if !is_allowed(
+            app,
+            owners,
+            user,
+            team_id,
+            &self.allowed_users,
+            &self.allowed_teams,
+        )
+        .await?
+        {
+            return Err(forbidden(
+                "the publish policy of this crate does not allow you to publish new versions",
+            ));
+        }
+
+        if !version.pre.is_empty()
+            && !is_allowed(
+                app,
+                owners,
+                user,
+                team_id,
+                &self.prerelease_users,
+                &self.prerelease_teams,
+            )
+            .await?
+        {
+            return Err(forbidden(
+                "the publish policy of this crate does not allow you to publish prerelease versions",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Checks whether `user` may change the policy.
+    ///
+    /// Only the allowed publishers may change a policy that restricts who
+    /// can publish, so that the owners it excludes can't loosen it.
+    pub async fn check_update(&self, app: &App, owners: &[Owner], user: &User) -> AppResult<()> {
+        let users = &self.allowed_users;
+        let teams = &self.allowed_teams;
+        if !is_allowed(app, owners, user, None, users, teams).await? {
+            return Err(forbidden(
+                "only the allowed publishers of this crate may change its publish policy",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks whether `user` is in the list of allowed users or a member of one
+/// of the allowed teams. Empty lists allow everyone.
+///
+/// Requests with a token that is owned by a team (`team_id`) are only
+/// allowed if that team is in the list of allowed teams.
+async fn is_allowed(
+    app: &App,
+    owners: &[Owner],
+    user: &User,
+    team_id: Option<i32>,
+    users: &[i32],
+    teams: &[i32],
+) -> AppResult<bool> {
+    if users.is_empty() && teams.is_empty() {
+        return Ok(true);
+    }
+
+    if let Some(team_id) = team_id {
+        return Ok(teams.contains(&team_id));
+    }
+
+    if users.contains(&user.id) {
+        return Ok(true);
+    }
+
+    for owner in owners {
+        if let Owner::Team(team) = owner {
+            if teams.contains(&team.id) && team.contains_user(app, user).await? {
+                return Ok(true);
+            }
+        }
+    }
+
+    Ok(false)
+}
+
+#[derive(Insertable, AsChangeset, Debug)]
+#[diesel(table_name = publish_policies, check_for_backend(diesel::pg::Pg))]
+pub struct NewPublishPolicy {
+    pub crate_id: i32,
+    pub allowed_users: Vec<i32>,
+    pub allowed_teams: Vec<i32>,
+    pub prerelease_users: Vec<i32>,
+    pub prerelease_teams: Vec<i32>,
+    pub require_token_expiry: bool,
+    pub require_trusted_publishing: bool,
+    pub updated_by: i32,
+}
+
+impl NewPublishPolicy {
+    /// Inserts the policy, or replaces the existing policy of the crate.
+    pub fn upsert(&self, conn: &mut PgConnection) -> QueryResult<PublishPolicy> {
+        diesel::insert_into(publish_policies::table)
+            .values(self)
+            .on_conflict(publish_policies::crate_id)
+            .do_update()
+            .set((self, publish_policies::updated_at.eq(now)))
+            .returning(PublishPolicy::as_returning())
+            .get_result(conn)
+    }
+}
@@ -14,7 +14,7 @@ use crate::sql::lower;
 
 /// For now, just a Github Team. Can be upgraded to other teams
 /// later if desirable.
-#[derive(Queryable, Identifiable, Serialize, Deserialize, Debug)]
+#[derive(Clone, Queryable, Identifiable, Serialize, Deserialize, Debug)]
 pub struct Team {
     /// Unique table id
     pub id: i32,
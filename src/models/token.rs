@@ -1,9 +1,12 @@
 mod scopes;
+mod usage;
 
 use chrono::NaiveDateTime;
 use diesel::prelude::*;
+use ipnetwork::IpNetwork;
 
 pub use self::scopes::{CrateScope, EndpointScope};
+pub use self::usage::{ApiTokenUsage, NewApiTokenUsage};
 use crate::models::User;
 use crate::schema::api_tokens;
 use crate::util::errors::{AppResult, InsecurelyGeneratedTokenRevoked};
@@ -30,6 +33,54 @@ pub struct ApiToken {
     pub endpoint_scopes: Option<Vec<EndpointScope>>,
     #[serde(with = "rfc3339::option")]
     pub expired_at: Option<NaiveDateTime>,
+    /// The trusted publisher configuration that this token was minted for,
+    /// or `None` for regular tokens created by the user
+    #[serde(skip)]
+    pub trusted_publisher_id: Option<i32>,
+    /// The team that owns this token, or `None` for regular tokens of the
+    /// user. Team tokens can only be used for crates that the team owns.
+    #[serde(skip)]
+    pub team_id: Option<i32>,
+    /// `None` or a list of CIDR blocks that requests with this token have
+    /// to come from
+    pub allowed_ips: Option<Vec<IpNetwork>>,
+    /// When the token was replaced by a new token, which also shortened its
+    /// expiry date to a grace period
+    #[serde(skip)]
+    pub rotated_at: Option<NaiveDateTime>,
+}
+
+/// Represents a new API token record insertable to the `api_tokens` table.
+///
+/// The secret of the token is generated when it is inserted.
+#[derive(Insertable, Debug, Default)]
+#[diesel(table_name = api_tokens, check_for_backend(diesel::pg::Pg))]
+pub struct NewApiToken<'a> {
+    pub user_id: i32,
+    pub name: &'a str,
+    pub crate_scopes: Option<Vec<CrateScope>>,
+    pub endpoint_scopes: Option<Vec<EndpointScope>>,
+    pub expired_at: Option<NaiveDateTime>,
+    pub trusted_publisher_id: Option<i32>,
+    pub team_id: Option<i32>,
+    pub allowed_ips: Option<Vec<IpNetwork>>,
+}
+
+impl NewApiToken<'_> {
+    /// Generates a secret for the token and inserts it into the database.
+    pub fn insert(&self, conn: &mut PgConnection) -> QueryResult<CreatedApiToken> {
+        let token = PlainToken::generate();
+
+        let model: ApiToken = diesel::insert_into(api_tokens::table)
+            .values((self, api_tokens::token.eq(token.hashed())))
+            .returning(ApiToken::as_returning())
+            .get_result(conn)?;
+
+        Ok(CreatedApiToken {
+            plaintext: token,
+            model,
+        })
+    }
 }
 
 impl ApiToken {
@@ -39,34 +90,59 @@ impl ApiToken {
         user_id: i32,
         name: &str,
     ) -> QueryResult<CreatedApiToken> {
-        Self::insert_with_scopes(conn, user_id, name, None, None, None)
+        NewApiToken {
+            user_id,
+            name,
+            ..Default::default()
+        }
+        .insert(conn)
     }
 
-    pub fn insert_with_scopes(
+    /// Generates a new secret for the token by creating a copy of it with
+    /// the same name, scopes, expiry date, owner and IP restrictions.
+    ///
+    /// `user_id` is the user that rotates the token, which can be a different
+    /// member of the team for tokens that are owned by a team.
+    ///
+    /// The old token stays valid until `valid_until` (or until it expires,
+    /// if that is earlier), so that it can be replaced without downtime.
+    /// Tokens can only be rotated once, since the copy of a token in its
+    /// grace period would inherit the shortened expiry date.
+    pub fn rotate(
+        &self,
         conn: &mut PgConnection,
         user_id: i32,
-        name: &str,
-        crate_scopes: Option<Vec<CrateScope>>,
-        endpoint_scopes: Option<Vec<EndpointScope>>,
-        expired_at: Option<NaiveDateTime>,
+        valid_until: NaiveDateTime,
     ) -> QueryResult<CreatedApiToken> {
-        let token = PlainToken::generate();
+        use diesel::dsl::now;
 
-        let model: ApiToken = diesel::insert_into(api_tokens::table)
-            .values((
-                api_tokens::user_id.eq(user_id),
-                api_tokens::name.eq(name),
-                api_tokens::token.eq(token.hashed()),
-                api_tokens::crate_scopes.eq(crate_scopes),
-                api_tokens::endpoint_scopes.eq(endpoint_scopes),
-                api_tokens::expired_at.eq(expired_at),
-            ))
-            .returning(ApiToken::as_returning())
-            .get_result(conn)?;
+        conn.transaction(|conn| {
+            let created = NewApiToken {
+                user_id,
+                name: &self.name,
+                crate_scopes: self.crate_scopes.clone(),
+                endpoint_scopes: self.endpoint_scopes.clone(),
+                expired_at: self.expired_at,
+                team_id: self.team_id,
+                allowed_ips: self.allowed_ips.clone(),
+                ..Default::default()
+            }
+            .insert(conn)?;
 
-        Ok(CreatedApiToken {
-            plaintext: token,
-            model,
+            diesel::update(api_tokens::table.find(self.id))
+                .set(api_tokens::rotated_at.eq(now.nullable()))
+                .execute(conn)?;
+
+            diesel::update(api_tokens::table.find(self.id))
+                .filter(
+                    api_tokens::expired_at
+                        .is_null()
+                        .or(api_tokens::expired_at.gt(valid_until)),
+                )
+                .set(api_tokens::expired_at.eq(valid_until))
+                .execute(conn)?;
+
+            Ok(created)
         })
     }
 
@@ -125,6 +201,10 @@ mod tests {
             crate_scopes: None,
             endpoint_scopes: None,
             expired_at: None,
+            trusted_publisher_id: None,
+            team_id: None,
+            allowed_ips: None,
+            rotated_at: None,
         };
         let json = serde_json::to_string(&tok).unwrap();
         assert_some!(json
@@ -13,6 +13,7 @@ pub enum EndpointScope {
     PublishUpdate,
     Yank,
     ChangeOwners,
+    Read,
 }
 
 impl From<&EndpointScope> for &[u8] {
@@ -22,6 +23,7 @@ impl From<&EndpointScope> for &[u8] {
             EndpointScope::PublishUpdate => b"publish-update",
             EndpointScope::Yank => b"yank",
             EndpointScope::ChangeOwners => b"change-owners",
+            EndpointScope::Read => b"read",
         }
     }
 }
@@ -42,6 +44,7 @@ impl TryFrom<&[u8]> for EndpointScope {
             b"publish-update" => Ok(EndpointScope::PublishUpdate),
             b"yank" => Ok(EndpointScope::Yank),
             b"change-owners" => Ok(EndpointScope::ChangeOwners),
+            b"read" => Ok(EndpointScope::Read),
             _ => Err("Unrecognized enum variant".to_string()),
         }
     }
@@ -138,6 +141,7 @@ mod tests {
         assert(EndpointScope::PublishNew, "\"publish-new\"");
         assert(EndpointScope::PublishUpdate, "\"publish-update\"");
         assert(EndpointScope::Yank, "\"yank\"");
+        assert(EndpointScope::Read, "\"read\"");
     }
 
     #[googletest::test]
@@ -0,0 +1,87 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::sql_types::BigInt;
+use ipnetwork::IpNetwork;
+
+use crate::models::ApiToken;
+use crate::schema::api_token_usages;
+
+/// Number of recent uses that are kept in the usage log of each API token.
+const MAX_USAGES_PER_TOKEN: i64 = 100;
+
+/// The model representing a row in the `api_token_usages` database table.
+#[derive(Debug, Identifiable, Queryable, Selectable, Associations)]
+#[diesel(
+    table_name = api_token_usages,
+    check_for_backend(diesel::pg::Pg),
+    belongs_to(ApiToken),
+)]
+pub struct ApiTokenUsage {
+    pub id: i64,
+    pub api_token_id: i32,
+    pub used_at: NaiveDateTime,
+    pub endpoint: String,
+    pub crate_name: Option<String>,
+    pub ip: Option<IpNetwork>,
+    pub rejection_reason: Option<String>,
+}
+
+impl ApiTokenUsage {
+    /// Returns the usage log of an API token, starting with the most recent
+    /// request.
+    pub fn recent(conn: &mut PgConnection, api_token_id: i32) -> QueryResult<Vec<Self>> {
+        api_token_usages::table
+            .filter(api_token_usages::api_token_id.eq(api_token_id))
+            .select(Self::as_select())
+            .order(api_token_usages::id.desc())
+            .limit(MAX_USAGES_PER_TOKEN)
+            .load(conn)
+    }
+
+    /// Removes the entries of all usage logs that are older than the most
+    /// recent [`MAX_USAGES_PER_TOKEN`] requests of their API token.
+    ///
+    /// Returns the number of removed entries.
+    pub fn prune(conn: &mut PgConnection) -> QueryResult<usize> {
+        diesel::sql_query(
+            r#"
+                DELETE FROM api_token_usages
+                WHERE id IN (
+                    SELECT id FROM (
+                        SELECT id, row_number() OVER (
+                            PARTITION BY api_token_id ORDER BY id DESC
+                        ) AS position
+                        FROM api_token_usages
+                    ) AS usages
+                    WHERE position > $1
+                );
+            "#,
+        )
+        .bind::<BigInt, _>(MAX_USAGES_PER_TOKEN)
+        .execute(conn)
+    }
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = api_token_usages, check_for_backend(diesel::pg::Pg))]
+pub struct NewApiTokenUsage<'a> {
+    pub api_token_id: i32,
+    pub endpoint: &'a str,
+    pub crate_name: Option<&'a str>,
+    pub ip: Option<IpNetwork>,
+    pub rejection_reason: Option<&'a str>,
+}
+
+impl NewApiTokenUsage<'_> {
+    /// Adds the request to the usage log of the API token.
+    ///
+    /// The entries that no longer fit into the usage log are removed by the
+    /// [`ApiTokenUsage::prune`] function.
+    pub fn insert(&self, conn: &mut PgConnection) -> QueryResult<()> {
+        diesel::insert_into(api_token_usages::table)
+            .values(self)
+            .execute(conn)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,183 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::dsl::{exists, now};
+use diesel::prelude::*;
+use sha2::{Digest, Sha256};
+
+use crate::models::User;
+use crate::schema::totp_credentials;
+use crate::util::token::generate_secure_alphanumeric_string;
+use crate::util::totp;
+
+/// Number of recovery codes that are generated when the enrollment is
+/// confirmed.
+const RECOVERY_CODE_COUNT: usize = 10;
+
+/// The model representing a row in the `totp_credentials` database table.
+///
+/// A row is created when the user starts the enrollment in two-factor
+/// authentication, but the credential is only enforced once the user
+/// confirmed the enrollment with a valid code.
+#[derive(Clone, Identifiable, Queryable, Selectable, Associations)]
+#[diesel(
+    table_name = totp_credentials,
+    check_for_backend(diesel::pg::Pg),
+    primary_key(user_id),
+    belongs_to(User),
+)]
+pub struct TotpCredential {
+    pub user_id: i32,
+    pub secret: Vec<u8>,
+    pub created_at: NaiveDateTime,
+    pub enabled_at: Option<NaiveDateTime>,
+    pub last_used_step: Option<i64>,
+    pub recovery_codes: Vec<Vec<u8>>,
+}
+
+impl TotpCredential {
+    /// Returns the credential of a user, including pending enrollments.
+    pub fn find(conn: &mut PgConnection, user_id: i32) -> QueryResult<Option<Self>> {
+        totp_credentials::table
+            .find(user_id)
+            .select(Self::as_select())
+            .first(conn)
+            .optional()
+    }
+
+    /// Checks whether the user has confirmed the enrollment in two-factor
+    /// authentication.
+    pub fn is_enabled(conn: &mut PgConnection, user_id: i32) -> QueryResult<bool> {
+        diesel::select(exists(
+            totp_credentials::table
+                .filter(totp_credentials::user_id.eq(user_id))
+                .filter(totp_credentials::enabled_at.is_not_null()),
+        ))
+        .get_result(conn)
+    }
+
+    /// Checks a code from the authenticator app or one of the recovery
+    /// codes.
+    ///
+    /// Accepted codes are invalidated, so each code can only be used once.
+    pub fn verify(&self, conn: &mut PgConnection, code: &str) -> QueryResult<bool> {
+        let timestamp = Utc::now().timestamp();
+        if let Some(step) = totp::verify_code(&self.secret, code, timestamp, self.last_used_step) {
+            // The filter prevents concurrent requests from using the same code
+            let updated = diesel::update(totp_credentials::table.find(self.user_id))
+                .filter(
+                    totp_credentials::last_used_step
+                        .is_null()
+                        .or(totp_credentials::last_used_step.lt(step)),
+                )
+                .set(totp_credentials::last_used_step.eq(step))
+                .execute(conn)?;
+
+            return Ok(updated > 0);
+        }
+
+        let hash = hash_recovery_code(code);
+        let Some(position) = self.recovery_codes.iter().position(|h| *h == hash) else {
+            return Ok(false);
+        };
+
+        let mut recovery_codes = self.recovery_codes.clone();
+        recovery_codes.remove(position);
+
+        let updated = diesel::update(totp_credentials::table.find(self.user_id))
+            .filter(totp_credentials::recovery_codes.eq(&self.recovery_codes))
+            .set(totp_credentials::recovery_codes.eq(recovery_codes))
+            .execute(conn)?;
+
+        Ok(updated > 0)
+    }
+
+    /// Confirms a pending enrollment and returns a new set of recovery codes.
+    ///
+    /// Only the hashes of the recovery codes are stored, so they can't be
+    /// shown to the user again.
+    pub fn enable(&self, conn: &mut PgConnection) -> QueryResult<Vec<String>> {
+        let recovery_codes = (0..RECOVERY_CODE_COUNT)
+            .map(|_| generate_recovery_code())
+            .collect::<Vec<_>>();
+
+        let hashes = recovery_codes
+            .iter()
+            .map(|code| hash_recovery_code(code))
+            .collect::<Vec<_>>();
+
+        diesel::update(totp_credentials::table.find(self.user_id))
+            .set((
+                totp_credentials::enabled_at.eq(now),
+                totp_credentials::recovery_codes.eq(hashes),
+            ))
+            .execute(conn)?;
+
+        Ok(recovery_codes)
+    }
+
+    /// Removes the credential of a user, which disables two-factor
+    /// authentication or cancels a pending enrollment.
+    pub fn delete(conn: &mut PgConnection, user_id: i32) -> QueryResult<bool> {
+        let deleted = diesel::delete(totp_credentials::table.find(user_id)).execute(conn)?;
+        Ok(deleted > 0)
+    }
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = totp_credentials, check_for_backend(diesel::pg::Pg))]
+pub struct NewTotpCredential<'a> {
+    pub user_id: i32,
+    pub secret: &'a [u8],
+}
+
+impl NewTotpCredential<'_> {
+    /// Starts a new enrollment, replacing any pending enrollment of the
+    /// user.
+    pub fn insert(&self, conn: &mut PgConnection) -> QueryResult<TotpCredential> {
+        conn.transaction(|conn| {
+            diesel::delete(totp_credentials::table.find(self.user_id))
+                .filter(totp_credentials::enabled_at.is_null())
+                .execute(conn)?;
+
+            diesel::insert_into(totp_credentials::table)
+                .values(self)
+                .returning(TotpCredential::as_returning())
+                .get_result(conn)
+        })
+    }
+}
+
+/// Generates a recovery code in the `xxxxx-xxxxx` format.
+fn generate_recovery_code() -> String {
+    let code = generate_secure_alphanumeric_string(10).to_lowercase();
+    format!("{}-{}", &code[..5], &code[5..])
+}
+
+/// Recovery codes are compared case-insensitively and without the dash,
+/// since users might type them in by hand.
+fn hash_recovery_code(code: &str) -> Vec<u8> {
+    let normalized = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect::<String>();
+
+    Sha256::digest(normalized.as_bytes()).to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recovery_codes() {
+        let code = generate_recovery_code();
+        assert_eq!(code.len(), 11);
+        assert_eq!(code.as_bytes()[5], b'-');
+
+        assert_eq!(
+            hash_recovery_code(&code),
+            hash_recovery_code(&code.replace('-', "").to_uppercase())
+        );
+        assert_ne!(hash_recovery_code(&code), hash_recovery_code("abcde-fghij"));
+    }
+}
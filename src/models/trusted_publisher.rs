@@ -0,0 +1,123 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+use crate::models::{Crate, User};
+use crate::schema::trusted_publishers;
+
+/// The model representing a row in the `trusted_publishers` database table.
+///
+/// A trusted publisher describes a CI workflow that is allowed to exchange
+/// an OIDC identity token for a short-lived API token, which can then be
+/// used to publish new versions of the crate.
+#[derive(Clone, Debug, Identifiable, Queryable, Selectable, Associations)]
+#[diesel(
+    table_name = trusted_publishers,
+    check_for_backend(diesel::pg::Pg),
+    belongs_to(Crate),
+    belongs_to(User, foreign_key = created_by),
+)]
+pub struct TrustedPublisher {
+    pub id: i32,
+    pub crate_id: i32,
+    pub repository_owner: String,
+    pub repository_name: String,
+    pub workflow_filename: String,
+    pub environment: Option<String>,
+    pub created_by: i32,
+    pub created_at: NaiveDateTime,
+}
+
+impl TrustedPublisher {
+    /// Returns all trusted publisher configurations of a crate, ordered by
+    /// their creation date.
+    pub fn for_crate(conn: &mut PgConnection, crate_id: i32) -> QueryResult<Vec<Self>> {
+        trusted_publishers::table
+            .filter(trusted_publishers::crate_id.eq(crate_id))
+            .select(Self::as_select())
+            .order(trusted_publishers::id)
+            .load(conn)
+    }
+
+    /// Checks whether the claims of a verified OIDC identity token match
+    /// this configuration.
+    ///
+    /// Repository owner, repository name and environment are compared
+    /// case-insensitively, since GitHub treats them that way too. If the
+    /// configuration does not specify an environment, any (or no)
+    /// environment is accepted.
+    pub fn matches(
+        &self,
+        repository_owner: &str,
+        repository_name: &str,
+        workflow_filename: &str,
+        environment: Option<&str>,
+    ) -> bool {
+        let environment_matches = match (&self.environment, environment) {
+            (None, _) => true,
+            (Some(expected), Some(actual)) => expected.eq_ignore_ascii_case(actual),
+            (Some(_), None) => false,
+        };
+
+        self.repository_owner.eq_ignore_ascii_case(repository_owner)
+            && self.repository_name.eq_ignore_ascii_case(repository_name)
+            && self.workflow_filename == workflow_filename
+            && environment_matches
+    }
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = trusted_publishers, check_for_backend(diesel::pg::Pg))]
+pub struct NewTrustedPublisher<'a> {
+    pub crate_id: i32,
+    pub repository_owner: &'a str,
+    pub repository_name: &'a str,
+    pub workflow_filename: &'a str,
+    pub environment: Option<&'a str>,
+    pub created_by: i32,
+}
+
+impl NewTrustedPublisher<'_> {
+    pub fn insert(&self, conn: &mut PgConnection) -> QueryResult<TrustedPublisher> {
+        diesel::insert_into(trusted_publishers::table)
+            .values(self)
+            .returning(TrustedPublisher::as_returning())
+            .get_result(conn)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn publisher(environment: Option<&str>) -> TrustedPublisher {
+        TrustedPublisher {
+            id: 1,
+            crate_id: 1,
+            repository_owner: "rust-lang".into(),
+            repository_name: "crates.io".into(),
+            workflow_filename: "release.yml".into(),
+            environment: environment.map(Into::into),
+            created_by: 1,
+            created_at: NaiveDateTime::default(),
+        }
+    }
+
+    #[test]
+    fn matches_repository_case_insensitively() {
+        let publisher = publisher(None);
+        assert!(publisher.matches("rust-lang", "crates.io", "release.yml", None));
+        assert!(publisher.matches("Rust-Lang", "Crates.IO", "release.yml", Some("prod")));
+        assert!(!publisher.matches("rust-lang", "crates.io", "Release.yml", None));
+        assert!(!publisher.matches("rust-lang", "cargo", "release.yml", None));
+        assert!(!publisher.matches("evil", "crates.io", "release.yml", None));
+    }
+
+    #[test]
+    fn matches_environment_if_configured() {
+        let publisher = publisher(Some("release"));
+        assert!(publisher.matches("rust-lang", "crates.io", "release.yml", Some("release")));
+        assert!(publisher.matches("rust-lang", "crates.io", "release.yml", Some("Release")));
+        assert!(!publisher.matches("rust-lang", "crates.io", "release.yml", Some("dev")));
+        assert!(!publisher.matches("rust-lang", "crates.io", "release.yml", None));
+    }
+}
@@ -0,0 +1,93 @@
+use chrono::NaiveDateTime;
+use diesel::dsl::now;
+use diesel::prelude::*;
+
+use crate::models::Crate;
+use crate::schema::typosquat_findings;
+use crate::sql::pg_enum;
+
+pg_enum! {
+    pub enum TyposquatStatus {
+        Open = 0,
+        Dismissed = 1,
+        Confirmed = 2,
+    }
+}
+
+/// The model representing a row in the `typosquat_findings` database table.
+#[derive(Debug, Identifiable, Queryable, Selectable, Associations)]
+#[diesel(
+    table_name = typosquat_findings,
+    check_for_backend(diesel::pg::Pg),
+    belongs_to(Crate, foreign_key = flagged_crate_id),
+)]
+pub struct TyposquatFinding {
+    pub id: i32,
+    /// `None` if the crate has been deleted since.
+    pub flagged_crate_id: Option<i32>,
+    pub crate_name: String,
+    pub similar_crate: String,
+    pub reason: String,
+    pub status: TyposquatStatus,
+    pub created_at: NaiveDateTime,
+    pub reviewed_at: Option<NaiveDateTime>,
+}
+
+impl TyposquatFinding {
+    /// Returns the findings with the given status, or all findings if no
+    /// status is given, starting with the oldest one.
+    pub fn list(
+        conn: &mut PgConnection,
+        status: Option<TyposquatStatus>,
+    ) -> QueryResult<Vec<Self>> {
+        let mut query = typosquat_findings::table
+            .select(Self::as_select())
+            .order(typosquat_findings::id)
+            .into_boxed();
+
+        if let Some(status) = status {
+            query = query.filter(typosquat_findings::status.eq(status));
+        }
+
+        query.load(conn)
+    }
+
+    /// Sets the status of all open findings of a crate, and returns the
+    /// number of updated findings.
+    pub fn review(
+        conn: &mut PgConnection,
+        crate_name: &str,
+        status: TyposquatStatus,
+    ) -> QueryResult<usize> {
+        diesel::update(typosquat_findings::table)
+            .filter(typosquat_findings::crate_name.eq(crate_name))
+            .filter(typosquat_findings::status.eq(TyposquatStatus::Open))
+            .set((
+                typosquat_findings::status.eq(status),
+                typosquat_findings::reviewed_at.eq(now),
+            ))
+            .execute(conn)
+    }
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = typosquat_findings, check_for_backend(diesel::pg::Pg))]
+pub struct NewTyposquatFinding<'a> {
+    pub flagged_crate_id: i32,
+    pub crate_name: &'a str,
+    pub similar_crate: &'a str,
+    pub reason: &'a str,
+}
+
+impl NewTyposquatFinding<'_> {
+    /// Inserts the finding, unless the same finding has already been
+    /// recorded for the crate.
+    pub fn insert(&self, conn: &mut PgConnection) -> QueryResult<()> {
+        diesel::insert_into(typosquat_findings::table)
+            .values(self)
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+
+        Ok(())
+    }
+}
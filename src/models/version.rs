@@ -6,6 +6,7 @@ use diesel::prelude::*;
 use crate::util::errors::{bad_request, AppResult};
 
 use crate::db::sql_types::semver::Triple;
+use crate::licenses::{license_query, parse_license_expr};
 use crate::models::{Crate, Dependency, User};
 use crate::schema::*;
 use crate::sql::split_part;
@@ -29,6 +30,9 @@ pub struct Version {
     pub links: Option<String>,
     pub rust_version: Option<String>,
     pub semver_no_prerelease: Option<Triple>,
+    pub yank_message: Option<String>,
+    pub yank_advisory: Option<String>,
+    pub license_query: Option<String>,
 }
 
 #[derive(Insertable, Debug)]
@@ -38,6 +42,7 @@ pub struct NewVersion {
     num: String,
     features: serde_json::Value,
     license: Option<String>,
+    license_query: Option<String>,
     crate_size: Option<i32>,
     published_by: i32,
     checksum: String,
@@ -142,12 +147,17 @@ impl NewVersion {
         rust_version: Option<String>,
     ) -> AppResult<Self> {
         let features = serde_json::to_value(features)?;
+        let license_query = license
+            .as_deref()
+            .and_then(|license| parse_license_expr(license).ok())
+            .map(|expression| license_query(&expression));
 
         Ok(NewVersion {
             crate_id,
             num: num.to_string(),
             features,
             license,
+            license_query,
             crate_size: Some(crate_size),
             published_by,
             checksum,
@@ -0,0 +1,232 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+use crate::models::{Crate, Owner, OwnerKind, User};
+use crate::schema::{crate_owners, webhook_deliveries, webhooks};
+use crate::util::rfc3339;
+
+/// The events that webhooks can subscribe to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    Publish,
+    Yank,
+    Unyank,
+    OwnerAdd,
+    OwnerRemove,
+    OwnerInvite,
+}
+
+impl WebhookEvent {
+    pub const ALL: [WebhookEvent; 6] = [
+        WebhookEvent::Publish,
+        WebhookEvent::Yank,
+        WebhookEvent::Unyank,
+        WebhookEvent::OwnerAdd,
+        WebhookEvent::OwnerRemove,
+        WebhookEvent::OwnerInvite,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::Publish => "publish",
+            WebhookEvent::Yank => "yank",
+            WebhookEvent::Unyank => "unyank",
+            WebhookEvent::OwnerAdd => "owner_add",
+            WebhookEvent::OwnerRemove => "owner_remove",
+            WebhookEvent::OwnerInvite => "owner_invite",
+        }
+    }
+}
+
+/// The model representing a row in the `webhooks` database table.
+///
+/// Webhooks with a `crate_id` are notified about the events of that crate.
+/// Webhooks without a `crate_id` are notified about the events of all crates
+/// that are owned by the user that registered them.
+#[derive(Clone, Debug, Identifiable, Queryable, Selectable, Associations)]
+#[diesel(
+    table_name = webhooks,
+    check_for_backend(diesel::pg::Pg),
+    belongs_to(Crate),
+    belongs_to(User),
+)]
+pub struct Webhook {
+    pub id: i32,
+    pub crate_id: Option<i32>,
+    pub user_id: i32,
+    pub url: String,
+    pub secret: String,
+    pub events: Vec<String>,
+    pub created_at: NaiveDateTime,
+}
+
+impl Webhook {
+    /// Returns all webhooks that were registered for a crate.
+    pub fn for_crate(conn: &mut PgConnection, crate_id: i32) -> QueryResult<Vec<Self>> {
+        webhooks::table
+            .filter(webhooks::crate_id.eq(crate_id))
+            .select(Self::as_select())
+            .order(webhooks::id)
+            .load(conn)
+    }
+
+    /// Returns all webhooks that a user registered for all of their crates.
+    pub fn for_user(conn: &mut PgConnection, user_id: i32) -> QueryResult<Vec<Self>> {
+        webhooks::table
+            .filter(webhooks::crate_id.is_null())
+            .filter(webhooks::user_id.eq(user_id))
+            .select(Self::as_select())
+            .order(webhooks::id)
+            .load(conn)
+    }
+
+    /// Returns the IDs of all webhooks that have to be notified about an
+    /// event of a crate.
+    ///
+    /// These are the webhooks of the crate itself, and the user-level
+    /// webhooks of the current individual owners of the crate. The user-level
+    /// webhooks of `target_user_id` are included too, so that users get
+    /// notified about invitations and removals even though they are not (or
+    /// no longer) owners of the crate.
+    pub fn subscribed_to(
+        conn: &mut PgConnection,
+        crate_id: i32,
+        event: WebhookEvent,
+        target_user_id: Option<i32>,
+    ) -> QueryResult<Vec<i32>> {
+        let user_ids = crate_owners::table
+            .filter(crate_owners::crate_id.eq(crate_id))
+            .filter(crate_owners::owner_kind.eq(OwnerKind::User))
+            .filter(crate_owners::deleted.eq(false))
+            .select(crate_owners::owner_id)
+            .load::<i32>(conn)?
+            .into_iter()
+            .chain(target_user_id)
+            .collect::<Vec<_>>();
+
+        webhooks::table
+            .filter(webhooks::events.contains(vec![event.as_str()]))
+            .filter(
+                webhooks::crate_id.eq(crate_id).or(webhooks::crate_id
+                    .is_null()
+                    .and(webhooks::user_id.eq_any(user_ids))),
+            )
+            .select(webhooks::id)
+            .order(webhooks::id)
+            .load(conn)
+    }
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = webhooks, check_for_backend(diesel::pg::Pg))]
+pub struct NewWebhook<'a> {
+    pub crate_id: Option<i32>,
+    pub user_id: i32,
+    pub url: &'a str,
+    pub secret: &'a str,
+    pub events: Vec<&'a str>,
+}
+
+impl NewWebhook<'_> {
+    pub fn insert(&self, conn: &mut PgConnection) -> QueryResult<Webhook> {
+        diesel::insert_into(webhooks::table)
+            .values(self)
+            .returning(Webhook::as_returning())
+            .get_result(conn)
+    }
+}
+
+/// The model representing a row in the `webhook_deliveries` database table.
+#[derive(Clone, Debug, Identifiable, Queryable, Selectable, Associations)]
+#[diesel(
+    table_name = webhook_deliveries,
+    check_for_backend(diesel::pg::Pg),
+    belongs_to(Webhook),
+)]
+pub struct WebhookDelivery {
+    pub id: i32,
+    pub webhook_id: i32,
+    pub event: String,
+    pub payload: serde_json::Value,
+    pub attempts: i32,
+    pub response_status: Option<i32>,
+    pub error: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub last_attempt_at: Option<NaiveDateTime>,
+    pub delivered_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = webhook_deliveries, check_for_backend(diesel::pg::Pg))]
+pub struct NewWebhookDelivery<'a> {
+    pub webhook_id: i32,
+    pub event: &'a str,
+    pub payload: &'a serde_json::Value,
+}
+
+impl NewWebhookDelivery<'_> {
+    pub fn insert(&self, conn: &mut PgConnection) -> QueryResult<i32> {
+        diesel::insert_into(webhook_deliveries::table)
+            .values(self)
+            .returning(webhook_deliveries::id)
+            .get_result(conn)
+    }
+}
+
+/// The JSON payload that is sent to webhooks.
+#[derive(Debug, Serialize)]
+pub struct WebhookPayload<'a> {
+    event: WebhookEvent,
+    #[serde(rename = "crate")]
+    krate: &'a str,
+    version: Option<&'a str>,
+    /// Login of the user that performed the action
+    user: Option<&'a str>,
+    /// Login of the user or team that was added, removed or invited as an owner
+    owner: Option<&'a str>,
+    #[serde(with = "rfc3339")]
+    time: NaiveDateTime,
+    #[serde(skip)]
+    target_user_id: Option<i32>,
+}
+
+impl<'a> WebhookPayload<'a> {
+    pub fn new(event: WebhookEvent, crate_name: &'a str) -> Self {
+        Self {
+            event,
+            krate: crate_name,
+            version: None,
+            user: None,
+            owner: None,
+            time: chrono::Utc::now().naive_utc(),
+            target_user_id: None,
+        }
+    }
+
+    pub fn version(mut self, version: &'a str) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    pub fn user(mut self, user: &'a User) -> Self {
+        self.user = Some(&user.gh_login);
+        self
+    }
+
+    pub fn owner(mut self, owner: &'a Owner) -> Self {
+        self.owner = Some(owner.login());
+        if let Owner::User(user) = owner {
+            self.target_user_id = Some(user.id);
+        }
+        self
+    }
+
+    pub fn event(&self) -> WebhookEvent {
+        self.event
+    }
+
+    pub fn target_user_id(&self) -> Option<i32> {
+        self.target_user_id
+    }
+}
@@ -14,15 +14,17 @@ pg_enum! {
         PublishNew = 0,
         PublishUpdate = 1,
         YankUnyank = 2,
+        TwoFactorVerification = 3,
     }
 }
 
 impl LimitedAction {
     pub fn default_rate_seconds(&self) -> u64 {
         match self {
-            LimitedAction::PublishNew => 10 * 60, // 10 minutes
-            LimitedAction::PublishUpdate => 60,   // 1 minute
-            LimitedAction::YankUnyank => 60,      // 1 minute
+            LimitedAction::PublishNew => 10 * 60,           // 10 minutes
+            LimitedAction::PublishUpdate => 60,             // 1 minute
+            LimitedAction::YankUnyank => 60,                // 1 minute
+            LimitedAction::TwoFactorVerification => 5 * 60, // 5 minutes
         }
     }
 
@@ -31,6 +33,7 @@ impl LimitedAction {
             LimitedAction::PublishNew => 5,
             LimitedAction::PublishUpdate => 30,
             LimitedAction::YankUnyank => 100,
+            LimitedAction::TwoFactorVerification => 10,
         }
     }
 
@@ -39,6 +42,7 @@ impl LimitedAction {
             LimitedAction::PublishNew => "PUBLISH_NEW",
             LimitedAction::PublishUpdate => "PUBLISH_UPDATE",
             LimitedAction::YankUnyank => "YANK_UNYANK",
+            LimitedAction::TwoFactorVerification => "TWO_FACTOR_VERIFICATION",
         }
     }
 
@@ -53,6 +57,9 @@ impl LimitedAction {
             LimitedAction::YankUnyank => {
                 "You have yanked or unyanked too many versions in a short period of time"
             }
+            LimitedAction::TwoFactorVerification => {
+                "You have entered too many two-factor authentication codes in a short period of time"
+            }
         }
     }
 }
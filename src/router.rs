@@ -41,7 +41,10 @@ pub fn build_axum_router(state: AppState) -> Router<()> {
             get(version::downloads::download),
         )
         // Routes used by the frontend
-        .route("/api/v1/crates/:crate_id", get(krate::metadata::show))
+        .route(
+            "/api/v1/crates/:crate_id",
+            get(krate::metadata::show).delete(krate::delete::delete),
+        )
         .route(
             "/api/v1/crates/:crate_id/:version",
             get(version::metadata::show),
@@ -54,6 +57,10 @@ pub fn build_axum_router(state: AppState) -> Router<()> {
             "/api/v1/crates/:crate_id/:version/dependencies",
             get(version::metadata::dependencies),
         )
+        .route(
+            "/api/v1/crates/:crate_id/:version/dependency_tree",
+            get(version::dependency_tree::dependency_tree),
+        )
         .route(
             "/api/v1/crates/:crate_id/:version/downloads",
             get(version::downloads::downloads),
@@ -86,10 +93,34 @@ pub fn build_axum_router(state: AppState) -> Router<()> {
             "/api/v1/crates/:crate_id/owner_user",
             get(krate::owners::owner_user),
         )
+        .route(
+            "/api/v1/crates/:crate_id/audit_log",
+            get(krate::audit_log::audit_log),
+        )
+        .route(
+            "/api/v1/crates/:crate_id/publish_policy",
+            get(krate::publish_policy::show).put(krate::publish_policy::update),
+        )
         .route(
             "/api/v1/crates/:crate_id/reverse_dependencies",
             get(krate::metadata::reverse_dependencies),
         )
+        .route(
+            "/api/v1/crates/:crate_id/trusted_publishers",
+            get(trusted_publishing::list).put(trusted_publishing::create),
+        )
+        .route(
+            "/api/v1/crates/:crate_id/trusted_publishers/:id",
+            delete(trusted_publishing::delete),
+        )
+        .route(
+            "/api/v1/crates/:crate_id/webhooks",
+            get(webhook::list_for_crate).put(webhook::create_for_crate),
+        )
+        .route("/api/v1/feeds/crates/:crate_id", get(feed::krate))
+        .route("/api/v1/feeds/users/:user_id", get(feed::user))
+        .route("/api/v1/feeds/teams/:team_id", get(feed::team))
+        .route("/api/v1/feeds/updates", get(feed::updates))
         .route("/api/v1/keywords", get(keyword::index))
         .route("/api/v1/keywords/:keyword_id", get(keyword::show))
         .route("/api/v1/categories", get(category::index))
@@ -105,7 +136,29 @@ pub fn build_axum_router(state: AppState) -> Router<()> {
         .route("/api/v1/me/updates", get(user::me::updates))
         .route("/api/v1/me/tokens", get(token::list).put(token::new))
         .route("/api/v1/me/tokens/:id", delete(token::revoke))
+        .route("/api/v1/me/tokens/:id/rotate", put(token::rotate))
+        .route("/api/v1/me/tokens/:id/usage", get(token::usage))
         .route("/api/v1/tokens/current", delete(token::revoke_current))
+        .route(
+            "/api/v1/me/two_factor",
+            get(user::two_factor::show)
+                .put(user::two_factor::begin_enrollment)
+                .delete(user::two_factor::disable),
+        )
+        .route(
+            "/api/v1/me/two_factor/confirm",
+            put(user::two_factor::confirm_enrollment),
+        )
+        .route(
+            "/api/v1/me/webhooks",
+            get(webhook::list_for_user).put(webhook::create_for_user),
+        )
+        .route("/api/v1/webhooks/:id", delete(webhook::delete))
+        .route("/api/v1/webhooks/:id/deliveries", get(webhook::deliveries))
+        .route(
+            "/api/v1/trusted_publishing/tokens",
+            put(trusted_publishing::exchange),
+        )
         .route(
             "/api/v1/me/crate_owner_invitations",
             get(crate_owner_invitation::list),
@@ -142,6 +195,10 @@ pub fn build_axum_router(state: AppState) -> Router<()> {
             get(user::session::authorize),
         )
         .route("/api/private/session", delete(user::session::logout))
+        .route(
+            "/api/private/session/two_factor",
+            put(user::two_factor::verify),
+        )
         // Metrics
         .route("/api/private/metrics/:kind", get(metrics::prometheus))
         // Crate ownership invitations management in the frontend
@@ -166,6 +223,12 @@ pub fn build_axum_router(state: AppState) -> Router<()> {
         );
     }
 
+    if state.config.serve_sparse_index {
+        router = router
+            .route("/index/config.json", get(sparse_index::config))
+            .route("/index/*path", get(sparse_index::index_file));
+    }
+
     router
         .fallback(|method: Method| async move {
             match method {
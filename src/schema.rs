@@ -24,6 +24,26 @@ pub mod sql_types {
     pub use diesel_full_text_search::Tsvector;
 }
 
+diesel::table! {
+    /// The most recent requests that were made with an API token, including the rejected ones, so that users can spot leaked tokens. Older entries are removed whenever a new one is added.
+    api_token_usages (id) {
+        /// Unique identifier of the entry.
+        id -> Int8,
+        /// Reference to the API token in the `api_tokens` table that the request was made with.
+        api_token_id -> Int4,
+        /// Date and time of the request.
+        used_at -> Timestamp,
+        /// HTTP method and route of the request, e.g. `PUT /api/v1/crates/new`.
+        endpoint -> Varchar,
+        /// Name of the crate that the request was made for, if any.
+        crate_name -> Nullable<Varchar>,
+        /// IP address of the client that sent the request.
+        ip -> Nullable<Inet>,
+        /// Reason why the token was rejected for the request, e.g. because of a scope mismatch, or `NULL` if the request was authenticated.
+        rejection_reason -> Nullable<Varchar>,
+    }
+}
+
 diesel::table! {
     /// Representation of the `api_tokens` table.
     ///
@@ -81,6 +101,14 @@ diesel::table! {
         ///
         /// (Automatically generated by Diesel.)
         expired_at -> Nullable<Timestamp>,
+        /// Reference to the trusted publisher configuration in the `trusted_publishers` table, if the token was minted via an OIDC token exchange.
+        trusted_publisher_id -> Nullable<Int4>,
+        /// Reference to the team in the `teams` table that owns the token, or NULL for tokens of the user in `user_id`. Team tokens can only be used for crates that the team owns, and `user_id` is the team member that created them.
+        team_id -> Nullable<Int4>,
+        /// NULL or an array of CIDR blocks that requests authenticated with the token have to come from.
+        allowed_ips -> Nullable<Array<Cidr>>,
+        /// Date and time when the token was replaced by a new token. Its `expired_at` has been shortened to a grace period at the same time, so it can not be rotated again.
+        rotated_at -> Nullable<Timestamp>,
     }
 }
 
@@ -181,6 +209,34 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    /// Audit log of ownership, publishing and administrative actions performed on crates.
+    crate_audit_actions (id) {
+        /// Unique identifier of the audit log entry.
+        id -> Int4,
+        /// Identifier of the crate in the `crates` table that the action was performed on. This is intentionally not a foreign key, so that the log survives the deletion of the crate.
+        crate_id -> Int4,
+        /// Name of the crate that the action was performed on, which allows administrators to look up the log of deleted crates.
+        crate_name -> Varchar,
+        /// Version number that the action was performed on, if the action is specific to a version.
+        version -> Nullable<Varchar>,
+        /// Type of the action that was performed (see `CrateAction` in `src/models/action.rs`).
+        action -> Int4,
+        /// Reference to the user in the `users` table that performed the action, or `NULL` if it was performed by an administrator via the command line.
+        user_id -> Nullable<Int4>,
+        /// Reference to the API token in the `api_tokens` table that was used to perform the action, if any.
+        api_token_id -> Nullable<Int4>,
+        /// Reference to the user in the `users` table that the action was targeted at (e.g. the invited or removed owner), if any.
+        target_user_id -> Nullable<Int4>,
+        /// Reference to the team in the `teams` table that the action was targeted at (e.g. the added or removed team owner), if any.
+        target_team_id -> Nullable<Int4>,
+        /// Optional free-form details about the action.
+        details -> Nullable<Varchar>,
+        /// Date and time when the action was performed.
+        time -> Timestamp,
+    }
+}
+
 diesel::table! {
     /// Number of downloads per crate. This was extracted from the `crates` table for performance reasons.
     crate_downloads (crate_id) {
@@ -594,6 +650,18 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    /// Progress of mirroring upstream registries, used to resume the mirror job incrementally.
+    mirror_progress (upstream) {
+        /// URL of the upstream git index.
+        upstream -> Varchar,
+        /// The last upstream index commit that was mirrored completely.
+        commit -> Varchar,
+        /// Date and time when the progress was last updated.
+        updated_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     /// List of all processed CDN log files, used to avoid processing the same file multiple times.
     processed_log_files (path) {
@@ -636,6 +704,30 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    /// Restrictions that the owners of a crate have configured for publishing new versions of it.
+    publish_policies (crate_id) {
+        /// Reference to the crate in the `crates` table.
+        crate_id -> Int4,
+        /// IDs of the users in the `users` table that may publish new versions. If both this and `allowed_teams` are empty, all owners may publish.
+        allowed_users -> Array<Int4>,
+        /// IDs of the teams in the `teams` table whose members may publish new versions.
+        allowed_teams -> Array<Int4>,
+        /// IDs of the users in the `users` table that may publish prerelease versions. If both this and `prerelease_teams` are empty, prerelease versions are not restricted any further.
+        prerelease_users -> Array<Int4>,
+        /// IDs of the teams in the `teams` table whose members may publish prerelease versions.
+        prerelease_teams -> Array<Int4>,
+        /// Whether new versions may only be published with API tokens that have an expiry date.
+        require_token_expiry -> Bool,
+        /// Whether new versions may only be published with API tokens that were minted via trusted publishing.
+        require_trusted_publishing -> Bool,
+        /// Reference to the user in the `users` table that last changed the policy.
+        updated_by -> Int4,
+        /// Date and time when the policy was last changed.
+        updated_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     /// Representation of the `publish_rate_overrides` table.
     ///
@@ -717,6 +809,8 @@ diesel::table! {
         ///
         /// (Automatically generated by Diesel.)
         name -> Text,
+        /// Date and time when the reservation expires, or `NULL` if the name is reserved permanently. Temporary reservations are used as a cooldown after a crate was deleted by its owners.
+        expires_at -> Nullable<Timestamp>,
     }
 }
 
@@ -752,6 +846,68 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    /// CI workflows that are allowed to exchange OIDC identity tokens for short-lived publish tokens of a crate.
+    trusted_publishers (id) {
+        /// Unique identifier of the trusted publisher configuration.
+        id -> Int4,
+        /// Reference to the crate in the `crates` table.
+        crate_id -> Int4,
+        /// Owner of the repository that the CI workflow runs in (e.g. `rust-lang`).
+        repository_owner -> Varchar,
+        /// Name of the repository that the CI workflow runs in (e.g. `crates.io`).
+        repository_name -> Varchar,
+        /// Filename of the CI workflow that is allowed to publish (e.g. `release.yml`).
+        workflow_filename -> Varchar,
+        /// Optional deployment environment that the CI workflow has to run in.
+        environment -> Nullable<Varchar>,
+        /// Reference to the user in the `users` table that created the configuration. Tokens minted for this configuration are owned by this user.
+        created_by -> Int4,
+        /// Date and time when the configuration was created.
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    /// TOTP secrets and recovery codes of users that enrolled in two-factor authentication.
+    totp_credentials (user_id) {
+        /// Reference to the user in the `users` table.
+        user_id -> Int4,
+        /// Shared secret for generating the time-based one-time passwords.
+        secret -> Bytea,
+        /// Date and time when the enrollment was started.
+        created_at -> Timestamp,
+        /// Date and time when the enrollment was confirmed with a valid code, or NULL if the enrollment is still pending.
+        enabled_at -> Nullable<Timestamp>,
+        /// Time step of the last accepted code, which prevents codes from being used more than once.
+        last_used_step -> Nullable<Int8>,
+        /// SHA256 hashes of the unused recovery codes.
+        recovery_codes -> Array<Bytea>,
+    }
+}
+
+diesel::table! {
+    /// Potential typosquatting of popular crates that was found when new crates were published, so that they can be reviewed by the crates.io team.
+    typosquat_findings (id) {
+        /// Unique identifier of the finding.
+        id -> Int4,
+        /// Reference to the flagged crate in the `crates` table. Set to NULL when the crate is deleted, so that the finding stays on record.
+        flagged_crate_id -> Nullable<Int4>,
+        /// Name of the flagged crate, which is kept after the crate has been deleted.
+        crate_name -> Varchar,
+        /// Name of the popular crate that the flagged crate may be typosquatting.
+        similar_crate -> Varchar,
+        /// Description of the check that flagged the crate.
+        reason -> Varchar,
+        /// Review status of the finding: 0 = open, 1 = dismissed, 2 = confirmed.
+        status -> Int4,
+        /// Date and time when the crate was flagged.
+        created_at -> Timestamp,
+        /// Date and time when the finding was dismissed or confirmed. NULL while the finding is open.
+        reviewed_at -> Nullable<Timestamp>,
+    }
+}
+
 diesel::table! {
     /// Representation of the `users` table.
     ///
@@ -893,6 +1049,13 @@ diesel::table! {
         ///
         /// (Automatically generated by Diesel.)
         time -> Timestamp,
+        /// Reference to the trusted publisher configuration in the `trusted_publishers` table, if the action was performed with a token minted via an OIDC token exchange.
+        trusted_publisher_id -> Nullable<Int4>,
+        /// Optional message that was given when the action was performed (e.g. the reason for a yank).
+        message -> Nullable<Varchar>,
+        /// Optional ID of a security advisory that was given when the action was performed.
+        advisory -> Nullable<Varchar>,        /// Reference to the team in the `teams` table, if the action was performed with an API token owned by the team.
+        team_id -> Nullable<Int4>,
     }
 }
 
@@ -995,6 +1158,12 @@ diesel::table! {
         ///
         /// (Automatically generated by Diesel.)
         semver_no_prerelease -> Nullable<SemverTriple>,
+        /// Optional message explaining why the version was yanked. Cleared when the version is unyanked.
+        yank_message -> Nullable<Varchar>,
+        /// Optional ID of a security advisory (e.g. `RUSTSEC-2024-0001` or `GHSA-xxxx-xxxx-xxxx`) that the version was yanked for. Cleared when the version is unyanked.
+        yank_advisory -> Nullable<Varchar>,
+        /// The SPDX license expression of the version as a `tsquery` over its license identifiers, as normalised by the `spdx` crate on publish. `NULL` if the license expression could not be parsed.
+        license_query -> Nullable<Text>,
     }
 }
 
@@ -1018,7 +1187,58 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    /// Delivery history of the events that were sent to webhooks.
+    webhook_deliveries (id) {
+        /// Unique identifier of the delivery. This is sent to the receiver in the `X-Crates-Io-Delivery` header.
+        id -> Int4,
+        /// Reference to the webhook in the `webhooks` table.
+        webhook_id -> Int4,
+        /// Name of the event (e.g. `publish` or `yank`).
+        event -> Varchar,
+        /// JSON payload that is sent to the webhook URL.
+        payload -> Jsonb,
+        /// Number of delivery attempts so far.
+        attempts -> Int4,
+        /// HTTP status code of the response to the last delivery attempt, if any response was received.
+        response_status -> Nullable<Int4>,
+        /// Error message of the last delivery attempt, if it failed.
+        error -> Nullable<Varchar>,
+        /// Date and time when the event happened.
+        created_at -> Timestamp,
+        /// Date and time of the last delivery attempt.
+        last_attempt_at -> Nullable<Timestamp>,
+        /// Date and time when the event was successfully delivered.
+        delivered_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    /// URLs that are notified about events of a crate, or of all crates owned by a user.
+    webhooks (id) {
+        /// Unique identifier of the webhook.
+        id -> Int4,
+        /// Reference to the crate in the `crates` table. If this is `NULL`, the webhook is notified about events of all crates owned by `user_id`.
+        crate_id -> Nullable<Int4>,
+        /// Reference to the user in the `users` table that registered the webhook.
+        user_id -> Int4,
+        /// URL that the event payloads are sent to via `POST` requests.
+        url -> Varchar,
+        /// Shared secret that is used to sign the event payloads with HMAC-SHA256.
+        secret -> Varchar,
+        /// Names of the events that the webhook is notified about (e.g. `publish` or `yank`).
+        events -> Array<Text>,
+        /// Date and time when the webhook was registered.
+        created_at -> Timestamp,
+    }
+}
+
+diesel::joinable!(api_token_usages -> api_tokens (api_token_id));
+diesel::joinable!(api_tokens -> teams (team_id));
+diesel::joinable!(api_tokens -> trusted_publishers (trusted_publisher_id));
 diesel::joinable!(api_tokens -> users (user_id));
+diesel::joinable!(crate_audit_actions -> api_tokens (api_token_id));
+diesel::joinable!(crate_audit_actions -> teams (target_team_id));
 diesel::joinable!(crate_downloads -> crates (crate_id));
 diesel::joinable!(crate_owner_invitations -> crates (crate_id));
 diesel::joinable!(crate_owners -> crates (crate_id));
@@ -1036,21 +1256,34 @@ diesel::joinable!(emails -> users (user_id));
 diesel::joinable!(follows -> crates (crate_id));
 diesel::joinable!(follows -> users (user_id));
 diesel::joinable!(publish_limit_buckets -> users (user_id));
+diesel::joinable!(publish_policies -> crates (crate_id));
+diesel::joinable!(publish_policies -> users (updated_by));
 diesel::joinable!(publish_rate_overrides -> users (user_id));
 diesel::joinable!(readme_renderings -> versions (version_id));
 diesel::joinable!(recent_crate_downloads -> crates (crate_id));
+diesel::joinable!(trusted_publishers -> crates (crate_id));
+diesel::joinable!(totp_credentials -> users (user_id));
+diesel::joinable!(trusted_publishers -> users (created_by));
+diesel::joinable!(typosquat_findings -> crates (flagged_crate_id));
 diesel::joinable!(version_downloads -> versions (version_id));
 diesel::joinable!(version_owner_actions -> api_tokens (api_token_id));
+diesel::joinable!(version_owner_actions -> teams (team_id));
+diesel::joinable!(version_owner_actions -> trusted_publishers (trusted_publisher_id));
 diesel::joinable!(version_owner_actions -> users (user_id));
 diesel::joinable!(version_owner_actions -> versions (version_id));
 diesel::joinable!(versions -> crates (crate_id));
 diesel::joinable!(versions -> users (published_by));
 diesel::joinable!(versions_published_by -> versions (version_id));
+diesel::joinable!(webhook_deliveries -> webhooks (webhook_id));
+diesel::joinable!(webhooks -> crates (crate_id));
+diesel::joinable!(webhooks -> users (user_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    api_token_usages,
     api_tokens,
     background_jobs,
     categories,
+    crate_audit_actions,
     crate_downloads,
     crate_owner_invitations,
     crate_owners,
@@ -1063,16 +1296,23 @@ diesel::allow_tables_to_appear_in_same_query!(
     follows,
     keywords,
     metadata,
+    mirror_progress,
     processed_log_files,
     publish_limit_buckets,
+    publish_policies,
     publish_rate_overrides,
     readme_renderings,
     recent_crate_downloads,
     reserved_crate_names,
     teams,
+    totp_credentials,
+    trusted_publishers,
+    typosquat_findings,
     users,
     version_downloads,
     version_owner_actions,
     versions,
     versions_published_by,
+    webhook_deliveries,
+    webhooks,
 );
@@ -1,4 +1,6 @@
-use diesel::sql_types::{Date, Double, Integer, Interval, SingleValue, Text, Timestamp};
+use diesel::sql_types::{
+    Array, Date, Double, Integer, Interval, Nullable, SingleValue, Text, Timestamp,
+};
 
 mod semver;
 
@@ -17,6 +19,8 @@ sql_function!(fn floor(x: Double) -> Integer);
 sql_function!(fn greatest<T: SingleValue>(x: T, y: T) -> T);
 sql_function!(fn least<T: SingleValue>(x: T, y: T) -> T);
 sql_function!(fn split_part(string: Text, delimiter: Text, n: Integer) -> Text);
+sql_function!(fn rust_version_parts(x: Nullable<Text>) -> Nullable<Array<BigInt>>);
+sql_function!(fn license_matches(query: Nullable<Text>, licenses: Array<Text>) -> Nullable<Bool>);
 
 macro_rules! pg_enum {
     (
@@ -92,4 +96,77 @@ mod tests {
         test(&mut conn, "0.4.45+curl-7.78.0", Some((0, 4, 45)));
         test(&mut conn, "0.1.4-preview+4.3.2", None);
     }
+
+    #[test]
+    fn rust_version_parts_works() {
+        let (_test_db, mut conn) = test_db_connection();
+
+        #[track_caller]
+        fn test(conn: &mut PgConnection, text: &str, expected: Option<Vec<i64>>) {
+            let query = select(rust_version_parts(text));
+            assert_eq!(
+                query.get_result::<Option<Vec<i64>>>(conn).unwrap(),
+                expected
+            );
+        }
+
+        test(&mut conn, "1", Some(vec![1, 0, 0]));
+        test(&mut conn, "1.70", Some(vec![1, 70, 0]));
+        test(&mut conn, "1.70.3", Some(vec![1, 70, 3]));
+        test(&mut conn, "1.70.0.1", None);
+        test(&mut conn, "1.", None);
+        test(&mut conn, "1.70-beta", None);
+        test(&mut conn, "invalid", None);
+    }
+
+    #[test]
+    fn license_matches_works() {
+        use crate::licenses::{license_query, parse_license_expr};
+
+        let (_test_db, mut conn) = test_db_connection();
+
+        #[track_caller]
+        fn test(conn: &mut PgConnection, expression: &str, licenses: &[&str], expected: bool) {
+            let query = license_query(&parse_license_expr(expression).unwrap());
+            let query = select(license_matches(query, licenses));
+            assert_eq!(
+                query.get_result::<Option<bool>>(conn).unwrap(),
+                Some(expected)
+            );
+        }
+
+        test(&mut conn, "MIT", &["MIT"], true);
+        test(&mut conn, "MIT OR Apache-2.0", &["MIT"], true);
+        test(&mut conn, "MIT/Apache-2.0", &["MIT"], true);
+        test(&mut conn, "MIT AND Apache-2.0", &["MIT"], false);
+        test(&mut conn, "Apache-2.0", &["MIT"], false);
+
+        test(
+            &mut conn,
+            "MIT AND Apache-2.0",
+            &["MIT", "Apache-2.0"],
+            true,
+        );
+        test(
+            &mut conn,
+            "MIT AND (Apache-2.0 OR Zlib)",
+            &["MIT", "Zlib"],
+            true,
+        );
+        test(&mut conn, "(MIT OR Apache-2.0) AND Zlib", &["MIT"], false);
+        test(
+            &mut conn,
+            "Apache-2.0 WITH LLVM-exception",
+            &["Apache-2.0"],
+            true,
+        );
+        test(&mut conn, "GPL-3.0-only", &["MIT", "Apache-2.0"], false);
+
+        test(&mut conn, "GPL-3.0-only", &["GPL-3.0"], true);
+        test(&mut conn, "GPL-3.0", &["GPL-3.0"], true);
+        test(&mut conn, "GPL-3.0-or-later", &["GPL-3.0"], true);
+        test(&mut conn, "GPL-3.0+", &["GPL-3.0"], true);
+        test(&mut conn, "GPL-2.0-only", &["GPL-3.0"], false);
+        test(&mut conn, "LicenseRef-Proprietary", &["MIT"], false);
+    }
 }
@@ -1,14 +1,18 @@
 use anyhow::Context;
-use crates_io_env_vars::required_var;
+use crates_io_env_vars::{required_var, var_parsed};
+use crates_io_index::signing::METADATA_PREFIX;
 use futures_util::{StreamExt, TryStreamExt};
 use hyper::body::Bytes;
 use object_store::aws::{AmazonS3, AmazonS3Builder};
+use object_store::azure::{MicrosoftAzure, MicrosoftAzureBuilder};
+use object_store::gcp::{GoogleCloudStorage, GoogleCloudStorageBuilder};
 use object_store::local::LocalFileSystem;
 use object_store::memory::InMemory;
 use object_store::path::Path;
 use object_store::prefix::PrefixStore;
 use object_store::{Attribute, Attributes, ClientOptions, ObjectStore, Result};
 use secrecy::{ExposeSecret, SecretString};
+use sha2::{Digest, Sha256};
 use std::fs;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -17,11 +21,16 @@ use tokio::io::AsyncWriteExt;
 
 const PREFIX_CRATES: &str = "crates";
 const PREFIX_READMES: &str = "readmes";
+const PREFIX_QUARANTINE: &str = "quarantine";
 const DEFAULT_REGION: &str = "us-west-1";
 const CONTENT_TYPE_CRATE: &str = "application/gzip";
 const CONTENT_TYPE_DB_DUMP: &str = "application/gzip";
+const CONTENT_TYPE_DB_DUMP_MANIFEST: &str = "application/json";
+const CONTENT_TYPE_DB_DUMP_PARQUET: &str = "application/vnd.apache.parquet";
 const CONTENT_TYPE_INDEX: &str = "text/plain";
+const CONTENT_TYPE_INDEX_METADATA: &str = "application/json";
 const CONTENT_TYPE_README: &str = "text/html";
+const CACHE_CONTROL_DB_DUMP_MANIFEST: &str = "public,max-age=600";
 const CACHE_CONTROL_IMMUTABLE: &str = "public,max-age=31536000,immutable";
 const CACHE_CONTROL_INDEX: &str = "public,max-age=600";
 const CACHE_CONTROL_README: &str = "public,max-age=604800";
@@ -37,8 +46,21 @@ pub struct StorageConfig {
 #[derive(Debug)]
 #[allow(clippy::large_enum_variant)]
 pub enum StorageBackend {
-    S3 { default: S3Config, index: S3Config },
-    LocalFileSystem { path: PathBuf },
+    S3 {
+        default: S3Config,
+        index: S3Config,
+    },
+    Gcs {
+        default: GcsConfig,
+        index: GcsConfig,
+    },
+    Azure {
+        default: AzureConfig,
+        index: AzureConfig,
+    },
+    LocalFileSystem {
+        path: PathBuf,
+    },
     InMemory,
 }
 
@@ -50,6 +72,23 @@ pub struct S3Config {
     secret_key: SecretString,
 }
 
+#[derive(Debug)]
+pub struct GcsConfig {
+    bucket: String,
+    /// The JSON service account key. Application default credentials are
+    /// used if this is not set.
+    service_account_key: Option<SecretString>,
+}
+
+#[derive(Debug)]
+pub struct AzureConfig {
+    container: String,
+    account: String,
+    access_key: SecretString,
+    /// Connects to a local Azurite emulator instead of Azure itself.
+    use_emulator: bool,
+}
+
 impl StorageConfig {
     pub fn in_memory() -> Self {
         Self {
@@ -91,6 +130,63 @@ impl StorageConfig {
             };
         }
 
+        if let Ok(bucket) = dotenvy::var("GCS_BUCKET") {
+            let cdn_prefix = dotenvy::var("GCS_CDN").ok();
+
+            let index_bucket = required_var("GCS_INDEX_BUCKET").unwrap();
+
+            let service_account_key: Option<SecretString> =
+                dotenvy::var("GCS_SERVICE_ACCOUNT_KEY").ok().map(Into::into);
+
+            let default = GcsConfig {
+                bucket,
+                service_account_key: service_account_key.clone(),
+            };
+
+            let index = GcsConfig {
+                bucket: index_bucket,
+                service_account_key,
+            };
+
+            let backend = StorageBackend::Gcs { default, index };
+
+            return Self {
+                backend,
+                cdn_prefix,
+            };
+        }
+
+        if let Ok(container) = dotenvy::var("AZURE_CONTAINER") {
+            let cdn_prefix = dotenvy::var("AZURE_CDN").ok();
+
+            let index_container = required_var("AZURE_INDEX_CONTAINER").unwrap();
+
+            let account = required_var("AZURE_STORAGE_ACCOUNT").unwrap();
+            let access_key: SecretString = required_var("AZURE_STORAGE_ACCESS_KEY").unwrap().into();
+            let use_emulator = var_parsed("AZURE_USE_EMULATOR").unwrap().unwrap_or(false);
+
+            let default = AzureConfig {
+                container,
+                account: account.clone(),
+                access_key: access_key.clone(),
+                use_emulator,
+            };
+
+            let index = AzureConfig {
+                container: index_container,
+                account,
+                access_key,
+                use_emulator,
+            };
+
+            let backend = StorageBackend::Azure { default, index };
+
+            return Self {
+                backend,
+                cdn_prefix,
+            };
+        }
+
         let current_dir = std::env::current_dir()
             .context("Failed to read the current directory")
             .unwrap();
@@ -122,13 +218,7 @@ impl Storage {
 
         match &config.backend {
             StorageBackend::S3 { default, index } => {
-                let options = ClientOptions::default()
-                    // The `BufWriter::new()` API currently does not allow
-                    // specifying any file attributes, so we need to set the
-                    // content type here instead for the database dump upload.
-                    .with_content_type_for_suffix("gz", CONTENT_TYPE_DB_DUMP);
-
-                let store = build_s3(default, options);
+                let store = build_s3(default, default_client_options());
 
                 let index_store = build_s3(index, Default::default());
 
@@ -143,6 +233,38 @@ impl Storage {
                 }
             }
 
+            StorageBackend::Gcs { default, index } => {
+                let store = build_gcs(default, default_client_options());
+
+                let index_store = build_gcs(index, Default::default());
+
+                if cdn_prefix.is_none() {
+                    panic!("Missing GCS_CDN environment variable");
+                }
+
+                Self {
+                    cdn_prefix,
+                    store: Arc::new(store),
+                    index_store: Arc::new(index_store),
+                }
+            }
+
+            StorageBackend::Azure { default, index } => {
+                let store = build_azure(default, default_client_options());
+
+                let index_store = build_azure(index, Default::default());
+
+                if cdn_prefix.is_none() {
+                    panic!("Missing AZURE_CDN environment variable");
+                }
+
+                Self {
+                    cdn_prefix,
+                    store: Arc::new(store),
+                    index_store: Arc::new(index_store),
+                }
+            }
+
             StorageBackend::LocalFileSystem { path } => {
                 warn!(?path, "Using local file system for file storage");
 
@@ -245,6 +367,34 @@ impl Storage {
         Ok(())
     }
 
+    /// Streams a stored crate file through SHA-256 and returns the hex
+    /// encoded checksum, or `None` if the file does not exist.
+    #[instrument(skip(self))]
+    pub async fn crate_file_checksum(&self, name: &str, version: &str) -> Result<Option<String>> {
+        let path = crate_file_path(name, version);
+        let mut stream = match self.store.get(&path).await {
+            Ok(result) => result.into_stream(),
+            Err(object_store::Error::NotFound { .. }) => return Ok(None),
+            Err(error) => return Err(error),
+        };
+
+        let mut hasher = Sha256::new();
+        while let Some(chunk) = stream.try_next().await? {
+            hasher.update(&chunk);
+        }
+
+        Ok(Some(hex::encode(hasher.finalize())))
+    }
+
+    /// Moves a crate file out of the public `crates/` prefix, so that it is
+    /// no longer served, but kept around for further investigation.
+    #[instrument(skip(self))]
+    pub async fn quarantine_crate_file(&self, name: &str, version: &str) -> Result<()> {
+        let path = crate_file_path(name, version);
+        let quarantine_path = format!("{PREFIX_QUARANTINE}/{path}").into();
+        self.store.rename(&path, &quarantine_path).await
+    }
+
     #[instrument(skip(self, content))]
     pub async fn sync_index(&self, name: &str, content: Option<String>) -> Result<()> {
         let path = crates_io_index::Repository::relative_index_file_for_url(name).into();
@@ -263,6 +413,79 @@ impl Storage {
         Ok(())
     }
 
+    /// Reads the index file of a crate, or returns `None` if the crate has
+    /// no index file.
+    #[instrument(skip(self))]
+    pub async fn read_index_file(&self, name: &str) -> Result<Option<Bytes>> {
+        let path = crates_io_index::Repository::relative_index_file_for_url(name).into();
+        match self.index_store.get(&path).await {
+            Ok(result) => Ok(Some(result.bytes().await?)),
+            Err(object_store::Error::NotFound { .. }) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Lists the crate names of all index files of the sparse index.
+    #[instrument(skip(self))]
+    pub async fn list_index_files(&self) -> Result<Vec<String>> {
+        self.index_store
+            .list(None)
+            .try_filter_map(|meta| async move {
+                let path = meta.location.as_ref();
+                let name = path.rsplit('/').next().unwrap_or_default();
+                let is_index_file = name.is_ascii()
+                    && crates_io_index::Repository::relative_index_file_for_url(name) == path;
+                Ok(is_index_file.then(|| name.to_string()))
+            })
+            .try_collect()
+            .await
+    }
+
+    /// Uploads a signed metadata file of the sparse index.
+    ///
+    /// The `path` is relative to the root of the index, see the
+    /// [`crates_io_index::signing`] module.
+    #[instrument(skip(self, content))]
+    pub async fn upload_index_metadata(&self, path: &str, content: Bytes) -> Result<()> {
+        let attributes = Attributes::from_iter([
+            (Attribute::ContentType, CONTENT_TYPE_INDEX_METADATA),
+            (Attribute::CacheControl, CACHE_CONTROL_INDEX),
+        ]);
+        let opts = attributes.into();
+        self.index_store
+            .put_opts(&path.into(), content.into(), opts)
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    pub async fn delete_index_metadata(&self, path: &str) -> Result<()> {
+        self.index_store.delete(&path.into()).await
+    }
+
+    /// Reads a signed metadata file of the sparse index, or returns `None`
+    /// if it does not exist.
+    #[instrument(skip(self))]
+    pub async fn read_index_metadata(&self, path: &str) -> Result<Option<Bytes>> {
+        match self.index_store.get(&path.into()).await {
+            Ok(result) => Ok(Some(result.bytes().await?)),
+            Err(object_store::Error::NotFound { .. }) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Lists the paths of all signed metadata files for individual index
+    /// files.
+    #[instrument(skip(self))]
+    pub async fn list_index_file_metadata(&self) -> Result<Vec<String>> {
+        let prefix = format!("{METADATA_PREFIX}/crates").into();
+        self.index_store
+            .list(Some(&prefix))
+            .map_ok(|meta| meta.location.to_string())
+            .try_collect()
+            .await
+    }
+
     #[instrument(skip(self))]
     pub async fn upload_db_dump(&self, target: &str, local_path: &StdPath) -> anyhow::Result<()> {
         let store = self.store.clone();
@@ -287,6 +510,32 @@ impl Storage {
         Ok(())
     }
 
+    /// Uploads the manifest of the incremental database dumps, or the schema
+    /// of the Parquet export.
+    #[instrument(skip(self, content))]
+    pub async fn upload_db_dump_manifest(&self, target: &str, content: Bytes) -> Result<()> {
+        let attributes = Attributes::from_iter([
+            (Attribute::ContentType, CONTENT_TYPE_DB_DUMP_MANIFEST),
+            (Attribute::CacheControl, CACHE_CONTROL_DB_DUMP_MANIFEST),
+        ]);
+        let opts = attributes.into();
+        self.store
+            .put_opts(&target.into(), content.into(), opts)
+            .await?;
+        Ok(())
+    }
+
+    /// Reads the manifest of the incremental database dumps, or returns `None`
+    /// if no incremental dump has been uploaded yet.
+    #[instrument(skip(self))]
+    pub async fn read_db_dump_manifest(&self, target: &str) -> Result<Option<Bytes>> {
+        match self.store.get(&target.into()).await {
+            Ok(result) => Ok(Some(result.bytes().await?)),
+            Err(object_store::Error::NotFound { .. }) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
     /// This should only be used for assertions in the test suite!
     pub fn as_inner(&self) -> &dyn ObjectStore {
         &self.store
@@ -305,6 +554,15 @@ impl Storage {
     }
 }
 
+fn default_client_options() -> ClientOptions {
+    ClientOptions::default()
+        // The `BufWriter::new()` API currently does not allow
+        // specifying any file attributes, so we need to set the
+        // content type here instead for the database dump upload.
+        .with_content_type_for_suffix("gz", CONTENT_TYPE_DB_DUMP)
+        .with_content_type_for_suffix("parquet", CONTENT_TYPE_DB_DUMP_PARQUET)
+}
+
 fn build_s3(config: &S3Config, client_options: ClientOptions) -> AmazonS3 {
     AmazonS3Builder::new()
         .with_region(config.region.as_deref().unwrap_or(DEFAULT_REGION))
@@ -317,7 +575,34 @@ fn build_s3(config: &S3Config, client_options: ClientOptions) -> AmazonS3 {
         .unwrap()
 }
 
-fn crate_file_path(name: &str, version: &str) -> Path {
+fn build_gcs(config: &GcsConfig, client_options: ClientOptions) -> GoogleCloudStorage {
+    let mut builder = GoogleCloudStorageBuilder::new()
+        .with_bucket_name(&config.bucket)
+        .with_client_options(client_options);
+
+    if let Some(service_account_key) = &config.service_account_key {
+        builder = builder.with_service_account_key(service_account_key.expose_secret());
+    }
+
+    builder
+        .build()
+        .context("Failed to initialize GCS code")
+        .unwrap()
+}
+
+fn build_azure(config: &AzureConfig, client_options: ClientOptions) -> MicrosoftAzure {
+    MicrosoftAzureBuilder::new()
+        .with_account(&config.account)
+        .with_access_key(config.access_key.expose_secret())
+        .with_container_name(&config.container)
+        .with_use_emulator(config.use_emulator)
+        .with_client_options(client_options)
+        .build()
+        .context("Failed to initialize Azure code")
+        .unwrap()
+}
+
+pub(crate) fn crate_file_path(name: &str, version: &str) -> Path {
     format!("{PREFIX_CRATES}/{name}/{name}-{version}.crate").into()
 }
 
@@ -532,6 +817,37 @@ mod tests {
         assert_eq!(stored_files(&s.store).await, expected_files);
     }
 
+    #[tokio::test]
+    async fn crate_file_checksum() {
+        let s = Storage::from_config(&StorageConfig::in_memory());
+
+        assert_eq!(s.crate_file_checksum("foo", "1.2.3").await.unwrap(), None);
+
+        let bytes = Bytes::from_static(b"hello world");
+        s.upload_crate_file("foo", "1.2.3", bytes).await.unwrap();
+
+        let checksum = s.crate_file_checksum("foo", "1.2.3").await.unwrap();
+        let expected = "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+        assert_eq!(checksum.as_deref(), Some(expected));
+    }
+
+    #[tokio::test]
+    async fn quarantine_crate_file() {
+        let storage = prepare().await;
+
+        storage.quarantine_crate_file("foo", "1.2.3").await.unwrap();
+
+        let expected_files = vec![
+            "crates/bar/bar-2.0.0.crate",
+            "crates/foo/foo-1.0.0.crate",
+            "quarantine/crates/foo/foo-1.2.3.crate",
+            "readmes/bar/bar-2.0.0.html",
+            "readmes/foo/foo-1.0.0.html",
+            "readmes/foo/foo-1.2.3.html",
+        ];
+        assert_eq!(stored_files(&storage.store).await, expected_files);
+    }
+
     #[tokio::test]
     async fn sync_index() {
         let s = Storage::from_config(&StorageConfig::in_memory());
@@ -549,6 +865,68 @@ mod tests {
         assert!(stored_files(&s.store).await.is_empty());
     }
 
+    #[tokio::test]
+    async fn read_index_file() {
+        let s = Storage::from_config(&StorageConfig::in_memory());
+
+        assert_eq!(s.read_index_file("foo").await.unwrap(), None);
+
+        let content = "foo".to_string();
+        s.sync_index("foo", Some(content)).await.unwrap();
+
+        let content = s.read_index_file("FOO").await.unwrap();
+        assert_eq!(content.as_deref(), Some(b"foo".as_slice()));
+    }
+
+    #[tokio::test]
+    async fn list_index_files() {
+        let s = Storage::from_config(&StorageConfig::in_memory());
+
+        s.sync_index("foo", Some("foo".to_string())).await.unwrap();
+        s.sync_index("a", Some("a".to_string())).await.unwrap();
+        let content = Bytes::from_static(b"{}");
+        s.upload_index_metadata("_meta/crates/3/f/foo.json", content.clone())
+            .await
+            .unwrap();
+        s.upload_index_metadata("_meta/snapshot.json", content)
+            .await
+            .unwrap();
+
+        let mut names = s.list_index_files().await.unwrap();
+        names.sort();
+        assert_eq!(names, vec!["a", "foo"]);
+    }
+
+    #[tokio::test]
+    async fn index_metadata() {
+        let s = Storage::from_config(&StorageConfig::in_memory());
+
+        let path = "_meta/crates/3/f/foo.json";
+        assert_eq!(s.read_index_metadata(path).await.unwrap(), None);
+
+        let content = Bytes::from_static(b"{}");
+        s.upload_index_metadata(path, content.clone())
+            .await
+            .unwrap();
+        s.upload_index_metadata("_meta/snapshot.json", content.clone())
+            .await
+            .unwrap();
+
+        let expected_files = vec![
+            "index/_meta/crates/3/f/foo.json",
+            "index/_meta/snapshot.json",
+        ];
+        assert_eq!(stored_files(&s.store).await, expected_files);
+        assert_eq!(s.read_index_metadata(path).await.unwrap(), Some(content));
+        assert_eq!(s.list_index_file_metadata().await.unwrap(), vec![path]);
+
+        s.delete_index_metadata(path).await.unwrap();
+        assert_eq!(
+            s.list_index_file_metadata().await.unwrap(),
+            Vec::<String>::new()
+        );
+    }
+
     #[tokio::test]
     async fn upload_db_dump() {
         let s = Storage::from_config(&StorageConfig::in_memory());
@@ -562,4 +940,113 @@ mod tests {
         let expected_files = vec![target];
         assert_eq!(stored_files(&s.store).await, expected_files);
     }
+
+    /// Runs all `Storage` operations against the given (empty) backend.
+    async fn round_trip(s: &Storage) {
+        let bytes = Bytes::from_static(b"hello world");
+        s.upload_crate_file("foo", "1.0.0", bytes.clone())
+            .await
+            .unwrap();
+        s.upload_readme("foo", "1.0.0", bytes).await.unwrap();
+        s.sync_index("foo", Some("foo".to_string())).await.unwrap();
+
+        let file = NamedTempFile::new().unwrap();
+        s.upload_db_dump("db-dump.tar.gz", file.path())
+            .await
+            .unwrap();
+
+        let expected_files = vec![
+            "crates/foo/foo-1.0.0.crate",
+            "db-dump.tar.gz",
+            "readmes/foo/foo-1.0.0.html",
+        ];
+        let mut files = stored_files(&s.store).await;
+        files.retain(|file| !file.starts_with("index/"));
+        assert_eq!(files, expected_files);
+
+        let content = s.read_index_file("foo").await.unwrap();
+        assert_eq!(content.as_deref(), Some(b"foo".as_slice()));
+
+        s.delete_crate_file("foo", "1.0.0").await.unwrap();
+        s.delete_all_readmes("foo").await.unwrap();
+        s.sync_index("foo", None).await.unwrap();
+        assert_eq!(s.read_index_file("foo").await.unwrap(), None);
+
+        let mut files = stored_files(&s.store).await;
+        files.retain(|file| !file.starts_with("index/"));
+        assert_eq!(files, vec!["db-dump.tar.gz"]);
+    }
+
+    #[tokio::test]
+    async fn in_memory_backend() {
+        round_trip(&Storage::from_config(&StorageConfig::in_memory())).await;
+    }
+
+    /// Needs a `fake-gcs-server` emulator with empty `crates` and `index` buckets:
+    ///
+    /// ```sh
+    /// docker run -p 4443:4443 fsouza/fake-gcs-server -scheme http -backend memory
+    /// curl -X POST --data '{"name":"crates"}' http://localhost:4443/storage/v1/b
+    /// curl -X POST --data '{"name":"index"}' http://localhost:4443/storage/v1/b
+    /// ```
+    #[tokio::test]
+    #[ignore]
+    async fn gcs_emulator_backend() {
+        let service_account_key = r#"{
+            "gcs_base_url": "http://localhost:4443",
+            "disable_oauth": true,
+            "client_email": "",
+            "private_key": "",
+            "private_key_id": ""
+        }"#;
+
+        let gcs_config = |bucket: &str| GcsConfig {
+            bucket: bucket.to_string(),
+            service_account_key: Some(service_account_key.to_string().into()),
+        };
+
+        let backend = StorageBackend::Gcs {
+            default: gcs_config("crates"),
+            index: gcs_config("index"),
+        };
+        let config = StorageConfig {
+            backend,
+            cdn_prefix: Some("static.crates.io".to_string()),
+        };
+
+        round_trip(&Storage::from_config(&config)).await;
+    }
+
+    /// Needs an Azurite emulator with empty `crates` and `index` containers:
+    ///
+    /// ```sh
+    /// docker run -p 10000:10000 mcr.microsoft.com/azure-storage/azurite azurite-blob --blobHost 0.0.0.0
+    /// az storage container create -n crates --connection-string "UseDevelopmentStorage=true"
+    /// az storage container create -n index --connection-string "UseDevelopmentStorage=true"
+    /// ```
+    #[tokio::test]
+    #[ignore]
+    async fn azure_emulator_backend() {
+        // The well-known credentials of the Azurite emulator
+        let account = "devstoreaccount1";
+        let access_key = "Eby8vdM02xNOcqFlqUwJPLlmEtlCDXJ1OUzFT50uSRZ6IFsuFq2UVErCz4I6tq/K1SZFPTOtr/KBHBeksoGMGw==";
+
+        let azure_config = |container: &str| AzureConfig {
+            container: container.to_string(),
+            account: account.to_string(),
+            access_key: access_key.to_string().into(),
+            use_emulator: true,
+        };
+
+        let backend = StorageBackend::Azure {
+            default: azure_config("crates"),
+            index: azure_config("index"),
+        };
+        let config = StorageConfig {
+            backend,
+            cdn_prefix: Some("static.crates.io".to_string()),
+        };
+
+        round_trip(&Storage::from_config(&config)).await;
+    }
 }
@@ -21,6 +21,7 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 use diesel::prelude::*;
 
 mod account_lock;
+mod auth_required;
 mod authentication;
 mod blocked_routes;
 mod builders;
@@ -33,6 +34,7 @@ mod models;
 mod not_found_error;
 mod owners;
 mod pagination;
+mod publish_policy;
 mod read_only_mode;
 mod routes;
 mod schema_details;
@@ -40,10 +42,13 @@ mod server;
 mod server_binary;
 mod team;
 mod token;
+mod trusted_publishing;
+mod two_factor;
 mod unhealthy_database;
 mod user;
 mod util;
 mod version;
+mod webhooks;
 mod worker;
 
 #[derive(Deserialize)]
@@ -0,0 +1,179 @@
+use crate::add_team_to_crate;
+use crate::builders::{CrateBuilder, PublishBuilder};
+use crate::util::{MockRequestExt, RequestHelper, TestApp};
+use crates_io::config;
+use crates_io::models::token::{CrateScope, EndpointScope};
+use crates_io::models::{NewTeam, Team};
+use http::StatusCode;
+use insta::assert_snapshot;
+
+const DOWNLOAD_URL: &str = "/api/v1/crates/foo/1.0.0/download";
+const INDEX_URL: &str = "/index/3/f/foo";
+
+fn private_registry(config: &mut config::Server) {
+    config.auth_required = true;
+    config.serve_sparse_index = true;
+}
+
+async fn publish_foo(app: &TestApp) {
+    let user = app.db_new_user("publisher");
+    let token = user.db_new_token("publish");
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+}
+
+async fn assert_status(client: &impl RequestHelper, status: StatusCode) {
+    // Successful downloads redirect to the storage location of the crate file
+    let download_status = match status {
+        StatusCode::OK => StatusCode::FOUND,
+        status => status,
+    };
+    let response = client.get::<()>(DOWNLOAD_URL).await;
+    assert_eq!(response.status(), download_status);
+
+    for url in [INDEX_URL, "/api/v1/crates", "/api/v1/crates/foo"] {
+        let response = client.get::<()>(url).await;
+        assert_eq!(response.status(), status, "{url}");
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn anonymous_requests_are_rejected() {
+    let (app, anon) = TestApp::full().with_config(private_registry).empty();
+    publish_foo(&app).await;
+
+    assert_status(&anon, StatusCode::UNAUTHORIZED).await;
+
+    let response = anon.get::<()>(DOWNLOAD_URL).await;
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"this registry requires authentication"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn config_json_is_public() {
+    let (_, anon) = TestApp::full().with_config(private_registry).empty();
+
+    let response = anon.get::<()>("/index/config.json").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_snapshot!(response.text(), @r###"{"api":"https://crates.io","auth-required":true,"dl":"https://crates.io/api/v1/crates"}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn cookie_and_legacy_token_users_can_read() {
+    let (app, _, cookie, token) = TestApp::full().with_config(private_registry).with_token();
+    publish_foo(&app).await;
+
+    let response = cookie.get::<()>(DOWNLOAD_URL).await;
+    assert_eq!(response.status(), StatusCode::FOUND);
+    assert_status(&token, StatusCode::OK).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn tokens_need_the_read_or_a_publish_scope() {
+    for scope in [
+        EndpointScope::Read,
+        EndpointScope::PublishNew,
+        EndpointScope::PublishUpdate,
+    ] {
+        let (app, _, _, token) = TestApp::full()
+            .with_config(private_registry)
+            .with_scoped_token(None, Some(vec![scope]));
+        publish_foo(&app).await;
+
+        assert_status(&token, StatusCode::OK).await;
+    }
+
+    let (app, _, _, token) = TestApp::full()
+        .with_config(private_registry)
+        .with_scoped_token(None, Some(vec![EndpointScope::Yank]));
+    publish_foo(&app).await;
+
+    assert_status(&token, StatusCode::FORBIDDEN).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn metrics_use_their_own_token() {
+    let (_, anon) = TestApp::init()
+        .with_config(|config| {
+            private_registry(config);
+            config.metrics_authorization_token = Some("foobar".into());
+        })
+        .empty();
+
+    let mut request = anon.get_request("/api/private/metrics/instance");
+    request.header("Authorization", "Bearer foobar");
+    let response = anon.run::<()>(request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn crate_scopes_restrict_downloads() {
+    let crate_scopes = Some(vec![CrateScope::try_from("bar").unwrap()]);
+    let (app, _, _, token) = TestApp::full()
+        .with_config(private_registry)
+        .with_scoped_token(crate_scopes, Some(vec![EndpointScope::Read]));
+    publish_foo(&app).await;
+
+    let response = token.get::<()>(DOWNLOAD_URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = token.get::<()>(INDEX_URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn crate_scopes_restrict_api_reads() {
+    let crate_scopes = Some(vec![CrateScope::try_from("bar").unwrap()]);
+    let (app, _, user, token) = TestApp::full()
+        .with_config(private_registry)
+        .with_scoped_token(crate_scopes, Some(vec![EndpointScope::Read]));
+    publish_foo(&app).await;
+    app.db(|conn| CrateBuilder::new("bar", user.as_model().id).expect_build(conn));
+
+    for url in ["/api/v1/crates/bar", "/api/v1/crates/bar/versions"] {
+        let response = token.get::<()>(url).await;
+        assert_eq!(response.status(), StatusCode::OK, "{url}");
+    }
+
+    // Endpoints without a crate return data of all crates
+    for url in [
+        "/api/v1/crates/foo",
+        "/api/v1/crates/foo/versions",
+        "/api/v1/crates",
+        "/api/v1/summary",
+    ] {
+        let response = token.get::<()>(url).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN, "{url}");
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn team_tokens_can_only_read_team_crates() {
+    let (app, _) = TestApp::full().with_config(private_registry).empty();
+    publish_foo(&app).await;
+
+    let member = app.db_new_user("user-one-team");
+    let team: Team = app.db(|conn| {
+        let krate = CrateBuilder::new("bar", member.as_model().id).expect_build(conn);
+        let team = NewTeam::new("github:test-org:all", 1000, 2000, None, None)
+            .create_or_update(conn)
+            .unwrap();
+        add_team_to_crate(&team, &krate, member.as_model(), conn).unwrap();
+        team
+    });
+    let token = member.db_new_team_token("ci", team.id, vec![EndpointScope::Read]);
+
+    let response = token.get::<()>("/api/v1/crates/bar").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // The team does not own `foo`
+    for url in [INDEX_URL, "/api/v1/crates/foo"] {
+        let response = token.get::<()>(url).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN, "{url}");
+    }
+
+    for url in ["/api/v1/crates", "/api/v1/summary"] {
+        let response = token.get::<()>(url).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN, "{url}");
+    }
+}
@@ -1,3 +1,4 @@
+use crates_io::models::DependencyKind;
 use crates_io::views::krate_publish as u;
 
 /// A builder for constructing a dependency of another crate.
@@ -7,6 +8,9 @@ pub struct DependencyBuilder {
     features: Vec<String>,
     registry: Option<String>,
     version_req: String,
+    optional: bool,
+    default_features: bool,
+    kind: Option<DependencyKind>,
 }
 
 impl DependencyBuilder {
@@ -18,6 +22,9 @@ impl DependencyBuilder {
             features: vec![],
             registry: None,
             version_req: "> 0".to_string(),
+            optional: false,
+            default_features: true,
+            kind: None,
         }
     }
 
@@ -44,6 +51,24 @@ impl DependencyBuilder {
         self
     }
 
+    /// Mark this dependency as optional.
+    pub fn optional(mut self) -> Self {
+        self.optional = true;
+        self
+    }
+
+    /// Disable the default features of this dependency.
+    pub fn no_default_features(mut self) -> Self {
+        self.default_features = false;
+        self
+    }
+
+    /// Set the kind of this dependency.
+    pub fn kind(mut self, kind: DependencyKind) -> Self {
+        self.kind = Some(kind);
+        self
+    }
+
     pub fn add_feature<T: Into<String>>(mut self, feature: T) -> Self {
         self.features.push(feature.into());
         self
@@ -54,12 +79,12 @@ impl DependencyBuilder {
     pub fn build(self) -> u::EncodableCrateDependency {
         u::EncodableCrateDependency {
             name: self.name,
-            optional: false,
-            default_features: true,
+            optional: self.optional,
+            default_features: self.default_features,
             features: self.features,
             version_req: self.version_req,
             target: None,
-            kind: None,
+            kind: self.kind,
             explicit_name_in_toml: self.explicit_name_in_toml,
             registry: self.registry,
         }
@@ -1,5 +1,5 @@
 use crates_io::{
-    models::{Category, Crate, Keyword, NewCrate},
+    models::{update_default_version, Category, Crate, Keyword, NewCrate},
     schema::{crates, version_downloads},
     util::errors::AppResult,
 };
@@ -142,6 +142,8 @@ impl<'a> CrateBuilder<'a> {
                 .id;
         }
 
+        update_default_version(krate.id, connection)?;
+
         if let Some(downloads) = self.recent_downloads {
             insert_into(version_downloads::table)
                 .values((
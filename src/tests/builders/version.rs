@@ -49,7 +49,8 @@ impl<'a> VersionBuilder<'a> {
         }
     }
 
-    /// Sets the version's `created_at` value.
+    /// Sets the version's `created_at` value, and its `updated_at` value to
+    /// the same time.
     pub fn created_at(mut self, created_at: NaiveDateTime) -> Self {
         self.created_at = Some(created_at);
         self
@@ -121,7 +122,10 @@ impl<'a> VersionBuilder<'a> {
 
         if let Some(created_at) = self.created_at {
             vers = update(&vers)
-                .set(versions::created_at.eq(created_at))
+                .set((
+                    versions::created_at.eq(created_at),
+                    versions::updated_at.eq(created_at),
+                ))
                 .get_result(connection)?;
         }
 
@@ -1,9 +1,23 @@
-use crates_io::worker::jobs::dump_db;
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::new_user;
+use chrono::{Duration, Utc};
+use crates_io::email::Emails;
+use crates_io::schema::{crates, versions};
+use crates_io::worker::jobs::dump_db::{self, ColumnType, ParquetSchema, TableChanges};
 use crates_io_test_db::TestDatabase;
+use diesel::prelude::*;
+use std::sync::{Mutex, PoisonError};
+
+/// The export directories are named after the current second, so the tests
+/// in this file must not create them concurrently.
+static DUMP_DIRECTORY_LOCK: Mutex<()> = Mutex::new(());
 
 #[test]
 fn dump_db_and_reimport_dump() {
     crates_io::util::tracing::init_for_test();
+    let _lock = DUMP_DIRECTORY_LOCK
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
 
     let db_one = TestDatabase::new();
 
@@ -19,3 +33,198 @@ fn dump_db_and_reimport_dump() {
 
     // TODO: Consistency checks on the re-imported data?
 }
+
+#[test]
+fn parquet_export() {
+    crates_io::util::tracing::init_for_test();
+    let _lock = DUMP_DIRECTORY_LOCK
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+
+    let db = TestDatabase::new();
+    let conn = &mut db.connect();
+    let user = new_user("foo")
+        .create_or_update(None, &Emails::new_in_memory(), conn)
+        .unwrap();
+    CrateBuilder::new("foo", user.id)
+        .version("1.0.0")
+        .version("1.1.0")
+        .expect_build(conn);
+
+    let directory = dump_db::DumpDirectory::create().unwrap();
+    directory.populate(db.url()).unwrap();
+    let schema = directory.export_parquet(db.url()).unwrap();
+
+    let parquet_dir = directory.export_dir.join("parquet");
+    let content = std::fs::read(parquet_dir.join("schema.json")).unwrap();
+    let schema_file: ParquetSchema = serde_json::from_slice(&content).unwrap();
+    assert_eq!(schema_file.tables.len(), schema.tables.len());
+
+    // Only the public columns are exported
+    let crates = &schema.tables["crates"];
+    assert_eq!(crates.path, "crates.parquet");
+    assert_eq!(crates.rows, 1);
+    let id = crates.columns.iter().find(|c| c.name == "id").unwrap();
+    assert_eq!(id.column_type, ColumnType::Int32);
+    assert_eq!(id.postgres_type, "integer");
+    assert!(!id.nullable);
+    let created_at = crates.columns.iter().find(|c| c.name == "created_at");
+    assert_eq!(created_at.unwrap().column_type, ColumnType::Timestamp);
+    let description = crates.columns.iter().find(|c| c.name == "description");
+    assert!(description.unwrap().nullable);
+    assert!(!crates
+        .columns
+        .iter()
+        .any(|c| c.name == "textsearchable_index_col"));
+
+    let versions = &schema.tables["versions"];
+    assert_eq!(versions.rows, 2);
+    let yanked = versions.columns.iter().find(|c| c.name == "yanked");
+    assert_eq!(yanked.unwrap().column_type, ColumnType::Boolean);
+
+    for table in schema.tables.values() {
+        let content = std::fs::read(parquet_dir.join(&table.path)).unwrap();
+        assert!(content.starts_with(b"PAR1"));
+        assert!(content.ends_with(b"PAR1"));
+    }
+
+    // The Parquet files are not included in the tarball
+    assert!(!directory
+        .export_dir
+        .join("data")
+        .join("crates.parquet")
+        .exists());
+}
+
+#[test]
+fn incremental_dump_and_import() {
+    crates_io::util::tracing::init_for_test();
+    let _lock = DUMP_DIRECTORY_LOCK
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+
+    let emails = Emails::new_in_memory();
+
+    let db_one = TestDatabase::new();
+    let conn = &mut db_one.connect();
+    let user = new_user("foo")
+        .create_or_update(None, &emails, conn)
+        .unwrap();
+    let yesterday = Utc::now().naive_utc() - Duration::days(1);
+    CrateBuilder::new("baz", user.id)
+        .version("1.0.0")
+        .updated_at(yesterday)
+        .expect_build(conn);
+    let krate = CrateBuilder::new("foo", user.id)
+        .version("1.0.0")
+        .expect_build(conn);
+
+    // Restore a full dump into the second database
+    let db_two = TestDatabase::new();
+    {
+        let directory = dump_db::DumpDirectory::create().unwrap();
+        directory.populate(db_one.url()).unwrap();
+
+        let import_script = directory.export_dir.join("import.sql");
+        dump_db::run_psql(&import_script, db_two.url()).unwrap();
+    }
+
+    // The first incremental dump includes all rows, and is only used for
+    // its watermarks here
+    let first = {
+        let directory = dump_db::DumpDirectory::create().unwrap();
+        let path = "db-dump-incremental/first.tar.gz".to_string();
+        directory
+            .populate_incremental(db_one.url(), path, None)
+            .unwrap()
+    };
+    assert_eq!(first.previous, None);
+    assert_eq!(first.tables["categories"], TableChanges::Full);
+    assert!(matches!(
+        &first.tables["crates"],
+        TableChanges::Incremental { column, since: None, until: Some(_) } if column == "updated_at"
+    ));
+
+    VersionBuilder::new("1.1.0").expect_build(krate.id, user.id, conn);
+    let user = new_user("bar")
+        .create_or_update(None, &emails, conn)
+        .unwrap();
+    CrateBuilder::new("bar", user.id)
+        .version("1.0.0")
+        .expect_build(conn);
+
+    // Rows are included even if their `updated_at` is older than the newest
+    // row of the previous dump, e.g. because their transaction took a while
+    CrateBuilder::new("qux", user.id)
+        .version("1.0.0")
+        .updated_at(yesterday)
+        .expect_build(conn);
+
+    diesel::delete(crates::table.filter(crates::name.eq("baz")))
+        .execute(conn)
+        .unwrap();
+
+    let directory = dump_db::DumpDirectory::create().unwrap();
+    let path = "db-dump-incremental/second.tar.gz".to_string();
+    let second = directory
+        .populate_incremental(db_one.url(), path, Some(first))
+        .unwrap();
+    assert_eq!(
+        second.previous.as_deref(),
+        Some("db-dump-incremental/first.tar.gz")
+    );
+
+    let manifest = directory.export_dir.join("manifest.json");
+    assert!(manifest.exists());
+    assert!(!directory.export_dir.join("watermarks.csv").exists());
+
+    // Only rows that changed since the first dump are included
+    let crates_csv = directory.export_dir.join("data").join("crates.csv");
+    let crates_csv = std::fs::read_to_string(crates_csv).unwrap();
+    assert!(crates_csv.contains("bar"));
+    assert!(crates_csv.contains("qux"));
+    assert!(!crates_csv.contains("baz"));
+
+    let crates_keys = directory.export_dir.join("data").join("crates.keys.csv");
+    let crates_keys = std::fs::read_to_string(crates_keys).unwrap();
+    assert_eq!(crates_keys.lines().count(), 4);
+
+    // The changes can't be applied to a database without the previous changes
+    let db_three = TestDatabase::new();
+    let error = dump_db::import_incremental(&directory.export_dir, db_three.url()).unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("is missing the changes of the previous incremental dump"));
+
+    // Only the incremental columns of the configuration are accepted
+    let original = std::fs::read(&manifest).unwrap();
+    let mut tampered: serde_json::Value = serde_json::from_slice(&original).unwrap();
+    tampered["tables"]["crates"]["column"] = json!("name\" FROM users --");
+    std::fs::write(&manifest, tampered.to_string()).unwrap();
+    let error = dump_db::import_incremental(&directory.export_dir, db_two.url()).unwrap_err();
+    assert!(error.to_string().contains("is not an incremental column"));
+    std::fs::write(&manifest, original).unwrap();
+
+    dump_db::import_incremental(&directory.export_dir, db_two.url()).unwrap();
+
+    let conn = &mut db_two.connect();
+    let crate_names: Vec<String> = crates::table
+        .select(crates::name)
+        .order(crates::name)
+        .load(conn)
+        .unwrap();
+    assert_eq!(crate_names, vec!["bar", "foo", "qux"]);
+
+    // The versions of the deleted crate are deleted as well
+    let num_versions: i64 = versions::table.count().get_result(conn).unwrap();
+    assert_eq!(num_versions, 4);
+
+    let foo_versions: Vec<String> = versions::table
+        .inner_join(crates::table)
+        .filter(crates::name.eq("foo"))
+        .select(versions::num)
+        .order(versions::num)
+        .load(conn)
+        .unwrap();
+    assert_eq!(foo_versions, vec!["1.0.0", "1.1.0"]);
+}
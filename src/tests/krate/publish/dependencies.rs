@@ -1,5 +1,6 @@
-use crate::builders::{CrateBuilder, DependencyBuilder, PublishBuilder};
+use crate::builders::{CrateBuilder, DependencyBuilder, PublishBuilder, VersionBuilder};
 use crate::util::{RequestHelper, TestApp};
+use crates_io::models::DependencyKind;
 use googletest::prelude::*;
 use http::StatusCode;
 use insta::{assert_json_snapshot, assert_snapshot};
@@ -22,7 +23,9 @@ async fn new_with_renamed_dependency() {
 
     app.db(|conn| {
         // Insert a crate directly into the database so that new-krate can depend on it
-        CrateBuilder::new("package-name", user.as_model().id).expect_build(conn);
+        CrateBuilder::new("package-name", user.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
     });
 
     let dependency = DependencyBuilder::new("package-name").rename("my-name");
@@ -139,7 +142,9 @@ async fn new_with_underscore_renamed_dependency() {
 
     app.db(|conn| {
         // Insert a crate directly into the database so that new-krate can depend on it
-        CrateBuilder::new("package-name", user.as_model().id).expect_build(conn);
+        CrateBuilder::new("package-name", user.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
     });
 
     let dependency = DependencyBuilder::new("package-name").rename("_my-name");
@@ -162,7 +167,9 @@ async fn new_krate_with_dependency() {
         // The name choice of `foo-dep` is important! It has the property of
         // name != canon_crate_name(name) and is a regression test for
         // https://github.com/rust-lang/crates.io/issues/651
-        CrateBuilder::new("foo-dep", user.as_model().id).expect_build(conn);
+        CrateBuilder::new("foo-dep", user.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
     });
 
     let dependency = DependencyBuilder::new("foo-dep").version_req("1.0.0");
@@ -230,7 +237,9 @@ async fn new_crate_allow_empty_alternative_registry_dependency() {
     let (app, _, user, token) = TestApp::full().with_token();
 
     app.db(|conn| {
-        CrateBuilder::new("foo-dep", user.as_model().id).expect_build(conn);
+        CrateBuilder::new("foo-dep", user.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
     });
 
     let dependency = DependencyBuilder::new("foo-dep").registry("");
@@ -287,14 +296,71 @@ async fn new_krate_dependency_missing() {
     assert_that!(app.stored_files().await, empty());
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn reject_new_krate_with_unsatisfiable_dependency_requirement() {
+    let (app, _, user, token) = TestApp::full().with_token();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo-dep", user.as_model().id)
+            .version("1.0.0")
+            .version(VersionBuilder::new("2.0.0").yanked(true))
+            .expect_build(conn);
+    });
+
+    for version_req in ["9.9", "2.0.0"] {
+        let dependency = DependencyBuilder::new("foo-dep").version_req(version_req);
+        let crate_to_publish = PublishBuilder::new("new_dep", "1.0.0").dependency(dependency);
+
+        let response = token.publish_crate(crate_to_publish).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let detail = format!(
+            "no non-yanked version of `foo-dep` matches the dependency requirement `^{version_req}`"
+        );
+        assert_eq!(response.json(), json!({ "errors": [{ "detail": detail }] }));
+    }
+
+    let dependency = DependencyBuilder::new("foo-dep")
+        .version_req("9.9")
+        .kind(DependencyKind::Build);
+    let crate_to_publish = PublishBuilder::new("new_dep", "1.0.0").dependency(dependency);
+    let response = token.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    assert_that!(app.stored_files().await, empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn new_krate_with_unsatisfiable_dev_dependency_requirement() {
+    let (app, _, user, token) = TestApp::full().with_token();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo-dep", user.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+    });
+
+    let dependency = DependencyBuilder::new("foo-dep")
+        .version_req("9.9")
+        .kind(DependencyKind::Dev);
+    let crate_to_publish = PublishBuilder::new("new_dep", "1.0.0").dependency(dependency);
+
+    let response = token.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_snapshot!(response.json()["warnings"], @r###"{"invalid_badges":[],"invalid_categories":[],"other":["no non-yanked version of `foo-dep` matches the dev-dependency requirement `^9.9`"]}"###);
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn new_krate_sorts_deps() {
     let (app, _, user, token) = TestApp::full().with_token();
 
     app.db(|conn| {
         // Insert crates directly into the database so that two-deps can depend on it
-        CrateBuilder::new("dep-a", user.as_model().id).expect_build(conn);
-        CrateBuilder::new("dep-b", user.as_model().id).expect_build(conn);
+        CrateBuilder::new("dep-a", user.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+        CrateBuilder::new("dep-b", user.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
     });
 
     let dep_a = DependencyBuilder::new("dep-a");
@@ -332,8 +398,12 @@ async fn test_dep_limit() {
         .with_token();
 
     app.db(|conn| {
-        CrateBuilder::new("dep-a", user.as_model().id).expect_build(conn);
-        CrateBuilder::new("dep-b", user.as_model().id).expect_build(conn);
+        CrateBuilder::new("dep-a", user.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+        CrateBuilder::new("dep-b", user.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
     });
 
     let crate_to_publish = PublishBuilder::new("foo", "1.0.0")
@@ -16,4 +16,5 @@ mod readme;
 mod similar_names;
 mod tarball;
 mod timestamps;
+mod typosquat;
 mod validation;
@@ -0,0 +1,69 @@
+use crate::builders::{CrateBuilder, PublishBuilder};
+use crate::util::{RequestHelper, TestApp};
+use googletest::prelude::*;
+use http::StatusCode;
+use insta::{assert_json_snapshot, assert_snapshot};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn new_crate_similar_to_popular_crate() {
+    let (app, _, user) = TestApp::full().with_user();
+    let other_user = app.db_new_user("bar");
+
+    app.db(|conn| {
+        CrateBuilder::new("my-crate", other_user.as_model().id)
+            .downloads(1000)
+            .expect_build(conn);
+    });
+
+    let crate_to_publish = PublishBuilder::new("mycrate", "1.0.0");
+    let response = user.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_json_snapshot!(response.json()["warnings"]);
+
+    // Only new crates are checked
+    let crate_to_publish = PublishBuilder::new("mycrate", "1.1.0");
+    let response = user.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json()["warnings"]["other"], json!([]));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn new_crate_similar_to_own_popular_crate() {
+    let (app, _, user) = TestApp::full().with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("my-crate", user.as_model().id)
+            .downloads(1000)
+            .expect_build(conn);
+    });
+
+    let crate_to_publish = PublishBuilder::new("mycrate", "1.0.0");
+    let response = user.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json()["warnings"]["other"], json!([]));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn new_crate_above_block_threshold() {
+    let (app, _, user) = TestApp::full()
+        .with_config(|config| config.typosquat_block_threshold = Some(1))
+        .with_user();
+    let other_user = app.db_new_user("bar");
+
+    app.db(|conn| {
+        CrateBuilder::new("my-crate", other_user.as_model().id)
+            .downloads(1000)
+            .expect_build(conn);
+    });
+
+    let crate_to_publish = PublishBuilder::new("mycrate", "1.0.0");
+    let response = user.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the crate name `mycrate` is too similar to the popular crate `my-crate`. Please choose a different name, or send us an email to help@crates.io if you believe this is a mistake."}]}"###);
+    assert_that!(app.stored_files().await, empty());
+
+    // Unrelated names are not affected by the threshold
+    let crate_to_publish = PublishBuilder::new("unrelated", "1.0.0");
+    let response = user.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
@@ -0,0 +1,288 @@
+use crate::add_team_to_crate;
+use crate::builders::{CrateBuilder, PublishBuilder};
+use crate::util::{MockCookieUser, RequestHelper, TestApp};
+use chrono::{Duration, Utc};
+use crates_io::models::token::{CrateScope, EndpointScope};
+use crates_io::models::{Crate, CrateOwner, NewTeam, NewTrustedPublisher, OwnerKind};
+use crates_io::schema::{api_tokens, crate_owners};
+use diesel::prelude::*;
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::Value;
+
+const URL: &str = "/api/v1/crates/foo/publish_policy";
+
+fn policy_body(policy: Value) -> String {
+    json!({ "publish_policy": policy }).to_string()
+}
+
+/// Creates the `foo` crate, owned by `user` and by the
+/// `github:test-org:core` team.
+fn create_crate(app: &TestApp, user: &MockCookieUser) -> Crate {
+    app.db(|conn| {
+        let user = user.as_model();
+        let krate = CrateBuilder::new("foo", user.id)
+            .version("1.0.0")
+            .expect_build(conn);
+        let team = NewTeam::new("github:test-org:core", 1000, 2001, None, None)
+            .create_or_update(conn)
+            .unwrap();
+        add_team_to_crate(&team, &krate, user, conn).unwrap();
+        krate
+    })
+}
+
+fn add_user_to_crate(app: &TestApp, krate: &Crate, user: &MockCookieUser) {
+    app.db(|conn| {
+        let crate_owner = CrateOwner {
+            crate_id: krate.id,
+            owner_id: user.as_model().id,
+            created_by: user.as_model().id,
+            owner_kind: OwnerKind::User,
+            email_notifications: true,
+        };
+
+        diesel::insert_into(crate_owners::table)
+            .values(&crate_owner)
+            .execute(conn)
+            .unwrap();
+    });
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn manage_publish_policy() {
+    let (app, anon, user) = TestApp::full().with_user();
+    create_crate(&app, &user);
+
+    let response = anon.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let json = user.get::<()>(URL).await.json();
+    assert_eq!(json["publish_policy"]["crate"], "foo");
+    assert_eq!(json["publish_policy"]["allowed_publishers"], json!([]));
+    assert_eq!(json["publish_policy"]["require_token_expiry"], false);
+    assert_eq!(json["publish_policy"]["updated_at"], Value::Null);
+
+    let body = policy_body(json!({
+        "allowed_publishers": ["github:test-org:core", "FOO"],
+        "prerelease_publishers": ["foo"],
+        "require_token_expiry": true,
+    }));
+    let response = user.put::<()>(URL, body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let json = user.get::<()>(URL).await.json();
+    assert_eq!(
+        json["publish_policy"]["allowed_publishers"],
+        json!(["foo", "github:test-org:core"])
+    );
+    assert_eq!(
+        json["publish_policy"]["prerelease_publishers"],
+        json!(["foo"])
+    );
+    assert_eq!(json["publish_policy"]["require_token_expiry"], true);
+    assert_eq!(json["publish_policy"]["require_trusted_publishing"], false);
+    assert!(json["publish_policy"]["updated_at"].is_string());
+
+    let json = user.get::<()>("/api/v1/crates/foo/audit_log").await.json();
+    assert_eq!(json["actions"][0]["action"], "update_publish_policy");
+
+    // Removing all restrictions again
+    let response = user.put::<()>(URL, policy_body(json!({}))).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.json();
+    assert_eq!(json["publish_policy"]["allowed_publishers"], json!([]));
+    assert_eq!(json["publish_policy"]["require_token_expiry"], false);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_owners_can_be_allowed_publishers() {
+    let (app, _, user) = TestApp::full().with_user();
+    create_crate(&app, &user);
+    app.db_new_user("bar");
+
+    let body = policy_body(json!({ "allowed_publishers": ["bar"] }));
+    let response = user.put::<()>(URL, body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"`bar` is not an owner of this crate"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_owners_can_manage_publish_policy() {
+    let (app, _, user) = TestApp::full().with_user();
+    create_crate(&app, &user);
+
+    let other_user = app.db_new_user("bar");
+    let response = other_user.put::<()>(URL, policy_body(json!({}))).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"only owners have permission to manage the publish policy"}]}"###);
+
+    let team_member = app.db_new_user("user-all-teams");
+    let response = team_member.put::<()>(URL, policy_body(json!({}))).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"team members don't have permission to manage the publish policy"}]}"###);
+
+    // API tokens can't be used to loosen the policy
+    let token = user.db_new_token("baz");
+    let response = token.put::<()>(URL, policy_body(json!({}))).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn excluded_owners_can_not_loosen_publish_policy() {
+    let (app, _, user) = TestApp::full().with_user();
+    let krate = create_crate(&app, &user);
+    let other_owner = app.db_new_user("bar");
+    add_user_to_crate(&app, &krate, &other_owner);
+
+    let body = policy_body(json!({ "allowed_publishers": ["foo"] }));
+    let response = user.put::<()>(URL, body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = policy_body(json!({ "allowed_publishers": ["foo", "bar"] }));
+    let response = other_owner.put::<()>(URL, body).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"only the allowed publishers of this crate may change its publish policy"}]}"###);
+
+    let response = other_owner.put::<()>(URL, policy_body(json!({}))).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let json = user.get::<()>(URL).await.json();
+    assert_eq!(json["publish_policy"]["allowed_publishers"], json!(["foo"]));
+
+    // The allowed publishers can still change it
+    let body = policy_body(json!({ "allowed_publishers": ["foo", "bar"] }));
+    let response = user.put::<()>(URL, body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn publish_restricted_to_allowed_publishers() {
+    let (app, _, user) = TestApp::full().with_user();
+    let krate = create_crate(&app, &user);
+    let other_owner = app.db_new_user("bar");
+    add_user_to_crate(&app, &krate, &other_owner);
+    let team_member = app.db_new_user("user-all-teams");
+
+    let body = policy_body(json!({ "allowed_publishers": ["bar", "github:test-org:core"] }));
+    let response = user.put::<()>(URL, body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let token = user.db_new_token("publish");
+    let response = token
+        .publish_crate(PublishBuilder::new("foo", "1.1.0"))
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the publish policy of this crate does not allow you to publish new versions"}]}"###);
+
+    let token = other_owner.db_new_token("publish");
+    let response = token
+        .publish_crate(PublishBuilder::new("foo", "1.1.0"))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let token = team_member.db_new_token("publish");
+    let response = token
+        .publish_crate(PublishBuilder::new("foo", "1.2.0"))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn prerelease_publishers() {
+    let (app, _, user) = TestApp::full().with_user();
+    create_crate(&app, &user);
+    let team_member = app.db_new_user("user-all-teams");
+
+    let body = policy_body(json!({ "prerelease_publishers": ["foo"] }));
+    let response = user.put::<()>(URL, body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let token = team_member.db_new_token("publish");
+    let response = token
+        .publish_crate(PublishBuilder::new("foo", "2.0.0-beta.1"))
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the publish policy of this crate does not allow you to publish prerelease versions"}]}"###);
+
+    let response = token
+        .publish_crate(PublishBuilder::new("foo", "1.1.0"))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let token = user.db_new_token("publish");
+    let response = token
+        .publish_crate(PublishBuilder::new("foo", "2.0.0-beta.1"))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn require_token_expiry() {
+    let (app, _, user) = TestApp::full().with_user();
+    create_crate(&app, &user);
+
+    let body = policy_body(json!({ "require_token_expiry": true }));
+    let response = user.put::<()>(URL, body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let token = user.db_new_token("publish");
+    let response = token
+        .publish_crate(PublishBuilder::new("foo", "1.1.0"))
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the publish policy of this crate only allows publishing with API tokens that have an expiry date"}]}"###);
+
+    let expired_at = (Utc::now() + Duration::days(1)).naive_utc();
+    let token = user.db_new_scoped_token(
+        "expiring",
+        Some(vec![CrateScope::try_from("foo").unwrap()]),
+        Some(vec![EndpointScope::PublishUpdate]),
+        Some(expired_at),
+    );
+    let response = token
+        .publish_crate(PublishBuilder::new("foo", "1.1.0"))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn require_trusted_publishing() {
+    let (app, _, user) = TestApp::full().with_user();
+    let krate = create_crate(&app, &user);
+
+    let body = policy_body(json!({ "require_trusted_publishing": true }));
+    let response = user.put::<()>(URL, body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let token = user.db_new_token("publish");
+    let response = token
+        .publish_crate(PublishBuilder::new("foo", "1.1.0"))
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the publish policy of this crate only allows publishing via trusted publishing"}]}"###);
+
+    // Simulate a token that was minted via trusted publishing
+    app.db(|conn| {
+        let trusted_publisher = NewTrustedPublisher {
+            crate_id: krate.id,
+            repository_owner: "rust-lang",
+            repository_name: "foo",
+            workflow_filename: "release.yml",
+            environment: None,
+            created_by: user.as_model().id,
+        }
+        .insert(conn)
+        .unwrap();
+
+        diesel::update(api_tokens::table.find(token.as_model().id))
+            .set(api_tokens::trusted_publisher_id.eq(trusted_publisher.id))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let response = token
+        .publish_crate(PublishBuilder::new("foo", "1.1.0"))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
@@ -0,0 +1,211 @@
+use crate::builders::{CrateBuilder, PublishBuilder};
+use crate::routes::crates::versions::yank_unyank::YankRequestHelper;
+use crate::util::{RequestHelper, TestApp};
+use crates_io::schema::{crates, users};
+use diesel::prelude::*;
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::Value;
+
+fn actions(json: &Value) -> Vec<&str> {
+    json["actions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|action| action["action"].as_str().unwrap())
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn records_version_and_ownership_actions() {
+    let (app, _, user, token) = TestApp::full().with_token();
+    let invitee = app.db_new_user("bar");
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    token.publish_crate(crate_to_publish).await.good();
+    token.yank("foo", "1.0.0").await.good();
+    token.unyank("foo", "1.0.0").await.good();
+    token.add_named_owner("foo", "bar").await.good();
+
+    let crate_id = app.db(|conn| {
+        crates::table
+            .filter(crates::name.eq("foo"))
+            .select(crates::id)
+            .first::<i32>(conn)
+            .unwrap()
+    });
+
+    let body = json!({
+        "crate_owner_invite": {
+            "invited_by_username": "",
+            "crate_name": "foo",
+            "crate_id": crate_id,
+            "created_at": "",
+            "accepted": true
+        }
+    });
+    let url = format!("/api/v1/me/crate_owner_invitations/{crate_id}");
+    let response = invitee.put::<()>(&url, body.to_string()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = json!({ "owners": ["bar"] }).to_string();
+    let response = user
+        .delete_with_body::<()>("/api/v1/crates/foo/owners", body)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = user.get::<()>("/api/v1/crates/foo/audit_log").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.json();
+
+    assert_eq!(
+        actions(&json),
+        vec![
+            "remove_owner",
+            "accept_invite",
+            "invite_owner",
+            "unyank",
+            "yank",
+            "publish"
+        ]
+    );
+    assert_eq!(json["meta"]["total"], 6);
+
+    let entries = json["actions"].as_array().unwrap();
+
+    let remove = &entries[0];
+    assert_eq!(remove["user"]["login"], "foo");
+    assert_eq!(remove["api_token"], Value::Null);
+    assert_eq!(remove["target_user"]["login"], "bar");
+
+    let accept = &entries[1];
+    assert_eq!(accept["user"]["login"], "bar");
+    assert_eq!(accept["target_user"], Value::Null);
+
+    let invite = &entries[2];
+    assert_eq!(invite["user"]["login"], "foo");
+    assert_eq!(invite["api_token"]["name"], "bar");
+    assert_eq!(invite["target_user"]["login"], "bar");
+
+    let publish = &entries[5];
+    assert_eq!(publish["version"], "1.0.0");
+    assert_eq!(publish["user"]["login"], "foo");
+    assert_eq!(publish["api_token"]["id"], token.as_model().id);
+    assert_eq!(publish["api_token"]["name"], "bar");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_owners_can_read_the_audit_log() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let another_user = app.db_new_user("bar");
+
+    app.db(|conn| CrateBuilder::new("foo", user.as_model().id).expect_build(conn));
+
+    let response = anon.get::<()>("/api/v1/crates/foo/audit_log").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"this action requires authentication"}]}"###);
+
+    let response = another_user.get::<()>("/api/v1/crates/foo/audit_log").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"only owners have permission to view the audit log"}]}"###);
+
+    let response = user.get::<()>("/api/v1/crates/unknown/audit_log").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"crate `unknown` does not exist"}]}"###);
+
+    let response = user.get::<()>("/api/v1/crates/foo/audit_log").await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn audit_log_is_paginated() {
+    let (_, _, user, token) = TestApp::full().with_token();
+
+    for version in ["1.0.0", "1.0.1", "1.0.2"] {
+        let crate_to_publish = PublishBuilder::new("foo", version);
+        token.publish_crate(crate_to_publish).await.good();
+    }
+
+    let response = user
+        .get_with_query::<()>("/api/v1/crates/foo/audit_log", "per_page=2")
+        .await;
+    let json = response.json();
+    assert_eq!(json["actions"].as_array().unwrap().len(), 2);
+    assert_eq!(json["actions"][0]["version"], "1.0.2");
+    assert_eq!(json["meta"]["total"], 3);
+    assert_eq!(json["meta"]["next_page"], "?per_page=2&page=2");
+    assert_eq!(json["meta"]["prev_page"], Value::Null);
+
+    let response = user
+        .get_with_query::<()>("/api/v1/crates/foo/audit_log", "per_page=2&page=2")
+        .await;
+    let json = response.json();
+    assert_eq!(json["actions"].as_array().unwrap().len(), 1);
+    assert_eq!(json["actions"][0]["version"], "1.0.0");
+    assert_eq!(json["meta"]["next_page"], Value::Null);
+    assert_eq!(json["meta"]["prev_page"], "?per_page=2&page=1");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn admins_can_read_the_audit_log_of_deleted_crates() {
+    let (app, _, user, token) = TestApp::full().with_token();
+    let admin = app.db_new_user("admin");
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    token.publish_crate(crate_to_publish).await.good();
+
+    app.db(|conn| {
+        diesel::update(users::table.find(admin.as_model().id))
+            .set(users::is_admin.eq(true))
+            .execute(conn)
+            .unwrap();
+
+        diesel::delete(crates::table.filter(crates::name.eq("foo")))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let response = user.get::<()>("/api/v1/crates/foo/audit_log").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = admin.get::<()>("/api/v1/crates/foo/audit_log").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(actions(&response.json()), vec!["publish"]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn new_owners_of_a_reused_name_cannot_read_the_old_log() {
+    let (app, _, _, token) = TestApp::full().with_token();
+    let admin = app.db_new_user("admin");
+    let new_owner = app.db_new_user("bar");
+    let new_token = new_owner.db_new_token("bar");
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    token.publish_crate(crate_to_publish).await.good();
+    token.yank("foo", "1.0.0").await.good();
+
+    app.db(|conn| {
+        diesel::update(users::table.find(admin.as_model().id))
+            .set(users::is_admin.eq(true))
+            .execute(conn)
+            .unwrap();
+
+        diesel::delete(crates::table.filter(crates::name.eq("foo")))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let crate_to_publish = PublishBuilder::new("foo", "2.0.0");
+    new_token.publish_crate(crate_to_publish).await.good();
+
+    let response = new_owner.get::<()>("/api/v1/crates/foo/audit_log").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.json();
+    assert_eq!(actions(&json), vec!["publish"]);
+    assert_eq!(json["actions"][0]["version"], "2.0.0");
+    assert_eq!(json["meta"]["total"], 1);
+
+    let response = admin.get::<()>("/api/v1/crates/foo/audit_log").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(actions(&response.json()), vec!["publish"]);
+}
@@ -0,0 +1,160 @@
+use crate::builders::{CrateBuilder, PublishBuilder, VersionBuilder};
+use crate::util::{RequestHelper, TestApp};
+use chrono::{Duration, Utc};
+use crates_io::schema::{crates, reserved_crate_names};
+use diesel::prelude::*;
+use http::StatusCode;
+use insta::assert_snapshot;
+
+fn age_crate(app: &TestApp, name: &str, hours: i64) {
+    app.db(|conn| {
+        let created_at = Utc::now().naive_utc() - Duration::hours(hours);
+        diesel::update(crates::table.filter(crates::name.eq(name)))
+            .set(crates::created_at.eq(created_at))
+            .execute(conn)
+            .unwrap();
+    });
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn delete_recent_crate() {
+    let (app, _, user, token) = TestApp::full().with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    token.publish_crate(crate_to_publish).await.good();
+    assert!(!app.stored_files().await.is_empty());
+
+    let response = user.delete::<()>("/api/v1/crates/foo").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_snapshot!(response.text(), @r###"{"ok":true}"###);
+
+    app.run_pending_background_jobs().await;
+
+    let response = user.get::<()>("/api/v1/crates/foo").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    assert!(!app.upstream_index().crate_exists("foo").unwrap());
+    assert!(app.stored_files().await.is_empty());
+
+    let response = user.get::<()>("/api/v1/crates/foo/audit_log").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn deleted_crate_name_is_reserved_temporarily() {
+    let (app, _, user, token) = TestApp::full().with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    token.publish_crate(crate_to_publish).await.good();
+
+    let response = user.delete::<()>("/api/v1/crates/foo").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    app.run_pending_background_jobs().await;
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    let response = token.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"cannot upload a crate with a reserved name"}]}"###);
+
+    // Pretend that the cooldown has passed
+    app.db(|conn| {
+        let expires_at = Utc::now().naive_utc() - Duration::hours(1);
+        diesel::update(reserved_crate_names::table.filter(reserved_crate_names::name.eq("foo")))
+            .set(reserved_crate_names::expires_at.eq(expires_at))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    token.publish_crate(crate_to_publish).await.good();
+
+    // Deleting the crate again replaces the expired reservation
+    let response = user.delete::<()>("/api/v1/crates/foo").await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_owners_can_delete_crates() {
+    let (app, anon, user) = TestApp::full().with_user();
+    let another_user = app.db_new_user("bar");
+
+    app.db(|conn| CrateBuilder::new("foo", user.as_model().id).expect_build(conn));
+
+    let response = anon.delete::<()>("/api/v1/crates/foo").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"this action requires authentication"}]}"###);
+
+    let response = another_user.delete::<()>("/api/v1/crates/foo").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"only owners have permission to delete crates"}]}"###);
+
+    let response = user.delete::<()>("/api/v1/crates/unknown").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"crate `unknown` does not exist"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn api_tokens_cannot_delete_crates() {
+    let (app, _, user, token) = TestApp::full().with_token();
+
+    app.db(|conn| CrateBuilder::new("foo", user.as_model().id).expect_build(conn));
+
+    let response = token.delete::<()>("/api/v1/crates/foo").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"this action can only be performed on the crates.io website"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn old_crates_without_usage_can_be_deleted() {
+    let (app, _, user) = TestApp::full().with_user();
+
+    app.db(|conn| CrateBuilder::new("foo", user.as_model().id).expect_build(conn));
+    age_crate(&app, "foo", 24 * 30);
+
+    let response = user.delete::<()>("/api/v1/crates/foo").await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn crates_with_reverse_dependencies_cannot_be_deleted() {
+    let (app, _, user) = TestApp::full().with_user();
+    let user_id = user.as_model().id;
+
+    app.db(|conn| {
+        let krate = CrateBuilder::new("foo", user_id).expect_build(conn);
+        CrateBuilder::new("bar", user_id)
+            .version(VersionBuilder::new("1.0.0").dependency(&krate, None))
+            .expect_build(conn);
+    });
+
+    // Reverse dependencies block the deletion even within the grace period
+    let response = user.delete::<()>("/api/v1/crates/foo").await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"only crates without reverse dependencies can be deleted"}]}"###);
+
+    age_crate(&app, "foo", 24 * 30);
+    let response = user.delete::<()>("/api/v1/crates/foo").await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"only crates without reverse dependencies can be deleted"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn old_crates_with_many_downloads_cannot_be_deleted() {
+    let (app, _, user) = TestApp::full().with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.as_model().id)
+            .downloads(1000)
+            .expect_build(conn);
+    });
+    age_crate(&app, "foo", 24 * 30);
+
+    let response = user.delete::<()>("/api/v1/crates/foo").await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"only crates with less than 500 downloads can be deleted after the grace period of 72 hours"}]}"###);
+
+    // Recent crates can be deleted regardless of their downloads
+    age_crate(&app, "foo", 1);
+    let response = user.delete::<()>("/api/v1/crates/foo").await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
@@ -1,6 +1,7 @@
 use crate::builders::{CrateBuilder, VersionBuilder};
 use crate::util::{RequestHelper, TestApp};
-use crate::{new_category, new_user};
+use crate::{add_team_to_crate, new_category, new_team, new_user};
+use chrono::{Duration, Utc};
 use crates_io::models::Category;
 use crates_io::schema::crates;
 use diesel::{dsl::*, prelude::*, update};
@@ -729,6 +730,312 @@ async fn index_include_yanked() {
     }
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn index_search_qualifiers() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+    let another_user = app.db_new_user("another");
+    let another_user = another_user.as_model();
+
+    app.db(|conn| {
+        new_category(
+            "Network programming",
+            "network-programming",
+            "Network crates",
+        )
+        .create_or_update(conn)
+        .unwrap();
+
+        let team = new_team("github:rust-lang:libs")
+            .create_or_update(conn)
+            .unwrap();
+
+        CrateBuilder::new("async_http", user.id)
+            .description("An async http client")
+            .keyword("async")
+            .keyword("http")
+            .category("network-programming")
+            .version(
+                VersionBuilder::new("1.0.0")
+                    .license(Some("MIT OR Apache-2.0"))
+                    .rust_version("1.65"),
+            )
+            .expect_build(conn);
+
+        CrateBuilder::new("sync_http", user.id)
+            .description("A blocking http client")
+            .keyword("http")
+            .category("network-programming")
+            .version(
+                VersionBuilder::new("1.0.0")
+                    .license(Some("Apache-2.0"))
+                    .rust_version("1.75.1"),
+            )
+            .expect_build(conn);
+
+        let krate = CrateBuilder::new("async_runtime", another_user.id)
+            .keyword("async")
+            .version(VersionBuilder::new("1.0.0").license(Some("MIT")))
+            .version(VersionBuilder::new("2.0.0").yanked(true))
+            .expect_build(conn);
+
+        add_team_to_crate(&team, &krate, another_user, conn).unwrap();
+
+        CrateBuilder::new("all_yanked", another_user.id)
+            .keyword("async")
+            .version(VersionBuilder::new("1.0.0").yanked(true))
+            .expect_build(conn);
+    });
+
+    let names = |json: &crate::CrateList| {
+        json.crates
+            .iter()
+            .map(|krate| krate.name.as_str())
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+
+    for json in search_both(&anon, "q=keyword:ASYNC").await {
+        assert_eq!(names(&json), "all_yanked,async_http,async_runtime");
+        assert_eq!(json.meta.total, 3);
+    }
+
+    for json in search_both(&anon, "q=keyword:async+keyword:http").await {
+        assert_eq!(names(&json), "async_http");
+    }
+
+    for json in search_both(&anon, "q=client+keyword:http+-yanked").await {
+        assert_eq!(names(&json), "async_http,sync_http");
+    }
+
+    for json in search_both(&anon, "q=keyword:async+-yanked").await {
+        assert_eq!(names(&json), "async_http,async_runtime");
+    }
+
+    for json in search_both(&anon, "q=category:network-programming+blocking").await {
+        assert_eq!(names(&json), "sync_http");
+    }
+
+    for json in search_both(&anon, "q=owner:foo").await {
+        assert_eq!(names(&json), "async_http,sync_http");
+    }
+
+    for json in search_both(&anon, "q=owner:rust-lang").await {
+        assert_eq!(names(&json), "async_runtime");
+    }
+
+    for json in search_both(&anon, "q=owner:github:rust-lang:libs").await {
+        assert_eq!(names(&json), "async_runtime");
+    }
+
+    for json in search_both(&anon, "q=license:MIT").await {
+        assert_eq!(names(&json), "async_http,async_runtime");
+    }
+
+    for json in search_both(&anon, "q=license:Apache-2.0+keyword:http").await {
+        assert_eq!(names(&json), "async_http,sync_http");
+    }
+
+    for json in search_both(&anon, "q=rust-version:%3C%3D1.70").await {
+        assert_eq!(names(&json), "async_http");
+    }
+
+    for json in search_both(&anon, "q=rust-version:1.75").await {
+        assert_eq!(names(&json), "async_http");
+    }
+
+    for json in search_both(&anon, "q=rust-version:%3E1.70").await {
+        assert_eq!(names(&json), "sync_http");
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn index_license_rust_version_and_update_filters() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    let current_time = Utc::now().naive_utc();
+    app.db(|conn| {
+        CrateBuilder::new("old_msrv", user.id)
+            .version(
+                VersionBuilder::new("1.0.0")
+                    .license(Some("MIT"))
+                    .rust_version("1.56"),
+            )
+            .updated_at(current_time - Duration::days(400))
+            .expect_build(conn);
+
+        CrateBuilder::new("new_msrv", user.id)
+            .version(
+                VersionBuilder::new("1.0.0")
+                    .license(Some("MIT"))
+                    .rust_version("1.56"),
+            )
+            // Only the default version is considered
+            .version(
+                VersionBuilder::new("2.0.0")
+                    .license(Some("Apache-2.0 AND BSD-3-Clause"))
+                    .rust_version("1.77"),
+            )
+            .updated_at(current_time - Duration::days(10))
+            .expect_build(conn);
+
+        CrateBuilder::new("copyleft", user.id)
+            .version(
+                VersionBuilder::new("1.0.0")
+                    .license(Some("GPL-3.0-only"))
+                    .rust_version("1.60"),
+            )
+            .updated_at(current_time - Duration::days(100))
+            .expect_build(conn);
+
+        CrateBuilder::new("no_metadata", user.id)
+            .updated_at(current_time - Duration::days(1))
+            .expect_build(conn);
+    });
+
+    let names = |json: &crate::CrateList| {
+        json.crates
+            .iter()
+            .map(|krate| krate.name.as_str())
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+
+    for json in search_both(&anon, "rust_version=1.70").await {
+        assert_eq!(names(&json), "copyleft,old_msrv");
+    }
+
+    for json in search_both(&anon, "rust_version=%3E%3D1.60").await {
+        assert_eq!(names(&json), "copyleft,new_msrv");
+    }
+
+    for json in search_both(&anon, "license=MIT,Apache-2.0").await {
+        assert_eq!(names(&json), "old_msrv");
+    }
+
+    for json in search_both(&anon, "license=MIT,Apache-2.0,BSD-3-Clause").await {
+        assert_eq!(names(&json), "new_msrv,old_msrv");
+    }
+
+    for json in search_both(&anon, "updated_after=180d").await {
+        assert_eq!(names(&json), "copyleft,new_msrv,no_metadata");
+    }
+
+    for json in search_both(&anon, "updated_after=180d&updated_before=5d").await {
+        assert_eq!(names(&json), "copyleft,new_msrv");
+    }
+
+    let query = format!(
+        "updated_before={}",
+        (current_time - Duration::days(365)).format("%Y-%m-%d")
+    );
+    for json in search_both(&anon, &query).await {
+        assert_eq!(names(&json), "old_msrv");
+    }
+
+    for json in search_both(&anon, "q=updated:%3E30d+rust-version:1.70").await {
+        assert_eq!(json.meta.total, 0);
+    }
+
+    for json in search_both(
+        &anon,
+        "q=updated:%3C30d&rust_version=1.70&license=GPL-3.0-only",
+    )
+    .await
+    {
+        assert_eq!(names(&json), "copyleft");
+    }
+
+    let response = anon
+        .get_with_query::<()>("/api/v1/crates", "updated_after=yesterday")
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_json_snapshot!(response.json(), @r###"
+    {
+      "errors": [
+        {
+          "detail": "invalid update time `yesterday`, expected a date like `2024-01-31` or a number of days like `180d`"
+        }
+      ]
+    }
+    "###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn index_license_filter_with_lax_expressions() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("slash", user.id)
+            .version(VersionBuilder::new("1.0.0").license(Some("MIT/Apache-2.0")))
+            .expect_build(conn);
+
+        CrateBuilder::new("gpl_plus", user.id)
+            .version(VersionBuilder::new("1.0.0").license(Some("GPL-3.0+")))
+            .expect_build(conn);
+
+        // Legacy license expressions that can't be parsed are never matched
+        CrateBuilder::new("lowercase", user.id)
+            .version(VersionBuilder::new("1.0.0").license(Some("MIT or Apache-2.0")))
+            .expect_build(conn);
+    });
+
+    let names = |json: &crate::CrateList| {
+        json.crates
+            .iter()
+            .map(|krate| krate.name.as_str())
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+
+    for json in search_both(&anon, "license=Apache-2.0").await {
+        assert_eq!(names(&json), "slash");
+    }
+
+    for json in search_both(&anon, "q=license:MIT").await {
+        assert_eq!(names(&json), "slash");
+    }
+
+    for json in search_both(&anon, "license=GPL-3.0-or-later").await {
+        assert_eq!(names(&json), "gpl_plus");
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn index_invalid_search_qualifiers() {
+    let (_app, anon) = TestApp::init().empty();
+
+    let response = anon
+        .get_with_query::<()>("/api/v1/crates", "q=http+license:mit")
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_json_snapshot!(response.json(), @r###"
+    {
+      "errors": [
+        {
+          "detail": "unknown SPDX license identifier `mit`"
+        }
+      ]
+    }
+    "###);
+
+    let response = anon
+        .get_with_query::<()>("/api/v1/crates", "q=http+foo:bar")
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_json_snapshot!(response.json(), @r###"
+    {
+      "errors": [
+        {
+          "detail": "unknown search qualifier `foo`, supported qualifiers are: keyword, category, owner, license, rust-version, updated"
+        }
+      ]
+    }
+    "###);
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn yanked_versions_are_not_considered_for_max_version() {
     let (app, anon, user) = TestApp::init().with_user();
@@ -1,3 +1,5 @@
+mod audit_log;
+mod delete;
 pub mod downloads;
 mod following;
 mod list;
@@ -0,0 +1,234 @@
+use crate::builders::{CrateBuilder, DependencyBuilder, PublishBuilder, VersionBuilder};
+use crate::routes::crates::versions::yank_unyank::YankRequestHelper;
+use crate::util::{RequestHelper, TestApp};
+use crates_io::models::DependencyKind;
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::Value;
+
+const URL: &str = "/api/v1/crates/root/1.0.0/dependency_tree";
+
+fn resolved(json: &Value) -> Vec<String> {
+    json["crates"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|krate| format!("{}@{}", krate["name"], krate["version"]).replace('"', ""))
+        .collect()
+}
+
+fn features<'a>(json: &'a Value, name: &str) -> Vec<&'a str> {
+    let krate = json["crates"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|krate| krate["name"] == name)
+        .unwrap();
+
+    krate["features"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|feature| feature.as_str().unwrap())
+        .collect()
+}
+
+async fn publish_crates(token: &impl RequestHelper) {
+    for version in ["1.0.0", "1.1.0", "1.2.0", "2.0.0"] {
+        let crate_to_publish = PublishBuilder::new("dep_a", version).feature("std", &[]);
+        token.publish_crate(crate_to_publish).await.good();
+    }
+    token.yank("dep_a", "1.2.0").await.good();
+
+    let crate_to_publish = PublishBuilder::new("dep_opt", "1.0.0").dependency(
+        DependencyBuilder::new("dep_a")
+            .version_req("^1")
+            .add_feature("std"),
+    );
+    token.publish_crate(crate_to_publish).await.good();
+
+    token
+        .publish_crate(PublishBuilder::new("dep_build", "0.1.0"))
+        .await
+        .good();
+    token
+        .publish_crate(PublishBuilder::new("dep_dev", "1.0.0"))
+        .await
+        .good();
+
+    let crate_to_publish = PublishBuilder::new("root", "1.0.0")
+        .feature("default", &["json"])
+        .feature("json", &["dep:dep_opt"])
+        .dependency(
+            DependencyBuilder::new("dep_a")
+                .version_req("^1.0")
+                .no_default_features(),
+        )
+        .dependency(
+            DependencyBuilder::new("dep_build")
+                .version_req("^0.1")
+                .kind(DependencyKind::Build),
+        )
+        .dependency(
+            DependencyBuilder::new("dep_dev")
+                .version_req("^1")
+                .kind(DependencyKind::Dev),
+        )
+        .dependency(
+            DependencyBuilder::new("dep_opt")
+                .version_req("^1")
+                .optional(),
+        );
+    token.publish_crate(crate_to_publish).await.good();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn resolves_transitive_dependencies() {
+    let (_, anon, _, token) = TestApp::full().with_token();
+    publish_crates(&token).await;
+
+    let response = anon.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.json();
+
+    assert_eq!(
+        resolved(&json),
+        vec![
+            "root@1.0.0",
+            "dep_a@1.1.0",
+            "dep_build@0.1.0",
+            "dep_opt@1.0.0"
+        ]
+    );
+    assert_eq!(features(&json, "root"), vec!["default", "json"]);
+    assert_eq!(features(&json, "dep_a"), vec!["std"]);
+
+    let edge = &json["crates"][0]["dependencies"][0];
+    assert_eq!(edge["crate_id"], "dep_a");
+    assert_eq!(edge["req"], "^1.0");
+    assert_eq!(edge["kind"], "normal");
+    assert_eq!(edge["version"], "1.1.0");
+
+    let edge = &json["crates"][0]["dependencies"][1];
+    assert_eq!(edge["crate_id"], "dep_build");
+    assert_eq!(edge["kind"], "build");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn honors_features_and_dependency_kinds() {
+    let (_, anon, _, token) = TestApp::full().with_token();
+    publish_crates(&token).await;
+
+    let response = anon
+        .get_with_query::<()>(URL, "default_features=false")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.json();
+    assert_eq!(
+        resolved(&json),
+        vec!["root@1.0.0", "dep_a@1.1.0", "dep_build@0.1.0"]
+    );
+    assert!(features(&json, "root").is_empty());
+    assert!(features(&json, "dep_a").is_empty());
+
+    let response = anon
+        .get_with_query::<()>(URL, "default_features=false&features=json")
+        .await;
+    let json = response.json();
+    assert_eq!(features(&json, "root"), vec!["json"]);
+    assert_eq!(json["crates"].as_array().unwrap().len(), 4);
+
+    let response = anon.get_with_query::<()>(URL, "include_dev=yes").await;
+    let json = response.json();
+    assert_eq!(
+        resolved(&json),
+        vec![
+            "root@1.0.0",
+            "dep_a@1.1.0",
+            "dep_build@0.1.0",
+            "dep_dev@1.0.0",
+            "dep_opt@1.0.0"
+        ]
+    );
+
+    let response = anon.get_with_query::<()>(URL, "features=unknown").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"crate `root` does not have a feature `unknown`"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn filters_platform_specific_dependencies() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let windows = CrateBuilder::new("windows_only", user.id).expect_build(conn);
+        let unix = CrateBuilder::new("unix_only", user.id).expect_build(conn);
+        let linux = CrateBuilder::new("linux_only", user.id).expect_build(conn);
+        CrateBuilder::new("root", user.id)
+            .version(
+                VersionBuilder::new("1.0.0")
+                    .dependency(&windows, Some("cfg(windows)"))
+                    .dependency(&unix, Some("cfg(unix)"))
+                    .dependency(&linux, Some("x86_64-unknown-linux-gnu")),
+            )
+            .expect_build(conn);
+    });
+
+    let response = anon.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json()["crates"].as_array().unwrap().len(), 4);
+
+    let response = anon
+        .get_with_query::<()>(URL, "target=x86_64-unknown-linux-gnu")
+        .await;
+    assert_eq!(
+        resolved(&response.json()),
+        vec!["root@1.0.0", "linux_only@0.99.0", "unix_only@0.99.0"]
+    );
+
+    let response = anon
+        .get_with_query::<()>(URL, "target=x86_64-pc-windows-msvc")
+        .await;
+    assert_eq!(
+        resolved(&response.json()),
+        vec!["root@1.0.0", "windows_only@0.99.0"]
+    );
+
+    let response = anon.get_with_query::<()>(URL, "target=unknown").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"unknown target `unknown`"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unresolvable_requirements_and_missing_versions() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let dep = CrateBuilder::new("yanked_dep", user.id)
+            .version(VersionBuilder::new("1.0.0").yanked(true))
+            .expect_build(conn);
+        CrateBuilder::new("root", user.id)
+            .version(VersionBuilder::new("1.0.0").dependency(&dep, None))
+            .expect_build(conn);
+    });
+
+    let response = anon.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.json();
+    assert_eq!(resolved(&json), vec!["root@1.0.0"]);
+    assert_eq!(json["crates"][0]["dependencies"][0]["version"], Value::Null);
+
+    let response = anon
+        .get::<()>("/api/v1/crates/root/2.0.0/dependency_tree")
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"crate `root` does not have a version `2.0.0`"}]}"###);
+
+    let response = anon
+        .get::<()>("/api/v1/crates/missing/1.0.0/dependency_tree")
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"crate `missing` does not exist"}]}"###);
+}
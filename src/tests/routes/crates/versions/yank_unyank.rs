@@ -9,6 +9,14 @@ pub trait YankRequestHelper {
 
     /// Unyank the specified version of the specified crate and run all pending background jobs
     async fn unyank(&self, krate_name: &str, version: &str) -> Response<OkBool>;
+
+    /// Yank the specified version with the given reason and run all pending background jobs
+    async fn yank_with_reason(
+        &self,
+        krate_name: &str,
+        version: &str,
+        reason: serde_json::Value,
+    ) -> Response<OkBool>;
 }
 
 impl<T: RequestHelper> YankRequestHelper for T {
@@ -25,6 +33,18 @@ impl<T: RequestHelper> YankRequestHelper for T {
         self.app().run_pending_background_jobs().await;
         response
     }
+
+    async fn yank_with_reason(
+        &self,
+        krate_name: &str,
+        version: &str,
+        reason: serde_json::Value,
+    ) -> Response<OkBool> {
+        let url = format!("/api/v1/crates/{krate_name}/{version}/yank");
+        let response = self.delete_with_body(&url, reason.to_string()).await;
+        self.app().run_pending_background_jobs().await;
+        response
+    }
 }
 
 #[tokio::test(flavor = "multi_thread")]
@@ -92,6 +112,147 @@ async fn unyank_records_an_audit_action() {
     assert_eq!(action.user.id, token.as_model().user_id);
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn yank_with_reason() {
+    let (_, anon, _, token) = TestApp::full().with_token();
+
+    let crate_to_publish = PublishBuilder::new("fyk", "1.0.0");
+    token.publish_crate(crate_to_publish).await.good();
+
+    // Yanking again with only an advisory keeps the previous message
+    let reason = json!({ "advisory": "RUSTSEC-2024-0001" });
+    token.yank_with_reason("fyk", "1.0.0", reason).await.good();
+
+    let json = anon.show_version("fyk", "1.0.0").await;
+    assert!(json.version.yanked);
+    assert_eq!(
+        json.version.yank_message.as_deref(),
+        Some("contains a use-after-free")
+    );
+    assert_eq!(
+        json.version.yank_advisory.as_deref(),
+        Some("RUSTSEC-2024-0001")
+    );
+
+    let action = &json.version.audit_actions[1];
+    assert_eq!(action.action, "yank");
+    assert_eq!(action.message.as_deref(), Some("contains a use-after-free"));
+    assert_eq!(action.advisory.as_deref(), Some("RUSTSEC-2024-0001"));
+
+    let json = anon.get::<()>("/api/v1/crates/fyk/versions").await.json();
+    assert_eq!(
+        json["versions"][0]["yank_message"],
+        "contains a use-after-free"
+    );
+    assert_eq!(json["versions"][0]["yank_advisory"], "RUSTSEC-2024-0001");
+
+    // Unyanking clears the reason on the version, but not in the audit log
+    token.unyank("fyk", "1.0.0").await.good();
+
+    let json = anon.show_version("fyk", "1.0.0").await;
+    assert!(!json.version.yanked);
+    assert_eq!(json.version.yank_message, None);
+    assert_eq!(json.version.yank_advisory, None);
+
+    let actions = json.version.audit_actions;
+    assert_eq!(actions.len(), 3);
+    assert_eq!(
+        actions[1].message.as_deref(),
+        Some("contains a use-after-free")
+    );
+    assert_eq!(actions[1].advisory.as_deref(), Some("RUSTSEC-2024-0001"));
+    assert_eq!(actions[2].action, "unyank");
+    assert_eq!(actions[2].message, None);
+    assert_eq!(actions[2].advisory, None);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn yank_again_updates_reason() {
+    let (_, anon, user, token) = TestApp::full().with_token();
+
+    let crate_to_publish = PublishBuilder::new("fyk", "1.0.0");
+    token.publish_crate(crate_to_publish).await.good();
+
+    let reason = json!({ "message": "contains a use-after-free" });
+    token.yank_with_reason("fyk", "1.0.0", reason).await.good();
+
+    // Yanking again without a reason keeps the previous one
+    token.yank("fyk", "1.0.0").await.good();
+
+    let json = anon.show_version("fyk", "1.0.0").await;
+    assert_eq!(
+        json.version.yank_message.as_deref(),
+        Some("contains a use-after-free")
+    );
+    assert_eq!(json.version.audit_actions.len(), 2);
+
+    // Yanking again with only an advisory keeps the previous message
+    let reason = json!({ "advisory": "RUSTSEC-2024-0001" });
+    token.yank_with_reason("fyk", "1.0.0", reason).await.good();
+
+    let json = anon.show_version("fyk", "1.0.0").await;
+    assert!(json.version.yanked);
+    assert_eq!(
+        json.version.yank_message.as_deref(),
+        Some("contains a use-after-free")
+    );
+    assert_eq!(
+        json.version.yank_advisory.as_deref(),
+        Some("RUSTSEC-2024-0001")
+    );
+
+    let actions = json.version.audit_actions;
+    assert_eq!(actions.len(), 3);
+    assert_eq!(actions[2].action, "yank");
+    assert_eq!(
+        actions[2].message.as_deref(),
+        Some("contains a use-after-free")
+    );
+    assert_eq!(actions[2].advisory.as_deref(), Some("RUSTSEC-2024-0001"));
+
+    let json = user.get::<()>("/api/v1/crates/fyk/audit_log").await.json();
+    assert_eq!(json["actions"][0]["action"], "yank");
+    assert_eq!(
+        json["actions"][0]["details"],
+        "contains a use-after-free (RUSTSEC-2024-0001)"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn yank_with_invalid_reason() {
+    let (_, anon, _, token) = TestApp::full().with_token();
+
+    let crate_to_publish = PublishBuilder::new("fyk", "1.0.0");
+    token.publish_crate(crate_to_publish).await.good();
+
+    let reason = json!({ "advisory": "CVE-2024-1234" });
+    let response = token.yank_with_reason("fyk", "1.0.0", reason).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "invalid advisory ID `CVE-2024-1234`, expected a `RUSTSEC-YYYY-NNNN` or `GHSA-xxxx-xxxx-xxxx` identifier" }] })
+    );
+
+    let reason = json!({ "message": "  " });
+    let response = token.yank_with_reason("fyk", "1.0.0", reason).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "yank message must not be empty" }] })
+    );
+
+    let reason = json!({ "message": "a".repeat(1001) });
+    let response = token.yank_with_reason("fyk", "1.0.0", reason).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "yank message must not be longer than 1000 characters" }] })
+    );
+
+    let json = anon.show_version("fyk", "1.0.0").await;
+    assert!(!json.version.yanked);
+}
+
 mod auth {
     use super::*;
     use crate::util::{MockAnonymousUser, MockCookieUser};
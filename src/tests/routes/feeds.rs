@@ -0,0 +1,206 @@
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::util::{MockRequestExt, RequestHelper, TestApp};
+use crate::{add_team_to_crate, new_team};
+use chrono::NaiveDate;
+use crates_io::schema::versions;
+use diesel::prelude::*;
+use http::{header, StatusCode};
+
+fn date(day: u32) -> chrono::NaiveDateTime {
+    NaiveDate::from_ymd_opt(2024, 4, day)
+        .unwrap()
+        .and_hms_opt(12, 0, 0)
+        .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn crate_feed() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.id)
+            .description("Fast & small")
+            .version(VersionBuilder::new("1.0.0").created_at(date(1)))
+            .version(
+                VersionBuilder::new("1.1.0")
+                    .created_at(date(2))
+                    .yanked(true),
+            )
+            .expect_build(conn);
+
+        CrateBuilder::new("bar", user.id)
+            .version(VersionBuilder::new("2.0.0").created_at(date(3)))
+            .expect_build(conn);
+    });
+
+    let response = anon.get::<()>("/api/v1/feeds/crates/foo").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[header::CONTENT_TYPE],
+        "application/atom+xml; charset=utf-8"
+    );
+    assert_eq!(
+        response.headers()[header::LAST_MODIFIED],
+        "Tue, 02 Apr 2024 12:00:00 GMT"
+    );
+    assert!(response.headers().contains_key(header::ETAG));
+
+    let body = response.text();
+    assert!(body.contains("<title>New versions of foo</title>"));
+    assert!(body.contains("<updated>2024-04-02T12:00:00Z</updated>"));
+    assert!(body.contains("<title>foo 1.0.0</title>"));
+    assert!(body.contains("<title>foo 1.1.0 (yanked)</title>"));
+    assert!(body.contains("<link href=\"https://crates.io/crates/foo/1.0.0\"/>"));
+    assert!(body.contains("<summary>Fast &amp; small</summary>"));
+    assert!(!body.contains("bar"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn crate_feed_not_found() {
+    let (_, anon) = TestApp::init().empty();
+
+    let response = anon.get::<()>("/api/v1/feeds/crates/unknown").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn crate_feed_conditional_requests() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.id)
+            .version(VersionBuilder::new("1.0.0").created_at(date(1)))
+            .expect_build(conn);
+    });
+
+    let response = anon.get::<()>("/api/v1/feeds/crates/foo").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let etag = response.headers()[header::ETAG]
+        .to_str()
+        .unwrap()
+        .to_string();
+
+    let mut request = anon.get_request("/api/v1/feeds/crates/foo");
+    request.header(header::IF_NONE_MATCH, &etag);
+    let response = anon.run::<()>(request).await;
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(response.headers()[header::ETAG], etag.as_str());
+    assert_eq!(response.text(), "");
+
+    let mut request = anon.get_request("/api/v1/feeds/crates/foo");
+    request.header(header::IF_NONE_MATCH, "\"outdated\"");
+    let response = anon.run::<()>(request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let mut request = anon.get_request("/api/v1/feeds/crates/foo");
+    request.header(header::IF_MODIFIED_SINCE, "Mon, 01 Apr 2024 12:00:00 GMT");
+    let response = anon.run::<()>(request).await;
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+    let mut request = anon.get_request("/api/v1/feeds/crates/foo");
+    request.header(header::IF_MODIFIED_SINCE, "Sun, 31 Mar 2024 12:00:00 GMT");
+    let response = anon.run::<()>(request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Yanking a version modifies the feed as well
+    app.db(|conn| {
+        diesel::update(versions::table)
+            .set(versions::yanked.eq(true))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let mut request = anon.get_request("/api/v1/feeds/crates/foo");
+    request.header(header::IF_MODIFIED_SINCE, "Mon, 01 Apr 2024 12:00:00 GMT");
+    let response = anon.run::<()>(request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response
+        .text()
+        .contains("<title>foo 1.0.0 (yanked)</title>"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn user_feed() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+    let other = app.db_new_user("other");
+    let other = other.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.id)
+            .version(VersionBuilder::new("1.0.0").created_at(date(1)))
+            .expect_build(conn);
+
+        CrateBuilder::new("bar", other.id)
+            .version(VersionBuilder::new("2.0.0").created_at(date(2)))
+            .expect_build(conn);
+    });
+
+    let url = format!("/api/v1/feeds/users/{}", user.gh_login);
+    let response = anon.get::<()>(&url).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = response.text();
+    assert!(body.contains("<title>foo 1.0.0</title>"));
+    assert!(!body.contains("bar"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn team_feed() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let team = new_team("github:test-org:core")
+            .create_or_update(conn)
+            .unwrap();
+
+        let krate = CrateBuilder::new("foo", user.id)
+            .version(VersionBuilder::new("1.0.0").created_at(date(1)))
+            .expect_build(conn);
+
+        CrateBuilder::new("bar", user.id)
+            .version(VersionBuilder::new("2.0.0").created_at(date(2)))
+            .expect_build(conn);
+
+        add_team_to_crate(&team, &krate, user, conn).unwrap();
+    });
+
+    let response = anon
+        .get::<()>("/api/v1/feeds/teams/github:test-org:core")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = response.text();
+    assert!(body.contains("<title>foo 1.0.0</title>"));
+    assert!(!body.contains("bar"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn updates_feed() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.id)
+            .version(VersionBuilder::new("1.0.0").created_at(date(1)))
+            .expect_build(conn);
+
+        CrateBuilder::new("bar", user.id)
+            .version(VersionBuilder::new("2.0.0").created_at(date(2)))
+            .expect_build(conn);
+    });
+
+    let response = anon.get::<()>("/api/v1/feeds/updates").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = response.text();
+    assert!(body.contains("<title>Recently published crate versions</title>"));
+    assert!(body.contains("<title>foo 1.0.0</title>"));
+    assert!(body.contains("<title>bar 2.0.0</title>"));
+
+    // newest versions come first
+    assert!(body.find("bar 2.0.0").unwrap() < body.find("foo 1.0.0").unwrap());
+}
@@ -1,7 +1,7 @@
 use crate::util::insta::{self, assert_json_snapshot};
 use crate::util::{RequestHelper, TestApp};
 use crates_io::models::token::{CrateScope, EndpointScope};
-use crates_io::models::ApiToken;
+use crates_io::models::{ApiToken, NewTeam};
 use diesel::prelude::*;
 use googletest::prelude::*;
 use http::StatusCode;
@@ -270,3 +270,204 @@ async fn create_token_with_expiry_date() {
         ".api_token.token" => insta::api_token_redaction(),
     });
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn create_team_token() {
+    let (app, _) = TestApp::init().empty();
+    let user = app.db_new_user("user-all-teams");
+    let team = app.db(|conn| {
+        NewTeam::new("github:test-org:core", 1000, 2001, None, None)
+            .create_or_update(conn)
+            .unwrap()
+    });
+
+    let json = json!({
+        "api_token": {
+            "name": "ci",
+            "endpoint_scopes": ["publish-update"],
+            "team": "github:test-org:core",
+        }
+    });
+
+    let response = user
+        .put::<()>("/api/v1/me/tokens", serde_json::to_vec(&json).unwrap())
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let tokens: Vec<ApiToken> = app.db(|conn| {
+        assert_ok!(ApiToken::belonging_to(user.as_model())
+            .select(ApiToken::as_select())
+            .load(conn))
+    });
+    assert_that!(tokens, len(eq(1)));
+    assert_eq!(tokens[0].team_id, Some(team.id));
+    assert_eq!(
+        tokens[0].endpoint_scopes,
+        Some(vec![EndpointScope::PublishUpdate])
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn create_team_token_as_non_member() {
+    let (app, _) = TestApp::init().empty();
+    let user = app.db_new_user("user-one-team");
+    app.db(|conn| {
+        NewTeam::new("github:test-org:core", 1000, 2001, None, None)
+            .create_or_update(conn)
+            .unwrap()
+    });
+
+    let json = json!({
+        "api_token": {
+            "name": "ci",
+            "endpoint_scopes": ["publish-update"],
+            "team": "github:test-org:core",
+        }
+    });
+
+    let response = user
+        .put::<()>("/api/v1/me/tokens", serde_json::to_vec(&json).unwrap())
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "only members of a team can create API tokens for it" }] })
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn create_team_token_for_unknown_team() {
+    let (app, _) = TestApp::init().empty();
+    let user = app.db_new_user("user-all-teams");
+
+    let json = json!({
+        "api_token": {
+            "name": "ci",
+            "endpoint_scopes": ["publish-update"],
+            "team": "github:test-org:core",
+        }
+    });
+
+    let response = user
+        .put::<()>("/api/v1/me/tokens", serde_json::to_vec(&json).unwrap())
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "could not find team `github:test-org:core`" }] })
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn create_team_token_without_endpoint_scopes() {
+    let (app, _) = TestApp::init().empty();
+    let user = app.db_new_user("user-all-teams");
+    app.db(|conn| {
+        NewTeam::new("github:test-org:core", 1000, 2001, None, None)
+            .create_or_update(conn)
+            .unwrap()
+    });
+
+    let json = json!({
+        "api_token": {
+            "name": "ci",
+            "team": "github:test-org:core",
+        }
+    });
+
+    let response = user
+        .put::<()>("/api/v1/me/tokens", serde_json::to_vec(&json).unwrap())
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "API tokens of a team require endpoint scopes" }] })
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn create_team_token_with_publish_new_scope() {
+    let (app, _) = TestApp::init().empty();
+    let user = app.db_new_user("user-all-teams");
+    app.db(|conn| {
+        NewTeam::new("github:test-org:core", 1000, 2001, None, None)
+            .create_or_update(conn)
+            .unwrap()
+    });
+
+    let json = json!({
+        "api_token": {
+            "name": "ci",
+            "endpoint_scopes": ["publish-new", "publish-update"],
+            "team": "github:test-org:core",
+        }
+    });
+
+    let response = user
+        .put::<()>("/api/v1/me/tokens", serde_json::to_vec(&json).unwrap())
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "API tokens of a team can not have the `publish-new` scope" }] })
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn create_token_with_allowed_ips() {
+    let (app, _, user) = TestApp::init().with_user();
+
+    let json = json!({
+        "api_token": {
+            "name": "bar",
+            "allowed_ips": ["10.1.2.3/16", "192.168.0.1", "2001:db8::/32"],
+        }
+    });
+
+    let response = user
+        .put::<()>("/api/v1/me/tokens", serde_json::to_vec(&json).unwrap())
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_json_snapshot!(response.json(), {
+        ".api_token.id" => insta::any_id_redaction(),
+        ".api_token.created_at" => "[datetime]",
+        ".api_token.last_used_at" => "[datetime]",
+        ".api_token.token" => insta::api_token_redaction(),
+    });
+
+    let tokens: Vec<ApiToken> = app.db(|conn| {
+        assert_ok!(ApiToken::belonging_to(user.as_model())
+            .select(ApiToken::as_select())
+            .load(conn))
+    });
+    assert_that!(tokens, len(eq(1)));
+    assert_eq!(
+        tokens[0].allowed_ips,
+        Some(vec![
+            "10.1.0.0/16".parse().unwrap(),
+            "192.168.0.1/32".parse().unwrap(),
+            "2001:db8::/32".parse().unwrap(),
+        ])
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn create_token_with_invalid_allowed_ips() {
+    let (_, _, user) = TestApp::init().with_user();
+
+    let json = json!({
+        "api_token": {
+            "name": "bar",
+            "allowed_ips": ["10.0.0.0/8", "10.0.0.0/33"],
+        }
+    });
+
+    let response = user
+        .put::<()>("/api/v1/me/tokens", serde_json::to_vec(&json).unwrap())
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "invalid CIDR block: 10.0.0.0/33" }] })
+    );
+}
@@ -2,7 +2,7 @@ use crate::util::insta::{self, assert_json_snapshot};
 use crate::util::{RequestHelper, TestApp};
 use chrono::{Duration, Utc};
 use crates_io::models::token::{CrateScope, EndpointScope};
-use crates_io::models::ApiToken;
+use crates_io::models::{ApiToken, NewApiToken};
 use http::StatusCode;
 
 #[tokio::test(flavor = "multi_thread")]
@@ -34,25 +34,24 @@ async fn list_tokens() {
     app.db(|conn| {
         vec![
             assert_ok!(ApiToken::insert(conn, id, "bar")),
-            assert_ok!(ApiToken::insert_with_scopes(
-                conn,
-                id,
-                "baz",
-                Some(vec![
+            assert_ok!(NewApiToken {
+                user_id: id,
+                name: "baz",
+                crate_scopes: Some(vec![
                     CrateScope::try_from("serde").unwrap(),
                     CrateScope::try_from("serde-*").unwrap()
                 ]),
-                Some(vec![EndpointScope::PublishUpdate]),
-                None
-            )),
-            assert_ok!(ApiToken::insert_with_scopes(
-                conn,
-                id,
-                "qux",
-                None,
-                None,
-                Some((Utc::now() - Duration::days(1)).naive_utc()),
-            )),
+                endpoint_scopes: Some(vec![EndpointScope::PublishUpdate]),
+                ..Default::default()
+            }
+            .insert(conn)),
+            assert_ok!(NewApiToken {
+                user_id: id,
+                name: "qux",
+                expired_at: Some((Utc::now() - Duration::days(1)).naive_utc()),
+                ..Default::default()
+            }
+            .insert(conn)),
         ]
     });
 
@@ -77,25 +76,25 @@ async fn list_recently_expired_tokens() {
     app.db(|conn| {
         vec![
             assert_ok!(ApiToken::insert(conn, id, "bar")),
-            assert_ok!(ApiToken::insert_with_scopes(
-                conn,
-                id,
-                "ancient",
-                Some(vec![
+            assert_ok!(NewApiToken {
+                user_id: id,
+                name: "ancient",
+                crate_scopes: Some(vec![
                     CrateScope::try_from("serde").unwrap(),
                     CrateScope::try_from("serde-*").unwrap()
                 ]),
-                Some(vec![EndpointScope::PublishUpdate]),
-                Some((Utc::now() - Duration::days(31)).naive_utc()),
-            )),
-            assert_ok!(ApiToken::insert_with_scopes(
-                conn,
-                id,
-                "recent",
-                None,
-                None,
-                Some((Utc::now() - Duration::days(1)).naive_utc()),
-            )),
+                endpoint_scopes: Some(vec![EndpointScope::PublishUpdate]),
+                expired_at: Some((Utc::now() - Duration::days(31)).naive_utc()),
+                ..Default::default()
+            }
+            .insert(conn)),
+            assert_ok!(NewApiToken {
+                user_id: id,
+                name: "recent",
+                expired_at: Some((Utc::now() - Duration::days(1)).naive_utc()),
+                ..Default::default()
+            }
+            .insert(conn)),
         ]
     });
 
@@ -2,3 +2,5 @@ pub mod create;
 pub mod delete;
 pub mod delete_current;
 pub mod list;
+pub mod rotate;
+pub mod usage;
@@ -0,0 +1,132 @@
+use crate::util::insta::{self, assert_json_snapshot, assert_snapshot};
+use crate::util::{RequestHelper, TestApp};
+use chrono::{Duration, Utc};
+use crates_io::models::token::{CrateScope, EndpointScope};
+use crates_io::models::ApiToken;
+use crates_io::schema::api_tokens;
+use diesel::prelude::*;
+use http::StatusCode;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rotate_logged_out() {
+    let (_, anon) = TestApp::init().empty();
+    anon.put::<()>("/api/v1/me/tokens/1/rotate", "")
+        .await
+        .assert_forbidden();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rotate_with_api_token_is_forbidden() {
+    let (_, _, _, token) = TestApp::init().with_token();
+    let url = format!("/api/v1/me/tokens/{}/rotate", token.as_model().id);
+    let response = token.put::<()>(&url, "").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"cannot use an API token to rotate an API token"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rotate_other_users_token() {
+    let (app, _, _, token) = TestApp::init().with_token();
+    let user2 = app.db_new_user("baz");
+
+    let url = format!("/api/v1/me/tokens/{}/rotate", token.as_model().id);
+    let response = user2.put::<()>(&url, "").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rotate_revoked_token() {
+    let (app, _, user, token) = TestApp::init().with_token();
+    let token_id = token.as_model().id;
+    app.db(|conn| {
+        diesel::update(api_tokens::table.find(token_id))
+            .set(api_tokens::revoked.eq(true))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let url = format!("/api/v1/me/tokens/{token_id}/rotate");
+    let response = user.put::<()>(&url, "").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rotate_token() {
+    let (app, _, user) = TestApp::init().with_user();
+    let expired_at = (Utc::now() + Duration::days(30)).naive_utc();
+    let token = user.db_new_scoped_token(
+        "bar",
+        Some(vec![CrateScope::try_from("serde-*").unwrap()]),
+        Some(vec![EndpointScope::PublishUpdate]),
+        Some(expired_at),
+    );
+    let old_id = token.as_model().id;
+
+    let url = format!("/api/v1/me/tokens/{old_id}/rotate");
+    let response = user.put::<()>(&url, "").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.json();
+    assert_json_snapshot!(json, {
+        ".api_token.id" => insta::any_id_redaction(),
+        ".api_token.created_at" => "[datetime]",
+        ".api_token.expired_at" => "[datetime]",
+        ".api_token.token" => insta::api_token_redaction(),
+    });
+
+    let plaintext = json["api_token"]["token"].as_str().unwrap().to_string();
+    let (old_token, new_token) = app.db(|conn| {
+        let old_token: ApiToken = api_tokens::table
+            .find(old_id)
+            .select(ApiToken::as_select())
+            .first(conn)
+            .unwrap();
+        let new_token = ApiToken::find_by_api_token(conn, &plaintext).unwrap();
+        (old_token, new_token)
+    });
+
+    assert_ne!(new_token.id, old_id);
+    assert_eq!(new_token.expired_at, token.as_model().expired_at);
+
+    // The old token stays valid for a short while
+    let old_expired_at = old_token.expired_at.unwrap();
+    assert!(old_expired_at > Utc::now().naive_utc());
+    assert!(old_expired_at <= (Utc::now() + Duration::hours(1)).naive_utc());
+    assert!(!old_token.revoked);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rotate_token_keeps_earlier_expiry() {
+    let (app, _, user) = TestApp::init().with_user();
+    let expired_at = (Utc::now() + Duration::minutes(5)).naive_utc();
+    let token = user.db_new_scoped_token("bar", None, None, Some(expired_at));
+    let old_id = token.as_model().id;
+
+    let url = format!("/api/v1/me/tokens/{old_id}/rotate");
+    let response = user.put::<()>(&url, "").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let old_expired_at = app.db(|conn| {
+        api_tokens::table
+            .find(old_id)
+            .select(api_tokens::expired_at)
+            .first::<Option<_>>(conn)
+            .unwrap()
+    });
+    assert_eq!(old_expired_at, token.as_model().expired_at);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rotate_token_twice() {
+    let (_, _, user) = TestApp::init().with_user();
+    let expired_at = (Utc::now() + Duration::days(30)).naive_utc();
+    let token = user.db_new_scoped_token("bar", None, None, Some(expired_at));
+    let old_id = token.as_model().id;
+
+    let url = format!("/api/v1/me/tokens/{old_id}/rotate");
+    let response = user.put::<()>(&url, "").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = user.put::<()>(&url, "").await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"this token has already been rotated"}]}"###);
+}
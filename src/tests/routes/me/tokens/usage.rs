@@ -0,0 +1,70 @@
+use crate::builders::CrateBuilder;
+use crate::util::insta::assert_json_snapshot;
+use crate::util::{RequestHelper, TestApp};
+use http::StatusCode;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn usage_logged_out() {
+    let (_, anon) = TestApp::init().empty();
+    anon.get::<()>("/api/v1/me/tokens/1/usage")
+        .await
+        .assert_forbidden();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn usage_with_api_token_is_forbidden() {
+    let (_, _, _, token) = TestApp::init().with_token();
+    let url = format!("/api/v1/me/tokens/{}/usage", token.as_model().id);
+    token.get::<()>(&url).await.assert_forbidden();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn usage_includes_rejected_attempts() {
+    let (app, _, user, token) = TestApp::init().with_token();
+    let token_id = token.as_model().id;
+    let url = format!("/api/v1/me/tokens/{token_id}/usage");
+    token.get::<()>(&url).await.assert_forbidden();
+    app.wait_for_token_usages(token_id, 1).await;
+
+    let json = user.get::<()>(&url).await.json();
+    let usage = json["usage"].as_array().unwrap();
+    assert_eq!(usage.len(), 1);
+    assert_eq!(usage[0]["endpoint"], "GET /api/v1/me/tokens/:id/usage");
+    assert_eq!(
+        usage[0]["rejection_reason"],
+        "API Token authentication was explicitly disallowed for this API"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn usage_of_other_users_token() {
+    let (app, _, _, token) = TestApp::init().with_token();
+    let user2 = app.db_new_user("baz");
+
+    let url = format!("/api/v1/me/tokens/{}/usage", token.as_model().id);
+    let response = user2.get::<()>(&url).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn usage() {
+    let (app, _, user, token) = TestApp::init().with_token();
+    app.db(|conn| CrateBuilder::new("foo_crate", user.as_model().id).expect_build(conn));
+    app.db_new_user("bar");
+
+    let token_id = token.as_model().id;
+    let url = format!("/api/v1/me/tokens/{token_id}/usage");
+    let json = user.get::<()>(&url).await.json();
+    assert_eq!(json["usage"].as_array().unwrap().len(), 0);
+
+    token.add_named_owner("foo_crate", "bar").await.good();
+    app.wait_for_token_usages(token_id, 1).await;
+    token.remove_named_owner("foo_crate", "bar").await.good();
+    app.wait_for_token_usages(token_id, 2).await;
+
+    let response = user.get::<()>(&url).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_json_snapshot!(response.json(), {
+        ".usage[].used_at" => "[datetime]",
+    });
+}
@@ -14,10 +14,12 @@
 pub mod categories;
 pub mod category_slugs;
 pub mod crates;
+pub mod feeds;
 pub mod keywords;
 pub mod me;
 pub mod metrics;
 mod private;
 pub mod session;
+mod sparse_index;
 pub mod summary;
 pub mod users;
@@ -0,0 +1,136 @@
+use crate::builders::PublishBuilder;
+use crate::routes::crates::versions::yank_unyank::YankRequestHelper;
+use crate::util::{MockRequestExt, RequestHelper, TestApp};
+use crates_io::worker::jobs::SignIndexSnapshot;
+use crates_io_index::signing::SigningKey;
+use crates_io_worker::BackgroundJob;
+use http::{header, StatusCode};
+use insta::assert_snapshot;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sparse_index_is_disabled_by_default() {
+    let (_, anon) = TestApp::init().empty();
+
+    let response = anon.get::<()>("/index/config.json").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn config_json() {
+    let (_, anon) = TestApp::init()
+        .with_config(|config| config.serve_sparse_index = true)
+        .empty();
+
+    let response = anon.get::<()>("/index/config.json").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+    assert!(response.headers().contains_key(header::ETAG));
+    assert_snapshot!(response.text(), @r###"{"api":"https://crates.io","dl":"https://crates.io/api/v1/crates"}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn index_files() {
+    let (_, anon, _, token) = TestApp::full()
+        .with_config(|config| config.serve_sparse_index = true)
+        .with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo_new", "1.0.0"))
+        .await
+        .good();
+
+    let response = anon.get::<()>("/index/fo/o_/foo_new").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "text/plain");
+    let etag = response.headers()[header::ETAG]
+        .to_str()
+        .unwrap()
+        .to_string();
+
+    let line: serde_json::Value = serde_json::from_str(response.text().trim()).unwrap();
+    assert_eq!(line["name"], "foo_new");
+    assert_eq!(line["vers"], "1.0.0");
+    assert_eq!(line["yanked"], false);
+
+    let mut request = anon.get_request("/index/fo/o_/foo_new");
+    request.header(header::IF_NONE_MATCH, &etag);
+    let response = anon.run::<()>(request).await;
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(response.headers()[header::ETAG], etag.as_str());
+    assert_eq!(response.text(), "");
+
+    // Yanking changes the index file, so the old `ETag` no longer matches
+    token.yank("foo_new", "1.0.0").await.good();
+
+    let mut request = anon.get_request("/index/fo/o_/foo_new");
+    request.header(header::IF_NONE_MATCH, &etag);
+    let response = anon.run::<()>(request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_ne!(response.headers()[header::ETAG], etag.as_str());
+    assert!(response.text().contains(r#""yanked":true"#));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unknown_index_files() {
+    let (_, anon, _, token) = TestApp::full()
+        .with_config(|config| config.serve_sparse_index = true)
+        .with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo_new", "1.0.0"))
+        .await
+        .good();
+
+    for path in [
+        "/index/un/kn/unknown",
+        "/index/fo/o_/FOO_NEW",
+        "/index/xx/yy/foo_new",
+        "/index/foo_new",
+    ] {
+        let response = anon.get::<()>(path).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "{path}");
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn signed_metadata_files() {
+    let (app, anon, _, token) = TestApp::full()
+        .with_config(|config| {
+            config.serve_sparse_index = true;
+            config.index_signing_key = Some(SigningKey::generate().unwrap().0);
+        })
+        .with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo_new", "1.0.0"))
+        .await
+        .good();
+
+    let response = anon
+        .get::<()>("/index/_meta/crates/fo/o_/foo_new.json")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+    let json = response.json();
+    assert_eq!(json["signed"]["name"], "foo_new");
+
+    let response = anon.get::<()>("/index/_meta/snapshot.json").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    app.db(|conn| SignIndexSnapshot.enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+
+    let response = anon.get::<()>("/index/_meta/snapshot.json").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.json()["signed"]["files"]["foo_new"].is_object());
+
+    for path in [
+        "/index/_meta/crates/fo/o_/FOO_NEW.json",
+        "/index/_meta/crates/fo/o_/foo_new",
+        "/index/_meta/other.json",
+        "/index/_meta/root.json",
+    ] {
+        let response = anon.get::<()>(path).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "{path}");
+    }
+}
@@ -4,8 +4,8 @@ use crate::{
     new_team, OwnerTeamsResponse, RequestHelper, TestApp,
 };
 use crates_io::{
-    models::{Crate, NewTeam},
-    schema::teams,
+    models::{token::EndpointScope, ApiToken, Crate, NewTeam},
+    schema::{api_tokens, teams, users, version_owner_actions},
 };
 
 use diesel::*;
@@ -489,3 +489,235 @@ async fn crates_by_team_id_not_including_deleted_owners() {
     let json = anon.search(&format!("team_id={}", team.id)).await;
     assert_eq!(json.crates.len(), 0);
 }
+
+/// Test publishing and yanking with a token that is owned by a team
+#[tokio::test(flavor = "multi_thread")]
+async fn publish_with_team_token() {
+    let (app, _) = TestApp::full().empty();
+    let owner = app.db_new_user("user-all-teams");
+    let member = app.db_new_user("user-one-team");
+
+    let team = app.db(|conn| {
+        let krate = CrateBuilder::new("foo_team_token", owner.as_model().id).expect_build(conn);
+        let team = NewTeam::new("github:test-org:all", 1000, 2000, None, None)
+            .create_or_update(conn)
+            .unwrap();
+        add_team_to_crate(&team, &krate, owner.as_model(), conn).unwrap();
+        team
+    });
+
+    let scopes = vec![EndpointScope::PublishUpdate, EndpointScope::Yank];
+    let token = member.db_new_team_token("ci", team.id, scopes);
+
+    let crate_to_publish = PublishBuilder::new("foo_team_token", "2.0.0");
+    token.publish_crate(crate_to_publish).await.good();
+
+    let response = token
+        .delete::<()>("/api/v1/crates/foo_team_token/2.0.0/yank")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // The version actions record the team that owns the token
+    let team_ids: Vec<Option<i32>> = app.db(|conn| {
+        version_owner_actions::table
+            .select(version_owner_actions::team_id)
+            .order(version_owner_actions::id)
+            .load(conn)
+            .unwrap()
+    });
+    assert_eq!(team_ids, vec![Some(team.id), Some(team.id)]);
+}
+
+/// Test that team tokens don't depend on the team membership of the user
+/// that created them, e.g. after they left the team
+#[tokio::test(flavor = "multi_thread")]
+async fn publish_with_team_token_of_former_member() {
+    let (app, _) = TestApp::full().empty();
+    let owner = app.db_new_user("user-all-teams");
+    let former_member = app.db_new_user("foo");
+
+    let team = app.db(|conn| {
+        let krate = CrateBuilder::new("foo_team_token", owner.as_model().id).expect_build(conn);
+        let team = NewTeam::new("github:test-org:all", 1000, 2000, None, None)
+            .create_or_update(conn)
+            .unwrap();
+        add_team_to_crate(&team, &krate, owner.as_model(), conn).unwrap();
+        team
+    });
+
+    let scopes = vec![EndpointScope::PublishUpdate];
+    let token = former_member.db_new_team_token("ci", team.id, scopes);
+
+    let crate_to_publish = PublishBuilder::new("foo_team_token", "2.0.0");
+    token.publish_crate(crate_to_publish).await.good();
+}
+
+/// Test that team tokens can only be used for crates that the team owns,
+/// even if the user that created them owns other crates
+#[tokio::test(flavor = "multi_thread")]
+async fn team_token_is_limited_to_team_crates() {
+    let (app, _) = TestApp::full().empty();
+    let member = app.db_new_user("user-one-team");
+
+    let team = app.db(|conn| {
+        CrateBuilder::new("foo_user_owned", member.as_model().id).expect_build(conn);
+        NewTeam::new("github:test-org:all", 1000, 2000, None, None)
+            .create_or_update(conn)
+            .unwrap()
+    });
+
+    let scopes = vec![EndpointScope::PublishUpdate, EndpointScope::ChangeOwners];
+    let token = member.db_new_team_token("ci", team.id, scopes);
+
+    let crate_to_publish = PublishBuilder::new("foo_user_owned", "2.0.0");
+    let response = token.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = token
+        .add_named_owner("foo_user_owned", "user-all-teams")
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // Team tokens can't be used for endpoints that are not about a crate
+    let response = token.get::<()>("/api/v1/me/updates").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+/// Test that team tokens can not be used to publish new crates, since the
+/// team would not own them
+#[tokio::test(flavor = "multi_thread")]
+async fn team_token_can_not_publish_new_crates() {
+    let (app, _) = TestApp::full().empty();
+    let member = app.db_new_user("user-one-team");
+
+    let team = app.db(|conn| {
+        NewTeam::new("github:test-org:all", 1000, 2000, None, None)
+            .create_or_update(conn)
+            .unwrap()
+    });
+
+    let scopes = vec![EndpointScope::PublishNew, EndpointScope::PublishUpdate];
+    let token = member.db_new_team_token("ci", team.id, scopes);
+
+    let crate_to_publish = PublishBuilder::new("foo_team_token", "1.0.0");
+    let response = token.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "this token is owned by a team and can not be used to publish new crates. Please publish the first version with a personal token and add the team as an owner afterwards." }] })
+    );
+    assert!(app.stored_files().await.is_empty());
+}
+
+/// Test that team tokens stop working if the user that created them has
+/// been locked out
+#[tokio::test(flavor = "multi_thread")]
+async fn publish_with_team_token_of_locked_member() {
+    let (app, _) = TestApp::full().empty();
+    let owner = app.db_new_user("user-all-teams");
+    let member = app.db_new_user("user-one-team");
+
+    let team = app.db(|conn| {
+        let krate = CrateBuilder::new("foo_team_token", owner.as_model().id).expect_build(conn);
+        let team = NewTeam::new("github:test-org:all", 1000, 2000, None, None)
+            .create_or_update(conn)
+            .unwrap();
+        add_team_to_crate(&team, &krate, owner.as_model(), conn).unwrap();
+        team
+    });
+
+    let scopes = vec![EndpointScope::PublishUpdate];
+    let token = member.db_new_team_token("ci", team.id, scopes);
+
+    app.db(|conn| {
+        diesel::update(users::table.find(member.as_model().id))
+            .set(users::account_lock_reason.eq("Spam"))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let crate_to_publish = PublishBuilder::new("foo_team_token", "2.0.0");
+    let response = token.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "This account is indefinitely locked. Reason: Spam" }] })
+    );
+}
+
+/// Test that all current members of a team can list, rotate and revoke the
+/// tokens of the team, regardless of which member created them
+#[tokio::test(flavor = "multi_thread")]
+async fn manage_team_tokens() {
+    let (app, _) = TestApp::init().empty();
+    let member = app.db_new_user("user-one-team");
+    let other_member = app.db_new_user("user-all-teams");
+    let non_member = app.db_new_user("foo");
+
+    let team = app.db(|conn| {
+        NewTeam::new("github:test-org:all", 1000, 2000, None, None)
+            .create_or_update(conn)
+            .unwrap()
+    });
+
+    let scopes = vec![EndpointScope::PublishUpdate];
+    let token = member.db_new_team_token("ci", team.id, scopes);
+    let token_id = token.as_model().id;
+
+    let url = "/api/v1/me/tokens?team=github:test-org:all";
+    let json = other_member.get::<()>(url).await.json();
+    let tokens = json["api_tokens"].as_array().unwrap();
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(tokens[0]["id"], token_id);
+
+    // Team tokens are not listed with the personal tokens of the user
+    let json = member.get::<()>("/api/v1/me/tokens").await.json();
+    assert_eq!(json["api_tokens"].as_array().unwrap().len(), 0);
+
+    let response = non_member.get::<()>(url).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "only members of a team can list its API tokens" }] })
+    );
+
+    let rotate_url = format!("/api/v1/me/tokens/{token_id}/rotate");
+    let response = non_member.put::<()>(&rotate_url, "").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let usage_url = format!("/api/v1/me/tokens/{token_id}/usage");
+    let response = non_member.get::<()>(&usage_url).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let response = other_member.get::<()>(&usage_url).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = other_member.put::<()>(&rotate_url, "").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let new_token_id = response.json()["api_token"]["id"].as_i64().unwrap() as i32;
+
+    let new_token: ApiToken = app.db(|conn| {
+        api_tokens::table
+            .find(new_token_id)
+            .select(ApiToken::as_select())
+            .first(conn)
+            .unwrap()
+    });
+    assert_eq!(new_token.team_id, Some(team.id));
+    assert_eq!(new_token.user_id, other_member.as_model().id);
+
+    let revoke_url = format!("/api/v1/me/tokens/{new_token_id}");
+    let response = non_member.delete::<()>(&revoke_url).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = other_member.delete::<()>(&revoke_url).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let revoked: Vec<bool> = app.db(|conn| {
+        api_tokens::table
+            .filter(api_tokens::id.eq_any([token_id, new_token_id]))
+            .select(api_tokens::revoked)
+            .order(api_tokens::id)
+            .load(conn)
+            .unwrap()
+    });
+    assert_eq!(revoked, vec![false, true]);
+}
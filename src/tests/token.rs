@@ -1,8 +1,11 @@
 use crate::util::MockRequestExt;
 use crate::{RequestHelper, TestApp};
+use crates_io::models::token::ApiTokenUsage;
+use crates_io::schema::api_tokens;
 use crates_io::{models::ApiToken, util::errors::TOKEN_FORMAT_ERROR, views::EncodableMe};
 use diesel::prelude::*;
 use http::{header, StatusCode};
+use ipnetwork::IpNetwork;
 
 #[tokio::test(flavor = "multi_thread")]
 async fn using_token_updates_last_used_at() {
@@ -42,3 +45,54 @@ async fn old_tokens_give_specific_error_message() {
         json!({ "errors": [{ "detail": TOKEN_FORMAT_ERROR }] })
     );
 }
+
+fn restrict_to_ips(app: &TestApp, token_id: i32, allowed_ips: Vec<IpNetwork>) {
+    app.db(|conn| {
+        diesel::update(api_tokens::table.find(token_id))
+            .set(api_tokens::allowed_ips.eq(allowed_ips))
+            .execute(conn)
+            .unwrap();
+    });
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn using_token_from_outside_of_allowed_ips() {
+    let url = "/api/v1/crates?following=1";
+    let (app, _, _, token) = TestApp::init().with_token();
+    let token_id = token.as_model().id;
+
+    let allowed_ips = vec!["10.0.0.0/8".parse().unwrap()];
+    restrict_to_ips(&app, token_id, allowed_ips);
+
+    let response = token.get::<()>(url).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "this token can not be used from your IP address" }] })
+    );
+
+    // Rejected attempts show up in the usage log of the token
+    app.wait_for_token_usages(token_id, 1).await;
+    let usage = app.db(|conn| ApiTokenUsage::recent(conn, token_id).unwrap());
+    assert_eq!(usage.len(), 1);
+    assert_eq!(usage[0].endpoint, "GET /api/v1/crates");
+    assert_eq!(
+        usage[0].rejection_reason.as_deref(),
+        Some("Client IP outside of the allowed CIDR blocks")
+    );
+
+    // The test requests are sent from 127.0.0.1
+    let allowed_ips = vec![
+        "10.0.0.0/8".parse().unwrap(),
+        "127.0.0.0/8".parse().unwrap(),
+    ];
+    restrict_to_ips(&app, token_id, allowed_ips);
+
+    let response = token.get::<()>(url).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    app.wait_for_token_usages(token_id, 2).await;
+    let usage = app.db(|conn| ApiTokenUsage::recent(conn, token_id).unwrap());
+    assert_eq!(usage.len(), 2);
+    assert_none!(&usage[0].rejection_reason);
+}
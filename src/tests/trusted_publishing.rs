@@ -0,0 +1,311 @@
+use crate::builders::{CrateBuilder, PublishBuilder};
+use crate::util::{MockAnonymousUser, MockCookieUser, MockRequestExt, RequestHelper, TestApp};
+use crate::GoodCrate;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::Utc;
+use crates_io::models::token::EndpointScope;
+use crates_io::models::{ApiToken, VersionOwnerAction};
+use crates_io::schema::api_tokens;
+use diesel::prelude::*;
+use googletest::prelude::*;
+use http::{header, Method, StatusCode};
+use insta::assert_snapshot;
+use p256::ecdsa::signature::Signer;
+use p256::ecdsa::{Signature, SigningKey};
+use serde_json::Value;
+
+const KEY_ID: &str = "test-key";
+const ISSUER: &str = "https://token.actions.githubusercontent.com";
+
+fn signing_key() -> SigningKey {
+    SigningKey::from_slice(&[42; 32]).unwrap()
+}
+
+/// Starts a local stand-in for the JWKS endpoint of the OIDC issuer and
+/// returns its URL.
+async fn start_jwks_server() -> String {
+    let point = signing_key().verifying_key().to_encoded_point(false);
+    let jwks = json!({
+        "keys": [{
+            "kid": KEY_ID,
+            "kty": "EC",
+            "alg": "ES256",
+            "crv": "P-256",
+            "x": URL_SAFE_NO_PAD.encode(point.x().unwrap()),
+            "y": URL_SAFE_NO_PAD.encode(point.y().unwrap()),
+        }]
+    });
+
+    let router = axum::Router::new().route(
+        "/.well-known/jwks",
+        axum::routing::get(move || async move { axum::Json(jwks) }),
+    );
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+    format!("http://{address}/.well-known/jwks")
+}
+
+async fn init() -> (TestApp, MockAnonymousUser, MockCookieUser) {
+    let jwks_url = start_jwks_server().await;
+    TestApp::full()
+        .with_config(|config| config.trusted_publishing.jwks_url = jwks_url)
+        .with_user()
+}
+
+fn default_claims() -> Value {
+    let now = Utc::now().timestamp();
+    json!({
+        "iss": ISSUER,
+        "aud": "crates.io",
+        "iat": now,
+        "nbf": now,
+        "exp": now + 300,
+        "repository": "rust-lang/foo",
+        "repository_owner": "rust-lang",
+        "workflow_ref": "rust-lang/foo/.github/workflows/release.yml@refs/tags/v1.0.0",
+        "environment": "release",
+    })
+}
+
+fn sign_jwt(claims: &Value) -> String {
+    let header = json!({ "alg": "ES256", "typ": "JWT", "kid": KEY_ID });
+    let header = URL_SAFE_NO_PAD.encode(header.to_string());
+    let claims = URL_SAFE_NO_PAD.encode(claims.to_string());
+    let message = format!("{header}.{claims}");
+    let signature: Signature = signing_key().sign(message.as_bytes());
+    format!("{message}.{}", URL_SAFE_NO_PAD.encode(signature.to_bytes()))
+}
+
+fn new_trusted_publisher_body(environment: Option<&str>) -> Vec<u8> {
+    let body = json!({
+        "trusted_publisher": {
+            "repository_owner": "rust-lang",
+            "repository_name": "foo",
+            "workflow_filename": "release.yml",
+            "environment": environment,
+        }
+    });
+    serde_json::to_vec(&body).unwrap()
+}
+
+async fn exchange(anon: &MockAnonymousUser, crate_name: &str, jwt: &str) -> Value {
+    let body = json!({ "jwt": jwt, "crate": crate_name });
+    let response = anon
+        .put::<()>("/api/v1/trusted_publishing/tokens", body.to_string())
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    response.json()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn manage_trusted_publishers() {
+    let (app, anon, user) = init().await;
+    app.db(|conn| CrateBuilder::new("foo", user.as_model().id).expect_build(conn));
+
+    let url = "/api/v1/crates/foo/trusted_publishers";
+
+    let response = anon.get::<()>(url).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = user.put::<()>(url, new_trusted_publisher_body(None)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.json();
+    assert_eq!(json["trusted_publisher"]["crate"], "foo");
+    assert_eq!(json["trusted_publisher"]["repository_owner"], "rust-lang");
+    assert_eq!(json["trusted_publisher"]["repository_name"], "foo");
+    assert_eq!(
+        json["trusted_publisher"]["workflow_filename"],
+        "release.yml"
+    );
+    assert_eq!(json["trusted_publisher"]["environment"], Value::Null);
+    let id = json["trusted_publisher"]["id"].as_i64().unwrap();
+
+    let response = user.get::<()>(url).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.json();
+    assert_that!(*json["trusted_publishers"].as_array().unwrap(), len(eq(1)));
+
+    let response = user.delete::<()>(&format!("{url}/{id}")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = user.delete::<()>(&format!("{url}/{id}")).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let json = user.get::<()>(url).await.json();
+    assert_that!(*json["trusted_publishers"].as_array().unwrap(), empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_owners_can_manage_trusted_publishers() {
+    let (app, _, user) = init().await;
+    app.db(|conn| CrateBuilder::new("foo", user.as_model().id).expect_build(conn));
+
+    let url = "/api/v1/crates/foo/trusted_publishers";
+
+    let other_user = app.db_new_user("bar");
+    let response = other_user
+        .put::<()>(url, new_trusted_publisher_body(None))
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"only owners have permission to manage trusted publishers"}]}"###);
+
+    // API tokens can't be used to manage trusted publishers
+    let token = user.db_new_token("baz");
+    let response = token.put::<()>(url, new_trusted_publisher_body(None)).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_workflow_filename() {
+    let (app, _, user) = init().await;
+    app.db(|conn| CrateBuilder::new("foo", user.as_model().id).expect_build(conn));
+
+    let body = json!({
+        "trusted_publisher": {
+            "repository_owner": "rust-lang",
+            "repository_name": "foo",
+            "workflow_filename": ".github/workflows/release.yml",
+        }
+    });
+    let response = user
+        .put::<()>("/api/v1/crates/foo/trusted_publishers", body.to_string())
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"workflow filename must be a `.yml` or `.yaml` file name without a path"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn exchange_and_publish() {
+    let (app, anon, user) = init().await;
+    app.db(|conn| CrateBuilder::new("foo", user.as_model().id).expect_build(conn));
+
+    let url = "/api/v1/crates/foo/trusted_publishers";
+    let response = user
+        .put::<()>(url, new_trusted_publisher_body(Some("release")))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let trusted_publisher_id = response.json()["trusted_publisher"]["id"].as_i64().unwrap();
+
+    let json = exchange(&anon, "foo", &sign_jwt(&default_claims())).await;
+    let token = json["api_token"]["token"].as_str().unwrap().to_string();
+    assert_eq!(json["api_token"]["crate_scopes"], json!(["foo"]));
+    assert_eq!(
+        json["api_token"]["endpoint_scopes"],
+        json!(["publish-update"])
+    );
+    assert!(json["api_token"]["expired_at"].is_string());
+
+    let api_token: ApiToken = app.db(|conn| {
+        api_tokens::table
+            .select(ApiToken::as_select())
+            .filter(api_tokens::trusted_publisher_id.is_not_null())
+            .first(conn)
+            .unwrap()
+    });
+    assert_eq!(api_token.user_id, user.as_model().id);
+    assert_eq!(
+        api_token.endpoint_scopes,
+        Some(vec![EndpointScope::PublishUpdate])
+    );
+
+    // Publish a new version with the minted token
+    let mut request = anon.request_builder(Method::PUT, "/api/v1/crates/new");
+    *request.body_mut() = PublishBuilder::new("foo", "1.1.0").body();
+    request.header(header::AUTHORIZATION, &token);
+    let response = anon.run::<GoodCrate>(request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // The audit action records the trusted publisher
+    let actions = app.db(|conn| VersionOwnerAction::all(conn).unwrap());
+    assert_that!(actions, len(eq(1)));
+    assert_eq!(actions[0].api_token_id, Some(api_token.id));
+    assert_eq!(
+        actions[0].trusted_publisher_id,
+        Some(trusted_publisher_id as i32)
+    );
+
+    // The minted token can't be used for other crates or other endpoints
+    let mut request = anon.request_builder(Method::PUT, "/api/v1/crates/new");
+    *request.body_mut() = PublishBuilder::new("bar", "1.0.0").body();
+    request.header(header::AUTHORIZATION, &token);
+    let response = anon.run::<()>(request).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let mut request = anon.request_builder(Method::DELETE, "/api/v1/crates/foo/1.1.0/yank");
+    request.header(header::AUTHORIZATION, &token);
+    let response = anon.run::<()>(request).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // Deleting the configuration revokes the minted tokens
+    let response = user
+        .delete::<()>(&format!("{url}/{trusted_publisher_id}"))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let mut request = anon.request_builder(Method::PUT, "/api/v1/crates/new");
+    *request.body_mut() = PublishBuilder::new("foo", "1.2.0").body();
+    request.header(header::AUTHORIZATION, &token);
+    let response = anon.run::<()>(request).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn exchange_rejects_mismatching_claims() {
+    let (app, anon, user) = init().await;
+    app.db(|conn| CrateBuilder::new("foo", user.as_model().id).expect_build(conn));
+
+    let url = "/api/v1/crates/foo/trusted_publishers";
+    let response = user
+        .put::<()>(url, new_trusted_publisher_body(Some("release")))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let exchange_url = "/api/v1/trusted_publishing/tokens";
+    let exchange = |claims: Value| {
+        let body = json!({ "jwt": sign_jwt(&claims), "crate": "foo" });
+        anon.put::<()>(exchange_url, body.to_string())
+    };
+
+    let mut claims = default_claims();
+    claims["environment"] = json!("dev");
+    let response = exchange(claims).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"no trusted publisher configuration of `foo` matches the identity token"}]}"###);
+
+    let mut claims = default_claims();
+    claims["workflow_ref"] = json!("rust-lang/foo/.github/workflows/ci.yml@refs/heads/main");
+    let response = exchange(claims).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let mut claims = default_claims();
+    claims["aud"] = json!("example.com");
+    let response = exchange(claims).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"invalid JWT: unexpected audience"}]}"###);
+
+    let mut claims = default_claims();
+    claims["iss"] = json!("https://example.com");
+    let response = exchange(claims).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"invalid JWT: unexpected issuer"}]}"###);
+
+    let mut claims = default_claims();
+    claims["exp"] = json!(Utc::now().timestamp() - 3600);
+    let response = exchange(claims).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"invalid JWT: token has expired"}]}"###);
+
+    let mut token = sign_jwt(&default_claims());
+    token.push('A');
+    let body = json!({ "jwt": token, "crate": "foo" });
+    let response = anon.put::<()>(exchange_url, body.to_string()).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // No tokens were minted
+    let count: i64 = app.db(|conn| api_tokens::table.count().get_result(conn).unwrap());
+    assert_eq!(count, 0);
+}
@@ -0,0 +1,271 @@
+use crate::builders::CrateBuilder;
+use crate::util::{
+    MockAnonymousUser, MockCookieUser, MockRequest, MockRequestExt, RequestHelper, Response,
+    TestApp,
+};
+use chrono::Utc;
+use cookie::Cookie;
+use crates_io::models::TotpCredential;
+use crates_io::util::totp;
+use http::{header, Method, StatusCode};
+use insta::assert_snapshot;
+
+const TWO_FACTOR_REQUIRED: &str = r#"{"errors":[{"detail":"this action requires two-factor authentication. Please confirm your identity with a code from your authenticator app and try again."}]}"#;
+
+/// Sends cookie authenticated requests with the session cookie of a
+/// previous response, which includes the two-factor verification.
+struct MockSessionUser {
+    anon: MockAnonymousUser,
+    cookie: String,
+}
+
+impl MockSessionUser {
+    fn from_response<T>(anon: MockAnonymousUser, response: &Response<T>) -> Self {
+        let header = response.headers()[header::SET_COOKIE].to_str().unwrap();
+        let cookie = Cookie::parse(header).unwrap();
+        let cookie = format!("{}={}", cookie.name(), cookie.value());
+        Self { anon, cookie }
+    }
+}
+
+impl RequestHelper for MockSessionUser {
+    fn request_builder(&self, method: Method, path: &str) -> MockRequest {
+        let mut request = self.anon.request_builder(method, path);
+        request.header(header::COOKIE, &self.cookie);
+        request
+    }
+
+    fn app(&self) -> &TestApp {
+        self.anon.app()
+    }
+}
+
+fn secret(app: &TestApp, user: &MockCookieUser) -> Vec<u8> {
+    let user_id = user.as_model().id;
+    app.db(|conn| TotpCredential::find(conn, user_id).unwrap().unwrap().secret)
+}
+
+/// Generates the code of the current time step, or of one of the
+/// neighbouring time steps that the server still accepts.
+fn code(secret: &[u8], offset: i64) -> String {
+    let step = totp::time_step(Utc::now().timestamp());
+    totp::generate_code(secret, step + offset)
+}
+
+fn code_body(code: &str) -> String {
+    json!({ "code": code }).to_string()
+}
+
+/// Enrolls the user in two-factor authentication, and returns the secret
+/// and the recovery codes.
+async fn enroll(app: &TestApp, user: &MockCookieUser) -> (Vec<u8>, Vec<String>) {
+    let response = user.put::<()>("/api/v1/me/two_factor", "").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let secret = secret(app, user);
+    let body = code_body(&code(&secret, 0));
+    let response = user.put::<()>("/api/v1/me/two_factor/confirm", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let recovery_codes = response.json()["recovery_codes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|code| code.as_str().unwrap().to_string())
+        .collect();
+
+    (secret, recovery_codes)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn enrollment() {
+    let (app, _, user) = TestApp::init().with_user();
+
+    let json = user.get::<()>("/api/v1/me/two_factor").await.json();
+    assert_eq!(json["two_factor"]["enabled"], false);
+
+    let response = user.put::<()>("/api/v1/me/two_factor", "").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.json();
+    let secret = secret(&app, &user);
+    assert_eq!(json["secret"], totp::encode_secret(&secret));
+    let uri = json["provisioning_uri"].as_str().unwrap();
+    assert!(uri.starts_with("otpauth://totp/crates.io:foo?secret="));
+
+    // The enrollment is only enabled once it has been confirmed
+    let json = user.get::<()>("/api/v1/me/two_factor").await.json();
+    assert_eq!(json["two_factor"]["enabled"], false);
+
+    let body = code_body("000000");
+    let response = user.put::<()>("/api/v1/me/two_factor/confirm", body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"invalid two-factor authentication code"}]}"###);
+
+    let body = code_body(&code(&secret, 0));
+    let response = user.put::<()>("/api/v1/me/two_factor/confirm", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let recovery_codes = response.json()["recovery_codes"].as_array().unwrap().len();
+    assert_eq!(recovery_codes, 10);
+
+    let json = user.get::<()>("/api/v1/me/two_factor").await.json();
+    assert_eq!(json["two_factor"]["enabled"], true);
+    assert_eq!(json["two_factor"]["recovery_codes_remaining"], 10);
+
+    let response = user.put::<()>("/api/v1/me/two_factor", "").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"two-factor authentication is already enabled"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sensitive_actions_require_verification() {
+    let (app, anon, user, token) = TestApp::init().with_token();
+    app.db(|conn| CrateBuilder::new("foo_crate", user.as_model().id).expect_build(conn));
+    app.db_new_user("bar");
+    let (secret, _) = enroll(&app, &user).await;
+
+    let token_body = json!({ "api_token": { "name": "bar" } }).to_string();
+    let response = user
+        .put::<()>("/api/v1/me/tokens", token_body.clone())
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(response.text(), TWO_FACTOR_REQUIRED);
+
+    let owners_body = json!({ "owners": ["bar"] }).to_string();
+    let response = user
+        .put::<()>("/api/v1/crates/foo_crate/owners", owners_body)
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(response.text(), TWO_FACTOR_REQUIRED);
+
+    let policy_body = json!({ "publish_policy": {} }).to_string();
+
+    let url = format!("/api/v1/users/{}", user.as_model().id);
+    let email_body = json!({ "user": { "email": "foo@example.com" } }).to_string();
+    let response = user.put::<()>(&url, email_body.clone()).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(response.text(), TWO_FACTOR_REQUIRED);
+
+    let policy_url = "/api/v1/crates/foo_crate/publish_policy";
+    let response = user.put::<()>(policy_url, policy_body.clone()).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(response.text(), TWO_FACTOR_REQUIRED);
+
+    // Reading the settings does not require a verification
+    for url in [
+        "/api/v1/crates/foo_crate/publish_policy",
+        "/api/v1/crates/foo_crate/trusted_publishers",
+        "/api/v1/crates/foo_crate/webhooks",
+        "/api/v1/me/webhooks",
+    ] {
+        let response = user.get::<()>(url).await;
+        assert_eq!(response.status(), StatusCode::OK, "{url}");
+    }
+
+    let response = user.delete::<()>("/api/v1/crates/foo_crate").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(response.text(), TWO_FACTOR_REQUIRED);
+
+    // API tokens are not affected by the two-factor requirement
+    let response = token.add_named_owner("foo_crate", "bar").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = code_body(&code(&secret, 1));
+    let response = user
+        .put::<()>("/api/private/session/two_factor", body)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let session = MockSessionUser::from_response(anon, &response);
+
+    let response = session.put::<()>("/api/v1/me/tokens", token_body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = session.put::<()>(&url, email_body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = session.put::<()>(policy_url, policy_body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn verification_is_only_required_with_step_up_enabled() {
+    let (app, _, user) = TestApp::init()
+        .with_config(|config| config.two_factor_step_up = false)
+        .with_user();
+    enroll(&app, &user).await;
+
+    let token_body = json!({ "api_token": { "name": "bar" } }).to_string();
+    let response = user.put::<()>("/api/v1/me/tokens", token_body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn codes_can_only_be_used_once() {
+    let (app, _, user) = TestApp::init().with_user();
+    let (secret, recovery_codes) = enroll(&app, &user).await;
+
+    // The code was already used to confirm the enrollment
+    let user_id = user.as_model().id;
+    let credential = app.db(|conn| TotpCredential::find(conn, user_id).unwrap().unwrap());
+    let used_step = credential.last_used_step.unwrap();
+    let body = code_body(&totp::generate_code(&secret, used_step));
+    let response = user
+        .put::<()>("/api/private/session/two_factor", body)
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"invalid two-factor authentication code"}]}"###);
+
+    // Recovery codes are accepted in any case and with or without the dash
+    let recovery_code = recovery_codes[0].replace('-', "").to_uppercase();
+    let body = code_body(&recovery_code);
+    let response = user
+        .put::<()>("/api/private/session/two_factor", body.clone())
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = user
+        .put::<()>("/api/private/session/two_factor", body)
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let json = user.get::<()>("/api/v1/me/two_factor").await.json();
+    assert_eq!(json["two_factor"]["recovery_codes_remaining"], 9);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn disable_two_factor() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let (_, recovery_codes) = enroll(&app, &user).await;
+
+    let response = user.delete::<()>("/api/v1/me/two_factor").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(response.text(), TWO_FACTOR_REQUIRED);
+
+    let body = code_body(&recovery_codes[0]);
+    let response = user
+        .put::<()>("/api/private/session/two_factor", body)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let session = MockSessionUser::from_response(anon, &response);
+
+    let response = session.delete::<()>("/api/v1/me/two_factor").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let json = user.get::<()>("/api/v1/me/two_factor").await.json();
+    assert_eq!(json["two_factor"]["enabled"], false);
+
+    let body = json!({ "api_token": { "name": "bar" } }).to_string();
+    let response = user.put::<()>("/api/v1/me/tokens", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn verification_requires_enrollment() {
+    let (_, _, user) = TestApp::init().with_user();
+
+    let body = code_body("123456");
+    let response = user
+        .put::<()>("/api/private/session/two_factor", body)
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"two-factor authentication is not enabled"}]}"###);
+}
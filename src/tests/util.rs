@@ -24,7 +24,7 @@ use crate::{
     OwnersResponse, VersionResponse,
 };
 use crates_io::middleware::session;
-use crates_io::models::{ApiToken, CreatedApiToken, User};
+use crates_io::models::{ApiToken, CreatedApiToken, NewApiToken, User};
 
 use http::{Method, Request};
 
@@ -49,8 +49,7 @@ mod response;
 mod test_app;
 
 pub(crate) use chaosproxy::ChaosProxy;
-use mock_request::MockRequest;
-pub use mock_request::MockRequestExt;
+pub use mock_request::{MockRequest, MockRequestExt};
 pub use response::Response;
 pub use test_app::TestApp;
 
@@ -292,14 +291,41 @@ impl MockCookieUser {
         expired_at: Option<NaiveDateTime>,
     ) -> MockTokenUser {
         let token = self.app.db(|conn| {
-            ApiToken::insert_with_scopes(
-                conn,
-                self.user.id,
+            NewApiToken {
+                user_id: self.user.id,
                 name,
                 crate_scopes,
                 endpoint_scopes,
                 expired_at,
-            )
+                ..Default::default()
+            }
+            .insert(conn)
+            .unwrap()
+        });
+        MockTokenUser {
+            app: self.app.clone(),
+            token,
+        }
+    }
+
+    /// Creates a token that is owned by a team and wraps it in a helper struct
+    ///
+    /// This method updates the database directly
+    pub fn db_new_team_token(
+        &self,
+        name: &str,
+        team_id: i32,
+        endpoint_scopes: Vec<EndpointScope>,
+    ) -> MockTokenUser {
+        let token = self.app.db(|conn| {
+            NewApiToken {
+                user_id: self.user.id,
+                name,
+                endpoint_scopes: Some(endpoint_scopes),
+                team_id: Some(team_id),
+                ..Default::default()
+            }
+            .insert(conn)
             .unwrap()
         });
         MockTokenUser {
@@ -3,6 +3,7 @@ use crate::util::chaosproxy::ChaosProxy;
 use crate::util::github::{MockGitHubClient, MOCK_GITHUB_DATA};
 use crates_io::config::{
     self, Base, CdnLogQueueConfig, CdnLogStorageConfig, DatabasePools, DbPoolConfig,
+    TrustedPublishingConfig,
 };
 use crates_io::middleware::cargo_compat::StatusCodeConfig;
 use crates_io::models::token::{CrateScope, EndpointScope};
@@ -113,6 +114,23 @@ impl TestApp {
         f(&mut self.0.test_database.connect())
     }
 
+    /// Waits until the usage log of an API token contains the given number of
+    /// entries, since the usage is recorded after the response has been sent.
+    pub async fn wait_for_token_usages(&self, api_token_id: i32, count: usize) {
+        use crates_io::models::token::ApiTokenUsage;
+
+        for _ in 0..50 {
+            let usages = self.db(|conn| ApiTokenUsage::recent(conn, api_token_id).unwrap());
+            if usages.len() >= count {
+                return;
+            }
+
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        panic!("API token usage log does not contain {count} entries");
+    }
+
     /// Create a new user with a verified email address in the database and return a mock user
     /// session
     ///
@@ -167,14 +185,21 @@ impl TestApp {
     }
 
     pub async fn run_pending_background_jobs(&self) {
+        let result = self.try_run_pending_background_jobs().await;
+        result.expect("Could not determine if jobs failed");
+    }
+
+    /// Like [`Self::run_pending_background_jobs`], but returns an error
+    /// instead of panicking if any of the jobs failed, so that tests can
+    /// cover the retry behavior of jobs.
+    pub async fn try_run_pending_background_jobs(&self) -> anyhow::Result<()> {
         let runner = &self.0.runner;
         let runner = runner.as_ref().expect("Index has not been initialized");
 
         let handle = runner.start();
         handle.wait_for_shutdown().await;
 
-        let result = runner.check_for_failed_jobs().await;
-        result.expect("Could not determine if jobs failed");
+        runner.check_for_failed_jobs().await
     }
 
     /// Obtain a reference to the inner `App` value
@@ -415,6 +440,7 @@ fn simple_config() -> config::Server {
         max_dependencies: 10,
         rate_limiter: Default::default(),
         new_version_rate_limit: Some(10),
+        typosquat_block_threshold: None,
         blocked_traffic: Default::default(),
         blocked_ips: Default::default(),
         max_allowed_page_offset: 200,
@@ -440,7 +466,23 @@ fn simple_config() -> config::Server {
         // The frontend code is not needed for the backend tests.
         serve_dist: false,
         serve_html: false,
+        serve_sparse_index: false,
+        auth_required: false,
+        allow_private_webhook_urls: false,
+        two_factor_step_up: true,
+        index_signing_key: None,
+        mirror_checkout_dir: None,
+        mirror_allow_file_urls: false,
         content_security_policy: None,
+
+        // Tests that need trusted publishing point the JWKS URL at a local
+        // stand-in server.
+        trusted_publishing: TrustedPublishingConfig {
+            issuer: "https://token.actions.githubusercontent.com".into(),
+            jwks_url: "http://127.0.0.1:1/.well-known/jwks".into(),
+            audience: "crates.io".into(),
+            token_lifetime: Duration::from_secs(30 * 60),
+        },
     }
 }
 
@@ -0,0 +1,374 @@
+use crate::builders::{CrateBuilder, PublishBuilder};
+use crate::routes::crates::versions::yank_unyank::YankRequestHelper;
+use crate::util::{RequestHelper, TestApp};
+use axum::body::Bytes;
+use axum::http::HeaderMap;
+use chrono::{Duration, Utc};
+use crates_io::config;
+use crates_io::schema::background_jobs;
+use diesel::prelude::*;
+use http::StatusCode;
+use insta::assert_snapshot;
+use ring::hmac;
+use serde_json::Value;
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// A local stand-in for the receiver of webhook deliveries, which records
+/// all requests and responds with a configurable status code.
+struct Receiver {
+    url: String,
+    requests: Arc<Mutex<Vec<(HeaderMap, Bytes)>>>,
+    status: Arc<AtomicU16>,
+}
+
+impl Receiver {
+    async fn start() -> Self {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let status = Arc::new(AtomicU16::new(200));
+
+        let router = axum::Router::new().route(
+            "/hook",
+            axum::routing::post({
+                let requests = requests.clone();
+                let status = status.clone();
+                move |headers: HeaderMap, body: Bytes| async move {
+                    requests.lock().unwrap().push((headers, body));
+                    StatusCode::from_u16(status.load(Ordering::SeqCst)).unwrap()
+                }
+            }),
+        );
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        let url = format!("http://{address}/hook");
+        Self {
+            url,
+            requests,
+            status,
+        }
+    }
+
+    fn set_status(&self, status: StatusCode) {
+        self.status.store(status.as_u16(), Ordering::SeqCst);
+    }
+
+    /// Returns the event name and the JSON payload of all received requests,
+    /// after verifying their signatures.
+    fn events(&self, secret: &str) -> Vec<(String, Value)> {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(headers, body)| {
+                let signature = headers["X-Crates-Io-Signature-256"].to_str().unwrap();
+                let signature = signature.strip_prefix("sha256=").unwrap();
+                let signature = hex::decode(signature).unwrap();
+                hmac::verify(&key, body, &signature).expect("invalid signature");
+
+                let event = headers["X-Crates-Io-Event"].to_str().unwrap().to_string();
+                (event, serde_json::from_slice(body).unwrap())
+            })
+            .collect()
+    }
+}
+
+/// The [`Receiver`] listens on a loopback address, which webhooks are not
+/// allowed to be delivered to by default.
+fn allow_private_urls(config: &mut config::Server) {
+    config.allow_private_webhook_urls = true;
+}
+
+fn webhook_body(url: &str, events: Option<&[&str]>) -> String {
+    let mut webhook = json!({ "url": url });
+    if let Some(events) = events {
+        webhook["events"] = json!(events);
+    }
+    json!({ "webhook": webhook }).to_string()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn crate_webhooks_receive_signed_events() {
+    let (app, _, user, token) = TestApp::full().with_config(allow_private_urls).with_token();
+    let receiver = Receiver::start().await;
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    token.publish_crate(crate_to_publish).await.good();
+    app.run_pending_background_jobs().await;
+
+    let body = webhook_body(&receiver.url, Some(&["publish", "yank"]));
+    let response = user.put::<()>("/api/v1/crates/foo/webhooks", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.json();
+    assert_eq!(json["webhook"]["crate"], "foo");
+    assert_eq!(json["webhook"]["events"], json!(["publish", "yank"]));
+    let secret = json["webhook"]["secret"].as_str().unwrap().to_string();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.1");
+    token.publish_crate(crate_to_publish).await.good();
+    token.yank("foo", "1.0.1").await.good();
+    token.unyank("foo", "1.0.1").await.good();
+    app.run_pending_background_jobs().await;
+
+    let events = receiver.events(&secret);
+    assert_eq!(events.len(), 2);
+
+    let (event, payload) = &events[0];
+    assert_eq!(event, "publish");
+    assert_eq!(payload["event"], "publish");
+    assert_eq!(payload["crate"], "foo");
+    assert_eq!(payload["version"], "1.0.1");
+    assert_eq!(payload["user"], "foo");
+
+    let (event, payload) = &events[1];
+    assert_eq!(event, "yank");
+    assert_eq!(payload["version"], "1.0.1");
+
+    // The secret is not revealed again
+    let response = user.get::<()>("/api/v1/crates/foo/webhooks").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.json();
+    assert_eq!(json["webhooks"].as_array().unwrap().len(), 1);
+    assert_eq!(json["webhooks"][0]["secret"], Value::Null);
+
+    let url = format!("/api/v1/webhooks/{}/deliveries", json["webhooks"][0]["id"]);
+    let response = user.get::<()>(&url).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.json();
+    assert_eq!(json["meta"]["total"], 2);
+    assert_eq!(json["deliveries"][0]["event"], "yank");
+    assert_eq!(json["deliveries"][0]["attempts"], 1);
+    assert_eq!(json["deliveries"][0]["response_status"], 200);
+    assert!(json["deliveries"][0]["delivered_at"].is_string());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn user_webhooks_receive_ownership_events() {
+    let (app, _, user, token) = TestApp::full().with_config(allow_private_urls).with_token();
+    let invitee = app.db_new_user("bar");
+    let receiver = Receiver::start().await;
+    let invitee_receiver = Receiver::start().await;
+
+    let body = webhook_body(&receiver.url, None);
+    let response = user.put::<()>("/api/v1/me/webhooks", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.json();
+    assert_eq!(json["webhook"]["crate"], Value::Null);
+    let secret = json["webhook"]["secret"].as_str().unwrap().to_string();
+
+    let body = webhook_body(&invitee_receiver.url, Some(&["owner_invite"]));
+    let response = invitee.put::<()>("/api/v1/me/webhooks", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let invitee_secret = response.json()["webhook"]["secret"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    token.publish_crate(crate_to_publish).await.good();
+    token.add_named_owner("foo", "bar").await.good();
+    app.run_pending_background_jobs().await;
+
+    let events = receiver.events(&secret);
+    let names = events
+        .iter()
+        .map(|(event, _)| event.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["publish", "owner_invite"]);
+    assert_eq!(events[1].1["owner"], "bar");
+
+    // The invited user is notified even though they are not an owner yet
+    let events = invitee_receiver.events(&invitee_secret);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].0, "owner_invite");
+    assert_eq!(events[0].1["crate"], "foo");
+    assert_eq!(events[0].1["user"], "foo");
+
+    let response = user.get::<()>("/api/v1/me/webhooks").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json()["webhooks"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn failed_deliveries_are_retried() {
+    let (app, _, user, token) = TestApp::full().with_config(allow_private_urls).with_token();
+    let receiver = Receiver::start().await;
+    receiver.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+
+    let body = webhook_body(&receiver.url, None);
+    let response = user.put::<()>("/api/v1/me/webhooks", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let id = response.json()["webhook"]["id"].as_i64().unwrap();
+    let url = format!("/api/v1/webhooks/{id}/deliveries");
+
+    // `publish_crate()` would fail on the failed delivery job, so the publish
+    // request is sent manually instead.
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    let response = token
+        .put::<()>("/api/v1/crates/new", crate_to_publish)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(app.try_run_pending_background_jobs().await.is_err());
+
+    let json = user.get::<()>(&url).await.json();
+    assert_eq!(json["deliveries"][0]["attempts"], 1);
+    assert_eq!(json["deliveries"][0]["response_status"], 500);
+    assert_eq!(
+        json["deliveries"][0]["error"],
+        "unexpected response status: 500 Internal Server Error"
+    );
+    assert_eq!(json["deliveries"][0]["delivered_at"], Value::Null);
+
+    // Pretend that the retry backoff has passed
+    receiver.set_status(StatusCode::OK);
+    app.db(|conn| {
+        let last_retry = Utc::now().naive_utc() - Duration::days(1);
+        diesel::update(background_jobs::table)
+            .set(background_jobs::last_retry.eq(last_retry))
+            .execute(conn)
+            .unwrap();
+    });
+    app.run_pending_background_jobs().await;
+
+    let json = user.get::<()>(&url).await.json();
+    assert_eq!(json["meta"]["total"], 1);
+    assert_eq!(json["deliveries"][0]["attempts"], 2);
+    assert_eq!(json["deliveries"][0]["response_status"], 200);
+    assert_eq!(json["deliveries"][0]["error"], Value::Null);
+    assert!(json["deliveries"][0]["delivered_at"].is_string());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn deliveries_to_private_addresses_are_blocked() {
+    let (app, _, user, token) = TestApp::full().with_token();
+    let receiver = Receiver::start().await;
+
+    let body = webhook_body(&receiver.url, None);
+    let response = user.put::<()>("/api/v1/me/webhooks", body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"webhook URL must not point to a non-public address"}]}"###);
+
+    let body = webhook_body("http://169.254.169.254/latest/meta-data", None);
+    let response = user.put::<()>("/api/v1/me/webhooks", body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // Hostnames are only resolved and checked when delivering events
+    let url = receiver.url.replace("127.0.0.1", "localhost");
+    let body = webhook_body(&url, None);
+    let response = user.put::<()>("/api/v1/me/webhooks", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let id = response.json()["webhook"]["id"].as_i64().unwrap();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    let response = token
+        .put::<()>("/api/v1/crates/new", crate_to_publish)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(app.try_run_pending_background_jobs().await.is_err());
+
+    let json = user
+        .get::<()>(&format!("/api/v1/webhooks/{id}/deliveries"))
+        .await
+        .json();
+    assert_eq!(json["deliveries"][0]["response_status"], Value::Null);
+    let error = json["deliveries"][0]["error"].as_str().unwrap();
+    assert!(error.starts_with("webhook host resolves to non-public address"));
+
+    assert!(receiver.requests.lock().unwrap().is_empty());
+
+    // The delivery would be retried until it gives up
+    app.db(|conn| {
+        diesel::delete(background_jobs::table)
+            .execute(conn)
+            .unwrap();
+    });
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_owners_can_manage_webhooks() {
+    let (app, anon, user, token) = TestApp::init().with_token();
+    let another_user = app.db_new_user("bar");
+
+    app.db(|conn| CrateBuilder::new("foo", user.as_model().id).expect_build(conn));
+
+    let body = webhook_body("https://example.com/hook", None);
+
+    let response = anon
+        .put::<()>("/api/v1/crates/foo/webhooks", body.clone())
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"this action requires authentication"}]}"###);
+
+    let response = token
+        .put::<()>("/api/v1/crates/foo/webhooks", body.clone())
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"this action can only be performed on the crates.io website"}]}"###);
+
+    let response = another_user
+        .put::<()>("/api/v1/crates/foo/webhooks", body.clone())
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"only owners have permission to manage webhooks"}]}"###);
+
+    let response = user
+        .put::<()>("/api/v1/crates/foo/webhooks", body.clone())
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let crate_webhook_id = response.json()["webhook"]["id"].as_i64().unwrap();
+
+    let response = user.put::<()>("/api/v1/me/webhooks", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let user_webhook_id = response.json()["webhook"]["id"].as_i64().unwrap();
+
+    for id in [crate_webhook_id, user_webhook_id] {
+        let url = format!("/api/v1/webhooks/{id}/deliveries");
+        let response = another_user.get::<()>(&url).await;
+        assert_ne!(response.status(), StatusCode::OK);
+
+        let url = format!("/api/v1/webhooks/{id}");
+        let response = another_user.delete::<()>(&url).await;
+        assert_ne!(response.status(), StatusCode::OK);
+
+        let response = user.delete::<()>(&url).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = user.delete::<()>(&url).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            response.json(),
+            json!({ "errors": [{ "detail": "webhook not found" }] })
+        );
+    }
+
+    let response = user.get::<()>("/api/v1/crates/foo/webhooks").await;
+    assert_eq!(response.json()["webhooks"], json!([]));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_webhooks_are_rejected() {
+    let (_, _, user) = TestApp::init().with_user();
+
+    let body = webhook_body("ftp://example.com/hook", None);
+    let response = user.put::<()>("/api/v1/me/webhooks", body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"webhook URL must be an `http` or `https` URL"}]}"###);
+
+    let body = webhook_body("not a url", None);
+    let response = user.put::<()>("/api/v1/me/webhooks", body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"invalid webhook URL: relative URL without a base"}]}"###);
+
+    let body = webhook_body("https://example.com/hook", Some(&[]));
+    let response = user.put::<()>("/api/v1/me/webhooks", body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"webhook must subscribe to at least one event"}]}"###);
+
+    let body = webhook_body("https://example.com/hook", Some(&["download"]));
+    let response = user.put::<()>("/api/v1/me/webhooks", body).await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
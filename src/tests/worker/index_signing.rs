@@ -0,0 +1,195 @@
+use crate::builders::PublishBuilder;
+use crate::routes::crates::versions::yank_unyank::YankRequestHelper;
+use crate::util::{RequestHelper, TestApp};
+use chrono::{Duration, Utc};
+use crates_io::worker::jobs::SignIndexSnapshot;
+use crates_io_index::signing::{
+    index_file_metadata_path, verify_index_file, verify_snapshot, IndexFileMetadata, Root,
+    SigningKey, Snapshot, SNAPSHOT_PATH,
+};
+use crates_io_worker::BackgroundJob;
+
+fn trusted_root(app: &TestApp) -> Root {
+    let signing_key = app.as_inner().config.index_signing_key.as_ref().unwrap();
+    let expires = Utc::now() + Duration::days(1);
+    Root::new(1, expires, vec![signing_key.verifying_key()])
+}
+
+async fn sign_snapshot(app: &TestApp, root: &Root) -> Snapshot {
+    app.db(|conn| SignIndexSnapshot.enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+
+    let storage = &app.as_inner().storage;
+    let snapshot = storage.read_index_metadata(SNAPSHOT_PATH).await.unwrap();
+    assert_ok!(verify_snapshot(&snapshot.unwrap(), root, Utc::now()))
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn index_files_are_signed() {
+    let (app, _, _, token) = TestApp::full()
+        .with_config(|config| {
+            config.index_signing_key = Some(SigningKey::generate().unwrap().0);
+        })
+        .with_token();
+
+    let storage = &app.as_inner().storage;
+    let root = trusted_root(&app);
+    let metadata_path = index_file_metadata_path("foo");
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+
+    let content = storage.read_index_file("foo").await.unwrap().unwrap();
+    let metadata = storage.read_index_metadata(&metadata_path).await.unwrap();
+    let old_metadata = metadata.expect("missing index file metadata");
+
+    let snapshot = sign_snapshot(&app, &root).await;
+    assert_eq!(snapshot.files.len(), 1);
+    assert_ok!(verify_index_file(
+        "foo",
+        &content,
+        &old_metadata,
+        &root,
+        &snapshot
+    ));
+
+    // Yanking changes the index file, and the signed hash along with it
+    token.yank("foo", "1.0.0").await.good();
+
+    let yanked_content = storage.read_index_file("foo").await.unwrap().unwrap();
+    assert_ne!(yanked_content, content);
+    let metadata = storage.read_index_metadata(&metadata_path).await.unwrap();
+    let metadata = metadata.unwrap();
+    assert_ok!(verify_index_file(
+        "foo",
+        &yanked_content,
+        &metadata,
+        &root,
+        &snapshot
+    ));
+    assert_err!(verify_index_file(
+        "foo", &content, &metadata, &root, &snapshot
+    ));
+
+    let snapshot = sign_snapshot(&app, &root).await;
+    assert_eq!(snapshot.files.len(), 1);
+    assert_ok!(snapshot.files["foo"].verify(&yanked_content));
+    assert_ok!(verify_index_file(
+        "foo",
+        &yanked_content,
+        &metadata,
+        &root,
+        &snapshot
+    ));
+
+    // The old index file still has a valid signature, but a newer snapshot
+    // prevents rolling back to it
+    assert_err!(verify_index_file(
+        "foo",
+        &content,
+        &old_metadata,
+        &root,
+        &snapshot
+    ));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn snapshot_migrates_files_to_a_rotated_key() {
+    let (app, _, _, token) = TestApp::full()
+        .with_config(|config| {
+            config.index_signing_key = Some(SigningKey::generate().unwrap().0);
+        })
+        .with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+
+    // Simulate a key rotation by signing the existing metadata file with
+    // a different key
+    let storage = &app.as_inner().storage;
+    let metadata_path = index_file_metadata_path("foo");
+    let metadata = storage.read_index_metadata(&metadata_path).await.unwrap();
+    let mut signed: serde_json::Value = serde_json::from_slice(&metadata.unwrap()).unwrap();
+    let (old_key, _) = SigningKey::generate().unwrap();
+    let resigned = old_key.sign(signed["signed"].take()).unwrap();
+    let bytes = serde_json::to_vec(&resigned).unwrap();
+    storage
+        .upload_index_metadata(&metadata_path, bytes.into())
+        .await
+        .unwrap();
+
+    let root = trusted_root(&app);
+    let content = storage.read_index_file("foo").await.unwrap().unwrap();
+    let metadata = storage.read_index_metadata(&metadata_path).await.unwrap();
+    let old_metadata = metadata.unwrap();
+
+    let snapshot = sign_snapshot(&app, &root).await;
+    assert_err!(verify_index_file(
+        "foo",
+        &content,
+        &old_metadata,
+        &root,
+        &snapshot
+    ));
+
+    let metadata = storage.read_index_metadata(&metadata_path).await.unwrap();
+    assert_ok!(verify_index_file(
+        "foo",
+        &content,
+        &metadata.unwrap(),
+        &root,
+        &snapshot
+    ));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn snapshot_backfills_and_repairs_metadata_files() {
+    let (app, _, _, token) = TestApp::full()
+        .with_config(|config| {
+            config.index_signing_key = Some(SigningKey::generate().unwrap().0);
+        })
+        .with_token();
+
+    for name in ["foo", "bar"] {
+        token
+            .publish_crate(PublishBuilder::new(name, "1.0.0"))
+            .await
+            .good();
+    }
+
+    // Simulate an index file that was published before signing was enabled
+    let storage = &app.as_inner().storage;
+    let foo_path = index_file_metadata_path("foo");
+    storage.delete_index_metadata(&foo_path).await.unwrap();
+
+    // Simulate a tampered metadata file with a hash that doesn't match the
+    // index file, signed by an untrusted key
+    let bar_path = index_file_metadata_path("bar");
+    let (untrusted_key, _) = SigningKey::generate().unwrap();
+    let tampered = IndexFileMetadata::new("bar", b"tampered", Utc::now());
+    let bytes = serde_json::to_vec(&untrusted_key.sign(tampered).unwrap()).unwrap();
+    storage
+        .upload_index_metadata(&bar_path, bytes.into())
+        .await
+        .unwrap();
+
+    let root = trusted_root(&app);
+    let snapshot = sign_snapshot(&app, &root).await;
+    assert_eq!(snapshot.files.len(), 2);
+
+    for (name, path) in [("foo", &foo_path), ("bar", &bar_path)] {
+        let content = storage.read_index_file(name).await.unwrap().unwrap();
+        assert_ok!(snapshot.files[name].verify(&content));
+        assert_err!(snapshot.files[name].verify(b"tampered"));
+
+        let metadata = storage.read_index_metadata(path).await.unwrap();
+        let metadata = metadata.expect("missing index file metadata");
+        assert_ok!(verify_index_file(
+            name, &content, &metadata, &root, &snapshot
+        ));
+    }
+}
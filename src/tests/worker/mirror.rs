@@ -0,0 +1,259 @@
+use crate::util::TestApp;
+use chrono::{Duration, Utc};
+use crates_io::schema::background_jobs;
+use crates_io::worker::jobs::{load_mirror_progress, MirrorUpstream};
+use crates_io_index::signing::{index_file_metadata_path, SigningKey};
+use crates_io_index::testing::UpstreamIndex;
+use crates_io_index::Repository;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use sha2::{Digest, Sha256};
+use tempfile::TempDir;
+use url::Url;
+
+/// An upstream registry with a git index, and crate files that are served
+/// from a local directory through `file://` URLs, which the mirror only
+/// reads if `mirror_allow_file_urls` is enabled.
+struct Upstream {
+    index: UpstreamIndex,
+    crate_files: TempDir,
+}
+
+impl Upstream {
+    fn new() -> Self {
+        let index = UpstreamIndex::new().unwrap();
+        let crate_files = TempDir::new().unwrap();
+
+        let dl = Url::from_directory_path(crate_files.path()).unwrap();
+        let config = json!({ "dl": format!("{dl}{{crate}}-{{version}}.crate") });
+        index
+            .write_file("config.json", &config.to_string())
+            .unwrap();
+
+        Self { index, crate_files }
+    }
+
+    fn url(&self) -> String {
+        self.index.url().to_string()
+    }
+
+    fn head(&self) -> String {
+        let repo = self.index.repository.lock().unwrap();
+        let head = repo.head().unwrap();
+        head.target().unwrap().to_string()
+    }
+
+    /// Writes the crate files and the index file of a crate, optionally with
+    /// a wrong `cksum` for the last version.
+    fn publish(&self, name: &str, versions: &[&str], corrupt: bool) {
+        let mut lines = Vec::new();
+        for (i, version) in versions.iter().enumerate() {
+            let content = format!("{name}-{version}");
+            let path = self.crate_files.path().join(format!("{content}.crate"));
+            std::fs::write(path, &content).unwrap();
+
+            let mut cksum = hex::encode(Sha256::digest(&content));
+            if corrupt && i == versions.len() - 1 {
+                cksum = hex::encode(Sha256::digest(b"corrupt"));
+            }
+
+            let entry = json!({
+                "name": name,
+                "vers": version,
+                "deps": [],
+                "cksum": cksum,
+                "features": {},
+                "yanked": false,
+            });
+            lines.push(entry.to_string());
+        }
+
+        let path = Repository::relative_index_file_for_url(name);
+        let content = lines.join("\n") + "\n";
+        self.index.write_file(&path, &content).unwrap();
+    }
+}
+
+async fn crate_files(app: &TestApp) -> Vec<String> {
+    let files = app.stored_files().await.into_iter();
+    let files = files.filter(|path| path.starts_with("crates/"));
+    files.collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn mirrors_upstream_incrementally() {
+    let (app, _) = TestApp::full()
+        .with_config(|config| config.mirror_allow_file_urls = true)
+        .empty();
+    let storage = &app.as_inner().storage;
+    let upstream = Upstream::new();
+
+    upstream.publish("foo", &["1.0.0", "1.1.0"], false);
+    upstream.publish("Bar", &["0.1.0"], false);
+
+    app.db(|conn| MirrorUpstream::new(upstream.url()).enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+
+    assert_eq!(
+        crate_files(&app).await,
+        vec![
+            "crates/Bar/Bar-0.1.0.crate",
+            "crates/foo/foo-1.0.0.crate",
+            "crates/foo/foo-1.1.0.crate",
+        ]
+    );
+
+    // Index files are mirrored unchanged
+    let expected = upstream.index.read_file("3/f/foo").unwrap();
+    let content = storage.read_index_file("foo").await.unwrap().unwrap();
+    assert_eq!(content, expected.as_bytes());
+    assert!(storage.read_index_file("bar").await.unwrap().is_some());
+
+    let head = upstream.head();
+    let progress = app.db(|conn| load_mirror_progress(&upstream.url(), conn).unwrap());
+    assert_eq!(progress.as_deref(), Some(head.as_str()));
+
+    // Only the new version is mirrored by the next run
+    storage.delete_crate_file("foo", "1.0.0").await.unwrap();
+    upstream.publish("foo", &["1.0.0", "1.1.0", "2.0.0"], false);
+
+    app.db(|conn| MirrorUpstream::new(upstream.url()).enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+
+    assert_eq!(
+        crate_files(&app).await,
+        vec![
+            "crates/Bar/Bar-0.1.0.crate",
+            "crates/foo/foo-1.1.0.crate",
+            "crates/foo/foo-2.0.0.crate",
+        ]
+    );
+
+    let head = upstream.head();
+    let progress = app.db(|conn| load_mirror_progress(&upstream.url(), conn).unwrap());
+    assert_eq!(progress.as_deref(), Some(head.as_str()));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn checksum_mismatch_is_retried() {
+    let (app, _) = TestApp::full()
+        .with_config(|config| config.mirror_allow_file_urls = true)
+        .empty();
+    let storage = &app.as_inner().storage;
+    let upstream = Upstream::new();
+
+    upstream.publish("foo", &["1.0.0"], false);
+    upstream.publish("bar", &["1.0.0", "1.1.0"], true);
+
+    app.db(|conn| MirrorUpstream::new(upstream.url()).enqueue(conn).unwrap());
+    assert_err!(app.try_run_pending_background_jobs().await);
+
+    // The index file of `bar` is not mirrored, since one of its crate files
+    // could not be verified
+    assert!(storage.read_index_file("foo").await.unwrap().is_some());
+    assert!(storage.read_index_file("bar").await.unwrap().is_none());
+    assert!(!crate_files(&app)
+        .await
+        .contains(&"crates/bar/bar-1.1.0.crate".to_string()));
+
+    let progress = app.db(|conn| load_mirror_progress(&upstream.url(), conn).unwrap());
+    assert_eq!(progress, None);
+
+    // Pretend that the upstream was fixed and the retry backoff has passed
+    upstream.publish("bar", &["1.0.0", "1.1.0"], false);
+    app.db(|conn| {
+        let last_retry = Utc::now().naive_utc() - Duration::days(1);
+        diesel::update(background_jobs::table)
+            .set(background_jobs::last_retry.eq(last_retry))
+            .execute(conn)
+            .unwrap();
+    });
+    app.run_pending_background_jobs().await;
+
+    assert!(storage.read_index_file("bar").await.unwrap().is_some());
+    assert!(crate_files(&app)
+        .await
+        .contains(&"crates/bar/bar-1.1.0.crate".to_string()));
+
+    let head = upstream.head();
+    let progress = app.db(|conn| load_mirror_progress(&upstream.url(), conn).unwrap());
+    assert_eq!(progress.as_deref(), Some(head.as_str()));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn file_urls_are_rejected_by_default() {
+    let (app, _) = TestApp::full().empty();
+    let storage = &app.as_inner().storage;
+    let upstream = Upstream::new();
+
+    upstream.publish("foo", &["1.0.0"], false);
+
+    app.db(|conn| MirrorUpstream::new(upstream.url()).enqueue(conn).unwrap());
+    assert_err!(app.try_run_pending_background_jobs().await);
+
+    assert!(storage.read_index_file("foo").await.unwrap().is_none());
+    assert!(crate_files(&app).await.is_empty());
+
+    let progress = app.db(|conn| load_mirror_progress(&upstream.url(), conn).unwrap());
+    assert_eq!(progress, None);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn deleted_index_files_are_deleted() {
+    let checkout_dir = TempDir::new().unwrap();
+    let checkout_path = checkout_dir.path().to_path_buf();
+    let (app, _) = TestApp::full()
+        .with_config(|config| {
+            config.index_signing_key = Some(SigningKey::generate().unwrap().0);
+            config.mirror_checkout_dir = Some(checkout_path);
+            config.mirror_allow_file_urls = true;
+        })
+        .empty();
+
+    let storage = &app.as_inner().storage;
+    let upstream = Upstream::new();
+
+    upstream.publish("foo", &["1.0.0"], false);
+    upstream.publish("bar", &["1.0.0"], false);
+
+    app.db(|conn| MirrorUpstream::new(upstream.url()).enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+
+    let metadata_path = index_file_metadata_path("foo");
+    assert!(storage.read_index_file("foo").await.unwrap().is_some());
+    assert!(storage
+        .read_index_metadata(&metadata_path)
+        .await
+        .unwrap()
+        .is_some());
+
+    // The checkout of the upstream index is kept for the next run
+    let checkouts = std::fs::read_dir(checkout_dir.path()).unwrap();
+    assert_eq!(checkouts.count(), 1);
+
+    upstream.index.delete_file("3/f/foo").unwrap();
+
+    app.db(|conn| MirrorUpstream::new(upstream.url()).enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+
+    assert!(storage.read_index_file("foo").await.unwrap().is_none());
+    assert!(storage
+        .read_index_metadata(&metadata_path)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(storage.read_index_file("bar").await.unwrap().is_some());
+
+    // The crate files are kept
+    assert_eq!(
+        crate_files(&app).await,
+        vec!["crates/bar/bar-1.0.0.crate", "crates/foo/foo-1.0.0.crate"]
+    );
+
+    let checkouts = std::fs::read_dir(checkout_dir.path()).unwrap();
+    assert_eq!(checkouts.count(), 1);
+
+    let head = upstream.head();
+    let progress = app.db(|conn| load_mirror_progress(&upstream.url(), conn).unwrap());
+    assert_eq!(progress.as_deref(), Some(head.as_str()));
+}
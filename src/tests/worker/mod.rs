@@ -1,2 +1,6 @@
 mod git;
+mod index_signing;
+mod mirror;
+mod prune_token_usages;
 mod sync_admins;
+mod verify_storage;
@@ -0,0 +1,45 @@
+use crate::util::TestApp;
+use crates_io::models::token::{ApiTokenUsage, NewApiTokenUsage};
+use crates_io::worker::jobs::PruneTokenUsages;
+use crates_io_worker::BackgroundJob;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn prune_token_usages() {
+    let (app, _, user) = TestApp::full().with_user();
+    let token_id = user.db_new_token("foo").as_model().id;
+    let other_token_id = user.db_new_token("bar").as_model().id;
+
+    app.db(|conn| {
+        for i in 0..110 {
+            let endpoint = format!("GET /api/v1/crates/{i}");
+            let usage = NewApiTokenUsage {
+                api_token_id: token_id,
+                endpoint: &endpoint,
+                crate_name: None,
+                ip: None,
+                rejection_reason: None,
+            };
+            usage.insert(conn).unwrap();
+        }
+
+        let usage = NewApiTokenUsage {
+            api_token_id: other_token_id,
+            endpoint: "GET /api/v1/crates",
+            crate_name: None,
+            ip: None,
+            rejection_reason: None,
+        };
+        usage.insert(conn).unwrap();
+    });
+
+    app.db(|conn| PruneTokenUsages.enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+
+    let usages = app.db(|conn| ApiTokenUsage::recent(conn, token_id).unwrap());
+    assert_eq!(usages.len(), 100);
+    assert_eq!(usages[0].endpoint, "GET /api/v1/crates/109");
+    assert_eq!(usages[99].endpoint, "GET /api/v1/crates/10");
+
+    let usages = app.db(|conn| ApiTokenUsage::recent(conn, other_token_id).unwrap());
+    assert_eq!(usages.len(), 1);
+}
@@ -0,0 +1,71 @@
+use crate::builders::PublishBuilder;
+use crate::util::{RequestHelper, TestApp};
+use bytes::Bytes;
+use crates_io::worker::jobs::{load_crate_files, verify_crate_files, Cdns, VerifyCrateFiles};
+use crates_io_worker::BackgroundJob;
+
+async fn prepare() -> TestApp {
+    let (app, _, _, token) = TestApp::full().with_token();
+
+    for (name, version) in [("foo", "1.0.0"), ("foo", "1.1.0"), ("bar", "1.0.0")] {
+        let crate_to_publish = PublishBuilder::new(name, version);
+        token.publish_crate(crate_to_publish).await.good();
+    }
+
+    let storage = &app.as_inner().storage;
+    let tampered = Bytes::from_static(b"tampered");
+    storage
+        .upload_crate_file("foo", "1.0.0", tampered)
+        .await
+        .unwrap();
+    storage.delete_crate_file("bar", "1.0.0").await.unwrap();
+
+    app
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reports_missing_and_mismatched_crate_files() {
+    let app = prepare().await;
+    let storage = &app.as_inner().storage;
+
+    let crate_files = app.db(|conn| load_crate_files(None, conn).unwrap());
+    let report = verify_crate_files(storage, &Cdns::default(), crate_files, false).await;
+    assert_eq!(report.checked, 3);
+    assert_eq!(report.missing, vec!["bar@1.0.0"]);
+    assert_eq!(report.mismatched, vec!["foo@1.0.0"]);
+    assert!(report.failed.is_empty());
+
+    let crate_files = app.db(|conn| load_crate_files(Some("bar"), conn).unwrap());
+    let report = verify_crate_files(storage, &Cdns::default(), crate_files, false).await;
+    assert_eq!(report.checked, 1);
+    assert_eq!(report.missing, vec!["bar@1.0.0"]);
+    assert!(report.mismatched.is_empty());
+
+    // Nothing is moved without the `quarantine` flag
+    assert!(app
+        .stored_files()
+        .await
+        .contains(&"crates/foo/foo-1.0.0.crate".to_string()));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn quarantines_mismatched_crate_files() {
+    let app = prepare().await;
+
+    app.db(|conn| VerifyCrateFiles::new(None, true).enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+
+    let crate_files = app
+        .stored_files()
+        .await
+        .into_iter()
+        .filter(|path| path.ends_with(".crate"))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        crate_files,
+        vec![
+            "crates/foo/foo-1.1.0.crate",
+            "quarantine/crates/foo/foo-1.0.0.crate"
+        ]
+    );
+}
@@ -3,11 +3,15 @@ use std::sync::Arc;
 use diesel::PgConnection;
 use thiserror::Error;
 use typomania::{
-    checks::{Bitflips, Omitted, SwappedWords, Typos},
+    checks::{Bitflips, Omitted, SwappedCharacters, SwappedWords, Typos},
     Harness,
 };
 
-use super::{checks::Affixes, config, database::TopCrates};
+use super::{
+    checks::{Affixes, Homoglyphs},
+    config::{self, Config},
+    database::TopCrates,
+};
 
 static NOTIFICATION_EMAILS_ENV: &str = "TYPOSQUAT_NOTIFICATION_EMAILS";
 
@@ -18,15 +22,15 @@ static NOTIFICATION_EMAILS_ENV: &str = "TYPOSQUAT_NOTIFICATION_EMAILS";
 /// discovered.
 pub struct Cache {
     emails: Vec<String>,
-    harness: Option<Harness<TopCrates>>,
+    harness: Harness<TopCrates>,
 }
 
 impl Cache {
     /// Instantiates a new [`Cache`] from the environment.
     ///
     /// This reads the `NOTIFICATION_EMAILS_ENV` environment variable to get the list of e-mail
-    /// addresses to send notifications to, then invokes [`Cache::new`] to read popular crates from
-    /// the database.
+    /// addresses to send notifications to, and the configuration via [`Config::from_env`]. It then
+    /// invokes [`Cache::new`] to read popular crates from the database.
     #[instrument(skip_all, err)]
     pub fn from_env(conn: &mut PgConnection) -> Result<Self, Error> {
         let emails: Vec<String> = crates_io_env_vars::var(NOTIFICATION_EMAILS_ENV)
@@ -41,48 +45,48 @@ impl Cache {
             .collect();
 
         if emails.is_empty() {
-            // If we're not notifying anyone, then there's really not much to do here.
+            // The findings are still recorded in the database, so we can go ahead anyway.
             warn!("$TYPOSQUAT_NOTIFICATION_EMAILS is not set; no typosquatting notifications will be sent");
-            Ok(Self {
-                emails,
-                harness: None,
-            })
-        } else {
-            // Otherwise, let's go get the top crates and build a corpus.
-            Self::new(emails, conn)
         }
+
+        let config = Config::from_env().map_err(|e| Error::Config(Arc::new(e)))?;
+        Self::new(emails, &config, conn)
     }
 
     /// Instantiates a cache by querying popular crates and building them into a typomania harness.
-    ///
-    /// This relies on configuration in the `super::config` module.
-    pub fn new(emails: Vec<String>, conn: &mut PgConnection) -> Result<Self, Error> {
-        let top = TopCrates::new(conn, config::TOP_CRATES)?;
+    pub fn new(
+        emails: Vec<String>,
+        config: &Config,
+        conn: &mut PgConnection,
+    ) -> Result<Self, Error> {
+        let top = TopCrates::new(conn, config.top_crates)?;
+        let typos = || config.typos.iter().map(|(c, typos)| (*c, typos.clone()));
 
         Ok(Self {
             emails,
-            harness: Some(
-                Harness::builder()
-                    .with_check(Bitflips::new(
-                        config::CRATE_NAME_ALPHABET,
-                        top.crates.keys().map(String::as_str),
-                    ))
-                    .with_check(Omitted::new(config::CRATE_NAME_ALPHABET))
-                    .with_check(SwappedWords::new("-_"))
-                    .with_check(Typos::new(config::TYPOS.iter().map(|(c, typos)| {
-                        (*c, typos.iter().map(|ss| ss.to_string()).collect())
-                    })))
-                    .with_check(Affixes::new(
-                        config::SUFFIXES.iter(),
-                        config::SUFFIX_SEPARATORS.iter(),
-                    ))
-                    .build(top),
-            ),
+            harness: Harness::builder()
+                .with_check(Bitflips::new(
+                    config::CRATE_NAME_ALPHABET,
+                    top.crates.keys().map(String::as_str),
+                ))
+                .with_check(Omitted::new(config::CRATE_NAME_ALPHABET))
+                .with_check(SwappedCharacters)
+                .with_check(SwappedWords::new("-_"))
+                .with_check(Typos::new(typos()))
+                .with_check(Homoglyphs::new(
+                    &config.homoglyphs,
+                    top.crates.keys().map(String::as_str),
+                ))
+                .with_check(
+                    Affixes::new(config.suffixes.iter(), config.suffix_separators.iter())
+                        .with_typos(typos()),
+                )
+                .build(top),
         })
     }
 
-    pub fn get_harness(&self) -> Option<&Harness<TopCrates>> {
-        self.harness.as_ref()
+    pub fn get_harness(&self) -> &Harness<TopCrates> {
+        &self.harness
     }
 
     pub fn iter_emails(&self) -> impl Iterator<Item = &str> {
@@ -103,6 +107,9 @@ pub enum Error {
         source: Arc<anyhow::Error>,
     },
 
+    #[error("error reading the typosquatting configuration: {0:?}")]
+    Config(#[source] Arc<anyhow::Error>),
+
     #[error("error getting top crates: {0:?}")]
     TopCrates(#[source] Arc<diesel::result::Error>),
 }
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use typomania::{
     checks::{Check, Squat},
     Corpus, Package,
@@ -5,9 +7,13 @@ use typomania::{
 
 /// A typomania check that checks if commonly used prefixes or suffixes have been added to or
 /// removed from a package name.
+///
+/// If typos are configured via [`Affixes::with_typos`], names that add an affix to a common typo
+/// of a popular package name are flagged as well; eg `sedre-rs` for `serde`.
 pub struct Affixes {
     affixes: Vec<String>,
     separators: Vec<String>,
+    typos: HashMap<char, Vec<String>>,
 }
 
 impl Affixes {
@@ -21,7 +27,48 @@ impl Affixes {
         Self {
             affixes: affixes.map(|s| s.to_string()).collect(),
             separators: separators.map(|s| s.to_string()).collect(),
+            typos: HashMap::new(),
+        }
+    }
+
+    /// Also checks the stems of affixed package names for the given typos, which work the same
+    /// way as in [`typomania::checks::Typos`].
+    pub fn with_typos(mut self, typos: impl Iterator<Item = (char, Vec<String>)>) -> Self {
+        self.typos = typos.collect();
+        self
+    }
+
+    /// Checks if replacing a single character of the stem with one of its typos results in a
+    /// popular package name.
+    fn typo_squats(
+        &self,
+        corpus: &dyn Corpus,
+        stem: &str,
+        name: &str,
+        package: &dyn Package,
+        message: &str,
+    ) -> typomania::Result<Vec<Squat>> {
+        let mut squats = Vec::new();
+
+        for (i, c) in stem.chars().enumerate() {
+            for typo in self.typos.get(&c).into_iter().flatten() {
+                let stem_to_check = stem
+                    .chars()
+                    .take(i)
+                    .chain(typo.chars())
+                    .chain(stem.chars().skip(i + 1))
+                    .collect::<String>();
+
+                if corpus.possible_squat(&stem_to_check, name, package)? {
+                    squats.push(Squat::Custom {
+                        message: message.to_string(),
+                        package: stem_to_check,
+                    });
+                }
+            }
         }
+
+        Ok(squats)
     }
 }
 
@@ -46,6 +93,9 @@ impl Check for Affixes {
                             package: stem.to_string(),
                         })
                     }
+
+                    let message = format!("adds the {combo} prefix to a common typo");
+                    squats.extend(self.typo_squats(corpus, stem, name, package, &message)?);
                 }
 
                 // Alternatively, let's see if adding the prefix and separator combo to the package
@@ -68,6 +118,9 @@ impl Check for Affixes {
                             package: stem.to_string(),
                         })
                     }
+
+                    let message = format!("adds the {combo} suffix to a common typo");
+                    squats.extend(self.typo_squats(corpus, stem, name, package, &message)?);
                 }
 
                 // Alternatively, let's see if adding the separator and suffix combo to the package
@@ -86,6 +139,100 @@ impl Check for Affixes {
     }
 }
 
+/// A typomania check that checks if a package name only differs from a popular package name by
+/// character sequences that look alike; eg `rnio` for `mio`, or `1og` for `log`.
+pub struct Homoglyphs {
+    /// Every sequence of the configured groups, longest first, along with the first sequence of
+    /// its group that it gets replaced with.
+    replacements: Vec<(String, String)>,
+    /// The names of the popular packages, indexed by their skeletons.
+    skeletons: HashMap<String, Vec<String>>,
+}
+
+impl Homoglyphs {
+    pub fn new<'a>(groups: &[Vec<String>], names: impl Iterator<Item = &'a str>) -> Self {
+        let mut replacements = groups
+            .iter()
+            .filter_map(|group| group.split_first())
+            .flat_map(|(canonical, others)| {
+                others
+                    .iter()
+                    .filter(|sequence| !sequence.is_empty())
+                    .map(|sequence| (sequence.to_lowercase(), canonical.to_lowercase()))
+            })
+            .collect::<Vec<_>>();
+
+        replacements.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()));
+
+        let mut check = Self {
+            replacements,
+            skeletons: HashMap::new(),
+        };
+
+        for name in names {
+            let skeleton = check.skeleton(name);
+            check
+                .skeletons
+                .entry(skeleton)
+                .or_default()
+                .push(name.to_string());
+        }
+
+        check
+    }
+
+    /// Returns the canonical form of a package name, in which every look-alike sequence has been
+    /// replaced by the first sequence of its group.
+    fn skeleton(&self, name: &str) -> String {
+        let name = name.to_lowercase().replace('_', "-");
+
+        let mut skeleton = String::with_capacity(name.len());
+        let mut rest = name.as_str();
+        while let Some(c) = rest.chars().next() {
+            let replacement = self
+                .replacements
+                .iter()
+                .find(|(sequence, _)| rest.starts_with(sequence.as_str()));
+
+            match replacement {
+                Some((sequence, canonical)) => {
+                    skeleton.push_str(canonical);
+                    rest = &rest[sequence.len()..];
+                }
+                None => {
+                    skeleton.push(c);
+                    rest = &rest[c.len_utf8()..];
+                }
+            }
+        }
+
+        skeleton
+    }
+}
+
+impl Check for Homoglyphs {
+    fn check(
+        &self,
+        corpus: &dyn Corpus,
+        name: &str,
+        package: &dyn Package,
+    ) -> typomania::Result<Vec<Squat>> {
+        let mut squats = Vec::new();
+
+        let similar_names = self.skeletons.get(&self.skeleton(name));
+        for similar_name in similar_names.into_iter().flatten() {
+            if similar_name != name && corpus.possible_squat(similar_name, name, package)? {
+                squats.push(Squat::Custom {
+                    message: "uses look-alike characters".to_string(),
+                    package: similar_name.clone(),
+                });
+            }
+        }
+
+        Ok(squats)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::{HashMap, HashSet};
@@ -141,6 +288,87 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_affixes_with_typos() -> anyhow::Result<()> {
+        let popular = TestCorpus::default()
+            .with_package(TestPackage::new("serde", "serde", ["Alice"]))
+            .with_package(TestPackage::new("tokio", "tokio", ["Bob"]));
+
+        let harness = Harness::empty_builder()
+            .with_check(
+                Affixes::new(["rs"].iter(), ["-"].iter())
+                    .with_typos([('f', vec!["d".to_string()])].into_iter()),
+            )
+            .build(popular);
+
+        for package in [
+            TestPackage::new("serfe", "typo without affix", ["Charlie"]),
+            TestPackage::new("serfe-rs", "shared author", ["Alice"]),
+            TestPackage::new("tokio-cli", "unknown affix", ["Charlie"]),
+        ]
+        .into_iter()
+        {
+            let name = package.name.clone();
+            let squats = harness.check_package(&name, Box::new(package))?;
+            assert_that!(squats, empty());
+        }
+
+        let package = TestPackage::new("serfe-rs", "no shared author", ["Charlie"]);
+        let squats = harness.check_package("serfe-rs", Box::new(package))?;
+        assert_that!(squats, len(eq(1)));
+        assert_eq!(
+            squats[0].to_string(),
+            "adds the -rs suffix to a common typo for serde"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_homoglyphs() -> anyhow::Result<()> {
+        let popular = TestCorpus::default()
+            .with_package(TestPackage::new("mio", "mio", ["Alice"]))
+            .with_package(TestPackage::new("log", "log", ["Bob"]))
+            .with_package(TestPackage::new("cloud-sdk", "cloud-sdk", ["Bob"]));
+
+        let groups = [
+            vec!["l".to_string(), "1".to_string(), "I".to_string()],
+            vec!["m".to_string(), "rn".to_string()],
+            vec!["d".to_string(), "cl".to_string()],
+        ];
+        let names = ["mio", "log", "cloud-sdk"];
+
+        let harness = Harness::empty_builder()
+            .with_check(Homoglyphs::new(&groups, names.into_iter()))
+            .build(popular);
+
+        for package in [
+            TestPackage::new("mio", "same name", ["Charlie"]),
+            TestPackage::new("rnio", "shared author", ["Alice"]),
+            TestPackage::new("dog", "unrelated package", ["Charlie"]),
+        ]
+        .into_iter()
+        {
+            let name = package.name.clone();
+            let squats = harness.check_package(&name, Box::new(package))?;
+            assert_that!(squats, empty());
+        }
+
+        for (name, similar_name) in [
+            ("rnio", "mio"),
+            ("1og", "log"),
+            ("Iog", "log"),
+            ("doud_sdk", "cloud-sdk"),
+        ] {
+            let package = TestPackage::new(name, "no shared author", ["Charlie"]);
+            let squats = harness.check_package(name, Box::new(package))?;
+            assert_that!(squats, len(eq(1)));
+            assert_eq!(squats[0].package(), similar_name);
+        }
+
+        Ok(())
+    }
+
     struct TestPackage {
         name: String,
         description: String,
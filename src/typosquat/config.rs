@@ -1,63 +1,95 @@
-//! Things that should really be read from a configuration file, but are just hardcoded while we
-//! experiment with this.
+//! Configuration of the typosquatting checks, which is read from a TOML file.
+//!
+//! The defaults live in `config.toml` next to this module and are compiled into the binary.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
 
 /// Valid characters in crate names.
 pub(super) static CRATE_NAME_ALPHABET: &str =
     "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz1234567890-_";
 
-/// Commonly used separators when building crate names.
-pub(super) static SUFFIX_SEPARATORS: &[&str] = &["-", "_"];
-
-/// Commonly used suffixes when building crate names.
-pub(super) static SUFFIXES: &[&str] = &["api", "cargo", "cli", "core", "lib", "rs", "rust", "sys"];
-
-/// The number of crates to consider in the "top crates" corpus.
-pub(super) static TOP_CRATES: i64 = 3000;
-
-/// This is based on a pre-existing list we've used with crates.io for "easily confused
-/// characters". This is a mixture of visual substitutions and typos on QWERTY, QWERTZ, and AZERTY
-/// keyboards.
-pub(super) static TYPOS: &[(char, &[&str])] = &[
-    ('1', &["2", "q", "i", "l"]),
-    ('2', &["1", "q", "w", "3"]),
-    ('3', &["2", "w", "e", "4"]),
-    ('4', &["3", "e", "r", "5"]),
-    ('5', &["4", "r", "t", "6", "s"]),
-    ('6', &["5", "t", "y", "7"]),
-    ('7', &["6", "y", "u", "8"]),
-    ('8', &["7", "u", "i", "9"]),
-    ('9', &["8", "i", "o", "0"]),
-    ('0', &["9", "o", "p", "-"]),
-    ('-', &["_", "0", "p", ".", ""]),
-    ('_', &["-", "0", "p", ".", ""]),
-    ('q', &["1", "2", "w", "a", "s", "z"]),
-    ('w', &["2", "3", "e", "s", "a", "q", "vv", "x"]),
-    ('e', &["3", "4", "r", "d", "s", "w", "z"]),
-    ('r', &["4", "5", "t", "f", "d", "e"]),
-    ('t', &["5", "6", "y", "g", "f", "r"]),
-    ('y', &["6", "7", "u", "h", "t", "i", "a", "s", "x"]),
-    ('u', &["7", "8", "i", "j", "y", "v"]),
-    ('i', &["1", "8", "9", "o", "l", "k", "j", "u", "y"]),
-    ('o', &["9", "0", "p", "l", "i"]),
-    ('p', &["0", "-", "o"]),
-    ('a', &["q", "w", "s", "z", "1", "2"]),
-    ('s', &["w", "d", "x", "z", "a", "5", "q"]),
-    ('d', &["e", "r", "f", "c", "x", "s"]),
-    ('f', &["r", "g", "v", "c", "d"]),
-    ('g', &["t", "h", "b", "v", "f"]),
-    ('h', &["y", "j", "n", "b", "g"]),
-    ('j', &["u", "i", "k", "m", "n", "h"]),
-    ('k', &["i", "o", "l", "m", "j"]),
-    ('l', &["i", "o", "p", "k", "1"]),
-    (
-        'z',
-        &["a", "s", "x", "6", "7", "u", "h", "t", "i", "e", "2", "3"],
-    ),
-    ('x', &["z", "s", "d", "c", "w"]),
-    ('c', &["x", "d", "f", "v"]),
-    ('v', &["c", "f", "g", "b", "u"]),
-    ('b', &["v", "g", "h", "n"]),
-    ('n', &["b", "h", "j", "m"]),
-    ('m', &["n", "j", "k", "rn"]),
-    ('.', &["-", "_", ""]),
-];
+static DEFAULT_CONFIG: &str = include_str!("config.toml");
+
+static CONFIG_PATH_ENV: &str = "TYPOSQUAT_CONFIG_PATH";
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// The number of crates to consider in the "top crates" corpus.
+    pub top_crates: i64,
+
+    /// Commonly used separators when building crate names.
+    pub suffix_separators: Vec<String>,
+
+    /// Commonly used suffixes when building crate names.
+    pub suffixes: Vec<String>,
+
+    /// Groups of character sequences that look alike.
+    #[serde(default)]
+    pub homoglyphs: Vec<Vec<String>>,
+
+    /// Characters that are easily confused, and the strings they are commonly confused with.
+    #[serde(default)]
+    pub typos: BTreeMap<char, Vec<String>>,
+}
+
+impl Config {
+    /// Reads the configuration from the file in the `CONFIG_PATH_ENV` environment variable, or
+    /// falls back to the default configuration if it is not set.
+    pub fn from_env() -> anyhow::Result<Self> {
+        match crates_io_env_vars::var_parsed::<PathBuf>(CONFIG_PATH_ENV)? {
+            Some(path) => Self::from_file(&path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Reads the configuration from a TOML file.
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+
+        content
+            .parse()
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+}
+
+impl std::str::FromStr for Config {
+    type Err = toml::de::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        toml::from_str(s)
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        DEFAULT_CONFIG
+            .parse()
+            .expect("the default typosquatting configuration is invalid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_config() {
+        let config = Config::default();
+        assert_eq!(config.top_crates, 3000);
+        assert_eq!(config.suffix_separators, vec!["-", "_"]);
+        assert!(config.suffixes.contains(&"rs".to_string()));
+        assert_eq!(config.typos[&'m'], vec!["n", "j", "k", "rn"]);
+        assert!(config.homoglyphs.iter().all(|group| group.len() > 1));
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        let config = "top_crates = 10\nsuffix_separators = []\nsuffixes = []\nfoo = 1";
+        assert!(config.parse::<Config>().is_err());
+    }
+}
@@ -112,6 +112,17 @@ impl Crate {
 
         Ok(Self { owners })
     }
+
+    /// Creates a crate that is only owned by the given user, like a new crate that is about to be
+    /// published.
+    pub fn owned_by(user_id: i32) -> Self {
+        use crate::models::OwnerKind;
+
+        let owner = Owner::new(user_id, OwnerKind::User as i32);
+        Self {
+            owners: HashSet::from([owner]),
+        }
+    }
 }
 
 impl Package for Crate {
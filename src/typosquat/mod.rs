@@ -7,4 +7,5 @@ mod database;
 pub(super) mod test_util;
 
 pub use cache::{Cache, Error as CacheError};
+pub use config::Config;
 pub use database::Crate;
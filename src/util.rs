@@ -7,9 +7,12 @@ pub use self::request_helpers::*;
 mod bytes_request;
 pub mod errors;
 mod io_util;
+pub mod ip;
+pub mod jwks;
 mod request_helpers;
 pub mod rfc3339;
 pub mod token;
+pub mod totp;
 pub mod tracing;
 
 #[derive(Debug, Copy, Clone)]
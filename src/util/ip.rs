@@ -0,0 +1,118 @@
+use ipnetwork::IpNetwork;
+use once_cell::sync::Lazy;
+use std::net::IpAddr;
+
+/// Networks that are reserved for special purposes by the IANA, and can't
+/// be reached via the public internet.
+///
+/// See <https://www.iana.org/assignments/iana-ipv4-special-registry/> and
+/// <https://www.iana.org/assignments/iana-ipv6-special-registry/>.
+static NON_PUBLIC_NETWORKS: Lazy<Vec<IpNetwork>> = Lazy::new(|| {
+    [
+        // "This network"
+        "0.0.0.0/8",
+        // Private-use
+        "10.0.0.0/8",
+        // Shared address space
+        "100.64.0.0/10",
+        // Loopback
+        "127.0.0.0/8",
+        // Link-local, including the cloud metadata endpoints
+        "169.254.0.0/16",
+        // Private-use
+        "172.16.0.0/12",
+        // IETF protocol assignments
+        "192.0.0.0/24",
+        // Documentation (TEST-NET-1)
+        "192.0.2.0/24",
+        // 6to4 relay anycast
+        "192.88.99.0/24",
+        // Private-use
+        "192.168.0.0/16",
+        // Benchmarking
+        "198.18.0.0/15",
+        // Documentation (TEST-NET-2 and TEST-NET-3)
+        "198.51.100.0/24",
+        "203.0.113.0/24",
+        // Multicast
+        "224.0.0.0/4",
+        // Reserved, including the limited broadcast address
+        "240.0.0.0/4",
+        // Unspecified, loopback and the deprecated IPv4-compatible addresses
+        "::/96",
+        // IPv4/IPv6 translation (NAT64)
+        "64:ff9b::/96",
+        "64:ff9b:1::/48",
+        // Discard-only
+        "100::/64",
+        // IETF protocol assignments, including Teredo
+        "2001::/23",
+        // Documentation
+        "2001:db8::/32",
+        // 6to4
+        "2002::/16",
+        // Unique local
+        "fc00::/7",
+        // Link-local
+        "fe80::/10",
+        // Site-local (deprecated)
+        "fec0::/10",
+        // Multicast
+        "ff00::/8",
+    ]
+    .iter()
+    .map(|network| network.parse().unwrap())
+    .collect()
+});
+
+/// Checks whether `ip` is a public address, i.e. not a loopback, private,
+/// link-local or other special-purpose address.
+///
+/// IPv4-mapped IPv6 addresses are checked like the IPv4 address they
+/// represent.
+pub fn is_public_address(ip: IpAddr) -> bool {
+    let ip = match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+        IpAddr::V4(_) => ip,
+    };
+
+    !NON_PUBLIC_NETWORKS
+        .iter()
+        .any(|network| network.contains(ip))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_public_address;
+
+    #[test]
+    fn public_addresses() {
+        let public = |ip: &str| is_public_address(ip.parse().unwrap());
+
+        assert!(public("93.184.216.34"));
+        assert!(public("2606:2800:220:1:248:1893:25c8:1946"));
+        assert!(public("::ffff:93.184.216.34"));
+
+        assert!(!public("127.0.0.1"));
+        assert!(!public("10.1.2.3"));
+        assert!(!public("172.16.0.1"));
+        assert!(!public("192.168.1.1"));
+        assert!(!public("169.254.169.254"));
+        assert!(!public("100.64.0.1"));
+        assert!(!public("0.0.0.0"));
+        assert!(!public("192.0.0.8"));
+        assert!(!public("198.18.0.1"));
+        assert!(!public("224.0.0.1"));
+        assert!(!public("240.0.0.1"));
+        assert!(!public("255.255.255.255"));
+        assert!(!public("::1"));
+        assert!(!public("::"));
+        assert!(!public("fd00::1"));
+        assert!(!public("fe80::1"));
+        assert!(!public("ff02::1"));
+        assert!(!public("::ffff:127.0.0.1"));
+        assert!(!public("::ffff:169.254.169.254"));
+        assert!(!public("64:ff9b::a9fe:a9fe"));
+        assert!(!public("2002:a9fe:a9fe::1"));
+    }
+}
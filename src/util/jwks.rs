@@ -0,0 +1,73 @@
+//! Cache of the JSON Web Key Sets (JWKS) that OIDC issuers use to sign their
+//! identity tokens.
+
+use chrono::{DateTime, Utc};
+use jsonwebtoken::jwk::JwkSet;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Minimum time to wait before refreshing a cached key set
+const CACHE_LIFETIME: Duration = Duration::from_secs(60 * 60); // 1 hour
+
+/// Minimum time to wait before refreshing a cached key set because of an
+/// unknown key ID. This allows picking up rotated keys without hammering the
+/// issuer with requests for bogus key IDs.
+const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Key sets that have been fetched from OIDC issuers, keyed by their URL.
+///
+/// The lock is only held while reading or updating the cache, so a slow
+/// issuer doesn't block the requests for other issuers or cached keys.
+#[derive(Default)]
+pub struct JwksCache {
+    client: reqwest::Client,
+    key_sets: Mutex<HashMap<String, CachedKeySet>>,
+}
+
+struct CachedKeySet {
+    keys: Arc<JwkSet>,
+    timestamp: DateTime<Utc>,
+}
+
+impl JwksCache {
+    /// Returns the key set at the given URL, which is fetched again if it
+    /// is outdated or doesn't contain the key with the given ID.
+    pub async fn get(&self, url: &str, kid: &str) -> reqwest::Result<Arc<JwkSet>> {
+        if let Some(keys) = self.cached(url, kid) {
+            return Ok(keys);
+        }
+
+        let keys: JwkSet = self
+            .client
+            .get(url)
+            .timeout(FETCH_TIMEOUT)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let keys = Arc::new(keys);
+        let cached = CachedKeySet {
+            keys: keys.clone(),
+            timestamp: Utc::now(),
+        };
+        self.key_sets.lock().insert(url.to_string(), cached);
+
+        Ok(keys)
+    }
+
+    fn cached(&self, url: &str, kid: &str) -> Option<Arc<JwkSet>> {
+        let key_sets = self.key_sets.lock();
+        let cached = key_sets.get(url)?;
+
+        let age = (Utc::now() - cached.timestamp).to_std().unwrap_or_default();
+        let contains_key = cached.keys.find(kid).is_some();
+        let is_valid = (contains_key && age < CACHE_LIFETIME) || age < MIN_REFRESH_INTERVAL;
+        is_valid.then(|| cached.keys.clone())
+    }
+}
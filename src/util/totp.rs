@@ -0,0 +1,184 @@
+//! Time-based one-time passwords (TOTP) as described in
+//! [RFC 6238](https://datatracker.ietf.org/doc/html/rfc6238).
+//!
+//! Only the parameters that all common authenticator apps support are
+//! implemented: HMAC-SHA1, six digits and a 30 second time step.
+
+use rand::rngs::OsRng;
+use rand::RngCore;
+use ring::hmac;
+
+/// Length of the shared secret in bytes, as recommended by RFC 4226.
+const SECRET_LENGTH: usize = 20;
+
+/// Number of seconds that a code is valid for.
+const TIME_STEP: i64 = 30;
+
+const DIGITS: u32 = 6;
+
+/// Number of time steps before and after the current one whose codes are
+/// accepted too, to allow for clock drift and slow typing.
+const ALLOWED_DRIFT: i64 = 1;
+
+/// Generates a new random shared secret.
+pub fn generate_secret() -> Vec<u8> {
+    let mut secret = vec![0; SECRET_LENGTH];
+    OsRng.fill_bytes(&mut secret);
+    secret
+}
+
+/// Returns the time step that a unix timestamp falls into.
+pub fn time_step(timestamp: i64) -> i64 {
+    timestamp.div_euclid(TIME_STEP)
+}
+
+/// Generates the code for a time step (see RFC 4226, section 5.3).
+pub fn generate_code(secret: &[u8], step: i64) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY, secret);
+    let tag = hmac::sign(&key, &step.to_be_bytes());
+    let hash = tag.as_ref();
+
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let truncated = u32::from_be_bytes(hash[offset..offset + 4].try_into().unwrap()) & 0x7fff_ffff;
+
+    format!(
+        "{:0width$}",
+        truncated % 10u32.pow(DIGITS),
+        width = DIGITS as usize
+    )
+}
+
+/// Checks a code against the codes of the time steps around `timestamp`,
+/// and returns the time step that the code belongs to.
+///
+/// Codes of time steps up to and including `last_used_step` are rejected,
+/// so that an intercepted code can't be used a second time.
+pub fn verify_code(
+    secret: &[u8],
+    code: &str,
+    timestamp: i64,
+    last_used_step: Option<i64>,
+) -> Option<i64> {
+    let code = code.trim();
+    if code.len() != DIGITS as usize || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let current_step = time_step(timestamp);
+    (current_step - ALLOWED_DRIFT..=current_step + ALLOWED_DRIFT)
+        .filter(|step| last_used_step.map_or(true, |last_used_step| *step > last_used_step))
+        .find(|step| generate_code(secret, *step) == code)
+}
+
+/// Encodes a secret in the unpadded base32 format that authenticator apps
+/// expect (see RFC 4648, section 6).
+pub fn encode_secret(secret: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+    let mut encoded = String::with_capacity((secret.len() * 8 + 4) / 5);
+    let mut buffer = 0u16;
+    let mut bits = 0;
+    for &byte in secret {
+        buffer = (buffer << 8) | byte as u16;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        encoded.push(ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    encoded
+}
+
+/// Returns the `otpauth://` URI that authenticator apps can import, usually
+/// by scanning it as a QR code.
+///
+/// See <https://github.com/google/google-authenticator/wiki/Key-Uri-Format>.
+pub fn provisioning_uri(secret: &[u8], issuer: &str, account: &str) -> String {
+    let label = format!("{issuer}:{account}");
+    let mut url = url::Url::parse("otpauth://totp/").unwrap();
+    url.path_segments_mut().unwrap().push(&label);
+    url.query_pairs_mut()
+        .append_pair("secret", &encode_secret(secret))
+        .append_pair("issuer", issuer)
+        .append_pair("algorithm", "SHA1")
+        .append_pair("digits", &DIGITS.to_string())
+        .append_pair("period", &TIME_STEP.to_string());
+    url.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The SHA1 secret of the RFC 6238 test vectors
+    const SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn test_generate_code() {
+        // The last six digits of the test vectors in RFC 6238, appendix B
+        assert_eq!(generate_code(SECRET, time_step(59)), "287082");
+        assert_eq!(generate_code(SECRET, time_step(1111111109)), "081804");
+        assert_eq!(generate_code(SECRET, time_step(1234567890)), "005924");
+        assert_eq!(generate_code(SECRET, time_step(2000000000)), "279037");
+    }
+
+    #[test]
+    fn test_verify_code() {
+        let timestamp = 1111111109;
+        let step = time_step(timestamp);
+
+        assert_eq!(verify_code(SECRET, "081804", timestamp, None), Some(step));
+        assert_eq!(
+            verify_code(SECRET, " 081804\n", timestamp, None),
+            Some(step)
+        );
+
+        // Codes of the neighbouring time steps are accepted too
+        let previous = generate_code(SECRET, step - 1);
+        assert_eq!(
+            verify_code(SECRET, &previous, timestamp, None),
+            Some(step - 1)
+        );
+        let too_old = generate_code(SECRET, step - 2);
+        assert_eq!(verify_code(SECRET, &too_old, timestamp, None), None);
+
+        // Codes can't be used twice
+        assert_eq!(verify_code(SECRET, "081804", timestamp, Some(step)), None);
+        assert_eq!(
+            verify_code(SECRET, &previous, timestamp, Some(step - 1)),
+            None
+        );
+
+        assert_eq!(verify_code(SECRET, "81804", timestamp, None), None);
+        assert_eq!(verify_code(SECRET, "08180a", timestamp, None), None);
+        assert_eq!(
+            verify_code(b"other secret", "081804", timestamp, None),
+            None
+        );
+    }
+
+    #[test]
+    fn test_encode_secret() {
+        // Test vectors from RFC 4648, section 10, without padding
+        assert_eq!(encode_secret(b""), "");
+        assert_eq!(encode_secret(b"f"), "MY");
+        assert_eq!(encode_secret(b"fo"), "MZXQ");
+        assert_eq!(encode_secret(b"foo"), "MZXW6");
+        assert_eq!(encode_secret(b"foob"), "MZXW6YQ");
+        assert_eq!(encode_secret(b"fooba"), "MZXW6YTB");
+        assert_eq!(encode_secret(b"foobar"), "MZXW6YTBOI");
+
+        assert_eq!(encode_secret(&generate_secret()).len(), 32);
+    }
+
+    #[test]
+    fn test_provisioning_uri() {
+        assert_eq!(
+            provisioning_uri(b"foobar", "crates.io", "octocat"),
+            "otpauth://totp/crates.io:octocat?secret=MZXW6YTBOI&issuer=crates.io&algorithm=SHA1&digits=6&period=30"
+        );
+    }
+}
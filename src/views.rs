@@ -2,14 +2,16 @@ use chrono::NaiveDateTime;
 use secrecy::ExposeSecret;
 
 use crate::external_urls::remove_blocked_urls;
+use crate::models::token::ApiTokenUsage;
 use crate::models::{
-    ApiToken, Category, Crate, CrateOwnerInvitation, CreatedApiToken, Dependency, DependencyKind,
-    Keyword, Owner, ReverseDependency, Team, TopVersions, User, Version, VersionDownload,
-    VersionOwnerAction,
+    ApiToken, Category, Crate, CrateAuditAction, CrateOwnerInvitation, CreatedApiToken, Dependency,
+    DependencyKind, Keyword, Owner, PublishPolicy, ReverseDependency, Team, TopVersions,
+    TrustedPublisher, User, Version, VersionDownload, VersionOwnerAction, Webhook, WebhookDelivery,
 };
 use crate::util::rfc3339;
 use crates_io_github as github;
 
+pub mod atom;
 pub mod krate_publish;
 pub use self::krate_publish::{EncodableCrateDependency, PublishMetadata};
 
@@ -150,6 +152,29 @@ impl EncodableDependency {
     }
 }
 
+/// A crate version that was selected while resolving a dependency tree.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableResolvedCrate {
+    pub name: String,
+    pub version: String,
+    pub features: Vec<String>,
+    pub dependencies: Vec<EncodableResolvedDependency>,
+}
+
+/// A dependency edge of an `EncodableResolvedCrate`.
+///
+/// `version` is `None` if no non-yanked version matches the requirement.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableResolvedDependency {
+    pub name: String,
+    pub crate_id: String,
+    pub req: String,
+    pub optional: bool,
+    pub target: Option<String>,
+    pub kind: DependencyKind,
+    pub version: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EncodableVersionDownload {
     pub version: i32,
@@ -391,7 +416,7 @@ impl From<Owner> for EncodableOwner {
     }
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 pub struct EncodableTeam {
     pub id: i32,
     pub login: String,
@@ -441,6 +466,213 @@ impl From<CreatedApiToken> for EncodableApiTokenWithToken {
     }
 }
 
+/// The serialization format for the `ApiTokenUsage` model.
+#[derive(Serialize, Debug)]
+pub struct EncodableApiTokenUsage {
+    #[serde(with = "rfc3339")]
+    pub used_at: NaiveDateTime,
+    pub endpoint: String,
+    #[serde(rename = "crate")]
+    pub krate: Option<String>,
+    pub ip: Option<String>,
+    /// `None` if the token was accepted for the request.
+    pub rejection_reason: Option<String>,
+}
+
+impl From<ApiTokenUsage> for EncodableApiTokenUsage {
+    fn from(usage: ApiTokenUsage) -> Self {
+        Self {
+            used_at: usage.used_at,
+            endpoint: usage.endpoint,
+            krate: usage.crate_name,
+            ip: usage.ip.map(|ip| ip.ip().to_string()),
+            rejection_reason: usage.rejection_reason,
+        }
+    }
+}
+
+/// The serialization format for the `PublishPolicy` model.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct EncodablePublishPolicy {
+    #[serde(rename = "crate")]
+    pub krate: String,
+    /// Logins of the users and teams that may publish new versions, or an
+    /// empty list if all owners may publish.
+    pub allowed_publishers: Vec<String>,
+    /// Logins of the users and teams that may publish prerelease versions,
+    /// or an empty list if prerelease versions are not restricted further.
+    pub prerelease_publishers: Vec<String>,
+    pub require_token_expiry: bool,
+    pub require_trusted_publishing: bool,
+    /// `None` if the owners have not configured a policy yet.
+    #[serde(with = "rfc3339::option")]
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+impl EncodablePublishPolicy {
+    pub fn from(
+        policy: PublishPolicy,
+        crate_name: &str,
+        allowed_publishers: Vec<String>,
+        prerelease_publishers: Vec<String>,
+    ) -> Self {
+        let PublishPolicy {
+            require_token_expiry,
+            require_trusted_publishing,
+            updated_at,
+            ..
+        } = policy;
+
+        EncodablePublishPolicy {
+            krate: crate_name.to_string(),
+            allowed_publishers,
+            prerelease_publishers,
+            require_token_expiry,
+            require_trusted_publishing,
+            updated_at: Some(updated_at),
+        }
+    }
+
+    /// The policy of crates whose owners have not configured one.
+    pub fn unrestricted(crate_name: &str) -> Self {
+        EncodablePublishPolicy {
+            krate: crate_name.to_string(),
+            allowed_publishers: Vec::new(),
+            prerelease_publishers: Vec::new(),
+            require_token_expiry: false,
+            require_trusted_publishing: false,
+            updated_at: None,
+        }
+    }
+}
+
+/// The serialization format for the `TrustedPublisher` model.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct EncodableTrustedPublisher {
+    pub id: i32,
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub repository_owner: String,
+    pub repository_name: String,
+    pub workflow_filename: String,
+    pub environment: Option<String>,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+impl EncodableTrustedPublisher {
+    pub fn from(trusted_publisher: TrustedPublisher, crate_name: &str) -> Self {
+        let TrustedPublisher {
+            id,
+            repository_owner,
+            repository_name,
+            workflow_filename,
+            environment,
+            created_at,
+            ..
+        } = trusted_publisher;
+
+        EncodableTrustedPublisher {
+            id,
+            krate: crate_name.to_string(),
+            repository_owner,
+            repository_name,
+            workflow_filename,
+            environment,
+            created_at,
+        }
+    }
+}
+
+/// The serialization format for the `Webhook` model.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct EncodableWebhook {
+    pub id: i32,
+    /// The name of the crate, or `None` for webhooks that are notified
+    /// about all crates of the user.
+    #[serde(rename = "crate")]
+    pub krate: Option<String>,
+    pub url: String,
+    pub events: Vec<String>,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+impl EncodableWebhook {
+    pub fn from(webhook: Webhook, crate_name: Option<&str>) -> Self {
+        let Webhook {
+            id,
+            url,
+            events,
+            created_at,
+            ..
+        } = webhook;
+
+        EncodableWebhook {
+            id,
+            krate: crate_name.map(ToString::to_string),
+            url,
+            events,
+            created_at,
+        }
+    }
+}
+
+/// The serialization format for a newly registered webhook. The secret is
+/// only revealed once, right after the webhook was registered.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct EncodableWebhookWithSecret {
+    #[serde(flatten)]
+    pub webhook: EncodableWebhook,
+    pub secret: String,
+}
+
+/// The serialization format for the `WebhookDelivery` model.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct EncodableWebhookDelivery {
+    pub id: i32,
+    pub event: String,
+    pub payload: serde_json::Value,
+    pub attempts: i32,
+    pub response_status: Option<i32>,
+    pub error: Option<String>,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "rfc3339::option")]
+    pub last_attempt_at: Option<NaiveDateTime>,
+    #[serde(with = "rfc3339::option")]
+    pub delivered_at: Option<NaiveDateTime>,
+}
+
+impl From<WebhookDelivery> for EncodableWebhookDelivery {
+    fn from(delivery: WebhookDelivery) -> Self {
+        let WebhookDelivery {
+            id,
+            event,
+            payload,
+            attempts,
+            response_status,
+            error,
+            created_at,
+            last_attempt_at,
+            delivered_at,
+            ..
+        } = delivery;
+
+        EncodableWebhookDelivery {
+            id,
+            event,
+            payload,
+            attempts,
+            response_status,
+            error,
+            created_at,
+            last_attempt_at,
+            delivered_at,
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 pub struct OwnedCrate {
     pub id: i32,
@@ -540,6 +772,62 @@ pub struct EncodableAuditAction {
     pub user: EncodablePublicUser,
     #[serde(with = "rfc3339")]
     pub time: NaiveDateTime,
+    pub message: Option<String>,
+    pub advisory: Option<String>,
+}
+
+/// The serialization format for the `CrateAuditAction` model.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct EncodableCrateAuditAction {
+    pub id: i32,
+    pub action: String,
+    pub version: Option<String>,
+    pub user: Option<EncodablePublicUser>,
+    pub api_token: Option<EncodableAuditApiToken>,
+    pub target_user: Option<EncodablePublicUser>,
+    pub target_team: Option<EncodableTeam>,
+    pub details: Option<String>,
+    #[serde(with = "rfc3339")]
+    pub time: NaiveDateTime,
+}
+
+/// The API token that was used to perform an audited action. Only the name
+/// is exposed, not the scopes or usage information of the token.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct EncodableAuditApiToken {
+    pub id: i32,
+    pub name: String,
+}
+
+impl EncodableCrateAuditAction {
+    pub fn from(
+        audit_action: CrateAuditAction,
+        user: Option<User>,
+        api_token: Option<EncodableAuditApiToken>,
+        target_user: Option<User>,
+        target_team: Option<Team>,
+    ) -> Self {
+        let CrateAuditAction {
+            id,
+            version,
+            action,
+            details,
+            time,
+            ..
+        } = audit_action;
+
+        EncodableCrateAuditAction {
+            id,
+            action: action.into(),
+            version,
+            user: user.map(User::into),
+            api_token,
+            target_user: target_user.map(User::into),
+            target_team: target_team.map(Team::into),
+            details,
+            time,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -558,6 +846,8 @@ pub struct EncodableVersion {
     pub downloads: i32,
     pub features: serde_json::Value,
     pub yanked: bool,
+    pub yank_message: Option<String>,
+    pub yank_advisory: Option<String>,
     // NOTE: Used by shields.io, altering `license` requires a PR with shields.io
     pub license: Option<String>,
     pub links: EncodableVersionLinks,
@@ -587,6 +877,8 @@ impl EncodableVersion {
             crate_size,
             checksum,
             rust_version,
+            yank_message,
+            yank_advisory,
             ..
         } = version;
 
@@ -607,6 +899,8 @@ impl EncodableVersion {
             downloads,
             features,
             yanked,
+            yank_message,
+            yank_advisory,
             license,
             links,
             crate_size,
@@ -619,6 +913,8 @@ impl EncodableVersion {
                     action: audit_action.action.into(),
                     user: user.into(),
                     time: audit_action.time,
+                    message: audit_action.message,
+                    advisory: audit_action.advisory,
                 })
                 .collect(),
         }
@@ -727,6 +1023,8 @@ mod tests {
             downloads: 0,
             features: serde_json::from_str("{}").unwrap(),
             yanked: false,
+            yank_message: None,
+            yank_advisory: None,
             license: None,
             links: EncodableVersionLinks {
                 dependencies: "".to_string(),
@@ -750,6 +1048,8 @@ mod tests {
                     .unwrap()
                     .and_hms_opt(14, 23, 12)
                     .unwrap(),
+                message: None,
+                advisory: None,
             }],
         };
         let json = serde_json::to_string(&ver).unwrap();
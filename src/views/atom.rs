@@ -0,0 +1,179 @@
+//! This module contains a minimal writer for Atom feeds, as specified in
+//! [RFC 4287](https://datatracker.ietf.org/doc/html/rfc4287). Only the
+//! elements that are needed for the feeds of new crate versions are
+//! supported.
+
+use chrono::{DateTime, NaiveDateTime, SecondsFormat, Utc};
+use std::fmt::Write;
+
+pub const CONTENT_TYPE: &str = "application/atom+xml; charset=utf-8";
+
+#[derive(Debug)]
+pub struct Feed {
+    pub id: String,
+    pub title: String,
+    pub updated: NaiveDateTime,
+    /// The URL of the feed itself
+    pub self_link: String,
+    /// The URL of the corresponding page on the website
+    pub alternate_link: String,
+    pub entries: Vec<Entry>,
+}
+
+#[derive(Debug)]
+pub struct Entry {
+    pub id: String,
+    pub title: String,
+    pub updated: NaiveDateTime,
+    pub link: String,
+    pub author: Option<Author>,
+    pub summary: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Author {
+    pub name: String,
+    pub uri: String,
+}
+
+impl Feed {
+    pub fn render(&self) -> String {
+        let mut xml = String::new();
+        xml.push_str(r#"<?xml version="1.0" encoding="utf-8"?>"#);
+        xml.push('\n');
+        xml.push_str(r#"<feed xmlns="http://www.w3.org/2005/Atom">"#);
+        xml.push('\n');
+        write_element(&mut xml, 1, "id", &self.id);
+        write_element(&mut xml, 1, "title", &self.title);
+        write_element(&mut xml, 1, "updated", &format_date(self.updated));
+        write_link(&mut xml, 1, Some("self"), &self.self_link);
+        write_link(&mut xml, 1, Some("alternate"), &self.alternate_link);
+
+        for entry in &self.entries {
+            xml.push_str("  <entry>\n");
+            write_element(&mut xml, 2, "id", &entry.id);
+            write_element(&mut xml, 2, "title", &entry.title);
+            write_element(&mut xml, 2, "updated", &format_date(entry.updated));
+            write_link(&mut xml, 2, None, &entry.link);
+            if let Some(author) = &entry.author {
+                xml.push_str("    <author>\n");
+                write_element(&mut xml, 3, "name", &author.name);
+                write_element(&mut xml, 3, "uri", &author.uri);
+                xml.push_str("    </author>\n");
+            }
+            if let Some(summary) = &entry.summary {
+                write_element(&mut xml, 2, "summary", summary);
+            }
+            xml.push_str("  </entry>\n");
+        }
+
+        xml.push_str("</feed>\n");
+        xml
+    }
+}
+
+/// Formats a date in the RFC 3339 format that is required by Atom.
+fn format_date(date: NaiveDateTime) -> String {
+    DateTime::<Utc>::from_naive_utc_and_offset(date, Utc).to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn write_element(xml: &mut String, depth: usize, name: &str, value: &str) {
+    let indent = "  ".repeat(depth);
+    let value = escape(value);
+    let _ = writeln!(xml, "{indent}<{name}>{value}</{name}>");
+}
+
+fn write_link(xml: &mut String, depth: usize, rel: Option<&str>, href: &str) {
+    let indent = "  ".repeat(depth);
+    let href = escape(href);
+    let _ = match rel {
+        Some(rel) => writeln!(xml, r#"{indent}<link rel="{rel}" href="{href}"/>"#),
+        None => writeln!(xml, r#"{indent}<link href="{href}"/>"#),
+    };
+}
+
+/// Escapes the characters that have a special meaning in XML text and
+/// attribute values.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Control characters are not allowed in XML 1.0 documents
+            c if c.is_control() && !matches!(c, '\t' | '\n' | '\r') => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use insta::assert_snapshot;
+
+    fn date() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 4, 28)
+            .unwrap()
+            .and_hms_opt(12, 30, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn escapes_special_characters() {
+        assert_eq!(
+            escape("a < b & c > \"d\" 'e'"),
+            "a &lt; b &amp; c &gt; &quot;d&quot; &apos;e&apos;"
+        );
+        assert_eq!(escape("bell\u{7}\ttab"), "bell\ttab");
+    }
+
+    #[test]
+    fn renders_feed() {
+        let feed = Feed {
+            id: "https://crates.io/crates/foo".into(),
+            title: "foo".into(),
+            updated: date(),
+            self_link: "https://crates.io/api/v1/feeds/crates/foo".into(),
+            alternate_link: "https://crates.io/crates/foo".into(),
+            entries: vec![Entry {
+                id: "https://crates.io/crates/foo/1.0.0".into(),
+                title: "foo 1.0.0".into(),
+                updated: date(),
+                link: "https://crates.io/crates/foo/1.0.0".into(),
+                author: Some(Author {
+                    name: "bar".into(),
+                    uri: "https://crates.io/users/bar".into(),
+                }),
+                summary: Some("Fast & small".into()),
+            }],
+        };
+
+        assert_snapshot!(feed.render(), @r###"
+        <?xml version="1.0" encoding="utf-8"?>
+        <feed xmlns="http://www.w3.org/2005/Atom">
+          <id>https://crates.io/crates/foo</id>
+          <title>foo</title>
+          <updated>2024-04-28T12:30:00Z</updated>
+          <link rel="self" href="https://crates.io/api/v1/feeds/crates/foo"/>
+          <link rel="alternate" href="https://crates.io/crates/foo"/>
+          <entry>
+            <id>https://crates.io/crates/foo/1.0.0</id>
+            <title>foo 1.0.0</title>
+            <updated>2024-04-28T12:30:00Z</updated>
+            <link href="https://crates.io/crates/foo/1.0.0"/>
+            <author>
+              <name>bar</name>
+              <uri>https://crates.io/users/bar</uri>
+            </author>
+            <summary>Fast &amp; small</summary>
+          </entry>
+        </feed>
+        "###);
+    }
+}
@@ -8,9 +8,10 @@ use crates_io_index::{Repository, RepositoryConfig};
 use deadpool_diesel::postgres::Pool as DeadpoolPool;
 use derive_builder::Builder;
 use diesel::PgConnection;
+use once_cell::sync::OnceCell;
 use parking_lot::{Mutex, MutexGuard};
 use std::ops::{Deref, DerefMut};
-use std::sync::{Arc, OnceLock};
+use std::sync::Arc;
 use std::time::Instant;
 
 #[derive(Builder)]
@@ -32,7 +33,7 @@ pub struct Environment {
 
     /// A lazily initialised cache of the most popular crates ready to use in typosquatting checks.
     #[builder(default, setter(skip))]
-    typosquat_cache: OnceLock<Result<typosquat::Cache, typosquat::CacheError>>,
+    typosquat_cache: OnceCell<typosquat::Cache>,
 }
 
 impl Environment {
@@ -75,13 +76,10 @@ impl Environment {
         // We have to pass conn back in here because the caller might be in a transaction, and
         // getting a new connection here to query crates can result in a deadlock.
         //
-        // Note that this intentionally won't retry if the initial call to `from_env` fails:
-        // typosquatting checks aren't on the critical path for publishing, and a warning will be
-        // generated if initialising the cache fails.
+        // Errors are not cached, so that a failed initialisation is retried by the next job
+        // instead of failing all typosquatting checks until the worker is restarted.
         self.typosquat_cache
-            .get_or_init(|| typosquat::Cache::from_env(conn))
-            .as_ref()
-            .map_err(|e| e.clone())
+            .get_or_try_init(|| typosquat::Cache::from_env(conn))
     }
 }
 
@@ -1,9 +1,14 @@
-use self::configuration::VisibilityConfig;
+pub use self::columnar::{ParquetColumn, ParquetSchema, ParquetTable};
+use self::configuration::{ColumnVisibility, VisibilityConfig};
+pub use self::parquet::ColumnType;
 use crate::storage::Storage;
 use crate::tasks::spawn_blocking;
 use crate::worker::Environment;
 use anyhow::{anyhow, Context};
 use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use diesel::sql_types::Bool;
+use std::collections::BTreeMap;
 use std::fs::{self, File};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -12,6 +17,11 @@ use std::sync::Arc;
 pub struct DumpDb {
     database_url: String,
     target_name: String,
+    /// If set, the public tables are additionally exported as Parquet files,
+    /// which are uploaded to `{parquet_prefix}/{table}.parquet` together with
+    /// a `{parquet_prefix}/schema.json` file.
+    #[serde(default)]
+    parquet_prefix: Option<String>,
 }
 
 impl DumpDb {
@@ -19,8 +29,14 @@ impl DumpDb {
         Self {
             database_url: database_url.into(),
             target_name: target_name.into(),
+            parquet_prefix: None,
         }
     }
+
+    pub fn with_parquet_prefix(mut self, parquet_prefix: impl Into<String>) -> Self {
+        self.parquet_prefix = Some(parquet_prefix.into());
+        self
+    }
 }
 
 impl BackgroundJob for DumpDb {
@@ -32,33 +48,155 @@ impl BackgroundJob for DumpDb {
     /// tarball and upload to S3.
     async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
         let database_url = self.database_url.clone();
+        let export_parquet = self.parquet_prefix.is_some();
 
-        let tarball = spawn_blocking(move || {
+        let (directory, tarball) = spawn_blocking(move || {
             let directory = DumpDirectory::create()?;
 
             info!(path = ?directory.export_dir, "Begin exporting database");
             directory.populate(&database_url)?;
 
+            if export_parquet {
+                info!(path = ?directory.export_dir, "Exporting Parquet files");
+                directory.export_parquet(&database_url)?;
+            }
+
             info!(path = ?directory.export_dir, "Creating tarball");
-            DumpTarball::create(&directory.export_dir)
+            let tarball = DumpTarball::create(&directory.export_dir)?;
+
+            Ok::<_, anyhow::Error>((directory, tarball))
         })
         .await?;
 
+        let storage = Storage::from_environment();
+
         info!("Uploading tarball");
-        Storage::from_environment()
+        storage
             .upload_db_dump(&self.target_name, &tarball.tarball_path)
             .await?;
         info!("Database dump tarball uploaded");
 
+        let mut paths = vec![self.target_name.clone()];
+
+        if let Some(parquet_prefix) = &self.parquet_prefix {
+            let parquet_dir = directory.export_dir.join("parquet");
+            let schema_bytes = tokio::fs::read(parquet_dir.join("schema.json")).await?;
+            let schema: ParquetSchema = serde_json::from_slice(&schema_bytes)?;
+
+            info!("Uploading Parquet files");
+            for table in schema.tables.values() {
+                let path = format!("{parquet_prefix}/{}", table.path);
+                storage
+                    .upload_db_dump(&path, &parquet_dir.join(&table.path))
+                    .await?;
+                paths.push(path);
+            }
+
+            // The schema is uploaded last, so that it never refers to files
+            // from an older dump.
+            let path = format!("{parquet_prefix}/schema.json");
+            storage
+                .upload_db_dump_manifest(&path, schema_bytes.into())
+                .await?;
+            paths.push(path);
+            info!("Parquet files uploaded");
+        }
+
+        info!("Invalidating CDN caches");
+        for path in &paths {
+            if let Some(cloudfront) = env.cloudfront() {
+                if let Err(error) = cloudfront.invalidate(path).await {
+                    warn!("failed to invalidate CloudFront cache: {}", error);
+                }
+            }
+
+            if let Some(fastly) = env.fastly() {
+                if let Err(error) = fastly.invalidate(path).await {
+                    warn!("failed to invalidate Fastly cache: {}", error);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Create an incremental dump with the rows that changed since the previous
+/// incremental dump, and upload it to `{target_prefix}/{timestamp}.tar.gz`.
+///
+/// The manifest of the latest incremental dump is uploaded to
+/// `{target_prefix}/manifest.json`. It links to the previous dump, and its
+/// `snapshot_xmin` is used as the starting point of the next dump. If there is
+/// no previous manifest, all rows are included.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct IncrementalDumpDb {
+    database_url: String,
+    target_prefix: String,
+}
+
+impl IncrementalDumpDb {
+    pub fn new(database_url: impl Into<String>, target_prefix: impl Into<String>) -> Self {
+        Self {
+            database_url: database_url.into(),
+            target_prefix: target_prefix.into(),
+        }
+    }
+}
+
+impl BackgroundJob for IncrementalDumpDb {
+    const JOB_NAME: &'static str = "incremental_dump_db";
+
+    type Context = Arc<Environment>;
+
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let manifest_path = format!("{}/manifest.json", self.target_prefix);
+
+        let previous = env
+            .storage
+            .read_db_dump_manifest(&manifest_path)
+            .await?
+            .map(|bytes| serde_json::from_slice::<IncrementalManifest>(&bytes))
+            .transpose()
+            .context("Failed to parse the manifest of the previous incremental dump")?;
+
+        let database_url = self.database_url.clone();
+        let target_prefix = self.target_prefix.clone();
+
+        let (tarball, manifest) = spawn_blocking(move || {
+            let directory = DumpDirectory::create()?;
+            let timestamp = directory.timestamp.format("%Y-%m-%d-%H%M%S");
+            let path = format!("{target_prefix}/{timestamp}.tar.gz");
+
+            info!(path = ?directory.export_dir, "Begin exporting database changes");
+            let manifest = directory.populate_incremental(&database_url, path, previous)?;
+
+            info!(path = ?directory.export_dir, "Creating tarball");
+            let tarball = DumpTarball::create(&directory.export_dir)?;
+
+            Ok::<_, anyhow::Error>((tarball, manifest))
+        })
+        .await?;
+
+        info!(path = %manifest.path, "Uploading tarball");
+        env.storage
+            .upload_db_dump(&manifest.path, &tarball.tarball_path)
+            .await?;
+
+        let bytes = serde_json::to_vec_pretty(&manifest)?;
+        env.storage
+            .upload_db_dump_manifest(&manifest_path, bytes.into())
+            .await?;
+        info!("Incremental database dump uploaded");
+
         info!("Invalidating CDN caches");
         if let Some(cloudfront) = env.cloudfront() {
-            if let Err(error) = cloudfront.invalidate(&self.target_name).await {
+            if let Err(error) = cloudfront.invalidate(&manifest_path).await {
                 warn!("failed to invalidate CloudFront cache: {}", error);
             }
         }
 
         if let Some(fastly) = env.fastly() {
-            if let Err(error) = fastly.invalidate(&self.target_name).await {
+            if let Err(error) = fastly.invalidate(&manifest_path).await {
                 warn!("failed to invalidate Fastly cache: {}", error);
             }
         }
@@ -67,6 +205,42 @@ impl BackgroundJob for DumpDb {
     }
 }
 
+/// The `manifest.json` file of an incremental dump.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IncrementalManifest {
+    /// The UTC time the dump was started.
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// Path of the tarball of this dump.
+    pub path: String,
+    /// Path of the tarball of the previous incremental dump, which has to be
+    /// applied before this one.
+    pub previous: Option<String>,
+    /// The `pg_snapshot_xmin()` of the dump, i.e. the oldest transaction that
+    /// was still running. The next dump includes all rows that were inserted
+    /// or updated by this or any later transaction.
+    pub snapshot_xmin: u64,
+    pub tables: BTreeMap<String, TableChanges>,
+}
+
+/// Describes which rows of a table are included in an incremental dump.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "lowercase")]
+pub enum TableChanges {
+    /// All rows of the table are included.
+    Full,
+    /// Only rows that were inserted or updated since the previous dump are
+    /// included, together with the keys of all rows to find deleted rows.
+    ///
+    /// `until` is the largest value of `column` in this dump, and becomes the
+    /// `since` value of the next dump. It is used to check that the previous
+    /// dump was applied before the next one.
+    Incremental {
+        column: String,
+        since: Option<String>,
+        until: Option<String>,
+    },
+}
+
 /// Manage the export directory.
 ///
 /// Create the directory, populate it with the psql scripts and CSV dumps, and
@@ -111,6 +285,105 @@ impl DumpDirectory {
             .context("Failed to create database dump")
     }
 
+    /// Convert the CSV files of the dump into Parquet files in the `parquet`
+    /// directory, which is not included in the tarball, and write the
+    /// `schema.json` file of the Parquet export.
+    pub fn export_parquet(&self, database_url: &str) -> anyhow::Result<ParquetSchema> {
+        let conn = &mut PgConnection::establish(database_url)
+            .context("Failed to establish database connection")?;
+
+        let parquet_dir = self.export_dir.join("parquet");
+        fs::create_dir(&parquet_dir).context("Failed to create `parquet` directory")?;
+
+        let data_dir = self.export_dir.join("data");
+        let tables = columnar::export_tables(conn, &data_dir, &parquet_dir)
+            .context("Failed to create Parquet files")?;
+
+        let schema = ParquetSchema {
+            timestamp: self.timestamp,
+            tables,
+        };
+
+        let path = parquet_dir.join("schema.json");
+        debug!(?path, "Writing schema.json file…");
+        let file = File::create(path).context("Failed to create schema.json file")?;
+        serde_json::to_writer_pretty(file, &schema)?;
+
+        Ok(schema)
+    }
+
+    /// Populate the directory with the rows that changed since the `previous`
+    /// incremental dump, and write the manifest of the new dump.
+    pub fn populate_incremental(
+        &self,
+        database_url: &str,
+        path: String,
+        previous: Option<IncrementalManifest>,
+    ) -> anyhow::Result<IncrementalManifest> {
+        self.add_readme()
+            .context("Failed to write README.md file")?;
+
+        self.add_metadata()
+            .context("Failed to write metadata.json file")?;
+
+        let mut watermarks = BTreeMap::new();
+        if let Some(previous) = &previous {
+            for (table, changes) in &previous.tables {
+                if let TableChanges::Incremental {
+                    until: Some(until), ..
+                } = changes
+                {
+                    watermarks.insert(table.clone(), until.clone());
+                }
+            }
+        }
+
+        let snapshot_xmin = previous.as_ref().map(|previous| previous.snapshot_xmin);
+        let (new_watermarks, new_snapshot_xmin) = self
+            .dump_db_changes(database_url, snapshot_xmin)
+            .context("Failed to create incremental database dump")?;
+
+        let mut tables = BTreeMap::new();
+        for (table, config) in VisibilityConfig::get().0 {
+            // Tables without public columns are not exported
+            let is_public = |vis: &ColumnVisibility| *vis == ColumnVisibility::Public;
+            if !config.columns.values().any(is_public) {
+                continue;
+            }
+
+            let changes = match config.incremental {
+                Some(incremental) => {
+                    let since = watermarks.get(&table).cloned();
+                    // Keep the previous watermark if the table is empty
+                    let until = new_watermarks.get(&table).cloned().or(since.clone());
+                    TableChanges::Incremental {
+                        column: incremental.column,
+                        since,
+                        until,
+                    }
+                }
+                None => TableChanges::Full,
+            };
+
+            tables.insert(table, changes);
+        }
+
+        let manifest = IncrementalManifest {
+            timestamp: self.timestamp,
+            path,
+            previous: previous.map(|previous| previous.path),
+            snapshot_xmin: new_snapshot_xmin,
+            tables,
+        };
+
+        let path = self.export_dir.join("manifest.json");
+        debug!(?path, "Writing manifest.json file…");
+        let file = File::create(path).context("Failed to create manifest.json file")?;
+        serde_json::to_writer_pretty(file, &manifest)?;
+
+        Ok(manifest)
+    }
+
     fn add_readme(&self) -> anyhow::Result<()> {
         use std::io::Write;
 
@@ -179,6 +452,53 @@ impl DumpDirectory {
 
         run_psql(&export_script, database_url)
     }
+
+    /// Export the rows that changed since the snapshot of the previous dump,
+    /// and return the new watermarks and the `pg_snapshot_xmin()` of this dump.
+    fn dump_db_changes(
+        &self,
+        database_url: &str,
+        snapshot_xmin: Option<u64>,
+    ) -> anyhow::Result<(BTreeMap<String, String>, u64)> {
+        debug!("Generating export.sql and import.sql files…");
+        let export_script = self.export_dir.join("export.sql");
+        let import_script = self.export_dir.join("import.sql");
+        gen_scripts::gen_incremental_scripts(&export_script, &import_script, snapshot_xmin)
+            .context("Failed to generate export/import scripts")?;
+
+        debug!("Filling data folder…");
+        fs::create_dir(self.export_dir.join("data"))
+            .context("Failed to create `data` directory")?;
+
+        run_psql(&export_script, database_url)?;
+
+        // The watermarks and the snapshot are part of the manifest, so the files
+        // themselves are not included in the tarball.
+        let path = self.export_dir.join("watermarks.csv");
+        let content = fs::read_to_string(&path).context("Failed to read watermarks.csv file")?;
+        fs::remove_file(&path).context("Failed to remove watermarks.csv file")?;
+
+        let watermarks = content
+            .lines()
+            .skip(1)
+            .filter_map(|line| line.split_once(','))
+            .filter(|(_, watermark)| !watermark.is_empty())
+            .map(|(table, watermark)| (table.to_string(), watermark.trim_matches('"').to_string()))
+            .collect();
+
+        let path = self.export_dir.join("snapshot.csv");
+        let content = fs::read_to_string(&path).context("Failed to read snapshot.csv file")?;
+        fs::remove_file(&path).context("Failed to remove snapshot.csv file")?;
+
+        let snapshot_xmin = content
+            .lines()
+            .nth(1)
+            .ok_or_else(|| anyhow!("snapshot.csv file is empty"))?
+            .parse()
+            .context("Failed to parse snapshot.csv file")?;
+
+        Ok((watermarks, snapshot_xmin))
+    }
 }
 
 impl Drop for DumpDirectory {
@@ -215,6 +535,64 @@ pub fn run_psql(script: &Path, database_url: &str) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Apply an extracted incremental dump to a database that was restored from a
+/// full dump, or that the previous incremental dumps were applied to.
+///
+/// Fails without changing the database if the changes of the previous
+/// incremental dump are missing from the database.
+pub fn import_incremental(directory: &Path, database_url: &str) -> anyhow::Result<()> {
+    let path = directory.join("manifest.json");
+    let manifest = fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let manifest: IncrementalManifest =
+        serde_json::from_slice(&manifest).context("Failed to parse manifest.json file")?;
+
+    let conn = &mut PgConnection::establish(database_url)
+        .context("Failed to establish database connection")?;
+
+    let config = VisibilityConfig::get();
+    for (table, changes) in &manifest.tables {
+        let TableChanges::Incremental {
+            column,
+            since: Some(since),
+            ..
+        } = changes
+        else {
+            continue;
+        };
+
+        // The table and column names end up in the query below, so only the
+        // ones from our own configuration are accepted.
+        let incremental = config.0.get(table).and_then(|c| c.incremental.as_ref());
+        if incremental.map(|incremental| &incremental.column) != Some(column) {
+            return Err(anyhow!(
+                "manifest.json references `{table}.{column}`, which is not an incremental column"
+            ));
+        }
+
+        #[derive(QueryableByName)]
+        struct Check {
+            #[diesel(sql_type = Bool)]
+            contains_previous_changes: bool,
+        }
+
+        // The watermark is compared to values of different column types, so
+        // it is passed as an escaped literal instead of a bound text parameter.
+        let since = since.replace('\'', "''");
+        let query = format!(
+            "SELECT coalesce(max(\"{column}\") >= '{since}', false) AS contains_previous_changes FROM \"{table}\""
+        );
+        let check: Check = diesel::sql_query(query).get_result(conn)?;
+        if !check.contains_previous_changes {
+            let previous = manifest.previous.as_deref().unwrap_or("unknown");
+            return Err(anyhow!(
+                "The `{table}` table is missing the changes of the previous incremental dump ({previous})"
+            ));
+        }
+    }
+
+    run_psql(&directory.join("import.sql"), database_url)
+}
+
 /// Manage the tarball of the database dump.
 ///
 /// Create the tarball, upload it to S3, and make sure it gets deleted.
@@ -278,8 +656,10 @@ impl Drop for DumpTarball {
     }
 }
 
+mod columnar;
 mod configuration;
 mod gen_scripts;
+mod parquet;
 
 #[cfg(test)]
 mod tests {
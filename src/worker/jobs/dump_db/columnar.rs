@@ -0,0 +1,257 @@
+//! Columnar export of the database dump.
+//!
+//! The CSV files of the dump are converted into Parquet files with typed
+//! columns, and a `schema.json` file describes the columns of each table.
+
+use super::configuration::VisibilityConfig;
+use super::parquet::{ColumnType, Field, ParquetWriter, Value};
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{Bool, Text};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+/// The `schema.json` file of the Parquet export.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ParquetSchema {
+    /// The UTC time the dump was started.
+    pub timestamp: DateTime<Utc>,
+    pub tables: BTreeMap<String, ParquetTable>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ParquetTable {
+    /// Path of the Parquet file, relative to the `schema.json` file.
+    pub path: String,
+    pub rows: i64,
+    pub columns: Vec<ParquetColumn>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct ParquetColumn {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub column_type: ColumnType,
+    /// The type of the column in the database, as reported by
+    /// `information_schema.columns.data_type`.
+    pub postgres_type: String,
+    pub nullable: bool,
+}
+
+#[derive(QueryableByName)]
+struct DatabaseColumn {
+    #[diesel(sql_type = Text)]
+    table_name: String,
+    #[diesel(sql_type = Text)]
+    column_name: String,
+    #[diesel(sql_type = Text)]
+    data_type: String,
+    #[diesel(sql_type = Bool)]
+    is_nullable: bool,
+}
+
+/// Converts the CSV file of each exported table in `data_dir` into a Parquet
+/// file in `parquet_dir`, using the column types of the database.
+pub fn export_tables(
+    conn: &mut PgConnection,
+    data_dir: &Path,
+    parquet_dir: &Path,
+) -> anyhow::Result<BTreeMap<String, ParquetTable>> {
+    let query = "
+        SELECT table_name::text, column_name::text, data_type::text, is_nullable = 'YES' AS is_nullable
+        FROM information_schema.columns
+        WHERE table_schema = 'public'";
+    let database_columns: Vec<DatabaseColumn> = diesel::sql_query(query).load(conn)?;
+
+    let mut database_tables: BTreeMap<_, BTreeMap<_, _>> = BTreeMap::new();
+    for column in database_columns {
+        let table = database_tables
+            .entry(column.table_name.clone())
+            .or_default();
+        table.insert(column.column_name.clone(), column);
+    }
+
+    let mut tables = BTreeMap::new();
+    for table in VisibilityConfig::get().0.keys() {
+        let csv_path = data_dir.join(table).with_extension("csv");
+        if !csv_path.exists() {
+            continue;
+        }
+
+        let database_columns = database_tables
+            .get(table)
+            .ok_or_else(|| anyhow!("Unknown table `{table}`"))?;
+
+        let path = format!("{table}.parquet");
+        debug!(%table, "Converting CSV file to {path}…");
+        let table_info = export_table(table, &csv_path, &parquet_dir.join(&path), database_columns)
+            .with_context(|| format!("Failed to export `{table}` table"))?;
+
+        tables.insert(table.clone(), table_info);
+    }
+
+    Ok(tables)
+}
+
+fn export_table(
+    table: &str,
+    csv_path: &Path,
+    parquet_path: &Path,
+    database_columns: &BTreeMap<String, DatabaseColumn>,
+) -> anyhow::Result<ParquetTable> {
+    let mut reader = csv::ReaderBuilder::new().from_path(csv_path)?;
+
+    let columns = reader
+        .headers()?
+        .iter()
+        .map(|name| {
+            let column = database_columns
+                .get(name)
+                .ok_or_else(|| anyhow!("Unknown column `{table}.{name}`"))?;
+
+            Ok(ParquetColumn {
+                name: name.to_string(),
+                column_type: column_type(&column.data_type),
+                postgres_type: column.data_type.clone(),
+                nullable: column.is_nullable,
+            })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let fields = columns
+        .iter()
+        .map(|column| Field {
+            name: column.name.clone(),
+            column_type: column.column_type,
+            nullable: column.nullable,
+        })
+        .collect();
+
+    let file = File::create(parquet_path)?;
+    let mut writer = ParquetWriter::new(BufWriter::new(file), fields)?;
+    let mut record = csv::StringRecord::new();
+    while reader.read_record(&mut record)? {
+        let row = columns
+            .iter()
+            .zip(&record)
+            .map(|(column, value)| {
+                field_value(column, value)
+                    .map(|value| parse_value(column.column_type, value))
+                    .transpose()
+                    .with_context(|| format!("Invalid value for column `{}`", column.name))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        writer.write_row(&row)?;
+    }
+    let rows = writer.finish()?;
+
+    Ok(ParquetTable {
+        path: parquet_path.file_name().unwrap().to_string_lossy().into(),
+        rows,
+        columns,
+    })
+}
+
+/// Maps the `data_type` of a database column to the type of the Parquet
+/// column. Types without a direct equivalent are exported as text.
+fn column_type(data_type: &str) -> ColumnType {
+    match data_type {
+        "boolean" => ColumnType::Boolean,
+        "smallint" => ColumnType::Int16,
+        "integer" => ColumnType::Int32,
+        "bigint" => ColumnType::Int64,
+        "real" => ColumnType::Float,
+        "double precision" => ColumnType::Double,
+        "date" => ColumnType::Date,
+        "timestamp without time zone" => ColumnType::Timestamp,
+        "timestamp with time zone" => ColumnType::TimestampUtc,
+        _ => ColumnType::String,
+    }
+}
+
+/// Parses a value in the text format of PostgreSQL.
+fn parse_value(column_type: ColumnType, value: &str) -> anyhow::Result<Value<'_>> {
+    let value = match column_type {
+        ColumnType::Boolean => match value {
+            "t" => Value::Boolean(true),
+            "f" => Value::Boolean(false),
+            _ => return Err(anyhow!("Invalid boolean `{value}`")),
+        },
+        ColumnType::Int16 | ColumnType::Int32 | ColumnType::Int64 => Value::Integer(value.parse()?),
+        ColumnType::Float | ColumnType::Double => Value::Float(value.parse()?),
+        ColumnType::Date => {
+            let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")?;
+            let epoch = DateTime::UNIX_EPOCH.date_naive();
+            Value::Integer(date.signed_duration_since(epoch).num_days())
+        }
+        ColumnType::Timestamp => {
+            let timestamp = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f")?;
+            Value::Integer(timestamp.and_utc().timestamp_micros())
+        }
+        ColumnType::TimestampUtc => {
+            let timestamp = DateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f%#z")?;
+            Value::Integer(timestamp.timestamp_micros())
+        }
+        ColumnType::String => Value::String(value),
+    };
+
+    Ok(value)
+}
+
+/// Returns the value of a CSV field, or `None` for `NULL` values.
+///
+/// `COPY ... TO ... WITH CSV` writes `NULL` values as unquoted empty fields
+/// and empty strings as `""`, but the quotes are not visible after parsing.
+/// Empty fields of nullable columns are therefore exported as `NULL`.
+fn field_value<'a>(column: &ParquetColumn, value: &'a str) -> Option<&'a str> {
+    if value.is_empty() && column.nullable {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_field_value() {
+        let column = |nullable| ParquetColumn {
+            name: "description".into(),
+            column_type: ColumnType::String,
+            postgres_type: "character varying".into(),
+            nullable,
+        };
+
+        assert_eq!(field_value(&column(true), "foo"), Some("foo"));
+        assert_eq!(field_value(&column(true), ""), None);
+        assert_eq!(field_value(&column(false), ""), Some(""));
+    }
+
+    #[test]
+    fn test_parse_value() {
+        let parse = |column_type, value| parse_value(column_type, value).unwrap();
+
+        assert_eq!(parse(ColumnType::Boolean, "t"), Value::Boolean(true));
+        assert_eq!(parse(ColumnType::Int64, "-42"), Value::Integer(-42));
+        assert_eq!(parse(ColumnType::Double, "1.5"), Value::Float(1.5));
+        assert_eq!(parse(ColumnType::Date, "1970-01-02"), Value::Integer(1));
+        assert_eq!(
+            parse(ColumnType::Timestamp, "1970-01-01 00:00:01.5"),
+            Value::Integer(1_500_000)
+        );
+        assert_eq!(
+            parse(ColumnType::TimestampUtc, "1970-01-01 01:00:00+01"),
+            Value::Integer(0)
+        );
+        assert_eq!(parse(ColumnType::String, "{a,b}"), Value::String("{a,b}"));
+
+        assert!(parse_value(ColumnType::Boolean, "true").is_err());
+        assert!(parse_value(ColumnType::Int32, "1.0").is_err());
+    }
+}
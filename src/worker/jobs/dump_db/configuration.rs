@@ -14,7 +14,9 @@ pub(super) enum ColumnVisibility {
 /// and should list all tables the current tables refers to with foreign key
 /// constraints on public columns. The `filter` field is a valid SQL expression
 /// used in a `WHERE` clause to filter the rows of the table. The `columns`
-/// field maps column names to their respective visibilities.
+/// field maps column names to their respective visibilities. The
+/// `incremental` field determines how the table is included in incremental
+/// dumps.
 #[derive(Clone, Debug, Default, Deserialize)]
 pub(super) struct TableConfig {
     #[serde(default)]
@@ -23,6 +25,30 @@ pub(super) struct TableConfig {
     pub columns: BTreeMap<String, ColumnVisibility>,
     #[serde(default)]
     pub column_defaults: BTreeMap<String, String>,
+    pub incremental: Option<IncrementalConfig>,
+}
+
+/// Configuration for the incremental dumps of a table. Tables without this
+/// configuration are included in full in every incremental dump.
+///
+/// Only rows that were inserted or updated since the previous incremental dump
+/// are exported. The `column` field is a public column whose value increases
+/// whenever a row is inserted or updated, like `updated_at` or `id`. It is
+/// used to check that the previous incremental dump was applied before the
+/// current one. The `key` field lists the public columns that uniquely
+/// identify a row, which are used to update existing rows on import.
+///
+/// The keys of all rows are exported as well, so that deleted rows can be
+/// deleted on import. Tables whose rows are only ever deleted by cascading
+/// foreign keys can instead map the referenced `dependencies` to the columns
+/// referencing them in the `deleted_with` field. Rows referencing a deleted
+/// row are then deleted on import.
+#[derive(Clone, Debug, Deserialize)]
+pub(super) struct IncrementalConfig {
+    pub column: String,
+    pub key: Vec<String>,
+    #[serde(default)]
+    pub deleted_with: BTreeMap<String, String>,
 }
 
 /// Maps table names to the respective configurations. Used to load `dump_db.toml`.
@@ -35,6 +61,20 @@ impl VisibilityConfig {
         toml::from_str(include_str!("dump-db.toml")).unwrap()
     }
 
+    /// Returns the key column of an incremental table that is referenced by
+    /// the `deleted_with` field of another table.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the table is not incremental, or has a key with multiple
+    /// columns.
+    pub(super) fn incremental_key(&self, table: &str) -> &str {
+        match self.0[table].incremental.as_ref().map(|i| i.key.as_slice()) {
+            Some([key]) => key,
+            _ => panic!("{table} needs to be incremental with a single key column"),
+        }
+    }
+
     /// Sort the tables in a way that dependencies come before dependent tables.
     ///
     /// Returns a vector of table names.
@@ -96,6 +136,49 @@ mod tests {
         assert_eq!(config.topological_sort(), ["d", "c", "b", "a"]);
     }
 
+    #[test]
+    fn incremental_columns_are_public() {
+        for (table, config) in VisibilityConfig::get().0 {
+            let Some(incremental) = config.incremental else {
+                continue;
+            };
+
+            let columns = std::iter::once(&incremental.column).chain(&incremental.key);
+            for column in columns {
+                assert_eq!(
+                    config.columns.get(column),
+                    Some(&ColumnVisibility::Public),
+                    "{table}.{column} is used for incremental dumps, but is not public"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn deletions_cascade_from_incremental_tables() {
+        let config = VisibilityConfig::get();
+        for (table, table_config) in &config.0 {
+            let Some(incremental) = &table_config.incremental else {
+                continue;
+            };
+
+            for (referenced, column) in &incremental.deleted_with {
+                assert!(
+                    table_config.dependencies.contains(referenced),
+                    "{table} is deleted with {referenced}, but does not depend on it"
+                );
+                assert_eq!(
+                    table_config.columns.get(column),
+                    Some(&ColumnVisibility::Public),
+                    "{table}.{column} references {referenced}, but is not public"
+                );
+
+                // Panics if the referenced table has no single key column
+                config.incremental_key(referenced);
+            }
+        }
+    }
+
     #[test]
     #[should_panic]
     fn topological_sort_panics_for_cyclic_dependency() {
@@ -9,7 +9,23 @@ pub fn gen_scripts(export_script: &Path, import_script: &Path) -> anyhow::Result
     let config = VisibilityConfig::get();
     let export_sql = File::create(export_script).context("Failed to create export script file")?;
     let import_sql = File::create(import_script).context("Failed to create import script file")?;
-    config.gen_psql_scripts(export_sql, import_sql)
+    let context = config.template_context();
+    config.gen_psql_scripts(&context, export_sql, import_sql)
+}
+
+/// Generates the scripts for an incremental dump. `snapshot_xmin` is the
+/// `pg_snapshot_xmin()` of the previous incremental dump, or `None` to export
+/// all rows.
+pub fn gen_incremental_scripts(
+    export_script: &Path,
+    import_script: &Path,
+    snapshot_xmin: Option<u64>,
+) -> anyhow::Result<()> {
+    let config = VisibilityConfig::get();
+    let export_sql = File::create(export_script).context("Failed to create export script file")?;
+    let import_sql = File::create(import_script).context("Failed to create import script file")?;
+    let context = config.incremental_template_context(snapshot_xmin);
+    config.gen_psql_scripts(&context, export_sql, import_sql)
 }
 
 /// Subset of the configuration data to be passed on to the Handlbars template.
@@ -19,6 +35,23 @@ struct HandlebarsTableContext<'a> {
     filter: Option<String>,
     columns: String,
     column_defaults: Vec<ColumnDefault<'a>>,
+    incremental: Option<IncrementalContext<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct IncrementalContext<'a> {
+    column: &'a str,
+    key: String,
+    /// The `SET` clause for existing rows, or `None` if all public columns
+    /// are part of the key.
+    update: Option<String>,
+    /// The query for the keys of all rows, which are used to delete the rows
+    /// that were deleted since the previous dump. `None` if deleted rows are
+    /// found through the `deleted_with` tables instead.
+    keys_query: Option<String>,
+    /// The condition that matches the rows that were deleted since the
+    /// previous dump on import.
+    deleted: String,
 }
 
 #[derive(Debug, Serialize)]
@@ -53,14 +86,108 @@ impl TableConfig {
                 filter,
                 columns,
                 column_defaults,
+                incremental: None,
             })
         }
     }
+
+    fn incremental_template_context<'a>(
+        &'a self,
+        name: &'a str,
+        config: &VisibilityConfig,
+        snapshot_xmin: Option<u64>,
+    ) -> Option<HandlebarsTableContext<'a>> {
+        let mut context = self.template_context(name)?;
+        let Some(incremental) = &self.incremental else {
+            return Some(context);
+        };
+
+        let key = incremental
+            .key
+            .iter()
+            .map(|col| format!("\"{col}\""))
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        let (keys_query, deleted) = if incremental.deleted_with.is_empty() {
+            let keys_query = match &context.filter {
+                Some(filter) => format!("SELECT {key} FROM \"{name}\" WHERE {filter}"),
+                None => format!("SELECT {key} FROM \"{name}\""),
+            };
+
+            let keys_match = incremental
+                .key
+                .iter()
+                .map(|col| format!("\"{name}_keys\".\"{col}\" = \"{name}\".\"{col}\""))
+                .collect::<Vec<String>>()
+                .join(" AND ");
+            let deleted = format!("NOT EXISTS (SELECT 1 FROM \"{name}_keys\" WHERE {keys_match})");
+
+            (Some(keys_query), deleted)
+        } else {
+            let deleted = incremental
+                .deleted_with
+                .iter()
+                .map(|(referenced, column)| {
+                    let referenced_key = config.incremental_key(referenced);
+                    format!("NOT EXISTS (SELECT 1 FROM \"{referenced}\" WHERE \"{referenced}\".\"{referenced_key}\" = \"{name}\".\"{column}\")")
+                })
+                .collect::<Vec<String>>()
+                .join(" OR ");
+
+            (None, deleted)
+        };
+
+        if let Some(snapshot_xmin) = snapshot_xmin {
+            let condition = changed_since_condition(snapshot_xmin);
+            context.filter = Some(match context.filter {
+                Some(filter) => format!("({filter}) AND {condition}"),
+                None => condition,
+            });
+        }
+
+        let update = self
+            .columns
+            .iter()
+            .filter(|&(_, &vis)| vis == ColumnVisibility::Public)
+            .filter(|&(col, _)| !incremental.key.contains(col))
+            .map(|(col, _)| format!("\"{col}\" = EXCLUDED.\"{col}\""))
+            .collect::<Vec<String>>();
+        let update = (!update.is_empty()).then(|| update.join(", "));
+
+        context.incremental = Some(IncrementalContext {
+            column: &incremental.column,
+            key,
+            update,
+            keys_query,
+            deleted,
+        });
+
+        Some(context)
+    }
+}
+
+/// Returns an SQL condition that matches the rows that were inserted or updated
+/// by transactions that were not finished when the snapshot with the given
+/// `pg_snapshot_xmin()` was taken. Unlike a watermark on `updated_at` or `id`,
+/// this doesn't miss the rows of transactions that commit after the snapshot.
+///
+/// The `xmin` system column only contains the lower 32 bits of the transaction
+/// ID, so it is compared with `age()`. If the snapshot is 2^31 or more
+/// transactions old, all rows are matched instead. Rows that are older than
+/// that can be matched by mistake, which only means that they are exported
+/// again.
+fn changed_since_condition(snapshot_xmin: u64) -> String {
+    let xid = snapshot_xmin % (1 << 32);
+    format!(
+        "(pg_snapshot_xmax(pg_current_snapshot())::text::bigint - {snapshot_xmin} >= 2147483648 OR age(xmin) <= age('{xid}'::xid))"
+    )
 }
 
 /// Subset of the configuration data to be passed on to the Handlbars template.
 #[derive(Debug, Serialize)]
 struct TemplateContext<'a> {
+    incremental: bool,
     tables: Vec<HandlebarsTableContext<'a>>,
 }
 
@@ -71,10 +198,32 @@ impl VisibilityConfig {
             .into_iter()
             .filter_map(|table| self.0[table].template_context(table))
             .collect();
-        TemplateContext { tables }
+        TemplateContext {
+            incremental: false,
+            tables,
+        }
     }
 
-    fn gen_psql_scripts<W>(&self, mut export_writer: W, mut import_writer: W) -> anyhow::Result<()>
+    fn incremental_template_context(&self, snapshot_xmin: Option<u64>) -> TemplateContext<'_> {
+        let tables = self
+            .topological_sort()
+            .into_iter()
+            .filter_map(|table| {
+                self.0[table].incremental_template_context(table, self, snapshot_xmin)
+            })
+            .collect();
+        TemplateContext {
+            incremental: true,
+            tables,
+        }
+    }
+
+    fn gen_psql_scripts<W>(
+        &self,
+        context: &TemplateContext<'_>,
+        mut export_writer: W,
+        mut import_writer: W,
+    ) -> anyhow::Result<()>
     where
         W: std::io::Write,
     {
@@ -86,20 +235,18 @@ impl VisibilityConfig {
         env.add_template("dump-import.sql", include_str!("dump-import.sql.j2"))
             .context("Failed to load dump-import.sql.j2 template")?;
 
-        let context = self.template_context();
-
         debug!("Rendering dump-export.sql file…");
         let export_sql = env
             .get_template("dump-export.sql")
             .unwrap()
-            .render(&context)
+            .render(context)
             .context("Failed to render dump-export.sql file")?;
 
         debug!("Rendering dump-import.sql file…");
         let import_sql = env
             .get_template("dump-import.sql")
             .unwrap()
-            .render(&context)
+            .render(context)
             .context("Failed to render dump-import.sql file")?;
 
         debug!("Writing dump-export.sql file…");
@@ -166,6 +313,81 @@ mod tests {
         );
     }
 
+    #[test]
+    fn incremental_context() {
+        let config: VisibilityConfig = toml::from_str(
+            r#"
+            [a]
+            filter = "NOT deleted"
+            [a.incremental]
+            column = "updated_at"
+            key = ["id"]
+            [a.columns]
+            id = "public"
+            name = "public"
+            updated_at = "public"
+            secret = "private"
+
+            [b]
+            dependencies = ["a"]
+            [b.incremental]
+            column = "id"
+            key = ["id"]
+            deleted_with = { a = "a_id" }
+            [b.columns]
+            id = "public"
+            a_id = "public"
+
+            [c.columns]
+            id = "public"
+            "#,
+        )
+        .unwrap();
+
+        let snapshot_xmin = (1 << 32) + 1000;
+        let context = config.incremental_template_context(Some(snapshot_xmin));
+        assert!(context.incremental);
+
+        let table = |name| context.tables.iter().find(|t| t.name == name).unwrap();
+        let changed = "(pg_snapshot_xmax(pg_current_snapshot())::text::bigint - 4294968296 >= 2147483648 OR age(xmin) <= age('1000'::xid))";
+
+        let a = table("a");
+        assert_eq!(a.filter, Some(format!("(NOT deleted) AND {changed}")));
+        let incremental = a.incremental.as_ref().unwrap();
+        assert_eq!(incremental.key, r#""id""#);
+        assert_eq!(
+            incremental.update.as_deref(),
+            Some(r#""name" = EXCLUDED."name", "updated_at" = EXCLUDED."updated_at""#)
+        );
+        assert_eq!(
+            incremental.keys_query.as_deref(),
+            Some(r#"SELECT "id" FROM "a" WHERE NOT deleted"#)
+        );
+        assert_eq!(
+            incremental.deleted,
+            r#"NOT EXISTS (SELECT 1 FROM "a_keys" WHERE "a_keys"."id" = "a"."id")"#
+        );
+
+        let b = table("b");
+        assert_eq!(b.filter.as_deref(), Some(changed));
+        let incremental = b.incremental.as_ref().unwrap();
+        assert_eq!(incremental.keys_query, None);
+        assert_eq!(
+            incremental.deleted,
+            r#"NOT EXISTS (SELECT 1 FROM "a" WHERE "a"."id" = "b"."a_id")"#
+        );
+
+        let c = table("c");
+        assert_eq!(c.filter, None);
+        assert!(c.incremental.is_none());
+
+        // Without a previous snapshot, all rows are exported
+        let context = config.incremental_template_context(None);
+        let table = |name| context.tables.iter().find(|t| t.name == name).unwrap();
+        assert_eq!(table("a").filter.as_deref(), Some("NOT deleted"));
+        assert_eq!(table("b").filter, None);
+    }
+
     mod information_schema {
         table! {
             information_schema.columns (table_schema, table_name, column_name) {
@@ -0,0 +1,535 @@
+//! Writes the rows of the columnar export to [Apache Parquet] files.
+//!
+//! The rows are collected into Arrow record batches of [`BATCH_SIZE`] rows,
+//! which are encoded by the [`ArrowWriter`] of the `parquet` crate. Only the
+//! current batch and the compressed pages of the current row group are kept
+//! in memory, so even the largest tables can be exported.
+//!
+//! [Apache Parquet]: https://parquet.apache.org/docs/file-format/
+
+use anyhow::anyhow;
+use arrow_array::builder::{
+    BooleanBuilder, Date32Builder, Float32Builder, Float64Builder, Int16Builder, Int32Builder,
+    Int64Builder, StringBuilder, TimestampMicrosecondBuilder,
+};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{DataType, Schema, SchemaRef, TimeUnit};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+use std::io::Write;
+use std::sync::Arc;
+
+/// Number of rows that are collected before they are passed to the
+/// [`ArrowWriter`].
+const BATCH_SIZE: usize = 64 * 1024;
+
+/// Maximum number of rows in a row group. The pages of each column are
+/// limited to the default data page size of the writer.
+const ROW_GROUP_SIZE: usize = 1024 * 1024;
+
+const COMPRESSION_LEVEL: i32 = 3;
+
+const UTC: &str = "UTC";
+
+/// The logical type of a column, which determines its type in the Arrow
+/// schema of the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnType {
+    Boolean,
+    Int16,
+    Int32,
+    Int64,
+    Float,
+    Double,
+    /// Days since the Unix epoch.
+    Date,
+    /// Microseconds since the Unix epoch, without a time zone.
+    Timestamp,
+    /// Microseconds since the Unix epoch in UTC.
+    TimestampUtc,
+    /// UTF-8 encoded text.
+    String,
+}
+
+impl ColumnType {
+    fn data_type(self) -> DataType {
+        match self {
+            ColumnType::Boolean => DataType::Boolean,
+            ColumnType::Int16 => DataType::Int16,
+            ColumnType::Int32 => DataType::Int32,
+            ColumnType::Int64 => DataType::Int64,
+            ColumnType::Float => DataType::Float32,
+            ColumnType::Double => DataType::Float64,
+            ColumnType::Date => DataType::Date32,
+            ColumnType::Timestamp => DataType::Timestamp(TimeUnit::Microsecond, None),
+            ColumnType::TimestampUtc => {
+                DataType::Timestamp(TimeUnit::Microsecond, Some(UTC.into()))
+            }
+            ColumnType::String => DataType::Utf8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub name: String,
+    pub column_type: ColumnType,
+    pub nullable: bool,
+}
+
+/// A non-null value of a row. Integers and floats are converted to the
+/// width of the column type when they are written.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value<'a> {
+    Boolean(bool),
+    Integer(i64),
+    Float(f64),
+    String(&'a str),
+}
+
+/// Writes rows to a Parquet file, which is finalized by [`Self::finish`].
+pub struct ParquetWriter<W: Write + Send> {
+    writer: ArrowWriter<W>,
+    schema: SchemaRef,
+    fields: Vec<Field>,
+    columns: Vec<ColumnBuilder>,
+    buffered_rows: usize,
+    num_rows: i64,
+}
+
+impl<W: Write + Send> ParquetWriter<W> {
+    pub fn new(writer: W, fields: Vec<Field>) -> anyhow::Result<Self> {
+        let schema = Arc::new(Schema::new(
+            fields
+                .iter()
+                .map(|field| {
+                    let data_type = field.column_type.data_type();
+                    arrow_schema::Field::new(&field.name, data_type, field.nullable)
+                })
+                .collect::<Vec<_>>(),
+        ));
+
+        let compression = Compression::ZSTD(ZstdLevel::try_new(COMPRESSION_LEVEL)?);
+        let properties = WriterProperties::builder()
+            .set_compression(compression)
+            .set_max_row_group_size(ROW_GROUP_SIZE)
+            .build();
+        let writer = ArrowWriter::try_new(writer, schema.clone(), Some(properties))?;
+
+        let columns = fields
+            .iter()
+            .map(|field| ColumnBuilder::new(field.column_type))
+            .collect();
+
+        Ok(Self {
+            writer,
+            schema,
+            fields,
+            columns,
+            buffered_rows: 0,
+            num_rows: 0,
+        })
+    }
+
+    /// Buffers a row with one value for each field. The writer must not be
+    /// used anymore if an error is returned.
+    pub fn write_row(&mut self, row: &[Option<Value<'_>>]) -> anyhow::Result<()> {
+        if row.len() != self.fields.len() {
+            return Err(anyhow!(
+                "Expected {} values, but the row has {}",
+                self.fields.len(),
+                row.len()
+            ));
+        }
+
+        let columns = self.fields.iter().zip(&mut self.columns);
+        for ((field, column), value) in columns.zip(row) {
+            column.push(field, *value)?;
+        }
+
+        self.buffered_rows += 1;
+        if self.buffered_rows >= BATCH_SIZE {
+            self.flush_batch()?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the remaining rows and the file metadata, and returns the
+    /// number of rows in the file.
+    pub fn finish(mut self) -> anyhow::Result<i64> {
+        self.flush_batch()?;
+
+        let mut writer = self.writer.into_inner()?;
+        writer.flush()?;
+
+        Ok(self.num_rows)
+    }
+
+    fn flush_batch(&mut self) -> anyhow::Result<()> {
+        if self.buffered_rows == 0 {
+            return Ok(());
+        }
+
+        let columns = self.columns.iter_mut().map(ColumnBuilder::finish).collect();
+        let batch = RecordBatch::try_new(self.schema.clone(), columns)?;
+        self.writer.write(&batch)?;
+
+        self.num_rows += self.buffered_rows as i64;
+        self.buffered_rows = 0;
+
+        Ok(())
+    }
+}
+
+/// Collects the values of a column for the next record batch.
+enum ColumnBuilder {
+    Boolean(BooleanBuilder),
+    Int16(Int16Builder),
+    Int32(Int32Builder),
+    Int64(Int64Builder),
+    Float(Float32Builder),
+    Double(Float64Builder),
+    Date(Date32Builder),
+    Timestamp(TimestampMicrosecondBuilder),
+    String(StringBuilder),
+}
+
+impl ColumnBuilder {
+    fn new(column_type: ColumnType) -> Self {
+        match column_type {
+            ColumnType::Boolean => Self::Boolean(BooleanBuilder::with_capacity(BATCH_SIZE)),
+            ColumnType::Int16 => Self::Int16(Int16Builder::with_capacity(BATCH_SIZE)),
+            ColumnType::Int32 => Self::Int32(Int32Builder::with_capacity(BATCH_SIZE)),
+            ColumnType::Int64 => Self::Int64(Int64Builder::with_capacity(BATCH_SIZE)),
+            ColumnType::Float => Self::Float(Float32Builder::with_capacity(BATCH_SIZE)),
+            ColumnType::Double => Self::Double(Float64Builder::with_capacity(BATCH_SIZE)),
+            ColumnType::Date => Self::Date(Date32Builder::with_capacity(BATCH_SIZE)),
+            ColumnType::Timestamp => {
+                Self::Timestamp(TimestampMicrosecondBuilder::with_capacity(BATCH_SIZE))
+            }
+            ColumnType::TimestampUtc => Self::Timestamp(
+                TimestampMicrosecondBuilder::with_capacity(BATCH_SIZE).with_timezone(UTC),
+            ),
+            ColumnType::String => Self::String(StringBuilder::new()),
+        }
+    }
+
+    fn push(&mut self, field: &Field, value: Option<Value<'_>>) -> anyhow::Result<()> {
+        let Some(value) = value else {
+            if !field.nullable {
+                return Err(anyhow!("Column `{}` is not nullable", field.name));
+            }
+            self.append_null();
+            return Ok(());
+        };
+
+        match (self, value) {
+            (Self::Boolean(builder), Value::Boolean(value)) => builder.append_value(value),
+            (Self::Int16(builder), Value::Integer(value)) => {
+                builder.append_value(i16::try_from(value)?)
+            }
+            (Self::Int32(builder), Value::Integer(value)) => {
+                builder.append_value(i32::try_from(value)?)
+            }
+            (Self::Date(builder), Value::Integer(value)) => {
+                builder.append_value(i32::try_from(value)?)
+            }
+            (Self::Int64(builder), Value::Integer(value)) => builder.append_value(value),
+            (Self::Timestamp(builder), Value::Integer(value)) => builder.append_value(value),
+            (Self::Float(builder), Value::Float(value)) => builder.append_value(value as f32),
+            (Self::Double(builder), Value::Float(value)) => builder.append_value(value),
+            (Self::String(builder), Value::String(value)) => builder.append_value(value),
+            (_, value) => {
+                return Err(anyhow!(
+                    "Unexpected value {value:?} for {:?} column `{}`",
+                    field.column_type,
+                    field.name
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn append_null(&mut self) {
+        match self {
+            Self::Boolean(builder) => builder.append_null(),
+            Self::Int16(builder) => builder.append_null(),
+            Self::Int32(builder) => builder.append_null(),
+            Self::Int64(builder) => builder.append_null(),
+            Self::Float(builder) => builder.append_null(),
+            Self::Double(builder) => builder.append_null(),
+            Self::Date(builder) => builder.append_null(),
+            Self::Timestamp(builder) => builder.append_null(),
+            Self::String(builder) => builder.append_null(),
+        }
+    }
+
+    /// Returns the collected values as an array, and resets the builder.
+    fn finish(&mut self) -> ArrayRef {
+        match self {
+            Self::Boolean(builder) => Arc::new(builder.finish()),
+            Self::Int16(builder) => Arc::new(builder.finish()),
+            Self::Int32(builder) => Arc::new(builder.finish()),
+            Self::Int64(builder) => Arc::new(builder.finish()),
+            Self::Float(builder) => Arc::new(builder.finish()),
+            Self::Double(builder) => Arc::new(builder.finish()),
+            Self::Date(builder) => Arc::new(builder.finish()),
+            Self::Timestamp(builder) => Arc::new(builder.finish()),
+            Self::String(builder) => Arc::new(builder.finish()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{
+        Date32Type, Float32Type, Float64Type, Int16Type, Int32Type, Int64Type,
+        TimestampMicrosecondType,
+    };
+    use arrow_array::{Array, RecordBatchReader};
+    use bytes::Bytes;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    #[derive(Debug, PartialEq)]
+    enum OwnedValue {
+        Boolean(bool),
+        Integer(i64),
+        Float(f64),
+        String(String),
+    }
+
+    impl From<Value<'_>> for OwnedValue {
+        fn from(value: Value<'_>) -> Self {
+            match value {
+                Value::Boolean(value) => OwnedValue::Boolean(value),
+                Value::Integer(value) => OwnedValue::Integer(value),
+                Value::Float(value) => OwnedValue::Float(value),
+                Value::String(value) => OwnedValue::String(value.into()),
+            }
+        }
+    }
+
+    fn column_type(data_type: &DataType) -> ColumnType {
+        match data_type {
+            DataType::Boolean => ColumnType::Boolean,
+            DataType::Int16 => ColumnType::Int16,
+            DataType::Int32 => ColumnType::Int32,
+            DataType::Int64 => ColumnType::Int64,
+            DataType::Float32 => ColumnType::Float,
+            DataType::Float64 => ColumnType::Double,
+            DataType::Date32 => ColumnType::Date,
+            DataType::Timestamp(TimeUnit::Microsecond, None) => ColumnType::Timestamp,
+            DataType::Timestamp(TimeUnit::Microsecond, Some(tz)) if &**tz == UTC => {
+                ColumnType::TimestampUtc
+            }
+            DataType::Utf8 => ColumnType::String,
+            data_type => panic!("Unexpected data type {data_type}"),
+        }
+    }
+
+    fn read_value(array: &dyn Array, index: usize) -> Option<OwnedValue> {
+        if array.is_null(index) {
+            return None;
+        }
+
+        let value = match array.data_type() {
+            DataType::Boolean => OwnedValue::Boolean(array.as_boolean().value(index)),
+            DataType::Int16 => {
+                OwnedValue::Integer(array.as_primitive::<Int16Type>().value(index).into())
+            }
+            DataType::Int32 => {
+                OwnedValue::Integer(array.as_primitive::<Int32Type>().value(index).into())
+            }
+            DataType::Int64 => OwnedValue::Integer(array.as_primitive::<Int64Type>().value(index)),
+            DataType::Float32 => {
+                OwnedValue::Float(array.as_primitive::<Float32Type>().value(index).into())
+            }
+            DataType::Float64 => {
+                OwnedValue::Float(array.as_primitive::<Float64Type>().value(index))
+            }
+            DataType::Date32 => {
+                OwnedValue::Integer(array.as_primitive::<Date32Type>().value(index).into())
+            }
+            DataType::Timestamp(..) => OwnedValue::Integer(
+                array
+                    .as_primitive::<TimestampMicrosecondType>()
+                    .value(index),
+            ),
+            DataType::Utf8 => OwnedValue::String(array.as_string::<i32>().value(index).into()),
+            data_type => panic!("Unexpected data type {data_type}"),
+        };
+
+        Some(value)
+    }
+
+    /// Reads the fields and rows of a Parquet file with the reader of the
+    /// `parquet` crate.
+    fn read_parquet(buf: Vec<u8>) -> (Vec<Field>, Vec<Vec<Option<OwnedValue>>>) {
+        let reader = ParquetRecordBatchReaderBuilder::try_new(Bytes::from(buf))
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let fields = reader
+            .schema()
+            .fields()
+            .iter()
+            .map(|field| Field {
+                name: field.name().clone(),
+                column_type: column_type(field.data_type()),
+                nullable: field.is_nullable(),
+            })
+            .collect();
+
+        let mut rows = Vec::new();
+        for batch in reader {
+            let batch = batch.unwrap();
+            for index in 0..batch.num_rows() {
+                let row = batch
+                    .columns()
+                    .iter()
+                    .map(|column| read_value(column, index))
+                    .collect();
+                rows.push(row);
+            }
+        }
+
+        (fields, rows)
+    }
+
+    fn fields() -> Vec<Field> {
+        vec![
+            Field {
+                name: "id".to_string(),
+                column_type: ColumnType::Int32,
+                nullable: false,
+            },
+            Field {
+                name: "name".to_string(),
+                column_type: ColumnType::String,
+                nullable: true,
+            },
+        ]
+    }
+
+    const ALL_COLUMN_TYPES: [ColumnType; 10] = [
+        ColumnType::Boolean,
+        ColumnType::Int16,
+        ColumnType::Int32,
+        ColumnType::Int64,
+        ColumnType::Float,
+        ColumnType::Double,
+        ColumnType::Date,
+        ColumnType::Timestamp,
+        ColumnType::TimestampUtc,
+        ColumnType::String,
+    ];
+
+    #[test]
+    fn test_round_trip() {
+        let mut fields = Vec::new();
+        for column_type in ALL_COLUMN_TYPES {
+            for nullable in [false, true] {
+                fields.push(Field {
+                    name: format!("{column_type:?}_{nullable}").to_lowercase(),
+                    column_type,
+                    nullable,
+                });
+            }
+        }
+
+        let value = |column_type, i: i64| match column_type {
+            ColumnType::Boolean => Value::Boolean(i % 3 == 0),
+            ColumnType::Int16 => Value::Integer(i16::MIN as i64 + i),
+            ColumnType::Int32 | ColumnType::Date => Value::Integer(i32::MAX as i64 - i),
+            ColumnType::Int64 | ColumnType::Timestamp | ColumnType::TimestampUtc => {
+                Value::Integer(i64::MIN + i)
+            }
+            ColumnType::Float | ColumnType::Double => Value::Float(i as f64 + 0.5),
+            ColumnType::String => Value::String(["", "foo", "ünïcödé"][i as usize % 3]),
+        };
+
+        let rows = (0..20)
+            .map(|i| {
+                fields
+                    .iter()
+                    .map(|field| {
+                        let is_null = field.nullable && (i % 7 < 3 || i == 19);
+                        (!is_null).then(|| value(field.column_type, i))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let mut buf = Vec::new();
+        let mut writer = ParquetWriter::new(&mut buf, fields.clone()).unwrap();
+        for row in &rows {
+            writer.write_row(row).unwrap();
+        }
+        assert_eq!(writer.finish().unwrap(), 20);
+
+        let expected_rows = rows
+            .into_iter()
+            .map(|row| row.into_iter().map(|v| v.map(Into::into)).collect())
+            .collect::<Vec<Vec<_>>>();
+
+        let (read_fields, read_rows) = read_parquet(buf);
+        assert_eq!(read_fields, fields);
+        assert_eq!(read_rows, expected_rows);
+    }
+
+    #[test]
+    fn test_multiple_batches() {
+        let num_rows = BATCH_SIZE * 2 + 1;
+
+        let mut buf = Vec::new();
+        let mut writer = ParquetWriter::new(&mut buf, fields()).unwrap();
+        for i in 0..num_rows {
+            let name = (i % 2 == 0).then_some(Value::String("foo"));
+            writer
+                .write_row(&[Some(Value::Integer(i as i64)), name])
+                .unwrap();
+        }
+        assert_eq!(writer.finish().unwrap(), num_rows as i64);
+
+        let (_, rows) = read_parquet(buf);
+        assert_eq!(rows.len(), num_rows);
+        for (i, row) in rows.iter().enumerate() {
+            assert_eq!(row[0], Some(OwnedValue::Integer(i as i64)));
+            assert_eq!(row[1].is_some(), i % 2 == 0);
+        }
+    }
+
+    #[test]
+    fn test_empty_file() {
+        let mut buf = Vec::new();
+        let writer = ParquetWriter::new(&mut buf, fields()).unwrap();
+        assert_eq!(writer.finish().unwrap(), 0);
+
+        let (read_fields, read_rows) = read_parquet(buf);
+        assert_eq!(read_fields, fields());
+        assert!(read_rows.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        let mut writer = ParquetWriter::new(Vec::new(), fields()).unwrap();
+        let error = writer.write_row(&[None, None]).unwrap_err();
+        assert_eq!(error.to_string(), "Column `id` is not nullable");
+
+        let mut writer = ParquetWriter::new(Vec::new(), fields()).unwrap();
+        let error = writer
+            .write_row(&[Some(Value::Boolean(true)), None])
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Unexpected value Boolean(true) for Int32 column `id`"
+        );
+    }
+}
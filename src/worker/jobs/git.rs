@@ -1,9 +1,11 @@
 use crate::models;
 use crate::tasks::spawn_blocking;
+use crate::worker::jobs::index_metadata::sync_index_file_metadata;
 use crate::worker::Environment;
 use anyhow::{anyhow, Context};
 use chrono::Utc;
 use crates_io_env_vars::var_parsed;
+use crates_io_index::signing::index_file_metadata_path;
 use crates_io_index::{Crate, Repository};
 use crates_io_worker::BackgroundJob;
 use diesel::prelude::*;
@@ -110,15 +112,30 @@ impl BackgroundJob for SyncToSparseIndex {
             .map_err(|err| anyhow!(err.to_string()))?
             .context("Failed to get index data")?;
 
+        let metadata_content = content.clone();
         let future = env.storage.sync_index(&self.krate, content);
         future.await.context("Failed to sync index data")?;
 
+        if let Some(signing_key) = &env.config.index_signing_key {
+            let content = metadata_content.as_deref().map(str::as_bytes);
+            let future = sync_index_file_metadata(&env.storage, signing_key, &self.krate, content);
+            future.await.context("Failed to sync index metadata")?;
+        }
+
         if let Some(cloudfront) = env.cloudfront() {
             let path = Repository::relative_index_file_for_url(&self.krate);
 
             info!(%path, "Invalidating index file on CloudFront");
             let future = cloudfront.invalidate(&path);
             future.await.context("Failed to invalidate CloudFront")?;
+
+            if env.config.index_signing_key.is_some() {
+                let path = index_file_metadata_path(&self.krate);
+
+                info!(%path, "Invalidating index metadata file on CloudFront");
+                let future = cloudfront.invalidate(&path);
+                future.await.context("Failed to invalidate CloudFront")?;
+            }
         }
         Ok(())
     }
@@ -0,0 +1,163 @@
+//! Generation of the signed metadata files of the sparse index.
+//!
+//! See the [`crates_io_index::signing`] module for the file formats.
+
+use crate::storage::Storage;
+use crate::worker::Environment;
+use anyhow::Context;
+use chrono::{Duration, Utc};
+use crates_io_index::signing::{
+    index_file_metadata_path, verify_index_file_metadata, IndexFileHash, IndexFileMetadata,
+    SigningKey, Snapshot, VerifyingKey, SNAPSHOT_PATH,
+};
+use crates_io_worker::BackgroundJob;
+use futures_util::{stream, StreamExt, TryStreamExt};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// How long a snapshot is valid. The [`SignIndexSnapshot`] job needs to run
+/// more often than this, otherwise mirrors will reject the snapshot.
+const SNAPSHOT_EXPIRY: Duration = Duration::days(7);
+
+/// How many index files are processed at the same time.
+const CONCURRENCY: usize = 16;
+
+/// Signs and uploads the metadata file for the index file of a crate, or
+/// deletes it if the index file was deleted.
+pub async fn sync_index_file_metadata(
+    storage: &Storage,
+    signing_key: &SigningKey,
+    name: &str,
+    content: Option<&[u8]>,
+) -> anyhow::Result<()> {
+    let path = index_file_metadata_path(name);
+
+    let Some(content) = content else {
+        storage.delete_index_metadata(&path).await?;
+        return Ok(());
+    };
+
+    let metadata = IndexFileMetadata::new(name, content, Utc::now());
+    let signed = signing_key.sign(metadata)?;
+    let bytes = serde_json::to_vec(&signed)?;
+    storage.upload_index_metadata(&path, bytes.into()).await?;
+
+    Ok(())
+}
+
+/// Collects the hashes of all index files into a new signed snapshot.
+///
+/// The hashes are computed from the index files themselves, so that the
+/// snapshot never depends on the content of a metadata file. Metadata files
+/// that are missing, don't match their index file, or don't have a valid
+/// signature by the current key are signed again. This backfills the
+/// metadata of index files that haven't changed since signing was enabled,
+/// and migrates all files to the new key after rotating the signing key.
+#[derive(Serialize, Deserialize)]
+pub struct SignIndexSnapshot;
+
+impl BackgroundJob for SignIndexSnapshot {
+    const JOB_NAME: &'static str = "sign_index_snapshot";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip_all)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let Some(signing_key) = &env.config.index_signing_key else {
+            warn!("Skipping index snapshot, since no signing key is configured");
+            return Ok(());
+        };
+
+        let storage = &env.storage;
+        let verifying_key = signing_key.verifying_key();
+        let keys = BTreeMap::from([(verifying_key.key_id(), verifying_key)]);
+
+        // Taken before collecting the files, so that mirrors accept index
+        // files that are updated while the snapshot is being generated.
+        let timestamp = Utc::now();
+
+        let names = storage.list_index_files().await?;
+        info!(count = names.len(), "Collecting index files");
+
+        let mut files = BTreeMap::new();
+        let mut results = stream::iter(names)
+            .map(|name| sign_index_file(storage, signing_key, &keys, name))
+            .buffer_unordered(CONCURRENCY);
+
+        while let Some(result) = results.try_next().await? {
+            // The index file was deleted while we were listing them
+            let Some((name, hash)) = result else { continue };
+            files.insert(name, hash);
+        }
+
+        let snapshot = Snapshot {
+            timestamp,
+            expires: timestamp + SNAPSHOT_EXPIRY,
+            files,
+        };
+
+        info!(count = snapshot.files.len(), "Uploading index snapshot");
+        let bytes = serde_json::to_vec(&signing_key.sign(snapshot)?)?;
+        storage
+            .upload_index_metadata(SNAPSHOT_PATH, bytes.into())
+            .await?;
+
+        if let Some(cloudfront) = env.cloudfront() {
+            info!("Invalidating index snapshot on CloudFront");
+            let future = cloudfront.invalidate(SNAPSHOT_PATH);
+            future.await.context("Failed to invalidate CloudFront")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Hashes an index file, and signs its metadata file again unless it already
+/// has a valid signature for the same hash.
+///
+/// Returns the crate name and the hash for the snapshot, or `None` if the
+/// index file does not exist anymore.
+async fn sign_index_file(
+    storage: &Storage,
+    signing_key: &SigningKey,
+    keys: &BTreeMap<String, VerifyingKey>,
+    name: String,
+) -> anyhow::Result<Option<(String, IndexFileHash)>> {
+    let Some(content) = storage.read_index_file(&name).await? else {
+        return Ok(None);
+    };
+    let hash = IndexFileHash::new(&content);
+
+    let path = index_file_metadata_path(&name);
+    let existing = storage.read_index_metadata(&path).await?;
+    let existing = existing
+        .and_then(|bytes| verify_index_file_metadata(&bytes, keys).ok())
+        .filter(|metadata| metadata.name.eq_ignore_ascii_case(&name) && metadata.hash == hash);
+
+    if let Some(metadata) = existing {
+        return Ok(Some((metadata.name, hash)));
+    }
+
+    // Index files contain the original spelling of the crate name, while the
+    // file name is always lowercase.
+    let name = crate_name(&content).unwrap_or(name);
+
+    info!(%path, "Signing index metadata file");
+    let metadata = IndexFileMetadata::new(&name, &content, Utc::now());
+    let bytes = serde_json::to_vec(&signing_key.sign(metadata)?)?;
+    storage.upload_index_metadata(&path, bytes.into()).await?;
+
+    Ok(Some((name, hash)))
+}
+
+/// Returns the crate name of the first entry of an index file.
+fn crate_name(content: &[u8]) -> Option<String> {
+    #[derive(Deserialize)]
+    struct IndexEntry {
+        name: String,
+    }
+
+    let line = content.split(|byte| *byte == b'\n').next()?;
+    let entry: IndexEntry = serde_json::from_slice(line).ok()?;
+    Some(entry.name)
+}
@@ -0,0 +1,510 @@
+//! Mirrors an upstream registry by following its git index.
+//!
+//! All index files that were modified since the last mirrored commit are
+//! uploaded to the sparse index of this registry unchanged, after the crate
+//! files they reference have been downloaded and verified against their
+//! `cksum`. Index files that were deleted upstream are deleted as well, but
+//! their crate files are kept. The last mirrored commit is saved in the
+//! `mirror_progress` table, so that the next run only has to look at newer
+//! changes.
+//!
+//! If a checkout directory is configured, the upstream index is cloned into
+//! a subdirectory of it once, and later runs only fetch the new commits.
+
+use crate::schema::mirror_progress;
+use crate::storage::Storage;
+use crate::tasks::spawn_blocking;
+use crate::worker::jobs::index_metadata::sync_index_file_metadata;
+use crate::worker::Environment;
+use anyhow::{anyhow, Context};
+use crates_io_index::signing::{index_file_metadata_path, SigningKey};
+use crates_io_index::{Credentials, Repository, RepositoryConfig};
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use futures_util::{stream, StreamExt};
+use hyper::body::Bytes;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use url::Url;
+
+/// How many index files are mirrored at the same time.
+const CONCURRENCY: usize = 16;
+
+/// The markers that cargo replaces in the `dl` field of the index
+/// configuration.
+const DOWNLOAD_URL_MARKERS: [&str; 5] = [
+    "{crate}",
+    "{version}",
+    "{prefix}",
+    "{lowerprefix}",
+    "{sha256-checksum}",
+];
+
+/// Mirrors all changes of the upstream git index since the last run.
+///
+/// The progress is only saved if all modified index files could be mirrored,
+/// so a failed run is retried from the same commit. Index files that were
+/// already mirrored by the failed run are skipped.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MirrorUpstream {
+    index_url: String,
+}
+
+impl MirrorUpstream {
+    pub fn new(index_url: impl Into<String>) -> Self {
+        let index_url = index_url.into();
+        Self { index_url }
+    }
+}
+
+impl BackgroundJob for MirrorUpstream {
+    const JOB_NAME: &'static str = "mirror_upstream";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip_all, fields(upstream = %self.index_url))]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let upstream = self.index_url.clone();
+        let conn = env.deadpool.get().await?;
+        let since = conn
+            .interact(move |conn| load_mirror_progress(&upstream, conn))
+            .await
+            .map_err(|err| anyhow!(err.to_string()))??;
+
+        let signing_key = env.config.index_signing_key.as_ref();
+        let checkout_dir = env.config.mirror_checkout_dir.as_deref();
+        let future = mirror_upstream(
+            &env.storage,
+            signing_key,
+            checkout_dir,
+            env.config.mirror_allow_file_urls,
+            &self.index_url,
+            since,
+        );
+        let report = future.await?;
+
+        info!(
+            commit = %report.commit,
+            index_files = report.index_files,
+            crate_files = report.crate_files,
+            failed = report.failed.len(),
+            "Finished mirroring upstream registry"
+        );
+
+        if !report.failed.is_empty() {
+            anyhow::bail!("Failed to mirror {} index files", report.failed.len());
+        }
+
+        let upstream = self.index_url.clone();
+        conn.interact(move |conn| save_mirror_progress(&upstream, &report.commit, conn))
+            .await
+            .map_err(|err| anyhow!(err.to_string()))??;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct MirrorReport {
+    /// The upstream commit that was mirrored.
+    pub commit: String,
+    /// Number of index files that were uploaded or deleted.
+    pub index_files: usize,
+    /// Number of crate files that were downloaded and uploaded.
+    pub crate_files: usize,
+    /// Crate names whose index files could not be mirrored.
+    pub failed: Vec<String>,
+}
+
+/// Loads the last upstream commit that was mirrored completely.
+pub fn load_mirror_progress(
+    upstream: &str,
+    conn: &mut PgConnection,
+) -> QueryResult<Option<String>> {
+    mirror_progress::table
+        .find(upstream)
+        .select(mirror_progress::commit)
+        .first(conn)
+        .optional()
+}
+
+pub fn save_mirror_progress(
+    upstream: &str,
+    commit: &str,
+    conn: &mut PgConnection,
+) -> QueryResult<()> {
+    diesel::insert_into(mirror_progress::table)
+        .values((
+            mirror_progress::upstream.eq(upstream),
+            mirror_progress::commit.eq(commit),
+        ))
+        .on_conflict(mirror_progress::upstream)
+        .do_update()
+        .set((
+            mirror_progress::commit.eq(commit),
+            mirror_progress::updated_at.eq(diesel::dsl::now),
+        ))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// Clones the upstream git index and mirrors all index files that were
+/// modified since the `since` commit, or all index files if `since` is `None`.
+///
+/// The upstream index is cloned into a subdirectory of `checkout_dir` and
+/// reused by later runs, or into a temporary directory if `checkout_dir` is
+/// `None`.
+///
+/// Crate files are only read from `file://` download URLs if
+/// `allow_file_urls` is set, since the download URLs are controlled by the
+/// upstream.
+///
+/// Index files that fail to mirror are reported in [`MirrorReport::failed`]
+/// instead of aborting the whole run.
+pub async fn mirror_upstream(
+    storage: &Storage,
+    signing_key: Option<&SigningKey>,
+    checkout_dir: Option<&Path>,
+    allow_file_urls: bool,
+    index_url: &str,
+    since: Option<String>,
+) -> anyhow::Result<MirrorReport> {
+    let index_location = Url::parse(index_url).context("Failed to parse upstream index URL")?;
+    let config = RepositoryConfig {
+        index_location,
+        credentials: Credentials::Missing,
+    };
+
+    // Every upstream gets its own checkout, so that mirroring a different
+    // upstream doesn't have to clone from scratch either.
+    let checkout_path = checkout_dir.map(|dir| dir.join(hex::encode(Sha256::digest(index_url))));
+
+    info!("Updating upstream index");
+    let (repo, commit, paths) = spawn_blocking(move || {
+        let repo = match checkout_path {
+            Some(path) => Repository::open_persistent(&config, &path),
+            None => Repository::open(&config),
+        };
+        let repo = repo.context("Failed to clone upstream index")?;
+        let commit = repo.head_oid()?.to_string();
+
+        let paths = match repo.get_files_modified_since(since.as_deref()) {
+            Ok(paths) => paths,
+            Err(error) => {
+                // The upstream index was most likely squashed, so the
+                // previous commit does not exist anymore.
+                warn!(?since, "Failed to diff against the last mirrored commit, mirroring all index files: {error:#}");
+                repo.get_files_modified_since(None)?
+            }
+        };
+
+        Ok::<_, anyhow::Error>((repo, commit, paths))
+    })
+    .await?;
+
+    let config_path = repo.checkout_path().join("config.json");
+    let index_config = spawn_blocking(move || {
+        let content = std::fs::read(config_path).context("Failed to read `config.json`")?;
+        Ok::<_, anyhow::Error>(serde_json::from_slice::<IndexConfig>(&content)?)
+    })
+    .await
+    .context("Failed to load upstream index configuration")?;
+
+    let names = paths.iter().filter_map(|path| index_file_name(path));
+    let names = names.collect::<Vec<_>>();
+    info!(%commit, count = names.len(), "Mirroring modified index files");
+
+    let mirror = Mirror {
+        storage,
+        signing_key,
+        client: reqwest::Client::new(),
+        download_url: index_config.dl,
+        allow_file_urls,
+        checkout_path: repo.checkout_path(),
+    };
+
+    let mut report = MirrorReport {
+        commit,
+        index_files: 0,
+        crate_files: 0,
+        failed: Vec::new(),
+    };
+
+    let mut results = stream::iter(names)
+        .map(|name| {
+            let mirror = &mirror;
+            async move {
+                let result = mirror.mirror_index_file(&name).await;
+                (name, result)
+            }
+        })
+        .buffer_unordered(CONCURRENCY);
+
+    while let Some((name, result)) = results.next().await {
+        match result {
+            Ok(Some(crate_files)) => {
+                report.index_files += 1;
+                report.crate_files += crate_files;
+            }
+            Ok(None) => {}
+            Err(error) => {
+                error!(krate.name = %name, "Failed to mirror index file: {error:#}");
+                report.failed.push(name);
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// The parts of the `config.json` file of the upstream index that are
+/// needed for mirroring.
+#[derive(Deserialize)]
+struct IndexConfig {
+    dl: String,
+}
+
+/// The parts of an index entry that are needed for mirroring.
+#[derive(Deserialize)]
+struct IndexEntry {
+    name: String,
+    vers: String,
+    cksum: String,
+}
+
+/// Returns the crate name if the path is an index file of the git index.
+fn index_file_name(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_str()?;
+
+    // `relative_index_file()` slices the name by bytes, which only works
+    // for ASCII names, and all valid crate names are ASCII.
+    if !name.is_ascii() || Repository::relative_index_file(name) != path {
+        return None;
+    }
+
+    Some(name.to_string())
+}
+
+struct Mirror<'a> {
+    storage: &'a Storage,
+    signing_key: Option<&'a SigningKey>,
+    client: reqwest::Client,
+    download_url: String,
+    /// Whether crate files may be read from `file://` download URLs.
+    allow_file_urls: bool,
+    checkout_path: &'a Path,
+}
+
+impl Mirror<'_> {
+    /// Mirrors the crate files of all new entries of an index file, and then
+    /// uploads the index file itself. If the index file was deleted upstream,
+    /// it is deleted from the sparse index instead.
+    ///
+    /// Returns the number of downloaded crate files, or `None` if the index
+    /// file was already up-to-date.
+    #[instrument(skip(self))]
+    async fn mirror_index_file(&self, name: &str) -> anyhow::Result<Option<usize>> {
+        let path = self
+            .checkout_path
+            .join(Repository::relative_index_file(name));
+        let content = spawn_blocking(move || match std::fs::read(path) {
+            Ok(content) => Ok(Some(content)),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(anyhow::Error::from(error)),
+        })
+        .await?;
+
+        let existing = self.storage.read_index_file(name).await?;
+
+        let Some(content) = content else {
+            return self.delete_index_file(name, existing.is_some()).await;
+        };
+        let content = String::from_utf8(content).context("Index file is not valid UTF-8")?;
+
+        // Entries in the currently uploaded index file have already been
+        // mirrored, since index files are only uploaded after all of their
+        // crate files.
+        if existing.as_deref() == Some(content.as_bytes()) {
+            return Ok(None);
+        }
+
+        // Entries that only changed their `yanked` status or other metadata
+        // still refer to the same crate file.
+        let existing = existing.unwrap_or_default();
+        let existing = existing
+            .split(|byte| *byte == b'\n')
+            .filter_map(|line| serde_json::from_slice::<IndexEntry>(line).ok())
+            .map(|entry| (entry.vers, entry.cksum))
+            .collect::<HashSet<_>>();
+
+        let mut crate_files = 0;
+        for line in content.lines().filter(|line| !line.is_empty()) {
+            let entry: IndexEntry = serde_json::from_str(line)
+                .with_context(|| format!("Failed to parse index entry: {line}"))?;
+
+            if existing.contains(&(entry.vers.clone(), entry.cksum.clone())) {
+                continue;
+            }
+
+            self.mirror_crate_file(name, &entry).await?;
+            crate_files += 1;
+        }
+
+        self.storage.sync_index(name, Some(content.clone())).await?;
+
+        if let Some(signing_key) = self.signing_key {
+            let content = Some(content.as_bytes());
+            sync_index_file_metadata(self.storage, signing_key, name, content).await?;
+        }
+
+        Ok(Some(crate_files))
+    }
+
+    /// Deletes an index file that was deleted upstream, together with its
+    /// metadata file.
+    ///
+    /// The metadata file is checked separately, in case a previous run
+    /// failed to delete it after deleting the index file.
+    async fn delete_index_file(&self, name: &str, exists: bool) -> anyhow::Result<Option<usize>> {
+        if exists {
+            info!("Deleting index file that was deleted upstream");
+            self.storage.sync_index(name, None).await?;
+        }
+
+        if let Some(signing_key) = self.signing_key {
+            let path = index_file_metadata_path(name);
+            if self.storage.read_index_metadata(&path).await?.is_some() {
+                sync_index_file_metadata(self.storage, signing_key, name, None).await?;
+            }
+        }
+
+        Ok(exists.then_some(0))
+    }
+
+    async fn mirror_crate_file(&self, name: &str, entry: &IndexEntry) -> anyhow::Result<()> {
+        let crate_file = format!("{}@{}", entry.name, entry.vers);
+
+        // The entry is used to build the storage path, so it must not be
+        // able to point anywhere else.
+        if !entry.name.eq_ignore_ascii_case(name) {
+            anyhow::bail!("Index entry `{crate_file}` does not belong to the `{name}` index file");
+        }
+        semver::Version::parse(&entry.vers)
+            .with_context(|| format!("Invalid version in index entry `{crate_file}`"))?;
+
+        let url = download_url(&self.download_url, &entry.name, &entry.vers, &entry.cksum);
+        debug!(%crate_file, %url, "Downloading crate file");
+        let bytes = self
+            .download(&url)
+            .await
+            .with_context(|| format!("Failed to download `{crate_file}` from {url}"))?;
+
+        let checksum = hex::encode(Sha256::digest(&bytes));
+        if checksum != entry.cksum {
+            anyhow::bail!(
+                "Checksum mismatch for `{crate_file}`: expected {}, got {checksum}",
+                entry.cksum
+            );
+        }
+
+        self.storage
+            .upload_crate_file(&entry.name, &entry.vers, bytes)
+            .await
+            .with_context(|| format!("Failed to upload `{crate_file}`"))?;
+
+        Ok(())
+    }
+
+    /// Downloads a file over HTTP(S), or reads it from the local file system
+    /// for `file://` URLs if this was explicitly allowed, which allows
+    /// mirroring from an offline copy of the upstream registry.
+    async fn download(&self, url: &str) -> anyhow::Result<Bytes> {
+        let url = Url::parse(url)?;
+        match url.scheme() {
+            "http" | "https" => {}
+            "file" if self.allow_file_urls => {
+                let path: PathBuf = url
+                    .to_file_path()
+                    .map_err(|_| anyhow!("Invalid file URL"))?;
+                let bytes = spawn_blocking(move || Ok::<_, anyhow::Error>(std::fs::read(path)?));
+                return Ok(bytes.await?.into());
+            }
+            scheme => anyhow::bail!("Downloads from `{scheme}` URLs are not allowed"),
+        }
+
+        let response = self.client.get(url).send().await?.error_for_status()?;
+        Ok(response.bytes().await?)
+    }
+}
+
+/// Builds the download URL of a crate file from the `dl` template of the
+/// index configuration, the same way cargo does.
+///
+/// See <https://doc.rust-lang.org/cargo/reference/registry-index.html#index-configuration>
+fn download_url(template: &str, name: &str, version: &str, cksum: &str) -> String {
+    if !DOWNLOAD_URL_MARKERS
+        .iter()
+        .any(|marker| template.contains(marker))
+    {
+        return format!("{template}/{name}/{version}/download");
+    }
+
+    let prefix = match name.len() {
+        1 => "1".to_string(),
+        2 => "2".to_string(),
+        3 => format!("3/{}", &name[..1]),
+        _ => format!("{}/{}", &name[..2], &name[2..4]),
+    };
+
+    template
+        .replace("{crate}", name)
+        .replace("{version}", version)
+        .replace("{lowerprefix}", &prefix.to_lowercase())
+        .replace("{prefix}", &prefix)
+        .replace("{sha256-checksum}", cksum)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_download_url() {
+        let url = download_url("https://static.crates.io/crates", "Foo", "1.0.0", "abc");
+        assert_eq!(url, "https://static.crates.io/crates/Foo/1.0.0/download");
+
+        let template =
+            "https://example.com/{prefix}/{lowerprefix}/{crate}-{version}.crate?{sha256-checksum}";
+        let url = download_url(template, "FooBar", "1.0.0", "abc");
+        assert_eq!(
+            url,
+            "https://example.com/Fo/oB/fo/ob/FooBar-1.0.0.crate?abc"
+        );
+
+        let url = download_url(
+            "https://example.com/{prefix}/{crate}",
+            "Foo",
+            "1.0.0",
+            "abc",
+        );
+        assert_eq!(url, "https://example.com/3/F/Foo");
+
+        let url = download_url("https://example.com/{prefix}/{crate}", "a", "1.0.0", "abc");
+        assert_eq!(url, "https://example.com/1/a");
+    }
+
+    #[test]
+    fn test_index_file_name() {
+        let name = |path: &str| index_file_name(Path::new(path));
+        assert_eq!(name("fo/ob/foobar").as_deref(), Some("foobar"));
+        assert_eq!(name("3/f/foo").as_deref(), Some("foo"));
+        assert_eq!(name("1/a").as_deref(), Some("a"));
+        assert_eq!(name("config.json"), None);
+        assert_eq!(name("fo/ob/foo"), None);
+        assert_eq!(name(".github/workflows/ci.yml"), None);
+    }
+}
@@ -9,21 +9,35 @@ mod daily_db_maintenance;
 mod downloads;
 pub mod dump_db;
 mod git;
+mod index_metadata;
+mod mirror;
+mod prune_token_usages;
 mod readmes;
 mod sync_admins;
 mod typosquat;
 mod update_default_version;
+mod verify_storage;
+mod webhooks;
 
 pub use self::daily_db_maintenance::DailyDbMaintenance;
 pub use self::downloads::{
     CleanProcessedLogFiles, ProcessCdnLog, ProcessCdnLogQueue, UpdateDownloads,
 };
-pub use self::dump_db::DumpDb;
+pub use self::dump_db::{DumpDb, IncrementalDumpDb};
 pub use self::git::{NormalizeIndex, SquashIndex, SyncToGitIndex, SyncToSparseIndex};
+pub use self::index_metadata::SignIndexSnapshot;
+pub use self::mirror::{
+    load_mirror_progress, mirror_upstream, save_mirror_progress, MirrorReport, MirrorUpstream,
+};
+pub use self::prune_token_usages::PruneTokenUsages;
 pub use self::readmes::RenderAndUploadReadme;
 pub use self::sync_admins::SyncAdmins;
 pub use self::typosquat::CheckTyposquat;
 pub use self::update_default_version::UpdateDefaultVersion;
+pub use self::verify_storage::{
+    load_crate_files, verify_crate_files, Cdns, CrateFile, VerificationReport, VerifyCrateFiles,
+};
+pub use self::webhooks::{enqueue_webhooks, DeliverWebhook};
 
 /// Enqueue both index sync jobs (git and sparse) for a crate, unless they
 /// already exist in the background job queue.
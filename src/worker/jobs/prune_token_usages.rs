@@ -0,0 +1,30 @@
+use crate::models::token::ApiTokenUsage;
+use crate::worker::Environment;
+use anyhow::anyhow;
+use crates_io_worker::BackgroundJob;
+use std::sync::Arc;
+
+/// Removes the entries of the API token usage logs that exceed the number of
+/// requests that are kept for each token.
+///
+/// The usage logs are only appended to while handling requests, so this job
+/// is supposed to be enqueued periodically.
+#[derive(Serialize, Deserialize)]
+pub struct PruneTokenUsages;
+
+impl BackgroundJob for PruneTokenUsages {
+    const JOB_NAME: &'static str = "prune_token_usages";
+
+    type Context = Arc<Environment>;
+
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let conn = env.deadpool.get().await?;
+        conn.interact(move |conn| {
+            let count = ApiTokenUsage::prune(conn)?;
+            info!("Removed {count} outdated API token usage log entries");
+            Ok(())
+        })
+        .await
+        .map_err(|err| anyhow!(err.to_string()))?
+    }
+}
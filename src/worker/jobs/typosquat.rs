@@ -2,10 +2,11 @@ use anyhow::anyhow;
 use std::sync::Arc;
 
 use crates_io_worker::BackgroundJob;
-use diesel::PgConnection;
+use diesel::prelude::*;
 use typomania::Package;
 
 use crate::email::Email;
+use crate::models::{self, NewTyposquatFinding};
 use crate::{
     typosquat::{Cache, Crate},
     worker::Environment,
@@ -50,30 +51,41 @@ fn check(
     conn: &mut PgConnection,
     name: &str,
 ) -> anyhow::Result<()> {
-    if let Some(harness) = cache.get_harness() {
-        info!(name, "Checking new crate for potential typosquatting");
-
-        let krate: Box<dyn Package> = Box::new(Crate::from_name(conn, name)?);
-        let squats = harness.check_package(name, krate)?;
-        if !squats.is_empty() {
-            // Well, well, well. For now, the only action we'll take is to e-mail people who
-            // hopefully care to check into things more closely.
-            info!(?squats, "Found potential typosquatting");
-
-            let email = PossibleTyposquatEmail {
-                domain: &emails.domain,
+    info!(name, "Checking new crate for potential typosquatting");
+
+    let krate: Box<dyn Package> = Box::new(Crate::from_name(conn, name)?);
+    let squats = cache.get_harness().check_package(name, krate)?;
+    if !squats.is_empty() {
+        info!(?squats, "Found potential typosquatting");
+
+        // Record the findings, so that they can be reviewed with `crates-admin typosquat`.
+        let crate_id = models::Crate::by_exact_name(name)
+            .select(crate::schema::crates::id)
+            .first(conn)?;
+
+        for squat in &squats {
+            NewTyposquatFinding {
+                flagged_crate_id: crate_id,
                 crate_name: name,
-                squats: &squats,
-            };
-
-            for recipient in cache.iter_emails() {
-                if let Err(error) = emails.send(recipient, email.clone()) {
-                    error!(
-                        ?error,
-                        ?recipient,
-                        "Failed to send possible typosquat notification"
-                    );
-                }
+                similar_crate: squat.package(),
+                reason: &squat.to_string(),
+            }
+            .insert(conn)?;
+        }
+
+        let email = PossibleTyposquatEmail {
+            domain: &emails.domain,
+            crate_name: name,
+            squats: &squats,
+        };
+
+        for recipient in cache.iter_emails() {
+            if let Err(error) = emails.send(recipient, email.clone()) {
+                error!(
+                    ?error,
+                    ?recipient,
+                    "Failed to send possible typosquat notification"
+                );
             }
         }
     }
@@ -119,8 +131,14 @@ Specific squat checks that triggered:
 
 #[cfg(test)]
 mod tests {
-    use crate::{test_util::test_db_connection, typosquat::test_util::Faker};
+    use crate::models::{TyposquatFinding, TyposquatStatus};
+    use crate::schema::crates;
+    use crate::{
+        test_util::test_db_connection,
+        typosquat::{test_util::Faker, Config},
+    };
     use lettre::Address;
+    use std::collections::HashSet;
 
     use super::*;
 
@@ -135,7 +153,8 @@ mod tests {
         faker.crate_and_version(&mut conn, "my-crate", "It's awesome", &user, 100)?;
 
         // Prime the cache so it only includes the crate we just created.
-        let cache = Cache::new(vec!["admin@example.com".to_string()], &mut conn)?;
+        let config = Config::default();
+        let cache = Cache::new(vec!["admin@example.com".to_string()], &config, &mut conn)?;
 
         // Now we'll create new crates: one problematic, one not so.
         let other_user = faker.user(&mut conn, "b")?;
@@ -157,6 +176,7 @@ mod tests {
         // Run the check with a crate that shouldn't cause problems.
         check(&emails, &cache, &mut conn, &angel.name)?;
         assert!(emails.mails_in_memory().unwrap().is_empty());
+        assert!(TyposquatFinding::list(&mut conn, None)?.is_empty());
 
         // Now run the check with a less innocent crate.
         check(&emails, &cache, &mut conn, &demon.name)?;
@@ -165,6 +185,27 @@ mod tests {
         let sent = sent_mail.into_iter().next().unwrap();
         assert_eq!(&sent.0.to(), &["admin@example.com".parse::<Address>()?]);
 
+        // The findings are recorded for review, even if the check runs twice.
+        check(&emails, &cache, &mut conn, &demon.name)?;
+        let findings = TyposquatFinding::list(&mut conn, Some(TyposquatStatus::Open))?;
+        assert!(!findings.is_empty());
+        for finding in &findings {
+            assert_eq!(finding.crate_name, "mycrate");
+            assert_eq!(finding.flagged_crate_id, Some(demon.id));
+            assert_eq!(finding.similar_crate, "my-crate");
+        }
+        let reasons = findings.iter().map(|f| &f.reason).collect::<HashSet<_>>();
+        assert_eq!(reasons.len(), findings.len());
+
+        // The findings are kept for the record when the crate is deleted.
+        diesel::delete(crates::table.find(demon.id)).execute(&mut conn)?;
+        let findings_after_delete = TyposquatFinding::list(&mut conn, None)?;
+        assert_eq!(findings_after_delete.len(), findings.len());
+        for finding in &findings_after_delete {
+            assert_eq!(finding.crate_name, "mycrate");
+            assert_eq!(finding.flagged_crate_id, None);
+        }
+
         Ok(())
     }
 }
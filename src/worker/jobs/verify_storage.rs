@@ -0,0 +1,195 @@
+//! Verifies that the stored crate files match the checksums in the database.
+//!
+//! Crate files are still stored by name and version. Deduplicating them by
+//! content is out of scope here, since the download URLs and the CDN cache
+//! keys are derived from that layout.
+
+use crate::cloudfront::CloudFront;
+use crate::fastly::Fastly;
+use crate::schema::{crates, versions};
+use crate::storage::{crate_file_path, Storage};
+use crate::worker::Environment;
+use anyhow::anyhow;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use futures_util::{stream, StreamExt};
+use std::sync::Arc;
+
+/// How many crate files are downloaded and hashed at the same time.
+const CONCURRENCY: usize = 16;
+
+/// Streams stored crate files through SHA-256 and compares the result to
+/// `versions.checksum`. Mismatching files are reported, and optionally moved
+/// to the `quarantine/` prefix of the storage and purged from the CDNs.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct VerifyCrateFiles {
+    /// Only verifies the files of this crate, instead of the whole store.
+    crate_name: Option<String>,
+    quarantine: bool,
+}
+
+impl VerifyCrateFiles {
+    pub fn new(crate_name: Option<String>, quarantine: bool) -> Self {
+        Self {
+            crate_name,
+            quarantine,
+        }
+    }
+}
+
+impl BackgroundJob for VerifyCrateFiles {
+    const JOB_NAME: &'static str = "verify_crate_files";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip_all, fields(krate.name = ?self.crate_name))]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let crate_name = self.crate_name.clone();
+        let conn = env.deadpool.get().await?;
+        let crate_files = conn
+            .interact(move |conn| load_crate_files(crate_name.as_deref(), conn))
+            .await
+            .map_err(|err| anyhow!(err.to_string()))??;
+
+        let cdns = Cdns {
+            cloudfront: env.cloudfront(),
+            fastly: env.fastly(),
+        };
+        let report = verify_crate_files(&env.storage, &cdns, crate_files, self.quarantine).await;
+
+        info!(
+            checked = report.checked,
+            missing = report.missing.len(),
+            mismatched = report.mismatched.len(),
+            failed = report.failed.len(),
+            "Finished verifying crate files"
+        );
+
+        Ok(())
+    }
+}
+
+/// A crate file that is expected to be in the storage.
+#[derive(Debug, Queryable)]
+pub struct CrateFile {
+    pub name: String,
+    pub version: String,
+    pub checksum: String,
+}
+
+#[derive(Debug, Default)]
+pub struct VerificationReport {
+    pub checked: usize,
+    /// Crate files (`name@version`) that do not exist in the storage.
+    pub missing: Vec<String>,
+    /// Crate files (`name@version`) that do not match the checksum in the
+    /// database.
+    pub mismatched: Vec<String>,
+    /// Crate files (`name@version`) that could not be read or quarantined.
+    pub failed: Vec<String>,
+}
+
+/// Loads the expected checksums of all versions, or of the versions of a
+/// single crate.
+pub fn load_crate_files(
+    crate_name: Option<&str>,
+    conn: &mut PgConnection,
+) -> QueryResult<Vec<CrateFile>> {
+    let mut query = versions::table
+        .inner_join(crates::table)
+        .select((crates::name, versions::num, versions::checksum))
+        .order((crates::name, versions::id))
+        .into_boxed();
+
+    if let Some(crate_name) = crate_name {
+        query = query.filter(crates::name.eq(crate_name));
+    }
+
+    query.load(conn)
+}
+
+/// The CDNs in front of the storage, which would keep serving quarantined
+/// crate files from their caches until they are invalidated.
+#[derive(Default)]
+pub struct Cdns<'a> {
+    pub cloudfront: Option<&'a CloudFront>,
+    pub fastly: Option<&'a Fastly>,
+}
+
+impl Cdns<'_> {
+    async fn invalidate(&self, path: &str) {
+        if let Some(cloudfront) = self.cloudfront {
+            if let Err(error) = cloudfront.invalidate(path).await {
+                error!(%path, "Failed to invalidate CloudFront cache: {error}");
+            }
+        }
+
+        if let Some(fastly) = self.fastly {
+            if let Err(error) = fastly.invalidate(path).await {
+                error!(%path, "Failed to invalidate Fastly cache: {error}");
+            }
+        }
+    }
+}
+
+/// Verifies the given crate files against their checksums.
+///
+/// Crate files that fail to be read or quarantined are reported in
+/// [`VerificationReport::failed`] instead of aborting the whole run.
+pub async fn verify_crate_files(
+    storage: &Storage,
+    cdns: &Cdns<'_>,
+    crate_files: Vec<CrateFile>,
+    quarantine: bool,
+) -> VerificationReport {
+    let mut report = VerificationReport::default();
+
+    let mut results = stream::iter(crate_files)
+        .map(|file| async move {
+            let checksum = storage.crate_file_checksum(&file.name, &file.version).await;
+            (file, checksum)
+        })
+        .buffered(CONCURRENCY);
+
+    while let Some((file, checksum)) = results.next().await {
+        report.checked += 1;
+
+        let crate_file = format!("{}@{}", file.name, file.version);
+        match checksum {
+            Err(error) => {
+                error!(%crate_file, "Failed to read crate file: {error}");
+                report.failed.push(crate_file);
+            }
+            Ok(None) => {
+                warn!(%crate_file, "Crate file is missing from the storage");
+                report.missing.push(crate_file);
+            }
+            Ok(Some(checksum)) if checksum != file.checksum => {
+                error!(%crate_file, expected = %file.checksum, actual = %checksum, "Crate file checksum mismatch");
+
+                if quarantine {
+                    match storage
+                        .quarantine_crate_file(&file.name, &file.version)
+                        .await
+                    {
+                        Ok(()) => {
+                            warn!(%crate_file, "Moved crate file to quarantine");
+
+                            let path = crate_file_path(&file.name, &file.version);
+                            cdns.invalidate(path.as_ref()).await;
+                        }
+                        Err(error) => {
+                            error!(%crate_file, "Failed to quarantine crate file: {error}");
+                            report.failed.push(crate_file.clone());
+                        }
+                    }
+                }
+
+                report.mismatched.push(crate_file);
+            }
+            Ok(Some(_)) => {}
+        }
+    }
+
+    report
+}
@@ -0,0 +1,220 @@
+use crate::models::{NewWebhookDelivery, Webhook, WebhookDelivery, WebhookPayload};
+use crate::schema::{webhook_deliveries, webhooks};
+use crate::util::ip::is_public_address;
+use crate::worker::Environment;
+use anyhow::anyhow;
+use chrono::Utc;
+use crates_io_worker::{BackgroundJob, EnqueueError};
+use diesel::prelude::*;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::redirect;
+use ring::hmac;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use url::{Host, Url};
+
+/// Name of the header that contains the name of the event.
+const EVENT_HEADER: &str = "X-Crates-Io-Event";
+
+/// Name of the header that contains the ID of the delivery, which stays
+/// the same across retries.
+const DELIVERY_HEADER: &str = "X-Crates-Io-Delivery";
+
+/// Name of the header that contains the hex-encoded HMAC-SHA256 signature of
+/// the request body, in the form of `sha256=<signature>`.
+const SIGNATURE_HEADER: &str = "X-Crates-Io-Signature-256";
+
+/// Maximum number of attempts to deliver an event. Since failed jobs are
+/// retried with an exponential backoff, this covers a couple of hours of
+/// downtime of the receiver.
+const MAX_ATTEMPTS: i32 = 8;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Records a delivery for every webhook that is subscribed to the event and
+/// enqueues a [`DeliverWebhook`] job for each of them.
+pub fn enqueue_webhooks(
+    crate_id: i32,
+    payload: &WebhookPayload<'_>,
+    conn: &mut PgConnection,
+) -> Result<(), EnqueueError> {
+    let event = payload.event();
+    let webhook_ids = Webhook::subscribed_to(conn, crate_id, event, payload.target_user_id())?;
+    if webhook_ids.is_empty() {
+        return Ok(());
+    }
+
+    let payload = serde_json::to_value(payload)?;
+    for webhook_id in webhook_ids {
+        let delivery_id = NewWebhookDelivery {
+            webhook_id,
+            event: event.as_str(),
+            payload: &payload,
+        }
+        .insert(conn)?;
+
+        DeliverWebhook::new(delivery_id).enqueue(conn)?;
+    }
+
+    Ok(())
+}
+
+/// Signs the request body with the secret of the webhook and returns the
+/// hex-encoded signature.
+fn sign_payload(secret: &str, body: &[u8]) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    hex::encode(hmac::sign(&key, body))
+}
+
+/// Resolves the host of the webhook URL and builds a client that only
+/// connects to the resolved addresses, after checking that they are public.
+///
+/// Pinning the addresses prevents the host from resolving to a different
+/// address between the check and the request, and redirects are not followed
+/// since they could point anywhere.
+async fn build_client(url: &str, allow_private: bool) -> anyhow::Result<reqwest::Client> {
+    let url = Url::parse(url)?;
+    let port = url
+        .port_or_known_default()
+        .ok_or_else(|| anyhow!("webhook URL has no port"))?;
+
+    let builder = reqwest::Client::builder().redirect(redirect::Policy::none());
+
+    let (builder, addresses) = match url.host() {
+        Some(Host::Domain(domain)) => {
+            let addresses = tokio::net::lookup_host((domain, port))
+                .await?
+                .collect::<Vec<_>>();
+            (builder.resolve_to_addrs(domain, &addresses), addresses)
+        }
+        Some(Host::Ipv4(ip)) => (builder, vec![SocketAddr::new(ip.into(), port)]),
+        Some(Host::Ipv6(ip)) => (builder, vec![SocketAddr::new(ip.into(), port)]),
+        None => return Err(anyhow!("webhook URL has no host")),
+    };
+
+    if addresses.is_empty() {
+        return Err(anyhow!("webhook host did not resolve to any address"));
+    }
+
+    if !allow_private {
+        if let Some(address) = addresses.iter().find(|a| !is_public_address(a.ip())) {
+            return Err(anyhow!(
+                "webhook host resolves to non-public address {}",
+                address.ip()
+            ));
+        }
+    }
+
+    Ok(builder.build()?)
+}
+
+/// A job to send an event payload to a webhook URL.
+///
+/// If the receiver can't be reached or responds with an unsuccessful status
+/// code, the job fails so that the background worker retries it later, up to
+/// a total of [`MAX_ATTEMPTS`] attempts.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DeliverWebhook {
+    delivery_id: i32,
+}
+
+impl DeliverWebhook {
+    pub fn new(delivery_id: i32) -> Self {
+        Self { delivery_id }
+    }
+}
+
+impl BackgroundJob for DeliverWebhook {
+    const JOB_NAME: &'static str = "deliver_webhook";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip(env), err)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let delivery_id = self.delivery_id;
+
+        let conn = env.deadpool.get().await?;
+        let delivery = conn
+            .interact(move |conn| {
+                webhook_deliveries::table
+                    .find(delivery_id)
+                    .inner_join(webhooks::table)
+                    .select((WebhookDelivery::as_select(), Webhook::as_select()))
+                    .first::<(WebhookDelivery, Webhook)>(conn)
+                    .optional()
+            })
+            .await
+            .map_err(|err| anyhow!(err.to_string()))??;
+
+        let Some((delivery, webhook)) = delivery else {
+            info!("Webhook delivery {delivery_id} no longer exists, skipping");
+            return Ok(());
+        };
+
+        if delivery.delivered_at.is_some() {
+            return Ok(());
+        }
+
+        let body = serde_json::to_vec(&delivery.payload)?;
+        let signature = sign_payload(&webhook.secret, &body);
+
+        let allow_private = env.config.allow_private_webhook_urls;
+        let result = match build_client(&webhook.url, allow_private).await {
+            Ok(client) => client
+                .post(&webhook.url)
+                .timeout(REQUEST_TIMEOUT)
+                .header(CONTENT_TYPE, "application/json")
+                .header(EVENT_HEADER, &delivery.event)
+                .header(DELIVERY_HEADER, delivery.id.to_string())
+                .header(SIGNATURE_HEADER, format!("sha256={signature}"))
+                .body(body)
+                .send()
+                .await
+                .map_err(|error| error.to_string()),
+            Err(error) => Err(error.to_string()),
+        };
+
+        let (response_status, error) = match result {
+            Ok(response) if response.status().is_success() => {
+                (Some(response.status().as_u16()), None)
+            }
+            Ok(response) => {
+                let status = response.status();
+                (
+                    Some(status.as_u16()),
+                    Some(format!("unexpected response status: {status}")),
+                )
+            }
+            Err(error) => (None, Some(error)),
+        };
+
+        let now = Utc::now().naive_utc();
+        let delivered_at = error.is_none().then_some(now);
+        let attempt_error = error.clone();
+        let attempts = conn
+            .interact(move |conn| {
+                diesel::update(webhook_deliveries::table.find(delivery_id))
+                    .set((
+                        webhook_deliveries::attempts.eq(webhook_deliveries::attempts + 1),
+                        webhook_deliveries::response_status.eq(response_status.map(i32::from)),
+                        webhook_deliveries::error.eq(attempt_error),
+                        webhook_deliveries::last_attempt_at.eq(now),
+                        webhook_deliveries::delivered_at.eq(delivered_at),
+                    ))
+                    .returning(webhook_deliveries::attempts)
+                    .get_result::<i32>(conn)
+            })
+            .await
+            .map_err(|err| anyhow!(err.to_string()))??;
+
+        match error {
+            None => Ok(()),
+            Some(error) if attempts >= MAX_ATTEMPTS => {
+                warn!(url = %webhook.url, "Giving up on webhook delivery after {attempts} attempts: {error}");
+                Ok(())
+            }
+            Some(error) => Err(anyhow!("Failed to deliver webhook: {error}")),
+        }
+    }
+}
@@ -22,16 +22,22 @@ impl RunnerExt for Runner<Arc<Environment>> {
         self.register_job_type::<jobs::CheckTyposquat>()
             .register_job_type::<jobs::CleanProcessedLogFiles>()
             .register_job_type::<jobs::DailyDbMaintenance>()
+            .register_job_type::<jobs::DeliverWebhook>()
             .register_job_type::<jobs::DumpDb>()
+            .register_job_type::<jobs::IncrementalDumpDb>()
+            .register_job_type::<jobs::MirrorUpstream>()
             .register_job_type::<jobs::NormalizeIndex>()
             .register_job_type::<jobs::ProcessCdnLog>()
             .register_job_type::<jobs::ProcessCdnLogQueue>()
+            .register_job_type::<jobs::PruneTokenUsages>()
             .register_job_type::<jobs::RenderAndUploadReadme>()
+            .register_job_type::<jobs::SignIndexSnapshot>()
             .register_job_type::<jobs::SquashIndex>()
             .register_job_type::<jobs::SyncAdmins>()
             .register_job_type::<jobs::SyncToGitIndex>()
             .register_job_type::<jobs::SyncToSparseIndex>()
             .register_job_type::<jobs::UpdateDownloads>()
             .register_job_type::<jobs::UpdateDefaultVersion>()
+            .register_job_type::<jobs::VerifyCrateFiles>()
     }
 }